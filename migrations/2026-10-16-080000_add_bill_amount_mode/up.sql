@@ -0,0 +1 @@
+ALTER TABLE bills ADD COLUMN amount_mode TEXT NOT NULL DEFAULT 'Fixed';
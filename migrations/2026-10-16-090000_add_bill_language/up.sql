@@ -0,0 +1 @@
+ALTER TABLE bills ADD COLUMN language TEXT NOT NULL DEFAULT 'De';
@@ -0,0 +1 @@
+ALTER TABLE bills ADD COLUMN currency TEXT NOT NULL DEFAULT 'CHF';
@@ -0,0 +1 @@
+ALTER TABLE bills ADD COLUMN pdf_hash TEXT;
@@ -0,0 +1,2 @@
+-- PDF BLOBs are moved to files by Database::extract_pdf_blobs after this migration
+ALTER TABLE bills ADD COLUMN pdf_path TEXT;
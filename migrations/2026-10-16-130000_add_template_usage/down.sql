@@ -0,0 +1,2 @@
+ALTER TABLE item_templates DROP COLUMN last_used;
+ALTER TABLE item_templates DROP COLUMN use_count;
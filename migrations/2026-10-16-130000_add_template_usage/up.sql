@@ -0,0 +1,2 @@
+ALTER TABLE item_templates ADD COLUMN use_count INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE item_templates ADD COLUMN last_used TEXT;
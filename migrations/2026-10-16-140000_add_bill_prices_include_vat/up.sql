@@ -0,0 +1 @@
+ALTER TABLE bills ADD COLUMN prices_include_vat BOOLEAN NOT NULL DEFAULT 0;
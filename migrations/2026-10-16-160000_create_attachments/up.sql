@@ -0,0 +1,11 @@
+-- Supporting documents of a bill; the files live below attachments/ in the data folder
+CREATE TABLE IF NOT EXISTS attachments (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    bill_id INTEGER NOT NULL REFERENCES bills (id) ON DELETE CASCADE,
+    filename TEXT NOT NULL,
+    mime TEXT NOT NULL,
+    path TEXT NOT NULL,
+    added_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS attachments_bill_id ON attachments (bill_id);
@@ -0,0 +1,2 @@
+ALTER TABLE item_templates DROP COLUMN unit;
+ALTER TABLE item_templates DROP COLUMN vat_rate;
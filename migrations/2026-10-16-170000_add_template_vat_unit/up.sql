@@ -0,0 +1,2 @@
+ALTER TABLE item_templates ADD COLUMN vat_rate DOUBLE NOT NULL DEFAULT 0;
+ALTER TABLE item_templates ADD COLUMN unit TEXT NOT NULL DEFAULT '';
@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS discount_templates;
+ALTER TABLE bills DROP COLUMN discount;
@@ -0,0 +1,8 @@
+ALTER TABLE bills ADD COLUMN discount TEXT;
+
+CREATE TABLE IF NOT EXISTS discount_templates (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    name TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    value DOUBLE NOT NULL
+);
@@ -0,0 +1,2 @@
+ALTER TABLE bills DROP COLUMN last_reminder_at;
+ALTER TABLE bills DROP COLUMN reminder_level;
@@ -0,0 +1,2 @@
+ALTER TABLE bills ADD COLUMN reminder_level INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE bills ADD COLUMN last_reminder_at TEXT;
@@ -0,0 +1 @@
+ALTER TABLE bills ADD COLUMN reference_type TEXT NOT NULL DEFAULT 'SCOR';
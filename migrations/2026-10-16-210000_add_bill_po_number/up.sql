@@ -0,0 +1 @@
+ALTER TABLE bills ADD COLUMN po_number TEXT NOT NULL DEFAULT '';
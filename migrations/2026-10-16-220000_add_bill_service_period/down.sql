@@ -0,0 +1,2 @@
+ALTER TABLE bills DROP COLUMN service_to;
+ALTER TABLE bills DROP COLUMN service_from;
@@ -0,0 +1,2 @@
+ALTER TABLE bills ADD COLUMN service_from TEXT;
+ALTER TABLE bills ADD COLUMN service_to TEXT;
@@ -0,0 +1 @@
+ALTER TABLE bills ADD COLUMN archived_at TEXT;
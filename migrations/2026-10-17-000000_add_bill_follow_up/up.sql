@@ -0,0 +1 @@
+ALTER TABLE bills ADD COLUMN follow_up TEXT;
@@ -0,0 +1,11 @@
+-- Money received for a bill, recorded by hand or imported from a bank statement
+CREATE TABLE IF NOT EXISTS payments (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    bill_id INTEGER NOT NULL REFERENCES bills (id) ON DELETE CASCADE,
+    amount DOUBLE NOT NULL,
+    paid_on TEXT NOT NULL,
+    note TEXT NOT NULL DEFAULT '',
+    source TEXT
+);
+
+CREATE INDEX IF NOT EXISTS payments_bill_id ON payments (bill_id);
@@ -0,0 +1 @@
+ALTER TABLE bills ADD COLUMN include_qr_bill BOOLEAN NOT NULL DEFAULT 1;
@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS bill_templates (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    name TEXT NOT NULL,
+    items TEXT NOT NULL,
+    notes TEXT NOT NULL DEFAULT '',
+    payment_days INTEGER NOT NULL DEFAULT 30
+);
@@ -0,0 +1 @@
+ALTER TABLE bills ADD COLUMN conversion TEXT;
@@ -0,0 +1 @@
+ALTER TABLE attachments DROP COLUMN append_to_pdf;
@@ -0,0 +1 @@
+ALTER TABLE attachments ADD COLUMN append_to_pdf BOOLEAN NOT NULL DEFAULT 0;
@@ -0,0 +1 @@
+ALTER TABLE bills DROP COLUMN round_to_five_cents;
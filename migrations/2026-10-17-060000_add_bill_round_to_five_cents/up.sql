@@ -0,0 +1 @@
+ALTER TABLE bills ADD COLUMN round_to_five_cents BOOLEAN NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+ALTER TABLE bills DROP COLUMN credited_bill_id;
+ALTER TABLE bills DROP COLUMN document_type;
@@ -0,0 +1,2 @@
+ALTER TABLE bills ADD COLUMN document_type TEXT NOT NULL DEFAULT 'invoice';
+ALTER TABLE bills ADD COLUMN credited_bill_id INTEGER;
@@ -0,0 +1,2 @@
+-- The history of one client or bill is looked up by entity
+CREATE INDEX IF NOT EXISTS audit_log_entity ON audit_log (entity_type, entity_id);
@@ -1,11 +1,52 @@
-use chrono::{DateTime, Local};
+use chrono::{DateTime, Datelike, NaiveDate};
+use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::mpsc::{Receiver, Sender};
 use std::sync::{Arc, Mutex};
 use iso_11649::RfCreditorReference;
 use iban::Iban;
+use sha2::{Digest, Sha256};
 
-use crate::db::Database;
-use crate::types::Address;
+use crate::db::{Database, IntegrityReport};
+use crate::email::{OutgoingEmail, SmtpSettings, render_email_text};
+use crate::export::{CsvColumn, DEFAULT_CSV_COLUMNS};
+use crate::import::{BillImportRecord, StatementEntry, parse_import_date};
+use crate::types::{Address, DocumentType, Language, ReferenceType};
+use crate::pdf::InvoiceOptions;
+
+/// Idle time after the last settings change before the settings are written to the database
+pub const SETTINGS_SAVE_DELAY: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Checks that a string is a single, syntactically valid email address
+pub fn validate_email(email: &str) -> bool {
+    email.trim().parse::<lettre::Address>().is_ok()
+}
+
+/// Reference as compared for collisions: without spaces, case-insensitive
+fn normalized_reference(reference: &str) -> String {
+    reference.split_whitespace().collect::<String>().to_uppercase()
+}
+
+/// References used by more than one bill, with the ids of those bills. Empty references
+/// never collide.
+pub fn find_duplicate_references(bills: &[Bill]) -> Vec<(String, Vec<u64>)> {
+    let mut by_reference: BTreeMap<String, Vec<u64>> = BTreeMap::new();
+    for bill in bills {
+        let reference = normalized_reference(&bill.reference);
+        if !reference.is_empty() {
+            by_reference.entry(reference).or_default().push(bill.id);
+        }
+    }
+    by_reference.into_iter().filter(|(_, ids)| ids.len() > 1).collect()
+}
+
+/// "#1, #4" style list of bill numbers
+pub fn format_bill_ids(ids: &[u64]) -> String {
+    ids.iter().map(|id| format!("#{}", id)).collect::<Vec<_>>().join(", ")
+}
 
 /// Sanitizes a string for use as a filename by replacing problematic characters
 fn sanitize_filename(name: &str) -> String {
@@ -39,16 +80,227 @@ pub fn validate_iban(iban_str: &str) -> bool {
     cleaned.parse::<Iban>().is_ok()
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Checks a bill against the field constraints of the Swiss QR-bill standard, so a bank
+/// doesn't reject it later. Returns every violated constraint.
+pub fn validate_qr_bill(bill: &Bill, client: &Client, creditor: &Address) -> Result<(), Vec<String>> {
+    let mut problems = Vec::new();
+
+    problems.extend(qr_address_problems("Creditor", creditor));
+    problems.extend(qr_address_problems("Debtor", &client.billing_address));
+
+    let iban = bill.iban.replace(' ', "").to_uppercase();
+    if !(iban.starts_with("CH") || iban.starts_with("LI")) {
+        problems.push("IBAN must be a Swiss or Liechtenstein account (CH/LI)".to_string());
+    }
+
+    if !QR_CURRENCIES.contains(&bill.qr_currency()) {
+        problems.push(format!("Currency '{}' is not supported (use {})", bill.qr_currency(), QR_CURRENCIES.join(" or ")));
+    }
+    if let Some(conversion) = &bill.conversion
+        && !(conversion.rate.is_finite() && conversion.rate > 0.0)
+    {
+        problems.push(format!("Exchange rate to {} must be greater than 0", conversion.currency));
+    }
+
+    let amount = round_cents(bill.qr_amount());
+    match bill.amount_mode {
+        AmountMode::Fixed => {
+            if amount < 0.0 {
+                problems.push(format!("Amount {:.2} is negative; the credit lines exceed the charges", amount));
+            } else if !(0.01..=999_999_999.99).contains(&amount) {
+                problems.push(format!("Amount {:.2} must be between 0.01 and 999999999.99", amount));
+            }
+        }
+        // The payment part leaves the amount blank, but a bill that owes the client money
+        // must not ask them to pay whatever they like
+        AmountMode::Open => {
+            if amount < 0.0 {
+                problems.push(format!("Total {:.2} is negative; an open amount can't settle a credit", amount));
+            }
+        }
+    }
+
+    problems.extend(reference_problem(bill.reference_type, &bill.reference));
+
+    if problems.is_empty() { Ok(()) } else { Err(problems) }
+}
+
+/// Reference in the groups printed on QR bills: QR references as 2 + 5×5 digits from the
+/// left, creditor references in blocks of four
+pub fn format_reference(reference_type: ReferenceType, reference: &str) -> String {
+    let compact: Vec<char> = reference.chars().filter(|c| !c.is_whitespace()).collect();
+    let groups: Vec<String> = match reference_type {
+        ReferenceType::Qrr if compact.len() > 2 => std::iter::once(compact[..2].iter().collect())
+            .chain(compact[2..].chunks(5).map(|g| g.iter().collect()))
+            .collect(),
+        ReferenceType::Scor => compact.chunks(4).map(|g| g.iter().collect()).collect(),
+        _ => return reference.trim().to_string(),
+    };
+    groups.join(" ")
+}
+
+/// Why a payment reference isn't valid for its reference type, if it isn't
+pub fn reference_problem(reference_type: ReferenceType, reference: &str) -> Option<String> {
+    let reference = reference.replace(' ', "");
+    match reference_type {
+        ReferenceType::Qrr => {
+            let digits = reference.len() == 27 && reference.chars().all(|c| c.is_ascii_digit());
+            if !digits {
+                Some("QR reference must be exactly 27 digits".to_string())
+            } else if qrr_check_digit(&reference[..26]) != reference[26..].parse::<u32>().unwrap_or(10) {
+                Some("QR reference has a wrong check digit".to_string())
+            } else {
+                None
+            }
+        }
+        ReferenceType::Scor => {
+            if reference.len() < 5 || reference.len() > 25 {
+                Some("Creditor reference must be 5 to 25 characters".to_string())
+            } else if RfCreditorReference::parse_str(&reference.to_uppercase()).is_err() {
+                Some("Creditor reference is not a valid ISO 11649 (RF…) reference".to_string())
+            } else {
+                None
+            }
+        }
+        ReferenceType::None => None,
+    }
+}
+
+/// Length limits of a structured QR-bill address
+fn qr_address_problems(role: &str, address: &Address) -> Vec<String> {
+    let limits = [
+        ("name", address.name.as_str(), 70),
+        ("street", address.street.as_deref().unwrap_or(""), 70),
+        ("building number", address.building_number.as_deref().unwrap_or(""), 16),
+        ("postal code", address.postal_code.as_str(), 16),
+        ("city", address.city.as_str(), 35),
+    ];
+
+    let mut problems: Vec<String> = limits
+        .into_iter()
+        .filter(|(_, value, max)| value.trim().chars().count() > *max)
+        .map(|(field, value, max)| {
+            format!("{} {} is {} characters long (max. {})", role, field, value.trim().chars().count(), max)
+        })
+        .collect();
+
+    let country = address.country.trim();
+    let iso_code = country.len() == 2 && country.chars().all(|c| c.is_ascii_uppercase());
+    if !country.is_empty() && !iso_code {
+        problems.push(format!("{} country must be a two-letter ISO code like CH, not '{}'", role, country));
+    }
+    problems
+}
+
+/// Currencies supported by the Swiss QR bill
+pub const QR_CURRENCIES: [&str; 2] = ["CHF", "EUR"];
+
+pub const DEFAULT_CURRENCY: &str = "CHF";
+
+/// Client that bills of a lost client are moved to by the database repair
+pub const RESTORED_CLIENT_NAME: &str = "Restored client";
+
+/// Due-date buttons offered until others are configured
+pub const DEFAULT_DUE_DATE_PRESETS: [i64; 2] = [7, 30];
+
+/// Example account shown until the real IBAN is entered in Settings
+pub const PLACEHOLDER_IBAN: &str = "CH93 0076 2011 6238 5295 7";
+
+/// Example creditor shown until the business address is entered in Settings
+pub fn placeholder_creditor() -> Address {
+    Address::new(
+        "Your Company Name".to_string(),
+        Some("Your Street".to_string()),
+        Some("1".to_string()),
+        "8000".to_string(),
+        "Zurich".to_string(),
+        "CH".to_string(),
+    )
+}
+
+/// Parts of the creditor details that are still the built-in examples, e.g. "business name"
+pub fn placeholder_creditor_fields(creditor: &Address, iban: &str) -> Vec<&'static str> {
+    let placeholder = placeholder_creditor();
+    let mut fields = Vec::new();
+    if creditor.name.trim() == placeholder.name {
+        fields.push("business name");
+    }
+    if creditor.street.as_deref().map(str::trim) == placeholder.street.as_deref() {
+        fields.push("street");
+    }
+    if iban.replace(' ', "").eq_ignore_ascii_case(&PLACEHOLDER_IBAN.replace(' ', "")) {
+        fields.push("IBAN");
+    }
+    fields
+}
+
+/// Checks that creditor, debtor and account are complete enough for a QR bill
+pub fn validate_pdf_parties(bill: &Bill, client: &Client, creditor: &Address) -> Result<(), String> {
+    let mut problems = Vec::new();
+
+    let creditor_missing = creditor.missing_fields();
+    if !creditor_missing.is_empty() {
+        problems.push(format!("Creditor (Settings) is missing: {}", creditor_missing.join(", ")));
+    }
+    // A plain invoice has no payment part, so the account isn't needed
+    if bill.shows_qr_bill() {
+        if !validate_iban(&bill.iban) {
+            problems.push("Bill IBAN is missing or invalid".to_string());
+        } else if let Err(e) = check_reference_type(bill.reference_type, &bill.iban) {
+            problems.push(e);
+        }
+    }
+
+    let debtor_missing = client.billing_address.missing_fields();
+    if !debtor_missing.is_empty() {
+        problems.push(format!(
+            "Billing address of client '{}' is missing: {}",
+            client.name,
+            debtor_missing.join(", ")
+        ));
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(problems.join("\n"))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Client {
     pub id: u64,
     pub name: String,
     pub address: Address,
     pub billing_address: Address,
+    #[serde(default)]
     pub email: String,
+    #[serde(default)]
     pub phone: String,
 }
 
+impl Client {
+    /// Checks the fields needed to produce a valid invoice; returns the missing ones
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut problems = Vec::new();
+        if self.name.trim().is_empty() {
+            problems.push("Name is required".to_string());
+        }
+
+        let mut contact_missing = self.address.missing_fields();
+        // The contact address has no own name field in the form; the client name is used
+        contact_missing.retain(|f| *f != "name");
+        for field in contact_missing {
+            problems.push(format!("Contact address: {} is required", field));
+        }
+        for field in self.billing_address.missing_fields() {
+            problems.push(format!("Billing address: {} is required", field));
+        }
+
+        if problems.is_empty() { Ok(()) } else { Err(problems) }
+    }
+}
+
 impl Default for Client {
     fn default() -> Self {
         let default_address = Address::new(
@@ -70,20 +322,42 @@ impl Default for Client {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ItemTemplate {
     pub id: u64,
     pub item_type: String,
     pub unit_price: f64,
+    /// VAT rate in percent for items created from this template
+    #[serde(default)]
+    pub vat_rate: f64,
+    #[serde(default)]
+    pub unit: String,
+    #[serde(default)]
+    pub use_count: u32,
+    #[serde(default, with = "crate::timezone::serde_zoned::option")]
+    pub last_used: Option<DateTime<Tz>>,
 }
 
 impl ItemTemplate {
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut problems = Vec::new();
+        if self.item_type.trim().is_empty() {
+            problems.push("Type is required".to_string());
+        }
+        if !self.unit_price.is_finite() {
+            problems.push("Unit price must be a number".to_string());
+        }
+
+        if problems.is_empty() { Ok(()) } else { Err(problems) }
+    }
+
     pub fn to_bill_item(&self) -> BillItem {
         BillItem {
             item_type: self.item_type.clone(),
-            quantity: 1.0,
             unit_price: self.unit_price,
-            note: String::new(),
+            vat_rate: self.vat_rate,
+            unit: self.unit.clone(),
+            ..BillItem::default()
         }
     }
 }
@@ -94,24 +368,57 @@ impl Default for ItemTemplate {
             id: 0,
             item_type: String::new(),
             unit_price: 0.0,
+            vat_rate: 0.0,
+            unit: String::new(),
+            use_count: 0,
+            last_used: None,
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct BillItem {
     pub item_type: String,
     pub quantity: f64,
     pub unit_price: f64,
     pub note: String,
+    /// VAT rate in percent
+    #[serde(default)]
+    pub vat_rate: f64,
+    /// Unit of the quantity, e.g. `h` or `Stk.`
+    #[serde(default)]
+    pub unit: String,
 }
 
 impl BillItem {
+    /// Line total rounded to cents, exactly as printed on the invoice
     pub fn total(&self) -> f64 {
-        self.quantity * self.unit_price
+        round_cents(self.quantity * self.unit_price)
+    }
+
+    /// A credit or refund, i.e. a line with a negative total
+    pub fn is_credit(&self) -> bool {
+        self.total() < 0.0
+    }
+
+    /// Decimals shown and kept for the quantity: none for units counted in whole pieces
+    pub fn quantity_decimals(&self, default: usize) -> usize {
+        let unit = self.unit.trim().trim_end_matches('.').to_lowercase();
+        if PIECE_UNITS.contains(&unit.as_str()) { 0 } else { default }
+    }
+
+    /// Empty item using the configured default VAT rate
+    pub fn with_vat_rate(vat_rate: f64) -> Self {
+        Self {
+            vat_rate,
+            ..Self::default()
+        }
     }
 }
 
+/// Units (lowercase, without trailing dot) that are counted in whole numbers
+pub const PIECE_UNITS: [&str; 7] = ["stk", "stück", "pcs", "pc", "piece", "pieces", "x"];
+
 impl Default for BillItem {
     fn default() -> Self {
         Self {
@@ -119,24 +426,219 @@ impl Default for BillItem {
             quantity: 1.0,
             unit_price: 0.0,
             note: String::new(),
+            vat_rate: 0.0,
+            unit: String::new(),
+        }
+    }
+}
+
+/// A reduction of the whole invoice, either relative or a fixed amount
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Discount {
+    /// Percentage of the items subtotal
+    Percent(f64),
+    /// Fixed amount in the bill's currency
+    Amount(f64),
+}
+
+impl Discount {
+    /// Amount taken off `subtotal`, in whole cents and never more than the subtotal itself
+    pub fn amount_off(&self, subtotal: f64) -> f64 {
+        let amount = match self {
+            Discount::Percent(percent) => subtotal * percent / 100.0,
+            Discount::Amount(amount) => *amount,
+        };
+        round_cents(amount.clamp(0.0, subtotal.max(0.0)))
+    }
+}
+
+impl std::fmt::Display for Discount {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Discount::Percent(percent) => write!(f, "{}%", percent),
+            Discount::Amount(amount) => write!(f, "{:.2}", amount),
+        }
+    }
+}
+
+/// Named, reusable discount, e.g. for a promotion
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiscountTemplate {
+    pub id: u64,
+    pub name: String,
+    pub discount: Discount,
+}
+
+impl Default for DiscountTemplate {
+    fn default() -> Self {
+        Self {
+            id: 0,
+            name: String::new(),
+            discount: Discount::Percent(10.0),
+        }
+    }
+}
+
+/// Saved invoice skeleton: the usual lines, notes and payment term of a recurring kind of
+/// bill, without client or dates
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BillTemplate {
+    pub id: u64,
+    pub name: String,
+    pub items: Vec<BillItem>,
+    pub notes: String,
+    /// Days from the bill date to the due date
+    pub payment_days: i64,
+}
+
+impl Default for BillTemplate {
+    fn default() -> Self {
+        Self {
+            id: 0,
+            name: String::new(),
+            items: Vec::new(),
+            notes: String::new(),
+            payment_days: 30,
+        }
+    }
+}
+
+/// Discount applied to a bill; the name is kept so the bill stays traceable if the
+/// template is changed or deleted later
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AppliedDiscount {
+    pub name: String,
+    pub discount: Discount,
+}
+
+impl From<&DiscountTemplate> for AppliedDiscount {
+    fn from(template: &DiscountTemplate) -> Self {
+        Self {
+            name: template.name.clone(),
+            discount: template.discount,
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Currency the QR payment part is issued in when it differs from the bill's currency
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CurrencyConversion {
+    /// CHF or EUR
+    pub currency: String,
+    /// Units of `currency` per unit of the bill's currency
+    pub rate: f64,
+}
+
+/// Reminder level of the final notice; no further reminders are recorded after it
+pub const FINAL_REMINDER_LEVEL: u8 = 3;
+
+/// Rounds an amount to whole cents
+pub fn round_cents(amount: f64) -> f64 {
+    round_to(amount, 2)
+}
+
+/// Swiss cash rounding to the nearest 0.05
+pub fn round_to_five_cents(amount: f64) -> f64 {
+    round_cents((amount * 20.0).round() / 20.0)
+}
+
+/// Rounds to the given number of decimals, e.g. to drop drag artefacts like `19.9999997`
+pub fn round_to(value: f64, decimals: usize) -> f64 {
+    let factor = 10f64.powi(decimals as i32);
+    (value * factor).round() / factor
+}
+
+/// Steps from a bill's items subtotal to its grand total
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BillTotals {
+    pub subtotal: f64,
+    /// Discount amount, as a positive number that is subtracted
+    pub discount: f64,
+    /// Name of the applied discount
+    pub discount_name: Option<String>,
+    /// VAT amount per rate in percent, ordered by rate
+    pub vat: Vec<(f64, f64)>,
+    /// The VAT is contained in the subtotal (gross prices) rather than added to it
+    pub vat_included: bool,
+    /// Difference added by rounding the total to 5 centimes; negative when rounded down
+    pub rounding: f64,
+    pub grand_total: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Bill {
     pub id: u64,
     pub client_id: u64,
-    pub date: DateTime<Local>,
-    pub due_date: DateTime<Local>,
+    #[serde(with = "crate::timezone::serde_zoned")]
+    pub date: DateTime<Tz>,
+    #[serde(with = "crate::timezone::serde_zoned")]
+    pub due_date: DateTime<Tz>,
     pub items: Vec<BillItem>,
     pub reference: String,
     pub iban: String,
     pub notes: String,
     pub status: BillStatus,
+    #[serde(default)]
+    pub amount_mode: AmountMode,
+    #[serde(default)]
+    pub language: Language,
+    #[serde(default = "default_currency")]
+    pub currency: String,
+    #[serde(default, with = "crate::timezone::serde_zoned::option")]
+    pub pdf_created_at: Option<DateTime<Tz>>,
+    /// Content hash of the invoice inputs the stored PDF was generated from
+    #[serde(default)]
+    pub pdf_hash: Option<String>,
+    /// Whether a PDF has been generated; the bytes are loaded via `Database::load_bill_pdf`
+    #[serde(default)]
+    pub has_pdf: bool,
+    /// Unit prices are gross amounts with VAT included; VAT is backed out instead of added
+    #[serde(default)]
+    pub prices_include_vat: bool,
+    /// Whole-invoice discount, applied to the items subtotal
+    #[serde(default)]
+    pub discount: Option<AppliedDiscount>,
+    /// Reminders sent so far; FINAL_REMINDER_LEVEL is the last notice
+    #[serde(default)]
+    pub reminder_level: u8,
+    #[serde(default, with = "crate::timezone::serde_zoned::option")]
+    pub last_reminder_at: Option<DateTime<Tz>>,
+    /// Kind of payment reference on the QR bill
+    #[serde(default)]
+    pub reference_type: ReferenceType,
+    /// Client's purchase-order number, printed on the invoice when set
+    #[serde(default)]
+    pub po_number: String,
+    /// Start of the period the invoiced services were delivered in
+    #[serde(default)]
+    pub service_from: Option<NaiveDate>,
+    /// End of the service period, inclusive
+    #[serde(default)]
+    pub service_to: Option<NaiveDate>,
+    /// When the bill was archived for tax retention; edits afterwards are warned about
+    #[serde(default, with = "crate::timezone::serde_zoned::option")]
+    pub archived_at: Option<DateTime<Tz>>,
+    /// When to personally follow up with the client; not printed on the invoice
+    #[serde(default, with = "crate::timezone::serde_zoned::option")]
+    pub follow_up: Option<DateTime<Tz>>,
+    /// Print the Swiss QR payment part; off for plain invoices, e.g. for clients paying by card
+    #[serde(default = "default_true")]
+    pub include_qr_bill: bool,
+    /// QR payment in another currency than the items, e.g. CHF for a EUR invoice
+    #[serde(default)]
+    pub conversion: Option<CurrencyConversion>,
+    /// Round the amount due to 5 centimes, as for cash; the difference gets its own line
+    #[serde(default)]
+    pub round_to_five_cents: bool,
+    #[serde(default)]
+    pub document_type: DocumentType,
+    /// Invoice a credit note refunds
+    #[serde(default)]
+    pub credited_bill_id: Option<u64>,
+    /// Columns that were unreadable when loading and got replaced by defaults; saving the
+    /// bill overwrites them
     #[serde(skip)]
-    pub pdf_data: Option<Vec<u8>>,
-    pub pdf_created_at: Option<DateTime<Local>>,
+    pub load_problems: Vec<String>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
@@ -145,6 +647,34 @@ pub enum BillStatus {
     Sent,
     Paid,
     Overdue,
+    /// Given up as uncollectible; kept for the records but no longer outstanding
+    WrittenOff,
+}
+
+impl BillStatus {
+    pub const ALL: [BillStatus; 5] = [
+        BillStatus::Draft,
+        BillStatus::Sent,
+        BillStatus::Paid,
+        BillStatus::Overdue,
+        BillStatus::WrittenOff,
+    ];
+
+    /// Position in the bill lifecycle, used for sorting
+    pub fn sort_key(&self) -> usize {
+        Self::ALL.iter().position(|s| s == self).unwrap_or(0)
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "Draft" => Some(BillStatus::Draft),
+            "Sent" => Some(BillStatus::Sent),
+            "Paid" => Some(BillStatus::Paid),
+            "Overdue" => Some(BillStatus::Overdue),
+            "WrittenOff" => Some(BillStatus::WrittenOff),
+            _ => None,
+        }
+    }
 }
 
 impl std::fmt::Display for BillStatus {
@@ -154,318 +684,3769 @@ impl std::fmt::Display for BillStatus {
             BillStatus::Sent => write!(f, "Sent"),
             BillStatus::Paid => write!(f, "Paid"),
             BillStatus::Overdue => write!(f, "Overdue"),
+            BillStatus::WrittenOff => write!(f, "Written off"),
         }
     }
 }
 
-impl Bill {
-    pub fn total(&self) -> f64 {
-        self.items.iter().map(|item| item.total()).sum()
+/// Inputs for rendering one bill's PDF, detached from the app so it can run on another thread
+struct PdfJob {
+    bill: Bill,
+    client: Client,
+    creditor: Address,
+    options: InvoiceOptions,
+}
+
+impl PdfJob {
+    /// Renders the PDF and stores it with its timestamp and content hash; returns the saved bill
+    fn run(&self, db: &Database) -> Result<Bill, String> {
+        let pdf_data = crate::pdf::generate_bill_pdf(&self.bill, &self.client, &self.creditor, &self.options)?;
+        let content_hash = crate::pdf::invoice_content_hash(&self.bill, &self.client, &self.creditor, &self.options);
+        let now = crate::timezone::now();
+
+        // Save to disk and database
+        db.save_bill_pdf(self.bill.id, &pdf_data, &now, &content_hash)
+            .map_err(|e| format!("Failed to save PDF: {}", e))?;
+        db.get_bill_by_id(self.bill.id)
+            .map_err(|e| format!("Database error: {}", e))?
+            .ok_or_else(|| "Bill not found".to_string())
     }
+}
 
-    pub fn generate_scor_reference(bill_id: u64, client_id: u64, year: i32) -> String {
-        
-        // Format: YYYY-CCC-BBBB (year-client-bill) without separators for calculation
-        // We'll add separators for display
-        let base = format!("{:04}Y{:03}K{:04}", year, (client_id+420) % 1000, (bill_id+4200) % 10000);
-        let rf = RfCreditorReference::new(base.as_str());
-        
+/// A supporting document (receipt, contract, …) attached to a bill
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attachment {
+    pub id: u64,
+    pub bill_id: u64,
+    pub filename: String,
+    pub mime: String,
+    /// Location relative to the data folder
+    pub path: String,
+    pub added_at: Option<DateTime<Tz>>,
+    /// Add the attachment's pages after the invoice in the generated PDF; PDFs only
+    pub append_to_pdf: bool,
+}
 
-        // Calculate ISO 11649 check digits for SCOR
-        rf.to_string()
+impl Attachment {
+    pub fn is_pdf(&self) -> bool {
+        self.mime == "application/pdf"
     }
 }
 
-impl Default for Bill {
-    fn default() -> Self {
-        let now = Local::now();
-        let due_date = now + chrono::Duration::days(30);
+/// Money received for a bill, in the bill's currency
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Payment {
+    pub id: u64,
+    pub bill_id: u64,
+    pub amount: f64,
+    pub paid_on: NaiveDate,
+    pub note: String,
+    /// Bank statement entry the payment was imported from (`statement id/entry ref`);
+    /// `None` when recorded by hand
+    pub source: Option<String>,
+}
 
-        Self {
-            id: 0,
-            client_id: 0,
-            date: now,
-            due_date,
-            items: vec![BillItem::default()],
-            reference: String::new(),
-            iban: String::new(),
-            notes: String::new(),
-            status: BillStatus::Draft,
-            pdf_data: None,
-            pdf_created_at: None,
+/// How a bank statement entry was matched to a bill
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchConfidence {
+    /// The entry's reference is stored on exactly one bill
+    Reference,
+    /// Bill and client ids decoded from a SCOR reference that no bill stores verbatim
+    Decoded,
+    /// Assigned by hand during the review
+    Manual,
+    Unmatched,
+}
+
+impl std::fmt::Display for MatchConfidence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MatchConfidence::Reference => write!(f, "Reference"),
+            MatchConfidence::Decoded => write!(f, "Decoded"),
+            MatchConfidence::Manual => write!(f, "Manual"),
+            MatchConfidence::Unmatched => write!(f, "—"),
         }
     }
 }
 
-pub struct BillManagerApp {
-    pub clients: Vec<Client>,
-    pub bills: Vec<Bill>,
-    pub item_templates: Vec<ItemTemplate>,
-    pub next_client_id: u64,
-    pub next_bill_id: u64,
-    pub next_template_id: u64,
+/// A bank statement credit with the bill it would be booked on
+#[derive(Debug, Clone)]
+pub struct ReconcileProposal {
+    pub entry: StatementEntry,
+    pub bill_id: Option<u64>,
+    pub confidence: MatchConfidence,
+    /// Why the match needs a closer look, e.g. a partial payment
+    pub warning: Option<String>,
+    /// Record the payment when the review is confirmed
+    pub accepted: bool,
+}
 
-    // UI State
-    pub selected_tab: Tab,
-    pub editing_client: Option<Client>,
-    pub editing_bill: Option<Bill>,
-    pub editing_template: Option<ItemTemplate>,
-    pub show_client_form: bool,
-    pub show_bill_form: bool,
-    pub show_template_form: bool,
-    pub bill_error: Option<String>,
+/// Proposed payments from an imported bank statement, waiting for review
+#[derive(Debug, Clone, Default)]
+pub struct ReconcileReport {
+    pub statement_id: String,
+    pub proposals: Vec<ReconcileProposal>,
+    /// Entries skipped because the statement was imported before
+    pub already_imported: usize,
+}
 
-    // Creditor info (your business)
-    pub creditor_address: Address,
-    pub default_iban: String,
+impl ReconcileReport {
+    /// Source recorded on the payment of an entry, identifying statement and entry
+    pub fn payment_source(&self, entry: &StatementEntry) -> String {
+        format!("{}/{}", self.statement_id, entry.entry_ref)
+    }
+}
 
-    // Database
-    pub db: Arc<Mutex<Database>>,
+/// A client's history derived from their bills
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ClientStats {
+    /// Date of the first bill, taken as the start of the client relationship
+    pub client_since: Option<NaiveDate>,
+    pub bill_count: usize,
+    /// Billed totals per currency, excluding written-off bills
+    pub billed: Vec<(String, f64)>,
+    /// Net amount still due per currency, after credit notes and payments
+    pub outstanding: Vec<(String, f64)>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub enum Tab {
-    Clients,
-    Bills,
-    ItemTemplates,
-    Settings,
+/// One step in a bill's history, built from the audit log and the bill's payments
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimelineEntry {
+    pub at: DateTime<Tz>,
+    pub label: String,
+    /// Edits and PDF runs, hidden unless asked for so the milestones stand out
+    pub is_detail: bool,
 }
 
-impl Default for Tab {
-    fn default() -> Self {
-        Tab::Bills
-    }
+/// Money still owed on a set of bills, after recorded payments
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OutstandingSummary {
+    pub count: usize,
+    /// Outstanding balance per currency
+    pub outstanding: Vec<(String, f64)>,
+    pub overdue_count: usize,
+    /// The part of `outstanding` that is past due
+    pub overdue: Vec<(String, f64)>,
 }
 
-impl BillManagerApp {
-    pub fn new(_cc: &eframe::CreationContext<'_>) -> Self {
-        // Initialize database
-        let db = Database::new("bills.db").expect("Failed to open database");
-        let db = Arc::new(Mutex::new(db));
+/// One recorded change from the audit log
+#[derive(Debug, Clone)]
+pub struct AuditEvent {
+    pub timestamp: Option<DateTime<Tz>>,
+    pub entity_type: String,
+    pub entity_id: u64,
+    pub action: String,
+    /// JSON snapshot or `{"field": [old, new]}` diff, depending on the action
+    pub details: Option<String>,
+}
 
-        // Load data from database
-        let clients = db.lock().unwrap().get_all_clients().unwrap_or_default();
-        let bills = db.lock().unwrap().get_all_bills().unwrap_or_default();
-        let item_templates = db.lock().unwrap().get_all_item_templates().unwrap_or_default();
+/// Column the bill list is sorted by
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum BillSortColumn {
+    #[default]
+    Id,
+    Client,
+    Date,
+    Due,
+    Total,
+    Status,
+}
 
-        let next_client_id = db.lock().unwrap().get_next_client_id().unwrap_or(1);
-        let next_bill_id = db.lock().unwrap().get_next_bill_id().unwrap_or(1);
-        let next_template_id = db.lock().unwrap().get_next_template_id().unwrap_or(1);
+/// Filters for the Bills tab; empty fields don't restrict the list
+#[derive(Debug, Clone, Default)]
+pub struct BillFilter {
+    /// Matched case-insensitively against client name, reference, notes and bill number
+    pub text: String,
+    pub status: Option<BillStatus>,
+    /// Bounds on `Bill::total()` as typed by the user
+    pub min_total: String,
+    pub max_total: String,
+    /// Inclusive bounds on the bill date
+    pub date_from: Option<NaiveDate>,
+    pub date_to: Option<NaiveDate>,
+}
 
-        let creditor_address = db
-            .lock()
-            .unwrap()
-            .get_creditor_address()
-            .unwrap_or(None)
-            .unwrap_or_else(|| Address::new(
-                "Your Company Name".to_string(),
-                Some("Your Street".to_string()),
-                Some("1".to_string()),
-                "8000".to_string(),
-                "Zurich".to_string(),
-                "CH".to_string(),
-            ));
+impl BillFilter {
+    pub fn matches(&self, bill: &Bill, client_name: &str) -> bool {
+        if let Some(status) = self.status && bill.status != status {
+            return false;
+        }
 
-        let default_iban = db
-            .lock()
-            .unwrap()
-            .get_default_iban()
-            .unwrap_or(None)
-            .unwrap_or_else(|| "CH93 0076 2011 6238 5295 7".to_string());
+        let total = bill.signed_total();
+        if let Some(min) = parse_amount_bound(&self.min_total) && total < min {
+            return false;
+        }
+        if let Some(max) = parse_amount_bound(&self.max_total) && total > max {
+            return false;
+        }
 
-        Self {
-            clients,
-            bills,
-            item_templates,
-            next_client_id,
-            next_bill_id,
-            next_template_id,
-            selected_tab: Tab::default(),
-            editing_client: None,
-            editing_bill: None,
+        let date = bill.date.date_naive();
+        if self.date_from.is_some_and(|from| date < from) || self.date_to.is_some_and(|to| date > to) {
+            return false;
+        }
+
+        let query = self.text.trim().to_lowercase();
+        query.is_empty()
+            || client_name.to_lowercase().contains(&query)
+            || bill.reference.to_lowercase().contains(&query)
+            || bill.notes.to_lowercase().contains(&query)
+            || bill.id.to_string() == query
+            // A pasted reference may be grouped differently than the stored one
+            || ScorEncoding::decode(&query).is_some_and(|e| e.matches(bill.id, bill.client_id) && e.year == bill.date.year())
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.text.trim().is_empty()
+            || self.status.is_some()
+            || parse_amount_bound(&self.min_total).is_some()
+            || parse_amount_bound(&self.max_total).is_some()
+            || self.date_from.is_some()
+            || self.date_to.is_some()
+    }
+
+    /// Sets the date range to the calendar month containing `today`
+    pub fn set_this_month(&mut self, today: NaiveDate) {
+        self.set_month_range(today.year(), today.month(), 1);
+    }
+
+    /// Sets the date range to the calendar quarter containing `today`
+    pub fn set_this_quarter(&mut self, today: NaiveDate) {
+        let first_month = (today.month0() / 3) * 3 + 1;
+        self.set_month_range(today.year(), first_month, 3);
+    }
+
+    /// Sets the date range to the calendar year containing `today`
+    pub fn set_this_year(&mut self, today: NaiveDate) {
+        self.set_month_range(today.year(), 1, 12);
+    }
+
+    fn set_month_range(&mut self, year: i32, first_month: u32, months: u32) {
+        let from = NaiveDate::from_ymd_opt(year, first_month, 1);
+        self.date_from = from;
+        self.date_to = from
+            .and_then(|d| d.checked_add_months(chrono::Months::new(months)))
+            .and_then(|d| d.pred_opt());
+    }
+}
+
+/// Parses an amount filter bound, accepting a comma as decimal separator; empty means unbounded
+pub fn parse_amount_bound(value: &str) -> Option<f64> {
+    let value = value.trim();
+    if value.is_empty() {
+        return None;
+    }
+    value.replace(',', ".").parse().ok()
+}
+
+fn default_currency() -> String {
+    DEFAULT_CURRENCY.to_string()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Whether the QR bill carries a fixed amount or leaves it for the payer to fill in.
+/// An open amount is allowed together with a SCOR/QRR reference by the QR-bill spec.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum AmountMode {
+    #[default]
+    Fixed,
+    Open,
+}
+
+impl std::fmt::Display for AmountMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AmountMode::Fixed => write!(f, "Fixed"),
+            AmountMode::Open => write!(f, "Open"),
+        }
+    }
+}
+
+impl Bill {
+    /// Sent or overdue bills that still await payment
+    pub fn is_outstanding(&self) -> bool {
+        // A credit note is owed to the client, not by them
+        self.document_type == DocumentType::Invoice && matches!(self.status, BillStatus::Sent | BillStatus::Overdue)
+    }
+
+    pub fn is_credit_note(&self) -> bool {
+        self.document_type == DocumentType::CreditNote
+    }
+
+    /// Amount due with its sign from our side: negative for credit notes
+    pub fn signed_total(&self) -> f64 {
+        if self.is_credit_note() { -self.total() } else { self.total() }
+    }
+
+    /// Documents linked to this one: the issued credit notes of an invoice, or the invoice a
+    /// credit note refunds. Drafts are left out since they don't count yet.
+    pub fn linked_documents<'a>(&self, bills: &'a [Bill]) -> Vec<&'a Bill> {
+        match self.document_type {
+            DocumentType::Invoice => bills
+                .iter()
+                .filter(|b| b.is_credit_note() && b.credited_bill_id == Some(self.id) && b.status != BillStatus::Draft)
+                .collect(),
+            DocumentType::CreditNote => bills
+                .iter()
+                .filter(|b| Some(b.id) == self.credited_bill_id)
+                .collect(),
+        }
+    }
+
+    /// Whether the PDF gets a QR payment part; credit notes never request a payment
+    pub fn shows_qr_bill(&self) -> bool {
+        self.include_qr_bill && !self.is_credit_note()
+    }
+
+    /// Moves all timestamps into the display zone `tz`; the instants stay the same
+    pub fn convert_to_display_zone(&mut self, tz: Tz) {
+        self.date = self.date.with_timezone(&tz);
+        self.due_date = self.due_date.with_timezone(&tz);
+        for dt in [
+            &mut self.pdf_created_at,
+            &mut self.last_reminder_at,
+            &mut self.archived_at,
+            &mut self.follow_up,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            *dt = dt.with_timezone(&tz);
+        }
+    }
+
+    /// Days from today until the due date (negative when past due)
+    pub fn days_until_due(&self) -> i64 {
+        (self.due_date.date_naive() - crate::timezone::now().date_naive()).num_days()
+    }
+
+    /// Sum of all line totals, before discounts, VAT and rounding
+    pub fn subtotal(&self) -> f64 {
+        // Summing the printed (rounded) lines keeps the subtotal equal to what the client adds up
+        round_cents(self.rounded_line_totals().iter().sum())
+    }
+
+    /// Line totals rounded to cents, in item order
+    pub fn rounded_line_totals(&self) -> Vec<f64> {
+        self.items.iter().map(BillItem::total).collect()
+    }
+
+    /// Breakdown from the items subtotal to the amount due, as shown on the invoice
+    pub fn totals(&self) -> BillTotals {
+        let subtotal = self.subtotal();
+        let discount = self.discount
+            .as_ref()
+            .map(|d| round_cents(d.discount.amount_off(subtotal)))
+            .unwrap_or(0.0);
+        // The discount reduces every VAT rate's share of the subtotal proportionally
+        let discount_factor = if subtotal != 0.0 { (subtotal - discount) / subtotal } else { 1.0 };
+
+        // Line totals per VAT rate, keyed in hundredths of a percent to group equal rates
+        let mut per_rate: BTreeMap<i64, f64> = BTreeMap::new();
+        for item in &self.items {
+            if item.vat_rate != 0.0 {
+                *per_rate.entry((item.vat_rate * 100.0).round() as i64).or_default() += item.total() * discount_factor;
+            }
+        }
+
+        let vat: Vec<(f64, f64)> = per_rate
+            .into_iter()
+            .map(|(key, amount)| {
+                let rate = key as f64 / 100.0;
+                let vat = if self.prices_include_vat {
+                    amount - amount / (1.0 + rate / 100.0)
+                } else {
+                    amount * rate / 100.0
+                };
+                (rate, round_cents(vat))
+            })
+            .collect();
+
+        let vat_total: f64 = vat.iter().map(|(_, amount)| amount).sum();
+        let discounted = subtotal - discount;
+        let exact_total = round_cents(if self.prices_include_vat { discounted } else { discounted + vat_total });
+        let grand_total = if self.round_to_five_cents {
+            round_to_five_cents(exact_total)
+        } else {
+            exact_total
+        };
+
+        BillTotals {
+            subtotal,
+            discount,
+            discount_name: self.discount.as_ref().map(|d| d.name.clone()),
+            vat,
+            vat_included: self.prices_include_vat,
+            rounding: round_cents(grand_total - exact_total),
+            grand_total,
+        }
+    }
+
+    /// Amount due (the grand total)
+    pub fn total(&self) -> f64 {
+        self.totals().grand_total
+    }
+
+    /// Currency of the QR payment part: the conversion's currency, or the bill's own
+    pub fn qr_currency(&self) -> &str {
+        self.conversion.as_ref().map_or(self.currency.as_str(), |c| c.currency.as_str())
+    }
+
+    /// Amount on the QR payment part, converted to `qr_currency` and rounded to cents
+    pub fn qr_amount(&self) -> f64 {
+        match &self.conversion {
+            Some(conversion) => round_cents(self.total() * conversion.rate),
+            None => self.total(),
+        }
+    }
+
+    pub fn generate_scor_reference(bill_id: u64, client_id: u64, year: i32) -> String {
+        Self::generate_salted_scor_reference(bill_id, client_id, year, 0)
+    }
+
+    /// SCOR reference with a salt appended to its base, to step around collisions of the
+    /// shortened ids; salt 0 gives the plain reference
+    pub fn generate_salted_scor_reference(bill_id: u64, client_id: u64, year: i32, salt: u32) -> String {
+        ScorEncoding::new(bill_id, client_id, year, salt).to_reference()
+    }
+
+    /// 27-digit QR reference: year, client and bill number followed by the modulo 10
+    /// recursive check digit
+    pub fn generate_qrr_reference(bill_id: u64, client_id: u64, year: i32) -> String {
+        let base = format!("{:04}{:06}{:016}", year, client_id % 1_000_000, bill_id);
+        format!("{}{}", base, qrr_check_digit(&base))
+    }
+
+    /// Reference of the given scheme; empty for bills without a reference
+    pub fn generate_reference(reference_type: ReferenceType, bill_id: u64, client_id: u64, year: i32) -> String {
+        match reference_type {
+            ReferenceType::Scor => Self::generate_scor_reference(bill_id, client_id, year),
+            ReferenceType::Qrr => Self::generate_qrr_reference(bill_id, client_id, year),
+            ReferenceType::None => String::new(),
+        }
+    }
+}
+
+/// Contents of a SCOR reference generated by this app: `RFxx YYYY Y CCC K BBBB [S salt]`.
+///
+/// Year (4 digits), client (3 digits) and bill (4 digits) are separated by the letters `Y` and
+/// `K`. Client and bill ids are shifted by an offset and kept modulo 1000/10000, so decoding
+/// yields the ids modulo those bounds; `matches` compares them with a bill's actual ids.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScorEncoding {
+    pub year: i32,
+    /// Client id modulo `CLIENT_MODULUS`
+    pub client: u64,
+    /// Bill id modulo `BILL_MODULUS`
+    pub bill: u64,
+    /// Disambiguates references whose shortened ids collide; 0 when absent
+    pub salt: u32,
+}
+
+impl ScorEncoding {
+    const CLIENT_OFFSET: u64 = 420;
+    const CLIENT_MODULUS: u64 = 1000;
+    const BILL_OFFSET: u64 = 4200;
+    const BILL_MODULUS: u64 = 10000;
+
+    pub fn new(bill_id: u64, client_id: u64, year: i32, salt: u32) -> Self {
+        ScorEncoding {
+            year,
+            client: client_id % Self::CLIENT_MODULUS,
+            bill: bill_id % Self::BILL_MODULUS,
+            salt,
+        }
+    }
+
+    /// Reference body without the `RF` prefix and check digits
+    fn base(&self) -> String {
+        let mut base = format!(
+            "{:04}Y{:03}K{:04}",
+            self.year,
+            (self.client + Self::CLIENT_OFFSET) % Self::CLIENT_MODULUS,
+            (self.bill + Self::BILL_OFFSET) % Self::BILL_MODULUS
+        );
+        if self.salt > 0 {
+            base.push_str(&format!("S{}", self.salt));
+        }
+        base
+    }
+
+    /// Full ISO 11649 reference with check digits, grouped in fours
+    pub fn to_reference(self) -> String {
+        RfCreditorReference::new(self.base().as_str()).to_string()
+    }
+
+    /// Parses a reference generated by `to_reference`, with or without spaces. Returns `None`
+    /// for invalid check digits or references following another scheme.
+    pub fn decode(reference: &str) -> Option<Self> {
+        let compact: String = reference.split_whitespace().collect::<String>().to_uppercase();
+        RfCreditorReference::parse_str(&compact).ok()?;
+        let body = compact.get(4..)?;
+
+        let (year, rest) = body.split_once('Y')?;
+        let (client, rest) = rest.split_once('K')?;
+        let (bill, salt) = match rest.split_once('S') {
+            Some((bill, salt)) => (bill, Some(salt)),
+            None => (rest, None),
+        };
+        // Fixed-width decimal field
+        let digits = |s: &str, len: usize| {
+            if s.len() == len && s.bytes().all(|b| b.is_ascii_digit()) { s.parse::<u64>().ok() } else { None }
+        };
+
+        let salt = match salt {
+            Some(salt) if !salt.is_empty() && salt.bytes().all(|b| b.is_ascii_digit()) => salt.parse().ok().filter(|s| *s > 0)?,
+            Some(_) => return None,
+            None => 0,
+        };
+        let encoded_client = digits(client, 3)?;
+        let encoded_bill = digits(bill, 4)?;
+
+        Some(ScorEncoding {
+            year: digits(year, 4)? as i32,
+            client: (encoded_client + Self::CLIENT_MODULUS - Self::CLIENT_OFFSET) % Self::CLIENT_MODULUS,
+            bill: (encoded_bill + Self::BILL_MODULUS - Self::BILL_OFFSET) % Self::BILL_MODULUS,
+            salt,
+        })
+    }
+
+    /// Whether the encoded ids are those of the given bill and client
+    pub fn matches(&self, bill_id: u64, client_id: u64) -> bool {
+        self.bill == bill_id % Self::BILL_MODULUS && self.client == client_id % Self::CLIENT_MODULUS
+    }
+}
+
+/// Check digit of a QR reference (modulo 10, recursive)
+fn qrr_check_digit(digits: &str) -> u32 {
+    const TABLE: [u32; 10] = [0, 9, 4, 6, 8, 2, 7, 1, 3, 5];
+    let carry = digits
+        .chars()
+        .filter_map(|c| c.to_digit(10))
+        .fold(0, |carry, digit| TABLE[((carry + digit) % 10) as usize]);
+    (10 - carry) % 10
+}
+
+/// QR-IBANs carry a QR-IID (30000–31999) in place of the bank's clearing number
+pub fn is_qr_iban(iban: &str) -> bool {
+    let cleaned = iban.replace(' ', "").to_uppercase();
+    (cleaned.starts_with("CH") || cleaned.starts_with("LI"))
+        && cleaned
+            .get(4..9)
+            .and_then(|iid| iid.parse::<u32>().ok())
+            .is_some_and(|iid| (30000..=31999).contains(&iid))
+}
+
+/// QRR references require a QR-IBAN, and a QR-IBAN requires a QRR reference
+pub fn check_reference_type(reference_type: ReferenceType, iban: &str) -> Result<(), String> {
+    match (reference_type, is_qr_iban(iban)) {
+        (ReferenceType::Qrr, false) => Err("A QRR reference requires a QR-IBAN".to_string()),
+        (ReferenceType::Scor | ReferenceType::None, true) => {
+            Err("A QR-IBAN can only be used with a QRR reference".to_string())
+        }
+        _ => Ok(()),
+    }
+}
+
+impl Default for Bill {
+    fn default() -> Self {
+        let now = crate::timezone::now();
+        let due_date = now + chrono::Duration::days(30);
+
+        Self {
+            id: 0,
+            client_id: 0,
+            date: now,
+            due_date,
+            items: vec![BillItem::default()],
+            reference: String::new(),
+            iban: String::new(),
+            notes: String::new(),
+            status: BillStatus::Draft,
+            amount_mode: AmountMode::Fixed,
+            language: Language::default(),
+            currency: DEFAULT_CURRENCY.to_string(),
+            pdf_created_at: None,
+            pdf_hash: None,
+            has_pdf: false,
+            prices_include_vat: false,
+            discount: None,
+            reminder_level: 0,
+            last_reminder_at: None,
+            reference_type: ReferenceType::default(),
+            po_number: String::new(),
+            service_from: None,
+            service_to: None,
+            archived_at: None,
+            follow_up: None,
+            include_qr_bill: true,
+            conversion: None,
+            round_to_five_cents: false,
+            document_type: DocumentType::Invoice,
+            credited_bill_id: None,
+            load_problems: Vec::new(),
+        }
+    }
+}
+
+pub struct BillManagerApp {
+    pub clients: Vec<Client>,
+    pub bills: Vec<Bill>,
+    pub item_templates: Vec<ItemTemplate>,
+    pub discount_templates: Vec<DiscountTemplate>,
+    pub bill_templates: Vec<BillTemplate>,
+    pub payments: Vec<Payment>,
+    /// Discount template being edited inline in the templates tab
+    pub editing_discount: Option<DiscountTemplate>,
+    pub discount_error: Option<String>,
+    /// Bill template open in its dialog, new from a bill or existing
+    pub editing_bill_template: Option<BillTemplate>,
+    pub bill_template_error: Option<String>,
+    pub next_client_id: u64,
+    pub next_bill_id: u64,
+    pub next_template_id: u64,
+
+    // UI State
+    pub selected_tab: Tab,
+    pub editing_client: Option<Client>,
+    /// Open bill forms, each in its own window
+    pub bill_editors: Vec<BillEditor>,
+    /// Key for the next opened bill form
+    pub next_editor_key: u64,
+    /// Bill form to raise above the other windows, e.g. when its bill is opened again
+    pub raise_bill_editor: Option<u64>,
+    pub editing_template: Option<ItemTemplate>,
+    pub show_client_form: bool,
+    pub show_template_form: bool,
+    pub client_snapshot: Option<Client>,
+    pub template_snapshot: Option<ItemTemplate>,
+    /// Bill form (by editor key) and line the open template form was launched from, if any
+    pub template_source_line: Option<(u64, usize)>,
+    pub update_line_from_template: bool,
+    pub pending_discard: Option<FormKind>,
+    pub bill_error: Option<String>,
+    pub client_error: Option<String>,
+    pub template_error: Option<String>,
+    /// Payment being entered in the record-payment dialog
+    pub pending_payment: Option<Payment>,
+    /// Imported bank statement waiting for its matches to be reviewed
+    pub reconcile_report: Option<ReconcileReport>,
+    /// Detailed PDF generation error, shown in its own dialog
+    pub pdf_error: Option<String>,
+    /// Bill PDF waiting for confirmation to overwrite an existing file
+    pub pending_pdf_overwrite: Option<(u64, PathBuf)>,
+    pub settings_message: Option<String>,
+    /// Reset waiting for confirmation in its dialog
+    pub pending_settings_reset: Option<SettingsReset>,
+    /// First-run setup, shown until finished; skipping hides it until the next start
+    pub setup_wizard: Option<SetupWizard>,
+    /// Findings of the last database check, shown in Settings until dismissed
+    pub integrity_report: Option<IntegrityReport>,
+    pub selected_bills: HashSet<u64>,
+    /// One short line per bill with the actions in a menu, unless the bill is expanded
+    pub bill_list_compact: bool,
+    /// Bills showing their full row in the compact list
+    pub expanded_bills: HashSet<u64>,
+    /// Imported clients waiting for the merge-or-create decision, with the matching client's id
+    pub pending_client_import: Vec<(Client, u64)>,
+    pub selected_clients: HashSet<u64>,
+    /// Search text of the Clients tab
+    pub client_filter: String,
+    /// Selected clients waiting for the delete confirmation
+    pub pending_client_delete: Option<Vec<u64>>,
+    /// Overdue bills selected on the dashboard for recording a reminder
+    pub selected_overdue: HashSet<u64>,
+    /// Also produce reminder PDFs, bundled in a ZIP, when recording reminders
+    pub reminder_pdfs: bool,
+    pub bill_filter: BillFilter,
+    pub bill_sort: BillSortColumn,
+    pub bill_sort_ascending: bool,
+
+    // Creditor info (your business)
+    pub creditor_address: Address,
+    pub default_iban: String,
+    pub default_currency: String,
+    pub default_language: Language,
+    /// VAT rate in percent for new bill items
+    pub default_vat_rate: f64,
+    pub default_reference_type: ReferenceType,
+    /// Decimals for item quantities, except for units counted in pieces
+    pub quantity_decimals: usize,
+    pub additional_info_templates: HashMap<Language, String>,
+    pub dashboard_horizon_days: i64,
+    /// Day offsets of the due-date buttons in the bill form
+    pub due_date_presets: Vec<i64>,
+    /// Columns of the bill CSV export, in file order
+    pub csv_columns: Vec<CsvColumn>,
+    /// Custom invoice template directory; empty means `templates` next to the database
+    pub template_dir: String,
+    /// Local directory with pre-bundled Typst packages; empty to only use the package cache
+    pub package_dir: String,
+    /// Accent color of invoices as `#rrggbb`, passed to the template as `accent-color`
+    pub brand_color: String,
+    /// IANA zone dates are shown and entered in; empty follows the system
+    pub timezone: String,
+    /// Lowest id, and so invoice number, for new bills
+    pub first_bill_number: u64,
+    pub auto_generate_pdf: bool,
+    /// Accessibility tags in generated PDFs
+    pub pdf_tagged: bool,
+    /// Generated PDFs may use fonts installed on the system
+    pub pdf_system_fonts: bool,
+    /// Remove a bill's PDF file when the bill is deleted
+    pub delete_pdf_with_bill: bool,
+    /// Year preselected for "Archive year"
+    pub archive_year: i32,
+    pub smtp: SmtpSettings,
+    /// Copied on every invoice email; entries may be blank or padded while being edited
+    pub email_cc: Vec<String>,
+    /// Blind-copied on every invoice email, e.g. an archive mailbox
+    pub email_bcc: Vec<String>,
+    /// Address replies should go to when it differs from the sender
+    pub email_reply_to: Option<String>,
+
+    // Background jobs
+    pub vacuum_job: Option<Receiver<Result<(u64, u64), String>>>,
+    pdf_job_sender: Sender<(u64, Result<Bill, String>)>,
+    pdf_job_receiver: Receiver<(u64, Result<Bill, String>)>,
+    pub pdf_jobs_running: usize,
+    /// Email shown in the preview dialog, waiting for Send or Cancel
+    pub pending_email: Option<(u64, OutgoingEmail)>,
+    /// Bill whose email is being sent, and the outcome once the server answered
+    pub email_job: Option<(u64, Receiver<Result<(), String>>)>,
+    /// SMTP connection test running in the background
+    pub smtp_test_job: Option<Receiver<Result<(), String>>>,
+    /// Running or finished batch of reminder emails; cleared when its report is dismissed
+    pub reminder_batch: Option<ReminderBatch>,
+
+    /// When settings were last changed without being saved yet
+    settings_dirty_since: Option<std::time::Instant>,
+
+    /// Short-lived notification and when it was raised
+    pub toast: Option<(String, std::time::Instant)>,
+
+    /// Current content hash per bill id with the bill it was computed for, so the bill list
+    /// doesn't rehash every bill each frame. Cleared when clients or settings change.
+    content_hashes: RefCell<HashMap<u64, (Bill, String)>>,
+
+    // Database
+    pub db: Arc<Mutex<Database>>,
+}
+
+/// Reminder emails being sent one after another on a background thread
+pub struct ReminderBatch {
+    receiver: Receiver<(u64, u8, Result<(), String>)>,
+    /// Number of emails handed to the sending thread
+    pub total: usize,
+    pub sent: Vec<u64>,
+    pub failed: Vec<(u64, String)>,
+    /// Bills left out before sending, e.g. because the client has no email address
+    pub skipped: Vec<(u64, String)>,
+    pub finished: bool,
+}
+
+impl ReminderBatch {
+    pub fn report(&self) -> String {
+        let mut report = format!(
+            "Reminders sent: {}, failed: {}, skipped: {}",
+            self.sent.len(),
+            self.failed.len(),
+            self.skipped.len()
+        );
+        for (id, reason) in &self.failed {
+            report.push_str(&format!("\nFailed #{}: {}", id, reason));
+        }
+        for (id, reason) in &self.skipped {
+            report.push_str(&format!("\nSkipped #{}: {}", id, reason));
+        }
+        report
+    }
+}
+
+/// Answers collected by the first-run setup before they are saved
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetupWizard {
+    /// 0: business address, 1: payment details, 2: summary
+    pub step: usize,
+    pub creditor: Address,
+    pub iban: String,
+    pub currency: String,
+    pub language: Language,
+    pub error: Option<String>,
+}
+
+impl SetupWizard {
+    pub const STEPS: usize = 3;
+
+    /// Starts with empty business fields so no placeholder gets saved by accident
+    pub fn new(currency: &str, language: Language) -> Self {
+        Self {
+            step: 0,
+            creditor: Address::new(String::new(), Some(String::new()), Some(String::new()), String::new(), String::new(), "CH".to_string()),
+            iban: String::new(),
+            currency: currency.to_string(),
+            language,
+            error: None,
+        }
+    }
+
+    /// Problems that keep the current step from being left forwards
+    pub fn step_problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        match self.step {
+            0 => {
+                for field in self.creditor.missing_fields() {
+                    problems.push(format!("The {} is required", field));
+                }
+                if self.creditor.name.trim() == placeholder_creditor().name {
+                    problems.push("Enter your own business name".to_string());
+                }
+            }
+            1 => {
+                if !validate_iban(&self.iban) {
+                    problems.push("Enter a valid IBAN".to_string());
+                } else if self.iban.replace(' ', "") == PLACEHOLDER_IBAN.replace(' ', "") {
+                    problems.push("Enter your own IBAN, not the example one".to_string());
+                }
+                if !QR_CURRENCIES.contains(&self.currency.as_str()) {
+                    problems.push(format!("The QR bill supports {} only", QR_CURRENCIES.join(" and ")));
+                }
+            }
+            _ => {}
+        }
+        problems
+    }
+}
+
+/// Reset offered in Settings, waiting for confirmation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SettingsReset {
+    /// Replace the invoice template in the template directory with the bundled one
+    Template,
+    /// Creditor, IBAN and invoice format settings back to their defaults
+    Settings,
+}
+
+/// Identifies one of the modal edit forms
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FormKind {
+    Client,
+    /// A bill form, by its editor key
+    Bill(u64),
+    Template,
+}
+
+/// One open bill form; several can be open at once, e.g. to copy from another bill
+#[derive(Debug, Clone)]
+pub struct BillEditor {
+    /// Identifies the form and its window; new bills only get their id when saved
+    pub key: u64,
+    pub bill: Bill,
+    /// The bill as opened, to detect unsaved changes
+    pub snapshot: Bill,
+    pub attachments: Vec<Attachment>,
+    pub history: Vec<TimelineEntry>,
+    /// Search text for the bill history
+    pub history_filter: String,
+    /// Also list edits and PDF runs in the bill history
+    pub history_show_details: bool,
+    pub client_picker_query: String,
+    pub client_picker_index: usize,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Tab {
+    Dashboard,
+    Clients,
+    #[default]
+    Bills,
+    ItemTemplates,
+    Settings,
+}
+
+impl BillManagerApp {
+    pub fn new(_cc: &eframe::CreationContext<'_>) -> Self {
+        // Initialize database
+        let db = Database::new("bills.db").expect("Failed to open database");
+
+        // The zone has to be known before any dates are loaded
+        let timezone = db.get_timezone().unwrap_or(None).unwrap_or_default();
+        if let Err(e) = crate::timezone::set_zone(&timezone) {
+            eprintln!("{}, using the system time zone", e);
+        }
+        Self::with_database(db)
+    }
+
+    /// Loads data and settings from `db`
+    pub fn with_database(db: Database) -> Self {
+        let db = Arc::new(Mutex::new(db));
+        let (pdf_job_sender, pdf_job_receiver) = std::sync::mpsc::channel();
+
+        let timezone = db
+            .lock()
+            .unwrap()
+            .get_timezone()
+            .unwrap_or(None)
+            .unwrap_or_default();
+
+        // Load data from database
+        let clients = db.lock().unwrap().get_all_clients().unwrap_or_default();
+        let bills = db.lock().unwrap().get_all_bills().unwrap_or_default();
+        let item_templates = db.lock().unwrap().get_all_item_templates().unwrap_or_default();
+        let discount_templates = db.lock().unwrap().get_all_discount_templates().unwrap_or_default();
+        let bill_templates = db.lock().unwrap().get_all_bill_templates().unwrap_or_default();
+        let payments = db.lock().unwrap().get_all_payments().unwrap_or_default();
+
+        let next_client_id = db.lock().unwrap().get_next_client_id().unwrap_or(1);
+        let next_bill_id = db.lock().unwrap().get_next_bill_id().unwrap_or(1);
+        let next_template_id = db.lock().unwrap().get_next_template_id().unwrap_or(1);
+
+        let creditor_address = db
+            .lock()
+            .unwrap()
+            .get_creditor_address()
+            .unwrap_or(None)
+            .unwrap_or_else(placeholder_creditor);
+
+        // Databases from before the wizard count as set up once the business address is filled in
+        let setup_completed = db
+            .lock()
+            .unwrap()
+            .get_setup_completed()
+            .unwrap_or(None)
+            .unwrap_or(false);
+        let needs_setup = !setup_completed && creditor_address == placeholder_creditor();
+
+        let default_iban = db
+            .lock()
+            .unwrap()
+            .get_default_iban()
+            .unwrap_or(None)
+            .unwrap_or_else(|| PLACEHOLDER_IBAN.to_string());
+
+        let default_currency = db
+            .lock()
+            .unwrap()
+            .get_default_currency()
+            .unwrap_or(None)
+            .unwrap_or_else(|| DEFAULT_CURRENCY.to_string());
+
+        let default_language = db
+            .lock()
+            .unwrap()
+            .get_default_language()
+            .unwrap_or(None)
+            .unwrap_or_default();
+
+        let setup_wizard = needs_setup.then(|| SetupWizard::new(&default_currency, default_language));
+
+        let template_dir = db
+            .lock()
+            .unwrap()
+            .get_template_dir()
+            .unwrap_or(None)
+            .unwrap_or_default();
+
+        let package_dir = db
+            .lock()
+            .unwrap()
+            .get_package_dir()
+            .unwrap_or(None)
+            .unwrap_or_default();
+
+        let brand_color = db
+            .lock()
+            .unwrap()
+            .get_brand_color()
+            .unwrap_or(None)
+            .unwrap_or_else(|| crate::pdf::DEFAULT_ACCENT_COLOR.to_string());
+
+        let auto_generate_pdf = db
+            .lock()
+            .unwrap()
+            .get_auto_generate_pdf()
+            .unwrap_or(None)
+            .unwrap_or(false);
+        let (pdf_tagged, pdf_system_fonts) = db
+            .lock()
+            .unwrap()
+            .get_pdf_output_options()
+            .unwrap_or((None, None));
+
+        let first_bill_number = db
+            .lock()
+            .unwrap()
+            .get_first_bill_number()
+            .unwrap_or(None)
+            .unwrap_or(1);
+
+        let bill_list_compact = db
+            .lock()
+            .unwrap()
+            .get_bill_list_compact()
+            .unwrap_or(None)
+            .unwrap_or(false);
+
+        let delete_pdf_with_bill = db
+            .lock()
+            .unwrap()
+            .get_delete_pdf_with_bill()
+            .unwrap_or(None)
+            .unwrap_or(true);
+
+        let default_vat_rate = db
+            .lock()
+            .unwrap()
+            .get_default_vat_rate()
+            .unwrap_or(None)
+            .unwrap_or(0.0);
+
+        let default_reference_type = db
+            .lock()
+            .unwrap()
+            .get_default_reference_type()
+            .unwrap_or(None)
+            .unwrap_or_default();
+
+        let quantity_decimals = db
+            .lock()
+            .unwrap()
+            .get_quantity_decimals()
+            .unwrap_or(None)
+            .unwrap_or(2);
+
+        let smtp = db
+            .lock()
+            .unwrap()
+            .get_smtp_settings()
+            .unwrap_or(None)
+            .unwrap_or_default();
+
+        let email_cc = db.lock().unwrap().get_email_cc().unwrap_or(None).unwrap_or_default();
+        let email_bcc = db.lock().unwrap().get_email_bcc().unwrap_or(None).unwrap_or_default();
+        let email_reply_to = db.lock().unwrap().get_email_reply_to().unwrap_or(None);
+
+        let due_date_presets = db
+            .lock()
+            .unwrap()
+            .get_due_date_presets()
+            .unwrap_or(None)
+            .unwrap_or_else(|| DEFAULT_DUE_DATE_PRESETS.to_vec());
+        let csv_columns = db
+            .lock()
+            .unwrap()
+            .get_csv_columns()
+            .unwrap_or(None)
+            .unwrap_or_else(|| DEFAULT_CSV_COLUMNS.to_vec());
+
+        let dashboard_horizon_days = db
+            .lock()
+            .unwrap()
+            .get_dashboard_horizon_days()
+            .unwrap_or(None)
+            .unwrap_or(14);
+
+        let additional_info_templates = db
+            .lock()
+            .unwrap()
+            .get_additional_info_templates()
+            .unwrap_or(None)
+            .unwrap_or_default();
+
+        Self {
+            clients,
+            bills,
+            item_templates,
+            discount_templates,
+            bill_templates,
+            payments,
+            editing_discount: None,
+            discount_error: None,
+            editing_bill_template: None,
+            bill_template_error: None,
+            next_client_id,
+            next_bill_id,
+            next_template_id,
+            selected_tab: Tab::default(),
+            editing_client: None,
+            bill_editors: Vec::new(),
+            next_editor_key: 1,
+            raise_bill_editor: None,
             editing_template: None,
             show_client_form: false,
-            show_bill_form: false,
             show_template_form: false,
+            client_snapshot: None,
+            template_snapshot: None,
+            template_source_line: None,
+            update_line_from_template: true,
+            pending_discard: None,
             bill_error: None,
+            client_error: None,
+            template_error: None,
+            pending_payment: None,
+            reconcile_report: None,
+            pdf_error: None,
+            pending_pdf_overwrite: None,
+            settings_message: None,
+            pending_settings_reset: None,
+            setup_wizard,
+            integrity_report: None,
+            selected_bills: HashSet::new(),
+            bill_list_compact,
+            expanded_bills: HashSet::new(),
+            pending_client_import: Vec::new(),
+            selected_clients: HashSet::new(),
+            client_filter: String::new(),
+            pending_client_delete: None,
+            selected_overdue: HashSet::new(),
+            reminder_pdfs: false,
+            bill_filter: BillFilter::default(),
+            bill_sort: BillSortColumn::default(),
+            bill_sort_ascending: true,
             creditor_address,
             default_iban,
+            default_currency,
+            default_language,
+            default_vat_rate,
+            default_reference_type,
+            quantity_decimals,
+            additional_info_templates,
+            dashboard_horizon_days,
+            due_date_presets,
+            csv_columns,
+            template_dir,
+            package_dir,
+            brand_color,
+            timezone,
+            first_bill_number,
+            auto_generate_pdf,
+            pdf_tagged: pdf_tagged.unwrap_or(true),
+            pdf_system_fonts: pdf_system_fonts.unwrap_or(true),
+            delete_pdf_with_bill,
+            archive_year: crate::timezone::now().year() - 1,
+            smtp,
+            email_cc,
+            email_bcc,
+            email_reply_to,
+            vacuum_job: None,
+            pdf_job_sender,
+            pdf_job_receiver,
+            pdf_jobs_running: 0,
+            pending_email: None,
+            email_job: None,
+            smtp_test_job: None,
+            reminder_batch: None,
+            settings_dirty_since: None,
+            toast: None,
+            content_hashes: RefCell::new(HashMap::new()),
             db,
         }
-    }
+    }
+
+    // Form state: each form keeps a snapshot of the entity as opened, to detect unsaved changes
+
+    pub fn open_client_form(&mut self, client: Client) {
+        self.client_snapshot = Some(client.clone());
+        self.editing_client = Some(client);
+        self.show_client_form = true;
+    }
+
+    /// Opens a bill in a new form window, or raises the window already editing it
+    pub fn open_bill_form(&mut self, bill: Bill) {
+        if bill.id != 0
+            && let Some(editor) = self.bill_editors.iter().find(|e| e.bill.id == bill.id)
+        {
+            self.raise_bill_editor = Some(editor.key);
+            return;
+        }
+
+        let history = if bill.id == 0 {
+            Vec::new()
+        } else {
+            self.bill_timeline(bill.id).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                Vec::new()
+            })
+        };
+        let key = self.next_editor_key;
+        self.next_editor_key += 1;
+        self.bill_editors.push(BillEditor {
+            key,
+            attachments: self.load_attachments(bill.id),
+            history,
+            history_filter: String::new(),
+            history_show_details: false,
+            client_picker_query: String::new(),
+            client_picker_index: 0,
+            error: None,
+            snapshot: bill.clone(),
+            bill,
+        });
+    }
+
+    pub fn bill_editor_mut(&mut self, key: u64) -> Option<&mut BillEditor> {
+        self.bill_editors.iter_mut().find(|e| e.key == key)
+    }
+
+    fn load_attachments(&self, bill_id: u64) -> Vec<Attachment> {
+        if bill_id == 0 {
+            return Vec::new();
+        }
+        let db = self.db.lock().unwrap();
+        db.get_attachments(bill_id).unwrap_or_else(|e| {
+            eprintln!("Failed to load attachments: {}", e);
+            Vec::new()
+        })
+    }
+
+    /// Copies a file into the data folder and attaches it to a bill
+    pub fn add_attachment(&mut self, bill_id: u64, source: &std::path::Path) -> Result<(), String> {
+        let db = self.db.lock().unwrap();
+        let attachment = db.add_attachment(bill_id, source)
+            .map_err(|e| format!("Failed to attach {}: {}", source.display(), e))?;
+        drop(db);
+
+        for editor in self.bill_editors.iter_mut().filter(|e| e.bill.id == bill_id) {
+            editor.attachments.push(attachment.clone());
+        }
+        self.invalidate_content_hashes();
+        Ok(())
+    }
+
+    pub fn remove_attachment(&mut self, id: u64) -> Result<(), String> {
+        let db = self.db.lock().unwrap();
+        db.remove_attachment(id)
+            .map_err(|e| format!("Failed to remove attachment: {}", e))?;
+        drop(db);
+
+        for editor in &mut self.bill_editors {
+            editor.attachments.retain(|a| a.id != id);
+        }
+        self.invalidate_content_hashes();
+        Ok(())
+    }
+
+    /// Flags a PDF attachment to be appended to the bill's PDF, or clears the flag
+    pub fn set_attachment_append(&mut self, id: u64, append: bool) -> Result<(), String> {
+        let db = self.db.lock().unwrap();
+        db.set_attachment_append(id, append)
+            .map_err(|e| format!("Failed to update attachment: {}", e))?;
+        drop(db);
+
+        for attachment in self.bill_editors.iter_mut().flat_map(|e| &mut e.attachments).filter(|a| a.id == id) {
+            attachment.append_to_pdf = append;
+        }
+        self.invalidate_content_hashes();
+        Ok(())
+    }
+
+    /// Files of the PDF attachments flagged to follow the invoice, with their ids and SHA-256
+    fn appended_pdfs(&self, bill_id: u64) -> (Vec<PathBuf>, Vec<(u64, String)>) {
+        self.load_attachments(bill_id)
+            .iter()
+            .filter(|a| a.append_to_pdf && a.is_pdf())
+            .map(|a| {
+                let path = self.attachment_path(a);
+                let hash = std::fs::read(&path)
+                    .map(|data| Sha256::digest(&data).iter().map(|b| format!("{:02x}", b)).collect())
+                    .unwrap_or_default();
+                (path, (a.id, hash))
+            })
+            .unzip()
+    }
+
+    /// Absolute location of an attachment's file
+    pub fn attachment_path(&self, attachment: &Attachment) -> PathBuf {
+        self.db.lock().unwrap().data_dir().join(&attachment.path)
+    }
+
+    pub fn open_template_form(&mut self, template: ItemTemplate) {
+        self.template_snapshot = Some(template.clone());
+        self.editing_template = Some(template);
+        self.template_source_line = None;
+        self.show_template_form = true;
+    }
+
+    /// Opens the template form on an unsaved copy of a template, e.g. for a variant with
+    /// another price
+    pub fn duplicate_item_template(&mut self, template: &ItemTemplate) {
+        let taken = |name: &str| self.item_templates.iter().any(|t| t.item_type == name);
+        let mut item_type = format!("{} (copy)", template.item_type);
+        let mut n = 2;
+        while taken(&item_type) {
+            item_type = format!("{} (copy {})", template.item_type, n);
+            n += 1;
+        }
+        self.open_template_form(ItemTemplate {
+            id: 0,
+            item_type,
+            use_count: 0,
+            last_used: None,
+            ..template.clone()
+        });
+    }
+
+    /// Opens the template form on top of a bill form, remembering which bill line it came from
+    pub fn open_template_form_for_line(&mut self, template: ItemTemplate, editor_key: u64, line: usize) {
+        self.open_template_form(template);
+        self.template_source_line = Some((editor_key, line));
+        self.update_line_from_template = true;
+    }
+
+    /// Copies a saved template's type and price onto the bill line it was edited from
+    pub fn apply_template_to_source_line(&mut self, template: &ItemTemplate) {
+        if let Some((key, line)) = self.template_source_line
+            && let Some(item) = self.bill_editor_mut(key).and_then(|e| e.bill.items.get_mut(line))
+        {
+            item.item_type = template.item_type.clone();
+            item.unit_price = template.unit_price;
+            item.vat_rate = template.vat_rate;
+            item.unit = template.unit.clone();
+        }
+    }
+
+    pub fn form_has_changes(&self, kind: FormKind) -> bool {
+        match kind {
+            FormKind::Client => self.editing_client != self.client_snapshot,
+            FormKind::Bill(key) => self.bill_editors.iter().any(|e| e.key == key && e.bill != e.snapshot),
+            FormKind::Template => self.editing_template != self.template_snapshot,
+        }
+    }
+
+    /// Closes a form and drops its edits without saving
+    pub fn close_form(&mut self, kind: FormKind) {
+        match kind {
+            FormKind::Client => {
+                self.editing_client = None;
+                self.client_snapshot = None;
+                self.show_client_form = false;
+                self.client_error = None;
+            }
+            FormKind::Bill(key) => {
+                self.bill_editors.retain(|e| e.key != key);
+                if self.template_source_line.is_some_and(|(k, _)| k == key) {
+                    self.template_source_line = None;
+                }
+            }
+            FormKind::Template => {
+                self.editing_template = None;
+                self.template_snapshot = None;
+                self.template_source_line = None;
+                self.show_template_form = false;
+                self.template_error = None;
+            }
+        }
+        if self.pending_discard == Some(kind) {
+            self.pending_discard = None;
+        }
+    }
+
+    /// Closes a form, or asks for confirmation first if it has unsaved changes
+    pub fn request_close_form(&mut self, kind: FormKind) {
+        if self.form_has_changes(kind) {
+            self.pending_discard = Some(kind);
+        } else {
+            self.close_form(kind);
+        }
+    }
+
+    pub fn add_client(&mut self, mut client: Client) {
+        let db = self.db.lock().unwrap();
+        let id = db.save_client(&client).expect("Failed to save client");
+        client.id = id;
+        drop(db);
+
+        self.clients.push(client);
+        self.next_client_id = self.next_client_id.max(id + 1);
+    }
+
+    pub fn update_client(&mut self, client: Client) {
+        let db = self.db.lock().unwrap();
+        db.save_client(&client).expect("Failed to update client");
+        drop(db);
+
+        if let Some(pos) = self.clients.iter().position(|c| c.id == client.id) {
+            self.clients[pos] = client;
+        }
+        self.invalidate_content_hashes();
+    }
+
+    /// Existing client that an imported one probably duplicates: same name and postal code
+    pub fn find_matching_client(&self, client: &Client) -> Option<&Client> {
+        self.clients.iter().find(|c| {
+            c.name.trim().eq_ignore_ascii_case(client.name.trim())
+                && c.address.postal_code.trim() == client.address.postal_code.trim()
+        })
+    }
+
+    /// Updates an existing client with an imported one; empty imported fields keep the
+    /// existing values
+    pub fn merge_client(&mut self, existing_id: u64, imported: Client) {
+        let Some(existing) = self.get_client(existing_id).cloned() else {
+            return;
+        };
+        let pick = |new: String, old: String| if new.trim().is_empty() { old } else { new };
+        let merge_address = |new: Address, old: Address| Address {
+            name: pick(new.name, old.name),
+            street: new.street.filter(|s| !s.trim().is_empty()).or(old.street),
+            building_number: new.building_number.filter(|s| !s.trim().is_empty()).or(old.building_number),
+            postal_code: pick(new.postal_code, old.postal_code),
+            city: pick(new.city, old.city),
+            country: pick(new.country, old.country),
+        };
+
+        self.update_client(Client {
+            id: existing_id,
+            name: pick(imported.name, existing.name),
+            address: merge_address(imported.address, existing.address),
+            billing_address: merge_address(imported.billing_address, existing.billing_address),
+            email: pick(imported.email, existing.email),
+            phone: pick(imported.phone, existing.phone),
+        });
+    }
+
+    pub fn client_has_bills(&self, client_id: u64) -> bool {
+        self.bills.iter().any(|b| b.client_id == client_id)
+    }
+
+    pub fn client_stats(&self, client_id: u64) -> ClientStats {
+        let bills: Vec<&Bill> = self.bills.iter().filter(|b| b.client_id == client_id).collect();
+        let mut billed: std::collections::BTreeMap<String, f64> = std::collections::BTreeMap::new();
+        for bill in bills.iter().filter(|b| b.status != BillStatus::WrittenOff) {
+            *billed.entry(bill.currency.clone()).or_default() += bill.signed_total();
+        }
+        let mut outstanding: std::collections::BTreeMap<String, f64> = std::collections::BTreeMap::new();
+        for bill in bills.iter().filter(|b| b.is_outstanding()) {
+            *outstanding.entry(bill.currency.clone()).or_default() += self.outstanding_amount(bill);
+        }
+        ClientStats {
+            client_since: bills.iter().map(|b| b.date.date_naive()).min(),
+            bill_count: bills.len(),
+            billed: billed.into_iter().collect(),
+            outstanding: outstanding
+                .into_iter()
+                .map(|(c, v)| (c, round_cents(v)))
+                .filter(|(_, v)| *v > 0.0)
+                .collect(),
+        }
+    }
+
+    /// Deletes a client; clients with bills are kept so no bill loses its debtor
+    pub fn delete_client(&mut self, id: u64) -> Result<(), String> {
+        if self.client_has_bills(id) {
+            return Err("The client has bills and can't be deleted".to_string());
+        }
+        let db = self.db.lock().unwrap();
+        db.delete_client(id).expect("Failed to delete client");
+        drop(db);
+
+        self.clients.retain(|c| c.id != id);
+        self.selected_clients.remove(&id);
+        Ok(())
+    }
+
+    /// Deletes several clients at once, skipping those with bills; returns a report
+    pub fn delete_clients(&mut self, ids: &[u64]) -> Result<String, String> {
+        let (with_bills, deletable): (Vec<u64>, Vec<u64>) = ids.iter().partition(|id| self.client_has_bills(**id));
+
+        let db = self.db.lock().unwrap();
+        db.delete_clients(&deletable)
+            .map_err(|e| format!("No clients were deleted: {}", e))?;
+        drop(db);
+
+        self.clients.retain(|c| !deletable.contains(&c.id));
+        for id in ids {
+            self.selected_clients.remove(id);
+        }
+
+        let mut report = format!("Deleted {} clients", deletable.len());
+        if !with_bills.is_empty() {
+            let names: Vec<String> = with_bills
+                .iter()
+                .filter_map(|id| self.get_client(*id).map(|c| c.name.clone()))
+                .collect();
+            report.push_str(&format!(", {} kept because they have bills: {}", with_bills.len(), names.join(", ")));
+        }
+        Ok(report)
+    }
+
+    /// Adds imported clients; those matching an existing client are queued for the
+    /// merge-or-create decision instead. Returns a report.
+    pub fn import_clients(&mut self, clients: Vec<Client>) -> String {
+        let mut added = 0;
+        let mut queued = 0;
+        for client in clients {
+            match self.find_matching_client(&client).map(|c| c.id) {
+                Some(existing_id) => {
+                    self.pending_client_import.push((client, existing_id));
+                    queued += 1;
+                }
+                None => {
+                    self.add_client(client);
+                    added += 1;
+                }
+            }
+        }
+        match queued {
+            0 => format!("Imported {} clients", added),
+            _ => format!("Imported {} clients, {} match existing ones", added, queued),
+        }
+    }
+
+    /// Clients whose name, city or email contains the Clients tab search text
+    pub fn filtered_clients(&self) -> Vec<&Client> {
+        let query = self.client_filter.trim().to_lowercase();
+        self.clients
+            .iter()
+            .filter(|c| {
+                query.is_empty()
+                    || c.name.to_lowercase().contains(&query)
+                    || c.address.city.to_lowercase().contains(&query)
+                    || c.email.to_lowercase().contains(&query)
+            })
+            .collect()
+    }
+
+    /// Everything that keeps a bill from being finalized; drafts may be saved regardless
+    pub fn finalize_problems(&self, bill: &Bill) -> Vec<String> {
+        let mut problems = Vec::new();
+        if bill.client_id == 0 || self.get_client(bill.client_id).is_none() {
+            problems.push("Please select a client.".to_string());
+        }
+        if bill.items.is_empty() {
+            problems.push("Add at least one item.".to_string());
+        } else if bill.amount_mode == AmountMode::Fixed && bill.total() < 0.0 {
+            // A QR bill can't request a negative payment
+            problems.push(format!(
+                "The credit lines exceed the charges (total {:.2}). Issue a credit note instead.",
+                bill.total()
+            ));
+        }
+        if bill.is_credit_note() {
+            match bill.credited_bill_id.and_then(|id| self.bills.iter().find(|b| b.id == id)) {
+                None => problems.push("Select the invoice this credit note refunds.".to_string()),
+                Some(credited) if credited.client_id != bill.client_id => {
+                    problems.push(format!("Invoice #{} belongs to another client.", credited.id));
+                }
+                Some(_) => {}
+            }
+        }
+        if bill.iban.trim().is_empty() {
+            if bill.shows_qr_bill() {
+                problems.push("Enter the IBAN the bill is paid to.".to_string());
+            }
+        } else if !validate_iban(&bill.iban) {
+            problems.push("Invalid IBAN format. Please correct the IBAN.".to_string());
+        } else if let Err(e) = check_reference_type(bill.reference_type, &bill.iban) {
+            problems.push(e);
+        }
+        problems.extend(reference_problem(bill.reference_type, &bill.reference));
+        problems
+    }
+
+    pub fn add_bill(&mut self, mut bill: Bill) -> u64 {
+        let db = self.db.lock().unwrap();
+        let id = db.save_bill(&bill).expect("Failed to save bill");
+        bill.id = id;
+        drop(db);
+        let bill_status = bill.status;
+
+        self.warn_duplicate_reference(&bill);
+        self.bills.push(bill);
+        self.next_bill_id = self.next_bill_id.max(id + 1);
+
+        if self.auto_generate_pdf && bill_status != BillStatus::Draft {
+            self.spawn_pdf_generation(id);
+        }
+        id
+    }
+
+    pub fn update_bill(&mut self, mut bill: Bill) {
+        let db = self.db.lock().unwrap();
+        db.save_bill(&bill).expect("Failed to update bill");
+        drop(db);
+        // All columns were just written from valid values
+        bill.load_problems.clear();
+
+        let bill_id = bill.id;
+        let is_draft = bill.status == BillStatus::Draft;
+        self.warn_duplicate_reference(&bill);
+
+        // Update in-memory cache
+        if let Some(pos) = self.bills.iter().position(|b| b.id == bill.id) {
+            self.bills[pos] = bill;
+        }
+
+        if self.auto_generate_pdf && !is_draft {
+            self.spawn_pdf_generation(bill_id);
+        }
+    }
+
+    /// Other bills using the same (non-empty) reference
+    pub fn bills_with_reference(&self, reference: &str, except_id: u64) -> Vec<u64> {
+        let reference = normalized_reference(reference);
+        if reference.is_empty() {
+            return Vec::new();
+        }
+        self.bills
+            .iter()
+            .filter(|b| b.id != except_id && normalized_reference(&b.reference) == reference)
+            .map(|b| b.id)
+            .collect()
+    }
+
+    /// Bill a payment reference belongs to: the one bill storing this reference (ignoring
+    /// spaces and case) or, failing that, the one bill whose ids decode from a SCOR reference.
+    /// Ambiguous references give `None` and are left for manual review.
+    pub fn bill_for_reference(&self, reference: &str) -> Option<(&Bill, MatchConfidence)> {
+        let normalized = normalized_reference(reference);
+        if normalized.is_empty() {
+            return None;
+        }
+        let exact: Vec<&Bill> = self.bills
+            .iter()
+            .filter(|b| normalized_reference(&b.reference) == normalized)
+            .collect();
+        match exact.as_slice() {
+            [bill] => return Some((bill, MatchConfidence::Reference)),
+            [] => {}
+            _ => return None,
+        }
+
+        let encoding = ScorEncoding::decode(&normalized)?;
+        let mut candidates = self.bills.iter().filter(|b| encoding.matches(b.id, b.client_id));
+        let bill = candidates.next()?;
+        candidates.next().is_none().then_some((bill, MatchConfidence::Decoded))
+    }
+
+    /// Reference of the given scheme that no other bill uses yet. SCOR references only keep
+    /// the ids modulo 1000/10000, so a colliding candidate is regenerated with a salt.
+    pub fn unique_reference(&self, reference_type: ReferenceType, bill_id: u64, client_id: u64, year: i32) -> String {
+        let mut reference = Bill::generate_reference(reference_type, bill_id, client_id, year);
+        if reference_type != ReferenceType::Scor {
+            return reference;
+        }
+        let mut salt = 0;
+        while !self.bills_with_reference(&reference, bill_id).is_empty() {
+            salt += 1;
+            reference = Bill::generate_salted_scor_reference(bill_id, client_id, year, salt);
+        }
+        reference
+    }
+
+    /// Saving is never blocked by a duplicate reference, but it breaks bank reconciliation
+    fn warn_duplicate_reference(&mut self, bill: &Bill) {
+        let others = self.bills_with_reference(&bill.reference, bill.id);
+        if !others.is_empty() {
+            self.show_toast(format!(
+                "Bill #{} was saved, but its reference is also used by {}",
+                bill.id,
+                format_bill_ids(&others)
+            ));
+        }
+    }
+
+    pub fn delete_bill(&mut self, id: u64) {
+        let db = self.db.lock().unwrap();
+        let warning = db.delete_bill(id, self.delete_pdf_with_bill).expect("Failed to delete bill");
+        drop(db);
+
+        if let Some(warning) = warning {
+            self.show_toast(warning);
+        }
+
+        self.bills.retain(|b| b.id != id);
+        self.payments.retain(|p| p.bill_id != id);
+    }
+
+    /// Schedules the settings to be saved once editing pauses for `SETTINGS_SAVE_DELAY`
+    pub fn mark_settings_dirty(&mut self) {
+        self.settings_dirty_since = Some(std::time::Instant::now());
+        self.invalidate_content_hashes();
+    }
+
+    /// Saves pending settings changes when editing has paused, or right away with `force`.
+    /// Returns how long to wait before the next check while changes are pending.
+    pub fn flush_settings(&mut self, force: bool) -> Option<std::time::Duration> {
+        let changed_at = self.settings_dirty_since?;
+        let idle = changed_at.elapsed();
+        if force || idle >= SETTINGS_SAVE_DELAY {
+            self.save_settings();
+            self.settings_dirty_since = None;
+            None
+        } else {
+            Some(SETTINGS_SAVE_DELAY - idle)
+        }
+    }
+
+    pub fn save_settings(&self) {
+        let db = self.db.lock().unwrap();
+        db.save_creditor_address(&self.creditor_address)
+            .expect("Failed to save creditor address");
+        db.save_default_iban(&self.default_iban)
+            .expect("Failed to save default IBAN");
+        db.save_default_currency(&self.default_currency)
+            .expect("Failed to save default currency");
+        db.save_default_language(self.default_language)
+            .expect("Failed to save default language");
+        db.save_default_vat_rate(self.default_vat_rate)
+            .expect("Failed to save default VAT rate");
+        db.save_default_reference_type(self.default_reference_type)
+            .expect("Failed to save default reference type");
+        db.save_quantity_decimals(self.quantity_decimals)
+            .expect("Failed to save quantity decimals");
+        db.save_additional_info_templates(&self.additional_info_templates)
+            .expect("Failed to save additional info templates");
+        db.save_dashboard_horizon_days(self.dashboard_horizon_days)
+            .expect("Failed to save dashboard horizon");
+        db.save_due_date_presets(&self.due_date_presets)
+            .expect("Failed to save due date presets");
+        db.save_csv_columns(&self.csv_columns)
+            .expect("Failed to save CSV columns");
+        db.save_template_dir(&self.template_dir)
+            .expect("Failed to save template directory");
+        db.save_package_dir(&self.package_dir)
+            .expect("Failed to save package directory");
+        db.save_brand_color(&self.brand_color)
+            .expect("Failed to save brand color");
+        db.save_timezone(&self.timezone)
+            .expect("Failed to save time zone");
+        db.save_auto_generate_pdf(self.auto_generate_pdf)
+            .expect("Failed to save auto-generate setting");
+        db.save_pdf_output_options(self.pdf_tagged, self.pdf_system_fonts)
+            .expect("Failed to save PDF output options");
+        db.save_first_bill_number(self.first_bill_number)
+            .expect("Failed to save first bill number");
+        db.save_bill_list_compact(self.bill_list_compact)
+            .expect("Failed to save bill list view");
+        db.save_delete_pdf_with_bill(self.delete_pdf_with_bill)
+            .expect("Failed to save PDF deletion setting");
+        db.save_smtp_settings(&self.smtp)
+            .expect("Failed to save SMTP settings");
+        db.save_email_cc(&self.email_cc)
+            .expect("Failed to save email CC");
+        db.save_email_bcc(&self.email_bcc)
+            .expect("Failed to save email BCC");
+        db.save_email_reply_to(self.email_reply_to.as_deref())
+            .expect("Failed to save email reply-to");
+    }
+
+    /// Takes over the answers of the first-run setup and saves them right away
+    pub fn complete_setup(&mut self, wizard: &SetupWizard) -> Result<(), String> {
+        self.creditor_address = wizard.creditor.clone();
+        self.default_iban = wizard.iban.trim().to_string();
+        self.default_currency = wizard.currency.clone();
+        self.default_language = wizard.language;
+        self.save_settings();
+        self.settings_dirty_since = None;
+        self.invalidate_content_hashes();
+        let db = self.db.lock().unwrap();
+        db.save_setup_completed(true)
+            .map_err(|e| format!("Failed to save the setup: {}", e))
+    }
+
+    /// Writes the bundled invoice template into the template directory, replacing a broken
+    /// or outdated one
+    pub fn restore_default_template(&self) -> Result<PathBuf, String> {
+        crate::pdf::write_default_template(&self.resolved_template_dir())
+    }
+
+    /// Puts creditor, IBAN and invoice format settings back to their defaults.
+    /// Email, folders and PDF options are left alone.
+    pub fn reset_settings(&mut self) {
+        self.creditor_address = placeholder_creditor();
+        self.default_iban = PLACEHOLDER_IBAN.to_string();
+        self.default_currency = DEFAULT_CURRENCY.to_string();
+        self.default_language = Language::default();
+        self.default_vat_rate = 0.0;
+        self.default_reference_type = ReferenceType::default();
+        self.quantity_decimals = 2;
+        self.additional_info_templates.clear();
+        self.due_date_presets = DEFAULT_DUE_DATE_PRESETS.to_vec();
+        self.brand_color = crate::pdf::DEFAULT_ACCENT_COLOR.to_string();
+        self.save_settings();
+        self.settings_dirty_since = None;
+        self.invalidate_content_hashes();
+    }
+
+    /// Returns the configured additional-info template for a language, or its default
+    pub fn additional_info_template(&self, language: Language) -> &str {
+        self.additional_info_templates
+            .get(&language)
+            .filter(|t| !t.trim().is_empty())
+            .map(|t| t.as_str())
+            .unwrap_or_else(|| language.default_additional_info())
+    }
+
+    pub fn recent_audit_events(&self, limit: i64) -> Result<Vec<AuditEvent>, String> {
+        let db = self.db.lock().unwrap();
+        db.get_recent_audit_events(limit)
+            .map_err(|e| format!("Failed to load audit log: {}", e))
+    }
+
+    /// What happened to a bill and when: creation, status changes, reminders and payments,
+    /// oldest first
+    pub fn bill_timeline(&self, bill_id: u64) -> Result<Vec<TimelineEntry>, String> {
+        let db = self.db.lock().unwrap();
+        let events = db.get_audit_events_for("bill", bill_id)
+            .map_err(|e| format!("Failed to load bill history: {}", e))?;
+        drop(db);
+
+        let detail_value = |details: &Option<String>, key: &str| {
+            details.as_deref()
+                .and_then(|d| serde_json::from_str::<serde_json::Value>(d).ok())
+                .and_then(|v| v.get(key).cloned())
+        };
+
+        let mut entries: Vec<TimelineEntry> = events.into_iter().filter_map(|event| {
+            let at = event.timestamp?;
+            let (label, is_detail) = match event.action.as_str() {
+                "create" => ("Created".to_string(), false),
+                "import" => ("Imported".to_string(), false),
+                "status" => {
+                    let status = detail_value(&event.details, "status")
+                        .and_then(|v| v.as_str().and_then(BillStatus::parse));
+                    match status {
+                        Some(status) => (format!("Marked as {}", status), false),
+                        None => ("Status changed".to_string(), false),
+                    }
+                }
+                "reminder" => {
+                    let level = detail_value(&event.details, "level").and_then(|v| v.as_u64()).unwrap_or(0);
+                    if level >= FINAL_REMINDER_LEVEL as u64 {
+                        ("Final reminder sent".to_string(), false)
+                    } else {
+                        (format!("Reminder {} sent", level), false)
+                    }
+                }
+                "archive" => ("Archived".to_string(), false),
+                "reassign" => ("Moved to another client".to_string(), false),
+                "pdf" => ("PDF generated".to_string(), true),
+                "update" => {
+                    let fields = event.details.as_deref()
+                        .and_then(|d| serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(d).ok())
+                        .map(|diff| diff.keys().cloned().collect::<Vec<_>>().join(", "))
+                        .unwrap_or_default();
+                    if fields.is_empty() {
+                        ("Edited".to_string(), true)
+                    } else {
+                        (format!("Edited {}", fields), true)
+                    }
+                }
+                other => (other.to_string(), true),
+            };
+            Some(TimelineEntry { at, label, is_detail })
+        }).collect();
+
+        let currency = self.bills.iter()
+            .find(|b| b.id == bill_id)
+            .map(|b| b.currency.clone())
+            .unwrap_or_default();
+        let tz = self.zone();
+        for payment in self.payments.iter().filter(|p| p.bill_id == bill_id) {
+            let Some(at) = payment.paid_on.and_time(chrono::NaiveTime::MIN).and_local_timezone(tz).earliest() else {
+                continue;
+            };
+            let mut label = format!("Payment of {} {:.2} received", currency, payment.amount);
+            if !payment.note.is_empty() {
+                label.push_str(&format!(" ({})", payment.note));
+            }
+            entries.push(TimelineEntry { at, label, is_detail: false });
+        }
+
+        entries.sort_by_key(|e| e.at);
+        Ok(entries)
+    }
+
+    /// Where the database, PDFs and templates live, made absolute for display
+    pub fn data_locations(&self) -> Vec<(&'static str, PathBuf)> {
+        let db = self.db.lock().unwrap();
+        let locations = vec![
+            ("Data folder", db.data_dir()),
+            ("Database", db.path().to_path_buf()),
+            ("PDFs", db.pdf_dir()),
+        ];
+        drop(db);
+
+        locations
+            .into_iter()
+            .chain(std::iter::once(("Templates", self.resolved_template_dir())))
+            .map(|(label, path)| (label, std::path::absolute(&path).unwrap_or(path)))
+            .collect()
+    }
+
+    /// Directory the invoice template is read from
+    pub fn resolved_template_dir(&self) -> PathBuf {
+        if self.template_dir.trim().is_empty() {
+            self.db.lock().unwrap().data_dir().join("templates")
+        } else {
+            PathBuf::from(self.template_dir.trim())
+        }
+    }
+
+    pub fn invoice_options(&self, bill: &Bill) -> InvoiceOptions {
+        InvoiceOptions {
+            additional_info_template: self.additional_info_template(bill.language).to_string(),
+            template_dir: self.resolved_template_dir(),
+            accent_color: self.brand_color.clone(),
+            package_dir: Some(self.package_dir.trim())
+                .filter(|dir| !dir.is_empty())
+                .map(PathBuf::from),
+            appended_pdfs: Vec::new(),
+            appended_hashes: Vec::new(),
+            reminder_level: 0,
+            tagged_pdf: self.pdf_tagged,
+            system_fonts: self.pdf_system_fonts,
+            debug_source: std::env::var_os(crate::pdf::DEBUG_SOURCE_ENV)
+                .map(|_| self.db.lock().unwrap().data_dir().join("typst-debug.typ")),
+        }
+    }
+
+    pub fn get_client(&self, id: u64) -> Option<&Client> {
+        self.clients.iter().find(|c| c.id == id)
+    }
+
+    /// Outstanding bills due within the next `horizon_days`, soonest first
+    pub fn upcoming_due_bills(&self, horizon_days: i64) -> Vec<&Bill> {
+        let mut bills: Vec<&Bill> = self.bills
+            .iter()
+            .filter(|b| self.has_balance(b) && (0..=horizon_days).contains(&b.days_until_due()))
+            .collect();
+        bills.sort_by_key(|b| b.due_date);
+        bills
+    }
+
+    /// Totals per currency of the bills matching `filter`, sorted by currency
+    pub fn totals_by_currency(&self, filter: impl Fn(&Bill) -> bool) -> Vec<(String, f64)> {
+        let mut totals: std::collections::BTreeMap<String, f64> = std::collections::BTreeMap::new();
+        for bill in self.bills.iter().filter(|b| filter(b)) {
+            *totals.entry(bill.currency.clone()).or_default() += bill.signed_total();
+        }
+        totals.into_iter().collect()
+    }
+
+    /// Outstanding balances of the given bills, e.g. the filtered Bills tab
+    pub fn outstanding_summary(&self, bills: &[Bill]) -> OutstandingSummary {
+        let mut outstanding: std::collections::BTreeMap<String, f64> = std::collections::BTreeMap::new();
+        let mut overdue: std::collections::BTreeMap<String, f64> = std::collections::BTreeMap::new();
+        let mut summary = OutstandingSummary::default();
+        for bill in bills.iter().filter(|b| self.has_balance(b)) {
+            let balance = self.outstanding_amount(bill);
+            summary.count += 1;
+            *outstanding.entry(bill.currency.clone()).or_default() += balance;
+            if bill.days_until_due() < 0 {
+                summary.overdue_count += 1;
+                *overdue.entry(bill.currency.clone()).or_default() += balance;
+            }
+        }
+        summary.outstanding = outstanding.into_iter().map(|(c, v)| (c, round_cents(v))).collect();
+        summary.overdue = overdue.into_iter().map(|(c, v)| (c, round_cents(v))).collect();
+        summary
+    }
+
+    /// Bills whose follow-up date is today or past, earliest first
+    pub fn follow_up_bills(&self) -> Vec<&Bill> {
+        let today = crate::timezone::now().date_naive();
+        let mut bills: Vec<&Bill> = self.bills
+            .iter()
+            .filter(|b| b.follow_up.is_some_and(|f| f.date_naive() <= today))
+            .collect();
+        bills.sort_by_key(|b| b.follow_up);
+        bills
+    }
+
+    /// Outstanding bills past their due date, longest overdue first
+    pub fn overdue_bills(&self) -> Vec<&Bill> {
+        let mut bills: Vec<&Bill> = self.bills
+            .iter()
+            .filter(|b| self.has_balance(b) && b.days_until_due() < 0)
+            .collect();
+        bills.sort_by_key(|b| b.due_date);
+        bills
+    }
+
+    pub fn get_bills(&self) -> Result<Vec<Bill>, String> {
+        let db = self.db.lock().unwrap();
+        db.get_all_bills()
+            .map_err(|e| format!("Failed to fetch bills: {}", e))
+    }
+
+    /// The client's most recent bill by date (the highest id on the same date), if any
+    pub fn last_bill_for_client(&self, client_id: u64) -> Option<&Bill> {
+        self.bills
+            .iter()
+            .filter(|b| b.client_id == client_id)
+            .max_by_key(|b| (b.date, b.id))
+    }
+
+    /// Bills matching the Bills tab filter, in the list's sort order
+    pub fn filtered_bills(&self) -> Result<Vec<Bill>, String> {
+        let client_name = |bill: &Bill| {
+            self.get_client(bill.client_id)
+                .map(|c| c.name.as_str())
+                .unwrap_or("")
+        };
+
+        let mut bills: Vec<Bill> = self.get_bills()?
+            .into_iter()
+            .filter(|bill| self.bill_filter.matches(bill, client_name(bill)))
+            .collect();
+
+        bills.sort_by(|a, b| {
+            let ordering = match self.bill_sort {
+                BillSortColumn::Id => a.id.cmp(&b.id),
+                BillSortColumn::Client => client_name(a).to_lowercase().cmp(&client_name(b).to_lowercase()),
+                BillSortColumn::Date => a.date.cmp(&b.date),
+                BillSortColumn::Due => a.due_date.cmp(&b.due_date),
+                BillSortColumn::Total => a.signed_total().total_cmp(&b.signed_total()),
+                BillSortColumn::Status => a.status.sort_key().cmp(&b.status.sort_key()),
+            };
+            // Ties keep a stable order by bill number
+            let ordering = ordering.then_with(|| a.id.cmp(&b.id));
+            if self.bill_sort_ascending { ordering } else { ordering.reverse() }
+        });
+
+        Ok(bills)
+    }
+
+    /// Sorts the bill list by a column, toggling the direction if it's already sorted by it
+    pub fn sort_bills_by(&mut self, column: BillSortColumn) {
+        if self.bill_sort == column {
+            self.bill_sort_ascending = !self.bill_sort_ascending;
+        } else {
+            self.bill_sort = column;
+            self.bill_sort_ascending = true;
+        }
+    }
+
+    pub fn add_item_template(&mut self, mut template: ItemTemplate) {
+        let db = self.db.lock().unwrap();
+        let id = db.save_item_template(&template).expect("Failed to save template");
+        template.id = id;
+        drop(db);
+
+        self.item_templates.push(template);
+        self.next_template_id = self.next_template_id.max(id + 1);
+    }
+
+    pub fn update_item_template(&mut self, mut template: ItemTemplate) {
+        let db = self.db.lock().unwrap();
+        db.save_item_template(&template).expect("Failed to update template");
+        drop(db);
+
+        if let Some(pos) = self.item_templates.iter().position(|t| t.id == template.id) {
+            // Usage stats are tracked separately and may have moved on while the form was open
+            template.use_count = self.item_templates[pos].use_count;
+            template.last_used = self.item_templates[pos].last_used;
+            self.item_templates[pos] = template;
+        }
+    }
+
+    /// Adds or updates a discount template
+    pub fn save_discount_template(&mut self, template: DiscountTemplate) -> Result<(), String> {
+        if template.name.trim().is_empty() {
+            return Err("Discount name is required".to_string());
+        }
+        let duplicate = self.discount_templates
+            .iter()
+            .any(|t| t.id != template.id && t.name.eq_ignore_ascii_case(template.name.trim()));
+        if duplicate {
+            return Err(format!("A discount named '{}' already exists", template.name.trim()));
+        }
+
+        let template = DiscountTemplate {
+            name: template.name.trim().to_string(),
+            ..template
+        };
+        let db = self.db.lock().unwrap();
+        let id = db.save_discount_template(&template)
+            .map_err(|e| format!("Failed to save discount: {}", e))?;
+        drop(db);
+
+        match self.discount_templates.iter_mut().find(|t| t.id == id) {
+            Some(existing) => *existing = template,
+            None => self.discount_templates.push(DiscountTemplate { id, ..template }),
+        }
+        self.discount_templates.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(())
+    }
+
+    pub fn delete_discount_template(&mut self, id: u64) {
+        let db = self.db.lock().unwrap();
+        db.delete_discount_template(id).expect("Failed to delete discount template");
+        drop(db);
+
+        self.discount_templates.retain(|t| t.id != id);
+    }
+
+    /// Adds or updates a bill template
+    pub fn save_bill_template(&mut self, template: BillTemplate) -> Result<(), String> {
+        if template.name.trim().is_empty() {
+            return Err("Template name is required".to_string());
+        }
+        let duplicate = self.bill_templates
+            .iter()
+            .any(|t| t.id != template.id && t.name.eq_ignore_ascii_case(template.name.trim()));
+        if duplicate {
+            return Err(format!("A bill template named '{}' already exists", template.name.trim()));
+        }
+
+        let template = BillTemplate {
+            name: template.name.trim().to_string(),
+            ..template
+        };
+        let db = self.db.lock().unwrap();
+        let id = db.save_bill_template(&template)
+            .map_err(|e| format!("Failed to save bill template: {}", e))?;
+        drop(db);
+
+        match self.bill_templates.iter_mut().find(|t| t.id == id) {
+            Some(existing) => *existing = template,
+            None => self.bill_templates.push(BillTemplate { id, ..template }),
+        }
+        self.bill_templates.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(())
+    }
+
+    pub fn delete_bill_template(&mut self, id: u64) {
+        let db = self.db.lock().unwrap();
+        db.delete_bill_template(id).expect("Failed to delete bill template");
+        drop(db);
+
+        self.bill_templates.retain(|t| t.id != id);
+    }
+
+    /// Skeleton of a bill's lines, notes and payment term, to be named and saved
+    pub fn bill_template_from(bill: &Bill) -> BillTemplate {
+        BillTemplate {
+            items: bill.items.clone(),
+            notes: bill.notes.clone(),
+            payment_days: (bill.due_date.date_naive() - bill.date.date_naive()).num_days().max(0),
+            ..BillTemplate::default()
+        }
+    }
+
+    /// Empty draft bill with the default account, reference, currency and language
+    pub fn new_bill(&self) -> Bill {
+        // Generate the reference with next bill ID (temporary, will be updated on save)
+        let year = crate::timezone::now().year();
+        Bill {
+            iban: self.default_iban.clone(),
+            reference_type: self.default_reference_type,
+            reference: self.unique_reference(self.default_reference_type, self.next_bill_id, 0, year),
+            currency: self.default_currency.clone(),
+            language: self.default_language,
+            items: vec![BillItem::with_vat_rate(self.default_vat_rate)],
+            ..Bill::default()
+        }
+    }
+
+    /// New draft bill dated today with the template's lines, notes and payment term
+    pub fn bill_from_template(&self, template: &BillTemplate) -> Bill {
+        let bill = self.new_bill();
+        Bill {
+            items: if template.items.is_empty() { bill.items.clone() } else { template.items.clone() },
+            notes: template.notes.clone(),
+            due_date: bill.date + chrono::Duration::days(template.payment_days),
+            ..bill
+        }
+    }
+
+    /// Draft credit note refunding the whole invoice; lines can be removed for a partial refund
+    pub fn credit_note_for(&self, invoice: &Bill) -> Bill {
+        let bill = self.new_bill();
+        Bill {
+            client_id: invoice.client_id,
+            document_type: DocumentType::CreditNote,
+            credited_bill_id: Some(invoice.id),
+            items: invoice.items.clone(),
+            currency: invoice.currency.clone(),
+            language: invoice.language,
+            prices_include_vat: invoice.prices_include_vat,
+            discount: invoice.discount.clone(),
+            round_to_five_cents: invoice.round_to_five_cents,
+            include_qr_bill: false,
+            reference_type: ReferenceType::None,
+            reference: String::new(),
+            ..bill
+        }
+    }
+
+    /// Counts a template as used when one of its lines is added to a bill
+    pub fn record_template_use(&mut self, id: u64) {
+        let now = crate::timezone::now();
+        let db = self.db.lock().unwrap();
+        if let Err(e) = db.record_item_template_use(id, &now) {
+            eprintln!("Failed to record template use: {}", e);
+            return;
+        }
+        drop(db);
+
+        if let Some(template) = self.item_templates.iter_mut().find(|t| t.id == id) {
+            template.use_count += 1;
+            template.last_used = Some(now);
+        }
+    }
+
+    /// Templates for the bill form pickers, most used first and alphabetical otherwise
+    pub fn templates_by_popularity(&self) -> Vec<ItemTemplate> {
+        let mut templates = self.item_templates.clone();
+        templates.sort_by(|a, b| {
+            b.use_count.cmp(&a.use_count)
+                .then_with(|| a.item_type.to_lowercase().cmp(&b.item_type.to_lowercase()))
+        });
+        templates
+    }
+
+    pub fn delete_item_template(&mut self, id: u64) {
+        let db = self.db.lock().unwrap();
+        db.delete_item_template(id).expect("Failed to delete template");
+        drop(db);
+
+        self.item_templates.retain(|t| t.id != id);
+    }
+
+    /// Generates the PDF for a bill; same path as `regenerate_pdf`
+    pub fn generate_pdf(&mut self, bill_id: u64) -> Result<(), String> {
+        self.regenerate_pdf(bill_id)
+    }
+
+    /// Renders a fresh PDF and replaces the stored one, its timestamp and content hash atomically.
+    /// The previous PDF is only replaced once rendering succeeded, so a failure leaves it intact.
+    pub fn regenerate_pdf(&mut self, bill_id: u64) -> Result<(), String> {
+        let job = self.prepare_pdf_job(bill_id)?;
+        let db = self.db.lock().unwrap().clone();
+        let saved = job.run(&db)?;
+        self.apply_saved_pdf(saved);
+        Ok(())
+    }
+
+    /// Renders an invoice for a made-up client with the current creditor, template and
+    /// packages, to check the setup without creating a bill. Returns the temporary PDF file.
+    pub fn generate_sample_pdf(&self) -> Result<PathBuf, String> {
+        let address = Address::new(
+            "Muster AG".to_string(),
+            Some("Musterstrasse".to_string()),
+            Some("12a".to_string()),
+            "8000".to_string(),
+            "Zürich".to_string(),
+            "CH".to_string(),
+        );
+        let client = Client {
+            id: 0,
+            name: "Muster AG".to_string(),
+            address: address.clone(),
+            billing_address: address,
+            email: "buchhaltung@muster.example".to_string(),
+            phone: String::new(),
+        };
+
+        // The configured IBAN shows whether it is accepted; otherwise use a well-known example
+        let (iban, reference_type) = if validate_iban(&self.default_iban) {
+            (self.default_iban.clone(), self.default_reference_type)
+        } else {
+            ("CH9300762011623852957".to_string(), ReferenceType::Scor)
+        };
+
+        let bill = Bill {
+            id: 1,
+            items: vec![
+                BillItem {
+                    item_type: "Beratung".to_string(),
+                    quantity: 4.5,
+                    unit_price: 150.0,
+                    note: "Workshop & Auswertung".to_string(),
+                    ..BillItem::with_vat_rate(self.default_vat_rate)
+                },
+                BillItem {
+                    item_type: "Spesen".to_string(),
+                    quantity: 1.0,
+                    unit_price: 42.8,
+                    note: "Reise Zürich–Bern".to_string(),
+                    ..BillItem::with_vat_rate(self.default_vat_rate)
+                },
+            ],
+            reference: Bill::generate_reference(reference_type, 1, 1, crate::timezone::now().year()),
+            reference_type,
+            iban,
+            notes: "Sample invoice generated from **Settings**.\n- Check logo, fonts and addresses\n- Scan the QR code".to_string(),
+            ..Bill::default()
+        };
+
+        validate_pdf_parties(&bill, &client, &self.creditor_address)?;
+
+        let pdf = crate::pdf::generate_bill_pdf(&bill, &client, &self.creditor_address, &self.invoice_options(&bill))?;
+
+        let path = std::env::temp_dir().join("bill-manager-sample-invoice.pdf");
+        std::fs::write(&path, pdf)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        Ok(path)
+    }
+
+    /// Records the next reminder for each of the given bills, without changing their status.
+    /// With `zip_path`, reminder PDFs are rendered first and bundled into that ZIP file; bills
+    /// whose PDF fails are not recorded. Returns a report including the skipped bills.
+    pub fn record_reminders(&mut self, ids: &[u64], zip_path: Option<&std::path::Path>) -> Result<String, String> {
+        let mut reminders: Vec<(u64, u8)> = Vec::new();
+        let mut skipped: Vec<String> = Vec::new();
+        let mut pdfs: Vec<(String, Vec<u8>)> = Vec::new();
+
+        for &id in ids {
+            let Some(bill) = self.bills.iter().find(|b| b.id == id) else {
+                skipped.push(format!("#{}: not found", id));
+                continue;
+            };
+            if bill.reminder_level >= FINAL_REMINDER_LEVEL {
+                skipped.push(format!("#{}: already at final notice", id));
+                continue;
+            }
+            let level = bill.reminder_level + 1;
+
+            if zip_path.is_some() {
+                match self.reminder_pdf(id, level) {
+                    Ok(pdf) => pdfs.push(pdf),
+                    Err(e) => {
+                        skipped.push(format!("#{}: PDF failed: {}", id, e.lines().next().unwrap_or_default()));
+                        continue;
+                    }
+                }
+            }
+            reminders.push((id, level));
+        }
+
+        if let Some(path) = zip_path
+            && !pdfs.is_empty()
+        {
+            crate::export::write_zip(path, &pdfs)?;
+        }
+
+        let now = crate::timezone::now();
+        let db = self.db.lock().unwrap();
+        db.record_reminders(&reminders, &now)
+            .map_err(|e| format!("Failed to record reminders: {}", e))?;
+        drop(db);
+
+        for (id, level) in &reminders {
+            if let Some(bill) = self.bills.iter_mut().find(|b| b.id == *id) {
+                bill.reminder_level = *level;
+                bill.last_reminder_at = Some(now);
+            }
+            self.selected_overdue.remove(id);
+        }
+
+        let mut report = format!("Recorded a reminder for {} bill(s)", reminders.len());
+        if let Some(path) = zip_path
+            && !pdfs.is_empty()
+        {
+            report.push_str(&format!(", PDFs saved to {}", path.display()));
+        }
+        if !skipped.is_empty() {
+            report.push_str(&format!("\nSkipped:\n{}", skipped.join("\n")));
+        }
+        Ok(report)
+    }
+
+    /// Renders a reminder of the given level for a bill, with its file name
+    fn reminder_pdf(&self, bill_id: u64, level: u8) -> Result<(String, Vec<u8>), String> {
+        let mut job = self.prepare_pdf_job(bill_id)?;
+        job.options.reminder_level = level;
+        let pdf = crate::pdf::generate_bill_pdf(&job.bill, &job.client, &job.creditor, &job.options)?;
+        Ok((format!("reminder_{}_{}_{}.pdf", level, sanitize_filename(&job.client.name), bill_id), pdf))
+    }
+
+    /// Collects and validates everything needed to render a bill's PDF
+    fn prepare_pdf_job(&self, bill_id: u64) -> Result<PdfJob, String> {
+        // Fetch bill from database
+        let db = self.db.lock().unwrap();
+        let bill = db.get_bill_by_id(bill_id)
+            .map_err(|e| format!("Database error: {}", e))?
+            .ok_or_else(|| "Bill not found".to_string())?;
+        drop(db);
+
+        if bill.status == BillStatus::Draft {
+            return Err(format!("Bill #{} is still a draft. Finalize it before generating its PDF.", bill.id));
+        }
+
+        let client = self.get_client(bill.client_id)
+            .ok_or_else(|| "Client not found".to_string())?;
+
+        // An invoice from "Your Company Name" would bill from a company that does not exist
+        let placeholders = placeholder_creditor_fields(&self.creditor_address, &bill.iban);
+        if !placeholders.is_empty() {
+            return Err(format!(
+                "Example values are still set for: {}. Enter your own details in Settings → \
+                 Your Business Information, and the bill's IBAN, before generating invoices.",
+                placeholders.join(", ")
+            ));
+        }
+
+        validate_pdf_parties(&bill, client, &self.creditor_address)?;
+        if bill.shows_qr_bill() {
+            validate_qr_bill(&bill, client, &self.creditor_address)
+                .map_err(|problems| format!("The bill violates QR-bill rules:\n{}", problems.join("\n")))?;
+        }
+
+        let (appended_pdfs, appended_hashes) = self.appended_pdfs(bill.id);
+
+        Ok(PdfJob {
+            options: InvoiceOptions {
+                appended_pdfs,
+                appended_hashes,
+                ..self.invoice_options(&bill)
+            },
+            client: client.clone(),
+            creditor: self.creditor_address.clone(),
+            bill,
+        })
+    }
+
+    /// Updates the cached bill with the PDF metadata of a freshly saved PDF
+    fn apply_saved_pdf(&mut self, saved: Bill) {
+        if let Some(bill) = self.bills.iter_mut().find(|b| b.id == saved.id) {
+            bill.has_pdf = saved.has_pdf;
+            bill.pdf_created_at = saved.pdf_created_at;
+            bill.pdf_hash = saved.pdf_hash;
+        }
+    }
+
+    /// Renders a bill's PDF on a background thread; the result is picked up by `poll_pdf_jobs`
+    pub fn spawn_pdf_generation(&mut self, bill_id: u64) {
+        let sender = self.pdf_job_sender.clone();
+        self.pdf_jobs_running += 1;
+        let job = match self.prepare_pdf_job(bill_id) {
+            Ok(job) => job,
+            Err(e) => {
+                sender.send((bill_id, Err(e))).ok();
+                return;
+            }
+        };
+
+        let db = self.db.lock().unwrap().clone();
+        std::thread::spawn(move || {
+            sender.send((bill_id, job.run(&db))).ok();
+        });
+    }
+
+    /// Applies finished background PDF generations; failures are shown as a toast
+    pub fn poll_pdf_jobs(&mut self) {
+        while let Ok((bill_id, result)) = self.pdf_job_receiver.try_recv() {
+            self.pdf_jobs_running = self.pdf_jobs_running.saturating_sub(1);
+            match result {
+                Ok(saved) => self.apply_saved_pdf(saved),
+                Err(e) => self.show_toast(format!("Bill #{} was saved, but its PDF could not be generated:\n{}", bill_id, e)),
+            }
+        }
+    }
+
+    /// Resolves the invoice email of a bill: recipients, texts and a current PDF, which is
+    /// regenerated first if missing or outdated
+    /// Invoice email with the recipients resolved and validated; subject, body and attachment
+    /// are left for the caller to fill in
+    fn address_email(&self, client: &Client) -> Result<OutgoingEmail, String> {
+        if client.email.trim().is_empty() {
+            return Err(format!("{} has no email address", client.name));
+        }
+        if !validate_email(&client.email) {
+            return Err(format!("{}'s email address '{}' is invalid", client.name, client.email));
+        }
+        let cc = crate::email::address_list(&self.email_cc);
+        let bcc = crate::email::address_list(&self.email_bcc);
+        let reply_to = self.email_reply_to.as_deref()
+            .map(str::trim)
+            .filter(|r| !r.is_empty())
+            .map(str::to_string);
+        for address in cc.iter().chain(&bcc).chain(&reply_to) {
+            if !validate_email(address) {
+                return Err(format!("Invalid address '{}' in the email settings", address));
+            }
+        }
+
+        Ok(OutgoingEmail {
+            to: client.email.trim().to_string(),
+            cc,
+            bcc,
+            reply_to,
+            subject: String::new(),
+            body: String::new(),
+            attachment_name: String::new(),
+            attachment: Vec::new(),
+        })
+    }
+
+    pub fn prepare_bill_email(&mut self, bill_id: u64) -> Result<OutgoingEmail, String> {
+        let bill = self.bills.iter()
+            .find(|b| b.id == bill_id)
+            .cloned()
+            .ok_or_else(|| "Bill not found".to_string())?;
+        let client = self.get_client(bill.client_id)
+            .cloned()
+            .ok_or_else(|| "Client not found".to_string())?;
+        let email = self.address_email(&client)?;
+
+        if !bill.has_pdf || self.is_pdf_stale(&bill) {
+            self.regenerate_pdf(bill_id)?;
+        }
+        let db = self.db.lock().unwrap();
+        let attachment = db.load_bill_pdf(bill_id)
+            .map_err(|e| format!("Failed to load PDF: {}", e))?
+            .ok_or_else(|| "The bill has no PDF".to_string())?;
+        drop(db);
+
+        Ok(OutgoingEmail {
+            subject: render_email_text(&self.smtp.subject, &bill, &client, &self.creditor_address),
+            body: render_email_text(&self.smtp.body, &bill, &client, &self.creditor_address),
+            attachment_name: format!(
+                "invoice_{}_{}.pdf",
+                sanitize_filename(&client.name),
+                sanitize_filename(&bill.reference)
+            ),
+            attachment,
+            ..email
+        })
+    }
+
+    /// Email with the next reminder of an overdue bill attached, and that reminder's level
+    pub fn prepare_reminder_email(&self, bill_id: u64) -> Result<(u8, OutgoingEmail), String> {
+        let bill = self.bills.iter()
+            .find(|b| b.id == bill_id)
+            .ok_or_else(|| "Bill not found".to_string())?;
+        let client = self.get_client(bill.client_id)
+            .ok_or_else(|| "Client not found".to_string())?;
+        if bill.reminder_level >= FINAL_REMINDER_LEVEL {
+            return Err("already at final notice".to_string());
+        }
+        let email = self.address_email(client)?;
+        let level = bill.reminder_level + 1;
+        let (attachment_name, attachment) = self.reminder_pdf(bill_id, level)?;
+
+        Ok((level, OutgoingEmail {
+            subject: render_email_text(&self.smtp.reminder_subject, bill, client, &self.creditor_address),
+            body: render_email_text(&self.smtp.reminder_body, bill, client, &self.creditor_address),
+            attachment_name,
+            attachment,
+            ..email
+        }))
+    }
+
+    /// Prepares a bill's email and opens its preview; nothing is sent before
+    /// `send_pending_email`
+    pub fn email_bill(&mut self, bill_id: u64) -> Result<(), String> {
+        if self.email_job.is_some() || self.reminder_batch.as_ref().is_some_and(|b| !b.finished) {
+            return Err("Another email is still being sent".to_string());
+        }
+        let email = self.prepare_bill_email(bill_id)?;
+        self.pending_email = Some((bill_id, email));
+        Ok(())
+    }
+
+    /// Sends the previewed email, with any edits made in the preview, on a background thread;
+    /// the result is picked up by `poll_email_job`
+    pub fn send_pending_email(&mut self) {
+        if let Some((bill_id, email)) = self.pending_email.take() {
+            self.send_email(bill_id, email);
+        }
+    }
+
+    fn send_email(&mut self, bill_id: u64, email: OutgoingEmail) {
+        let smtp = self.smtp.clone();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            sender.send(crate::email::send(&smtp, &email)).ok();
+        });
+        self.email_job = Some((bill_id, receiver));
+    }
+
+    /// Reports a finished email as a toast
+    pub fn poll_email_job(&mut self) {
+        let Some((bill_id, receiver)) = &self.email_job else {
+            return;
+        };
+        let bill_id = *bill_id;
+        let result = match receiver.try_recv() {
+            Ok(result) => result,
+            Err(std::sync::mpsc::TryRecvError::Empty) => return,
+            Err(std::sync::mpsc::TryRecvError::Disconnected) => Err("Sending was aborted".to_string()),
+        };
+        self.email_job = None;
+        match result {
+            Ok(()) => self.show_toast(format!("Bill #{} sent by email", bill_id)),
+            Err(e) => self.pdf_error = Some(format!("Bill #{} could not be emailed:\n{}", bill_id, e)),
+        }
+    }
+
+    /// Checks the SMTP settings on a background thread; the result is shown by `poll_smtp_test`
+    pub fn start_smtp_test(&mut self) {
+        if self.smtp_test_job.is_some() {
+            return;
+        }
+        let smtp = self.smtp.clone();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            sender.send(crate::email::test_connection(&smtp)).ok();
+        });
+        self.smtp_test_job = Some(receiver);
+    }
+
+    /// Reports a finished SMTP connection test as a toast
+    pub fn poll_smtp_test(&mut self) {
+        let Some(receiver) = &self.smtp_test_job else {
+            return;
+        };
+        let result = match receiver.try_recv() {
+            Ok(result) => result,
+            Err(std::sync::mpsc::TryRecvError::Empty) => return,
+            Err(std::sync::mpsc::TryRecvError::Disconnected) => Err("The connection test was aborted".to_string()),
+        };
+        self.smtp_test_job = None;
+        self.show_toast(match result {
+            Ok(()) => format!("Connected to {} successfully", self.smtp.host.trim()),
+            Err(e) => format!("SMTP test failed: {}", e),
+        });
+    }
+
+    /// Emails the next reminder to the client of every overdue bill, pausing between sends
+    /// as configured. Bills whose email or PDF can't be prepared are skipped; each reminder
+    /// is recorded once its email went out.
+    pub fn start_reminder_batch(&mut self) -> Result<(), String> {
+        if self.email_job.is_some() || self.reminder_batch.as_ref().is_some_and(|b| !b.finished) {
+            return Err("Another email is still being sent".to_string());
+        }
+        if !self.smtp.is_configured() {
+            return Err("SMTP server and sender address must be set in Settings".to_string());
+        }
+
+        let mut emails = Vec::new();
+        let mut skipped = Vec::new();
+        let ids: Vec<u64> = self.overdue_bills().iter().map(|b| b.id).collect();
+        for id in ids {
+            match self.prepare_reminder_email(id) {
+                Ok((level, email)) => emails.push((id, level, email)),
+                Err(e) => skipped.push((id, e.lines().next().unwrap_or_default().to_string())),
+            }
+        }
+
+        let smtp = self.smtp.clone();
+        let total = emails.len();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            for (idx, (id, level, email)) in emails.into_iter().enumerate() {
+                if idx > 0 {
+                    std::thread::sleep(std::time::Duration::from_secs(smtp.batch_delay_secs));
+                }
+                if sender.send((id, level, crate::email::send(&smtp, &email))).is_err() {
+                    return;
+                }
+            }
+        });
+
+        self.reminder_batch = Some(ReminderBatch {
+            receiver,
+            total,
+            sent: Vec::new(),
+            failed: Vec::new(),
+            skipped,
+            finished: total == 0,
+        });
+        Ok(())
+    }
+
+    /// Records the reminders the batch has sent so far
+    pub fn poll_reminder_batch(&mut self) {
+        let Some(batch) = &mut self.reminder_batch else {
+            return;
+        };
+        if batch.finished {
+            return;
+        }
+
+        let mut sent = Vec::new();
+        loop {
+            match batch.receiver.try_recv() {
+                Ok((id, level, Ok(()))) => {
+                    sent.push((id, level));
+                    batch.sent.push(id);
+                }
+                Ok((id, _, Err(e))) => batch.failed.push((id, e)),
+                Err(std::sync::mpsc::TryRecvError::Empty) => break,
+                Err(std::sync::mpsc::TryRecvError::Disconnected) => {
+                    batch.finished = true;
+                    break;
+                }
+            }
+        }
+        if sent.is_empty() {
+            return;
+        }
+
+        let now = crate::timezone::now();
+        let db = self.db.lock().unwrap();
+        let result = db.record_reminders(&sent, &now);
+        drop(db);
+        if let Err(e) = result {
+            self.pdf_error = Some(format!("Reminders were emailed but could not be recorded: {}", e));
+            return;
+        }
+        for (id, level) in sent {
+            if let Some(bill) = self.bills.iter_mut().find(|b| b.id == id) {
+                bill.reminder_level = level;
+                bill.last_reminder_at = Some(now);
+            }
+        }
+    }
+
+    pub fn show_toast(&mut self, message: String) {
+        self.toast = Some((message, std::time::Instant::now()));
+    }
+
+    /// Whether the bill (or its client/creditor data) changed since its PDF was generated
+    pub fn is_pdf_stale(&self, bill: &Bill) -> bool {
+        let Some(stored_hash) = &bill.pdf_hash else {
+            return false;
+        };
+        self.current_content_hash(bill).is_some_and(|hash| hash != *stored_hash)
+    }
+
+    /// `invoice_content_hash` of the bill with the current client, creditor, settings and
+    /// appended attachments.
+    /// Reused while the bill is unchanged; `None` if its client is missing.
+    fn current_content_hash(&self, bill: &Bill) -> Option<String> {
+        if let Some((cached_bill, hash)) = self.content_hashes.borrow().get(&bill.id)
+            && cached_bill == bill
+        {
+            return Some(hash.clone());
+        }
+
+        let client = self.get_client(bill.client_id)?;
+        let (_, appended_hashes) = self.appended_pdfs(bill.id);
+        let options = InvoiceOptions { appended_hashes, ..self.invoice_options(bill) };
+        let hash = crate::pdf::invoice_content_hash(bill, client, &self.creditor_address, &options);
+        self.content_hashes.borrow_mut().insert(bill.id, (bill.clone(), hash.clone()));
+        Some(hash)
+    }
+
+    /// Drops the cached content hashes after a change to clients, creditor, settings or
+    /// attachments
+    fn invalidate_content_hashes(&self) {
+        self.content_hashes.borrow_mut().clear();
+    }
+
+    /// Asks where to save a bill's PDF; the returned path always ends in `.pdf`
+    pub fn pick_pdf_save_path(&self, bill_id: u64) -> Result<Option<PathBuf>, String> {
+        let db = self.db.lock().unwrap();
+        let bill = db.get_bill_by_id(bill_id)
+            .map_err(|e| format!("Database error: {}", e))?
+            .ok_or_else(|| "Bill not found".to_string())?;
+        drop(db);
+
+        // Get client name for the filename
+        let client_name = self.get_client(bill.client_id)
+            .map(|c| sanitize_filename(&c.name))
+            .unwrap_or_else(|| "Unknown".to_string());
+
+        let reference = sanitize_filename(&bill.reference);
+
+        // Use native file dialog
+        let file_dialog = rfd::FileDialog::new()
+            .add_filter("PDF", &["pdf"])
+            .set_file_name(format!("invoice_{}_{}.pdf", client_name, reference));
+
+        Ok(file_dialog.save_file().map(|path| {
+            let is_pdf = path
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("pdf"));
+            if is_pdf {
+                path
+            } else {
+                let mut name = path.clone().into_os_string();
+                name.push(".pdf");
+                PathBuf::from(name)
+            }
+        }))
+    }
+
+    /// Writes a bill's stored PDF to `path`, replacing any existing file
+    pub fn write_pdf_to_path(&self, bill_id: u64, path: &std::path::Path) -> Result<(), String> {
+        let db = self.db.lock().unwrap();
+        let pdf_data = db.load_bill_pdf(bill_id)
+            .map_err(|e| format!("Failed to read PDF: {}", e))?
+            .ok_or_else(|| "PDF not generated yet".to_string())?;
+        drop(db);
+
+        std::fs::write(path, pdf_data).map_err(|e| match e.kind() {
+            std::io::ErrorKind::PermissionDenied => {
+                format!("Permission denied: cannot write to {}", path.display())
+            }
+            _ => format!("Failed to save PDF: {}", e),
+        })
+    }
+
+    /// Sends a bill's PDF to the default printer, generating it first if there is none yet
+    pub fn print_bill(&mut self, bill_id: u64) -> Result<(), String> {
+        let has_pdf = self.bills.iter().any(|b| b.id == bill_id && b.has_pdf);
+        if !has_pdf {
+            self.regenerate_pdf(bill_id)?;
+        }
+
+        let path = std::env::temp_dir().join(format!("bill-manager-print-{}.pdf", bill_id));
+        self.write_pdf_to_path(bill_id, &path)?;
+        crate::print::print_pdf(&path)
+    }
+
+    /// Exports all bills dated in `year` as PDFs plus a `manifest.csv` into `dir` and flags
+    /// them as archived. Missing PDFs are generated first; if any fails, nothing is archived.
+    pub fn archive_year(&mut self, year: i32, dir: &std::path::Path) -> Result<String, String> {
+        let ids: Vec<u64> = self.bills
+            .iter()
+            .filter(|b| b.date.year() == year)
+            .map(|b| b.id)
+            .collect();
+        if ids.is_empty() {
+            return Err(format!("There are no bills dated {}", year));
+        }
+
+        let mut problems = Vec::new();
+        for id in &ids {
+            let has_pdf = self.bills.iter().any(|b| b.id == *id && b.has_pdf);
+            if !has_pdf && let Err(e) = self.regenerate_pdf(*id) {
+                problems.push(format!("#{}: {}", id, e.lines().next().unwrap_or_default()));
+            }
+        }
+        if !problems.is_empty() {
+            return Err(format!(
+                "Nothing was archived, PDFs could not be generated for:\n{}",
+                problems.join("\n")
+            ));
+        }
+
+        let mut rows = Vec::new();
+        for id in &ids {
+            let Some(bill) = self.bills.iter().find(|b| b.id == *id) else {
+                continue;
+            };
+            let client_name = self.get_client(bill.client_id)
+                .map(|c| c.name.clone())
+                .unwrap_or_else(|| "Unknown Client".to_string());
+            let file = format!("invoice_{}_{}.pdf", bill.id, sanitize_filename(&client_name));
+
+            let db = self.db.lock().unwrap();
+            let pdf = db.load_bill_pdf(bill.id)
+                .map_err(|e| format!("Failed to read PDF of bill #{}: {}", bill.id, e))?
+                .ok_or_else(|| format!("Bill #{} has no PDF", bill.id))?;
+            drop(db);
+            std::fs::write(dir.join(&file), &pdf)
+                .map_err(|e| format!("Failed to write {}: {}", dir.join(&file).display(), e))?;
+
+            rows.push(crate::export::ArchiveManifestRow {
+                id: bill.id,
+                client: client_name,
+                date: bill.date.format("%Y-%m-%d").to_string(),
+                due_date: bill.due_date.format("%Y-%m-%d").to_string(),
+                status: bill.status.to_string(),
+                currency: bill.currency.clone(),
+                total: match bill.amount_mode {
+                    AmountMode::Fixed => format!("{:.2}", bill.total()),
+                    AmountMode::Open => String::new(),
+                },
+                reference: bill.reference.clone(),
+                file,
+                sha256: Sha256::digest(&pdf).iter().map(|b| format!("{:02x}", b)).collect(),
+            });
+        }
+        crate::export::write_archive_manifest(&dir.join("manifest.csv"), &rows)?;
 
-    pub fn add_client(&mut self, mut client: Client) {
+        let now = crate::timezone::now();
         let db = self.db.lock().unwrap();
-        let id = db.save_client(&client).expect("Failed to save client");
-        client.id = id;
+        db.mark_archived(&ids, &now)
+            .map_err(|e| format!("PDFs were exported, but the bills could not be flagged as archived: {}", e))?;
         drop(db);
+        for bill in self.bills.iter_mut().filter(|b| ids.contains(&b.id)) {
+            bill.archived_at = Some(now);
+        }
 
-        self.clients.push(client);
-        self.next_client_id = self.next_client_id.max(id + 1);
+        Ok(format!("Archived {} bills of {} to {}", ids.len(), year, dir.display()))
     }
 
-    pub fn update_client(&mut self, client: Client) {
-        let db = self.db.lock().unwrap();
-        db.save_client(&client).expect("Failed to update client");
-        drop(db);
+    pub fn update_bill_status(&mut self, bill_id: u64, new_status: BillStatus) {
+        if let Some(bill) = self.bills.iter_mut().find(|b| b.id == bill_id) {
+            bill.status = new_status;
+            let db = self.db.lock().unwrap();
+            db.save_bill(bill).ok();
+        }
+    }
 
-        if let Some(pos) = self.clients.iter().position(|c| c.id == client.id) {
-            self.clients[pos] = client;
+    /// The zone dates are shown and entered in; the system zone if none or an unknown one is set
+    pub fn zone(&self) -> Tz {
+        crate::timezone::parse_zone(&self.timezone)
+            .ok()
+            .flatten()
+            .unwrap_or_else(crate::timezone::system_zone)
+    }
+
+    /// Switches the zone dates are shown and entered in and converts the loaded dates.
+    /// An empty name follows the system zone.
+    pub fn set_timezone(&mut self, name: &str) -> Result<(), String> {
+        crate::timezone::set_zone(name)?;
+        self.timezone = name.trim().to_string();
+        let tz = self.zone();
+        for bill in self.bills.iter_mut() {
+            bill.convert_to_display_zone(tz);
+        }
+        for editor in &mut self.bill_editors {
+            editor.bill.convert_to_display_zone(tz);
+            editor.snapshot.convert_to_display_zone(tz);
+            for attachment in &mut editor.attachments {
+                attachment.added_at = attachment.added_at.map(|dt| dt.with_timezone(&tz));
+            }
         }
+        for template in self
+            .item_templates
+            .iter_mut()
+            .chain(self.editing_template.iter_mut())
+            .chain(self.template_snapshot.iter_mut())
+        {
+            template.last_used = template.last_used.map(|dt| dt.with_timezone(&tz));
+        }
+        // Dates are hashed with their offset
+        self.invalidate_content_hashes();
+        Ok(())
     }
 
-    pub fn delete_client(&mut self, id: u64) {
-        let db = self.db.lock().unwrap();
-        db.delete_client(id).expect("Failed to delete client");
-        drop(db);
+    /// Changes the lowest id for new bills; existing bills keep their ids
+    pub fn set_first_bill_number(&mut self, number: u64) {
+        self.first_bill_number = number.max(1);
+        let after_last = self.bills.iter().map(|b| b.id).max().unwrap_or(0) + 1;
+        self.next_bill_id = after_last.max(self.first_bill_number);
+    }
 
-        self.clients.retain(|c| c.id != id);
+    /// Sets or clears a bill's follow-up date without opening the form
+    pub fn set_follow_up(&mut self, bill_id: u64, follow_up: Option<DateTime<Tz>>) {
+        if let Some(bill) = self.bills.iter_mut().find(|b| b.id == bill_id) {
+            bill.follow_up = follow_up;
+            let db = self.db.lock().unwrap();
+            db.save_bill(bill).ok();
+        }
     }
 
-    pub fn add_bill(&mut self, mut bill: Bill) {
-        let db = self.db.lock().unwrap();
-        let id = db.save_bill(&bill).expect("Failed to save bill");
-        bill.id = id;
-        drop(db);
+    /// Sum of the payments recorded for a bill
+    pub fn paid_amount(&self, bill_id: u64) -> f64 {
+        round_cents(self.payments.iter().filter(|p| p.bill_id == bill_id).map(|p| p.amount).sum())
+    }
 
-        self.bills.push(bill);
-        self.next_bill_id = self.next_bill_id.max(id + 1);
+    /// Total of the issued credit notes refunding an invoice
+    pub fn credited_amount(&self, invoice: &Bill) -> f64 {
+        match invoice.document_type {
+            DocumentType::Invoice => round_cents(invoice.linked_documents(&self.bills).iter().map(|b| b.total()).sum()),
+            DocumentType::CreditNote => 0.0,
+        }
+    }
+
+    /// Invoice total less its credit notes; other bills keep their own total
+    pub fn net_total(&self, bill: &Bill) -> f64 {
+        round_cents(bill.total() - self.credited_amount(bill))
+    }
+
+    /// Amount still due after credit notes and the recorded payments; negative when overpaid
+    pub fn outstanding_amount(&self, bill: &Bill) -> f64 {
+        round_cents(self.net_total(bill) - self.paid_amount(bill.id))
+    }
+
+    /// Outstanding bill that still has something to pay, i.e. isn't fully covered by credit notes
+    fn has_balance(&self, bill: &Bill) -> bool {
+        bill.is_outstanding() && self.net_total(bill) > 0.0
     }
 
-    pub fn update_bill(&mut self, bill: Bill) {
+    /// Records payments and marks the bills they fully cover as Paid. Returns the ids of
+    /// the bills that became paid.
+    pub fn record_payments(&mut self, payments: Vec<Payment>) -> Result<Vec<u64>, String> {
+        let mut received: HashMap<u64, f64> = HashMap::new();
+        for payment in &payments {
+            *received.entry(payment.bill_id).or_default() += payment.amount;
+        }
+        let mut paid: Vec<u64> = self.bills
+            .iter()
+            .filter(|b| b.status != BillStatus::Paid)
+            .filter(|b| received.get(&b.id).is_some_and(|amount| self.outstanding_amount(b) - amount < 0.005))
+            .map(|b| b.id)
+            .collect();
+        paid.sort_unstable();
+
         let db = self.db.lock().unwrap();
-        db.save_bill(&bill).expect("Failed to update bill");
+        let ids = db.add_payments(&payments, &paid)
+            .map_err(|e| format!("Failed to record payments: {}", e))?;
         drop(db);
 
-        // Update in-memory cache
-        if let Some(pos) = self.bills.iter().position(|b| b.id == bill.id) {
-            self.bills[pos] = bill;
+        self.payments.extend(payments.into_iter().zip(ids).map(|(payment, id)| Payment { id, ..payment }));
+        for bill in self.bills.iter_mut().filter(|b| paid.contains(&b.id)) {
+            bill.status = BillStatus::Paid;
         }
+        Ok(paid)
     }
 
-    pub fn delete_bill(&mut self, id: u64) {
-        let db = self.db.lock().unwrap();
-        db.delete_bill(id).expect("Failed to delete bill");
-        drop(db);
+    /// Opens the record-payment dialog for a bill, prefilled with the outstanding amount
+    pub fn open_payment_dialog(&mut self, bill_id: u64) {
+        let Some(bill) = self.bills.iter().find(|b| b.id == bill_id) else {
+            return;
+        };
+        self.pending_payment = Some(Payment {
+            id: 0,
+            bill_id,
+            amount: self.outstanding_amount(bill).max(0.0),
+            paid_on: crate::timezone::now().date_naive(),
+            note: String::new(),
+            source: None,
+        });
+    }
 
-        self.bills.retain(|b| b.id != id);
+    /// Records a payment entered by hand. Returns whether the bill is now paid in full.
+    pub fn record_payment(&mut self, payment: Payment) -> Result<bool, String> {
+        if !payment.amount.is_finite() || payment.amount <= 0.0 {
+            return Err("The amount must be greater than 0".to_string());
+        }
+        let bill_id = payment.bill_id;
+        let paid = self.record_payments(vec![Payment { amount: round_cents(payment.amount), ..payment }])?;
+        Ok(paid.contains(&bill_id))
     }
 
-    pub fn save_settings(&self) {
-        let db = self.db.lock().unwrap();
-        db.save_creditor_address(&self.creditor_address)
-            .expect("Failed to save creditor address");
-        db.save_default_iban(&self.default_iban)
-            .expect("Failed to save default IBAN");
+    /// Why booking a statement entry on a bill needs a closer look, given the amount still
+    /// due on it; `None` when the entry pays exactly that amount
+    pub fn payment_warning(&self, entry: &StatementEntry, bill: &Bill, due: f64) -> Option<String> {
+        if !entry.currency.is_empty() && entry.currency != bill.currency {
+            Some(format!("Paid in {}, bill is in {}", entry.currency, bill.currency))
+        } else if bill.status == BillStatus::WrittenOff {
+            Some("Bill was written off".to_string())
+        } else if due < 0.005 {
+            Some("Bill is already paid".to_string())
+        } else if entry.amount < due - 0.005 {
+            Some(format!("Partial payment, {:.2} outstanding", due))
+        } else if entry.amount > due + 0.005 {
+            Some(format!("Overpaid, {:.2} outstanding", due))
+        } else {
+            None
+        }
     }
 
-    pub fn get_client(&self, id: u64) -> Option<&Client> {
-        self.clients.iter().find(|c| c.id == id)
+    /// Reads a camt.053 bank statement and proposes a bill for each credit by its reference.
+    /// Nothing is recorded until the proposals are confirmed with `apply_reconciliation`.
+    pub fn import_camt053(&self, path: &std::path::Path) -> Result<ReconcileReport, String> {
+        let statement = crate::import::read_camt053(path)?;
+        let imported: HashSet<&str> = self.payments.iter().filter_map(|p| p.source.as_deref()).collect();
+
+        let mut report = ReconcileReport {
+            statement_id: statement.id,
+            ..ReconcileReport::default()
+        };
+        // Outstanding amounts as they shrink with each matched entry
+        let mut outstanding: HashMap<u64, f64> = HashMap::new();
+
+        for entry in statement.credits {
+            if imported.contains(report.payment_source(&entry).as_str()) {
+                report.already_imported += 1;
+                continue;
+            }
+            let proposal = match entry.reference.as_deref().and_then(|r| self.bill_for_reference(r)) {
+                Some((bill, confidence)) => {
+                    let due = *outstanding.entry(bill.id).or_insert_with(|| self.outstanding_amount(bill));
+                    let warning = self.payment_warning(&entry, bill, due);
+                    outstanding.insert(bill.id, round_cents(due - entry.amount));
+                    ReconcileProposal {
+                        bill_id: Some(bill.id),
+                        confidence,
+                        accepted: warning.is_none(),
+                        warning,
+                        entry,
+                    }
+                }
+                None => ReconcileProposal {
+                    entry,
+                    bill_id: None,
+                    confidence: MatchConfidence::Unmatched,
+                    warning: None,
+                    accepted: false,
+                },
+            };
+            report.proposals.push(proposal);
+        }
+
+        Ok(report)
     }
 
-    pub fn get_bills(&self) -> Result<Vec<Bill>, String> {
-        let db = self.db.lock().unwrap();
-        db.get_all_bills()
-            .map_err(|e| format!("Failed to fetch bills: {}", e))
+    /// Records the accepted proposals of a reviewed statement as payments. Entries in another
+    /// currency than their bill are skipped.
+    pub fn apply_reconciliation(&mut self, report: &ReconcileReport) -> Result<String, String> {
+        let mut payments = Vec::new();
+        let mut skipped = 0;
+        for proposal in report.proposals.iter().filter(|p| p.accepted) {
+            let entry = &proposal.entry;
+            let Some(bill) = proposal.bill_id.and_then(|id| self.bills.iter().find(|b| b.id == id)) else {
+                continue;
+            };
+            if !entry.currency.is_empty() && entry.currency != bill.currency {
+                skipped += 1;
+                continue;
+            }
+            payments.push(Payment {
+                id: 0,
+                bill_id: bill.id,
+                amount: entry.amount,
+                paid_on: entry.booking_date.unwrap_or_else(|| crate::timezone::now().date_naive()),
+                note: [entry.debtor.as_str(), entry.remittance.as_str()]
+                    .into_iter()
+                    .filter(|s| !s.is_empty())
+                    .collect::<Vec<_>>()
+                    .join(" – "),
+                source: Some(report.payment_source(entry)),
+            });
+        }
+
+        let recorded = payments.len();
+        let paid = if payments.is_empty() { Vec::new() } else { self.record_payments(payments)? };
+
+        let mut summary = format!("Recorded {} payments", recorded);
+        if !paid.is_empty() {
+            summary.push_str(&format!(", paid in full: {}", format_bill_ids(&paid)));
+        }
+        if skipped > 0 {
+            summary.push_str(&format!(", {} skipped for their currency", skipped));
+        }
+        Ok(summary)
     }
 
-    pub fn add_item_template(&mut self, mut template: ItemTemplate) {
+    /// Applies a status to several bills at once; the database update is all-or-nothing
+    pub fn update_bill_statuses(&mut self, bill_ids: &[u64], new_status: BillStatus) -> Result<(), String> {
         let db = self.db.lock().unwrap();
-        let id = db.save_item_template(&template).expect("Failed to save template");
-        template.id = id;
+        db.update_bill_statuses(bill_ids, new_status)
+            .map_err(|e| format!("Failed to update bill statuses: {}", e))?;
         drop(db);
 
-        self.item_templates.push(template);
-        self.next_template_id = self.next_template_id.max(id + 1);
+        for bill in self.bills.iter_mut().filter(|b| bill_ids.contains(&b.id)) {
+            bill.status = new_status;
+        }
+
+        Ok(())
+    }
+
+    /// Exports the given bills, e.g. the currently filtered list, to CSV in their given order
+    pub fn export_bills_csv(&self, bills: &[Bill], path: &std::path::Path) -> Result<usize, String> {
+        crate::export::write_bills_csv(
+            path,
+            bills,
+            &self.csv_columns,
+            |client_id| {
+                self.get_client(client_id)
+                    .map(|c| c.name.as_str())
+                    .unwrap_or("Unknown Client")
+            },
+            |bill_id| self.paid_amount(bill_id),
+        )
+    }
+
+    /// Writes the due dates of all sent, unpaid bills to an iCalendar file
+    pub fn export_due_ical(&self, path: &std::path::Path) -> Result<usize, String> {
+        let mut bills: Vec<&Bill> = self.bills.iter().filter(|b| b.is_outstanding()).collect();
+        bills.sort_by_key(|b| b.due_date);
+        crate::export::write_due_ical(path, &bills, |client_id| {
+            self.get_client(client_id)
+                .map(|c| c.name.as_str())
+                .unwrap_or("Unknown Client")
+        })
     }
 
-    pub fn update_item_template(&mut self, template: ItemTemplate) {
+    /// Imports historical bills keeping their original ids. Nothing is imported if any record
+    /// is invalid; the error lists every problematic record.
+    pub fn import_bills(&mut self, records: Vec<BillImportRecord>) -> Result<usize, String> {
+        let mut taken_ids: HashSet<u64> = self.bills.iter().map(|b| b.id).collect();
+        let mut problems = Vec::new();
+        let mut bills = Vec::new();
+
+        for (idx, record) in records.into_iter().enumerate() {
+            let row = format!("Record {} (bill #{})", idx + 1, record.id);
+
+            if record.id == 0 {
+                problems.push(format!("{}: bill id must be greater than 0", row));
+            } else if !taken_ids.insert(record.id) {
+                problems.push(format!("{}: bill id already exists", row));
+            }
+            if self.get_client(record.client_id).is_none() {
+                problems.push(format!("{}: unknown client id {}", row, record.client_id));
+            }
+            let status = BillStatus::parse(&record.status);
+            if status.is_none() {
+                problems.push(format!("{}: unknown status '{}'", row, record.status));
+            }
+            let date = parse_import_date(&record.date);
+            if date.is_none() {
+                problems.push(format!("{}: invalid date '{}'", row, record.date));
+            }
+            let due_date = parse_import_date(&record.due_date);
+            if due_date.is_none() {
+                problems.push(format!("{}: invalid due date '{}'", row, record.due_date));
+            }
+
+            if let (Some(status), Some(date), Some(due_date)) = (status, date, due_date) {
+                bills.push(Bill {
+                    id: record.id,
+                    client_id: record.client_id,
+                    date,
+                    due_date,
+                    items: record.items,
+                    reference: record.reference,
+                    iban: record.iban,
+                    notes: record.notes,
+                    status,
+                    ..Bill::default()
+                });
+            }
+        }
+
+        if !problems.is_empty() {
+            return Err(problems.join("\n"));
+        }
+
         let db = self.db.lock().unwrap();
-        db.save_item_template(&template).expect("Failed to update template");
+        db.insert_bills_with_ids(&bills)
+            .map_err(|e| format!("Failed to import bills: {}", e))?;
+        let next_bill_id = db.get_next_bill_id()
+            .map_err(|e| format!("Failed to determine next bill id: {}", e))?;
         drop(db);
 
-        if let Some(pos) = self.item_templates.iter().position(|t| t.id == template.id) {
-            self.item_templates[pos] = template;
-        }
+        let count = bills.len();
+        self.bills.extend(bills);
+        self.next_bill_id = next_bill_id;
+
+        Ok(count)
     }
 
-    pub fn delete_item_template(&mut self, id: u64) {
+    pub fn check_integrity(&self) -> Result<IntegrityReport, String> {
         let db = self.db.lock().unwrap();
-        db.delete_item_template(id).expect("Failed to delete template");
+        db.check_integrity().map_err(|e| format!("Failed to check database: {}", e))
+    }
+
+    /// Moves bills whose client is missing to a placeholder client, created on first use,
+    /// so they can be opened and reassigned in the bill form
+    pub fn reassign_orphaned_bills(&mut self, ids: &[u64]) -> Result<String, String> {
+        let client_id = match self.clients.iter().find(|c| c.name == RESTORED_CLIENT_NAME) {
+            Some(client) => client.id,
+            None => {
+                let client = Client {
+                    name: RESTORED_CLIENT_NAME.to_string(),
+                    ..Client::default()
+                };
+                let db = self.db.lock().unwrap();
+                let id = db.save_client(&client)
+                    .map_err(|e| format!("Failed to create placeholder client: {}", e))?;
+                drop(db);
+                self.clients.push(Client { id, ..client });
+                self.next_client_id = self.next_client_id.max(id + 1);
+                id
+            }
+        };
+
+        let db = self.db.lock().unwrap();
+        db.reassign_bills(ids, client_id)
+            .map_err(|e| format!("Failed to reassign bills: {}", e))?;
         drop(db);
 
-        self.item_templates.retain(|t| t.id != id);
+        for bill in self.bills.iter_mut().filter(|b| ids.contains(&b.id)) {
+            bill.client_id = client_id;
+        }
+        Ok(format!("Moved {} to client \"{}\"", format_bill_ids(ids), RESTORED_CLIENT_NAME))
     }
 
-    pub fn generate_pdf(&mut self, bill_id: u64) -> Result<(), String> {
-        // Fetch bill from database
+    /// Deletes payments and attachment rows of bills that no longer exist
+    pub fn remove_orphaned_rows(&mut self) -> Result<String, String> {
         let db = self.db.lock().unwrap();
-        let bill = db.get_bill_by_id(bill_id)
-            .map_err(|e| format!("Database error: {}", e))?
-            .ok_or_else(|| "Bill not found".to_string())?;
+        let deleted = db.delete_orphaned_rows()
+            .map_err(|e| format!("Failed to remove leftover rows: {}", e))?;
         drop(db);
 
-        let client = self.get_client(bill.client_id)
-            .ok_or_else(|| "Client not found".to_string())?;
+        let bill_ids: HashSet<u64> = self.bills.iter().map(|b| b.id).collect();
+        self.payments.retain(|p| bill_ids.contains(&p.bill_id));
+        Ok(format!("Removed {} leftover rows", deleted))
+    }
 
-        // Generate PDF in memory
-        let pdf_data = crate::pdf::generate_bill_pdf(&bill, client, &self.creditor_address)?;
-        let now = Local::now();
+    /// Starts compacting the database on a background thread; poll `vacuum_job` for the result
+    pub fn start_vacuum(&mut self) {
+        if self.vacuum_job.is_some() {
+            return;
+        }
 
-        // Save to database
-        let db = self.db.lock().unwrap();
-        db.save_bill_pdf(bill_id, &pdf_data, &now)
-            .map_err(|e| format!("Failed to save PDF to database: {}", e))?;
-        drop(db);
+        // Clone the pool handle so the UI keeps access to the database while VACUUM runs
+        let db = self.db.lock().unwrap().clone();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let result = db.vacuum().map_err(|e| format!("Failed to compact database: {}", e));
+            sender.send(result).ok();
+        });
+        self.vacuum_job = Some(receiver);
+    }
+}
 
-        // Update bill in memory cache
-        if let Some(bill) = self.bills.iter_mut().find(|b| b.id == bill_id) {
-            bill.pdf_data = Some(pdf_data);
-            bill.pdf_created_at = Some(now);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn address(name: &str) -> Address {
+        Address::new(
+            name.to_string(),
+            Some("Bahnhofstrasse".to_string()),
+            Some("1".to_string()),
+            "8001".to_string(),
+            "Zürich".to_string(),
+            "CH".to_string(),
+        )
+    }
+
+    fn client() -> Client {
+        Client {
+            id: 1,
+            name: "Muster AG".to_string(),
+            address: address(""),
+            billing_address: address("Muster AG"),
+            ..Client::default()
         }
+    }
 
-        Ok(())
+    #[test]
+    fn complete_client_is_valid() {
+        assert_eq!(client().validate(), Ok(()));
     }
 
-    pub fn save_pdf_to_file(&self, bill_id: u64) -> Result<Option<std::path::PathBuf>, String> {
-        let db = self.db.lock().unwrap();
-        let bill = db.get_bill_by_id(bill_id)
-            .map_err(|e| format!("Database error: {}", e))?
-            .ok_or_else(|| "Bill not found".to_string())?;
-        drop(db);
+    #[test]
+    fn client_validation_lists_every_missing_field() {
+        let mut client = client();
+        client.name = "  ".to_string();
+        client.address.city.clear();
+        client.billing_address.postal_code.clear();
+        client.billing_address.country.clear();
 
-        // Get client name for the filename
-        let client_name = self.get_client(bill.client_id)
-            .map(|c| sanitize_filename(&c.name))
-            .unwrap_or_else(|| "Unknown".to_string());
+        let problems = client.validate().unwrap_err();
+        assert_eq!(problems, [
+            "Name is required",
+            "Contact address: city is required",
+            "Billing address: postal code is required",
+            "Billing address: country is required",
+        ]);
+    }
 
-        let reference = sanitize_filename(&bill.reference);
+    #[test]
+    fn item_template_needs_a_type_and_a_finite_price() {
+        let template = ItemTemplate {
+            item_type: "Consulting".to_string(),
+            unit_price: 150.0,
+            ..ItemTemplate::default()
+        };
+        assert_eq!(template.validate(), Ok(()));
 
-        // Use native file dialog
-        let file_dialog = rfd::FileDialog::new()
-            .add_filter("PDF", &["pdf"])
-            .set_file_name(&format!("invoice_{}_{}.pdf", client_name, reference));
+        let invalid = ItemTemplate {
+            item_type: " ".to_string(),
+            unit_price: f64::NAN,
+            ..ItemTemplate::default()
+        };
+        assert_eq!(invalid.validate().unwrap_err(), ["Type is required", "Unit price must be a number"]);
+    }
 
-        if let Some(path) = file_dialog.save_file() {
-            // Fetch bill from database
+    fn vat_item(quantity: f64, unit_price: f64, vat_rate: f64) -> BillItem {
+        BillItem {
+            item_type: "Leistung".to_string(),
+            quantity,
+            unit_price,
+            vat_rate,
+            ..BillItem::default()
+        }
+    }
 
-            if let Some(pdf_data) = &bill.pdf_data {
-                std::fs::write(&path, pdf_data)
-                    .map_err(|e| format!("Failed to save PDF: {}", e))?;
-                return Ok(Some(path));
-            } else {
-                return Err("PDF not generated yet".to_string());
-            }
+    #[test]
+    fn inclusive_and_exclusive_prices_give_different_totals_for_the_same_lines() {
+        let items = vec![vat_item(1.0, 100.0, 8.1), vat_item(2.0, 50.0, 2.6)];
+        let exclusive = Bill { items: items.clone(), ..Bill::default() }.totals();
+        let inclusive = Bill { items, prices_include_vat: true, ..Bill::default() }.totals();
+
+        // Net prices: VAT is added on top
+        assert_eq!(exclusive.subtotal, 200.0);
+        assert_eq!(exclusive.vat, [(2.6, 2.6), (8.1, 8.1)]);
+        assert!(!exclusive.vat_included);
+        assert_eq!(exclusive.grand_total, 210.7);
+
+        // Gross prices: the same VAT rates are backed out of the unchanged total
+        assert_eq!(inclusive.subtotal, 200.0);
+        assert_eq!(inclusive.vat, [(2.6, 2.53), (8.1, 7.49)]);
+        assert!(inclusive.vat_included);
+        assert_eq!(inclusive.grand_total, 200.0);
+    }
+
+    /// Empty database in a temporary folder, deleted when the folder is dropped
+    fn test_db() -> (tempfile::TempDir, Database) {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Database::new(dir.path().join("bills.db").to_str().unwrap()).expect("Failed to open test database");
+        (dir, db)
+    }
+
+    #[test]
+    fn new_items_use_the_configured_default_vat_rate() {
+        let (_dir, db) = test_db();
+        db.save_default_vat_rate(8.1).unwrap();
+        let app = BillManagerApp::with_database(db);
+
+        assert_eq!(app.default_vat_rate, 8.1);
+        assert_eq!(app.new_bill().items[0].vat_rate, 8.1);
+        assert_eq!(BillItem::with_vat_rate(2.6).vat_rate, 2.6);
+        // Items from templates keep the template's own rate
+        let template = ItemTemplate { vat_rate: 2.6, ..ItemTemplate::default() };
+        assert_eq!(template.to_bill_item().vat_rate, 2.6);
+    }
+
+    fn qr_bill(items: Vec<BillItem>) -> Bill {
+        Bill {
+            id: 1,
+            client_id: 1,
+            items,
+            iban: PLACEHOLDER_IBAN.to_string(),
+            reference_type: ReferenceType::Scor,
+            reference: Bill::generate_scor_reference(1, 1, 2025),
+            ..Bill::default()
         }
+    }
 
-        Ok(None)
+    #[test]
+    fn valid_qr_bill_passes() {
+        let bill = qr_bill(vec![vat_item(2.0, 150.0, 0.0)]);
+        assert_eq!(validate_qr_bill(&bill, &client(), &address("Muster GmbH")), Ok(()));
     }
 
-    pub fn update_bill_status(&mut self, bill_id: u64, new_status: BillStatus) {
-        if let Some(bill) = self.bills.iter_mut().find(|b| b.id == bill_id) {
-            bill.status = new_status;
-            let db = self.db.lock().unwrap();
-            db.save_bill(bill).ok();
+    #[test]
+    fn qr_bill_validation_lists_every_violation() {
+        let mut bill = qr_bill(vec![vat_item(1.0, -20.0, 0.0)]);
+        bill.iban = "DE89 3704 0044 0532 0130 00".to_string();
+        bill.currency = "USD".to_string();
+        let mut creditor = address(&"x".repeat(71));
+        creditor.country = "Schweiz".to_string();
+
+        let problems = validate_qr_bill(&bill, &client(), &creditor).unwrap_err();
+        assert_eq!(problems, [
+            "Creditor name is 71 characters long (max. 70)",
+            "Creditor country must be a two-letter ISO code like CH, not 'Schweiz'",
+            "IBAN must be a Swiss or Liechtenstein account (CH/LI)",
+            "Currency 'USD' is not supported (use CHF or EUR)",
+            "Amount -20.00 is negative; the credit lines exceed the charges",
+        ]);
+    }
+
+    #[test]
+    fn last_bill_for_client_takes_the_latest_date_then_the_highest_id() {
+        let (_dir, db) = test_db();
+        let mut app = BillManagerApp::with_database(db);
+        let now = crate::timezone::now();
+        let day = |d: i64| now + chrono::Duration::days(d);
+        let bill = |id: u64, client_id: u64, date| Bill { id, client_id, date, ..Bill::default() };
+        app.bills = vec![bill(4, 1, day(2)), bill(7, 1, day(5)), bill(5, 1, day(5)), bill(9, 3, day(9))];
+
+        assert_eq!(app.last_bill_for_client(1).map(|b| b.id), Some(7));
+        assert_eq!(app.last_bill_for_client(3).map(|b| b.id), Some(9));
+        // A client without bills, and one that doesn't exist at all
+        assert!(app.last_bill_for_client(2).is_none());
+        assert!(app.last_bill_for_client(42).is_none());
+    }
+
+    #[test]
+    fn flagging_a_pdf_attachment_makes_the_stored_pdf_stale() {
+        let (dir, db) = test_db();
+        let mut app = BillManagerApp::with_database(db);
+        app.add_client(client());
+        let client_id = app.clients[0].id;
+        let bill_id = app.add_bill(Bill { client_id, ..qr_bill(vec![vat_item(1.0, 80.0, 0.0)]) });
+        let mut bill = app.bills[0].clone();
+        bill.pdf_hash = app.current_content_hash(&bill);
+
+        let source = dir.path().join("receipt.pdf");
+        std::fs::write(&source, b"%PDF-1.7 receipt").unwrap();
+        app.add_attachment(bill_id, &source).unwrap();
+        assert!(!app.is_pdf_stale(&bill));
+
+        let attachment = app.load_attachments(bill_id)[0].clone();
+        app.set_attachment_append(attachment.id, true).unwrap();
+        assert!(app.is_pdf_stale(&bill));
+
+        // Replacing the file's content changes the hash as well
+        let flagged = app.current_content_hash(&bill);
+        std::fs::write(app.attachment_path(&attachment), b"%PDF-1.7 other receipt").unwrap();
+        app.invalidate_content_hashes();
+        assert_ne!(app.current_content_hash(&bill), flagged);
+
+        app.remove_attachment(attachment.id).unwrap();
+        assert!(!app.is_pdf_stale(&bill));
+    }
+
+    #[test]
+    fn duplicate_references_are_found_regardless_of_spacing_and_case() {
+        let bill = |id: u64, reference: &str| Bill { id, reference: reference.to_string(), ..Bill::default() };
+        let bills = [
+            bill(1, "RF18 5390 0754 7034"),
+            bill(2, "rf18539007547034"),
+            bill(3, "RF71 2025 Y421 K420 1"),
+            bill(4, ""),
+            bill(5, "  "),
+        ];
+
+        assert_eq!(find_duplicate_references(&bills), [("RF18539007547034".to_string(), vec![1, 2])]);
+    }
+
+    #[test]
+    fn subtotal_is_the_sum_of_the_printed_line_totals() {
+        let items = vec![vat_item(0.333, 1.00, 0.0); 3];
+        let bill = Bill { items, ..Bill::default() };
+
+        // Each line prints as 0.33; the unrounded sum 0.999 would print as 1.00
+        assert_eq!(bill.rounded_line_totals(), [0.33, 0.33, 0.33]);
+        assert_eq!(bill.subtotal(), 0.99);
+        assert_eq!(bill.total(), 0.99);
+    }
+
+    #[test]
+    fn colliding_scor_references_get_a_salt() {
+        // Bill ids are kept modulo 10000, so 1 and 10001 encode the same
+        assert_eq!(Bill::generate_scor_reference(1, 1, 2025), Bill::generate_scor_reference(10001, 1, 2025));
+
+        let (_dir, db) = test_db();
+        let mut app = BillManagerApp::with_database(db);
+        app.bills = vec![qr_bill(Vec::new())];
+        let reference = app.unique_reference(ReferenceType::Scor, 10001, 1, 2025);
+
+        assert_ne!(reference, app.bills[0].reference);
+        assert_eq!(reference, Bill::generate_salted_scor_reference(10001, 1, 2025, 1));
+        assert_eq!(reference_problem(ReferenceType::Scor, &reference), None);
+        // Without a collision the plain reference is kept
+        assert_eq!(app.unique_reference(ReferenceType::Scor, 2, 1, 2025), Bill::generate_scor_reference(2, 1, 2025));
+    }
+
+    #[test]
+    fn scor_references_decode_back_to_their_ids() {
+        for salt in [0, 7] {
+            let encoding = ScorEncoding::new(12345, 1042, 2025, salt);
+            let reference = encoding.to_reference();
+            let compact: String = reference.split_whitespace().collect();
+
+            assert!(reference.contains(' '));
+            assert_eq!(ScorEncoding::decode(&reference), Some(encoding));
+            assert_eq!(ScorEncoding::decode(&compact.to_lowercase()), Some(encoding));
+            assert!(encoding.matches(12345, 1042));
+            assert_eq!((encoding.bill, encoding.client, encoding.salt), (2345, 42, salt));
+
+            let check: u32 = compact[2..4].parse().unwrap();
+            let wrong_check = format!("RF{:02}{}", (check + 1) % 100, &compact[4..]);
+            assert_eq!(ScorEncoding::decode(&wrong_check), None);
+        }
+    }
+
+    #[test]
+    fn malformed_creditor_setting_falls_back_to_the_placeholder() {
+        let (_dir, db) = test_db();
+        db.save_setting("creditor_address", "{\"name\": \"Muster").unwrap();
+        assert!(db.get_creditor_address().unwrap().is_none());
+
+        let app = BillManagerApp::with_database(db);
+        assert_eq!(app.creditor_address, placeholder_creditor());
+        assert!(app.setup_wizard.is_some());
+    }
+
+    fn stats_bill(id: u64, client_id: u64, amount: f64, currency: &str, status: BillStatus) -> Bill {
+        Bill {
+            id,
+            client_id,
+            items: vec![vat_item(1.0, amount, 0.0)],
+            currency: currency.to_string(),
+            status,
+            ..Bill::default()
         }
     }
+
+    #[test]
+    fn client_stats_sum_per_currency() {
+        let (_dir, db) = test_db();
+        let mut app = BillManagerApp::with_database(db);
+        let mut first = stats_bill(1, 1, 100.0, "CHF", BillStatus::Sent);
+        first.date -= chrono::Duration::days(400);
+        app.bills = vec![
+            first.clone(),
+            stats_bill(2, 1, 50.0, "CHF", BillStatus::Paid),
+            stats_bill(3, 1, 200.0, "EUR", BillStatus::Overdue),
+            stats_bill(4, 1, 30.0, "CHF", BillStatus::WrittenOff),
+            stats_bill(5, 2, 999.0, "CHF", BillStatus::Sent),
+        ];
+        app.payments = vec![Payment {
+            id: 1,
+            bill_id: 1,
+            amount: 40.0,
+            paid_on: first.date.date_naive(),
+            note: String::new(),
+            source: None,
+        }];
+
+        let stats = app.client_stats(1);
+        assert_eq!(stats.client_since, Some(first.date.date_naive()));
+        assert_eq!(stats.bill_count, 4);
+        // Written-off bills aren't counted as billed
+        assert_eq!(stats.billed, [("CHF".to_string(), 150.0), ("EUR".to_string(), 200.0)]);
+        assert_eq!(stats.outstanding, [("CHF".to_string(), 60.0), ("EUR".to_string(), 200.0)]);
+
+        assert_eq!(app.client_stats(3), ClientStats::default());
+    }
+
+    #[test]
+    fn bills_from_a_template_get_its_lines_notes_and_payment_term() {
+        let (_dir, db) = test_db();
+        let app = BillManagerApp::with_database(db);
+        let template = BillTemplate {
+            id: 1,
+            name: "Monthly hosting".to_string(),
+            items: vec![vat_item(1.0, 49.0, 8.1), vat_item(2.0, 15.0, 8.1)],
+            notes: "Thank you".to_string(),
+            payment_days: 10,
+        };
+
+        let bill = app.bill_from_template(&template);
+        assert_eq!(bill.items, template.items);
+        assert_eq!(bill.notes, "Thank you");
+        assert_eq!(bill.due_date - bill.date, chrono::Duration::days(10));
+        assert_eq!(bill.client_id, 0);
+        assert_eq!(bill.status, BillStatus::Draft);
+
+        // A template without lines still starts the bill with an empty one
+        let empty = app.bill_from_template(&BillTemplate::default());
+        assert_eq!(empty.items, app.new_bill().items);
+        assert_eq!(empty.due_date - empty.date, chrono::Duration::days(30));
+    }
+
+    #[test]
+    fn eur_bill_can_be_paid_in_chf() {
+        let mut bill = qr_bill(vec![vat_item(3.0, 33.33, 0.0)]);
+        bill.currency = "EUR".to_string();
+        bill.conversion = Some(CurrencyConversion { currency: "CHF".to_string(), rate: 0.9412 });
+
+        assert_eq!(bill.total(), 99.99);
+        assert_eq!(bill.qr_currency(), "CHF");
+        // 99.99 × 0.9412 = 94.110588, rounded to cents
+        assert_eq!(bill.qr_amount(), 94.11);
+        assert_eq!(validate_qr_bill(&bill, &client(), &address("Muster GmbH")), Ok(()));
+
+        bill.conversion = Some(CurrencyConversion { currency: "CHF".to_string(), rate: 0.0 });
+        assert_eq!(
+            validate_qr_bill(&bill, &client(), &address("Muster GmbH")).unwrap_err()[0],
+            "Exchange rate to CHF must be greater than 0"
+        );
+    }
+
+    #[test]
+    fn open_amount_bills_validate_with_either_reference() {
+        let creditor = address("Muster GmbH");
+        let scor = Bill { amount_mode: AmountMode::Open, ..qr_bill(vec![vat_item(1.0, 80.0, 0.0)]) };
+        assert_eq!(validate_qr_bill(&scor, &client(), &creditor), Ok(()));
+
+        let qrr = Bill {
+            iban: "CH44 3199 9123 0008 8901 2".to_string(),
+            reference_type: ReferenceType::Qrr,
+            reference: Bill::generate_qrr_reference(1, 1, 2025),
+            ..scor.clone()
+        };
+        assert_eq!(check_reference_type(qrr.reference_type, &qrr.iban), Ok(()));
+        assert_eq!(validate_qr_bill(&qrr, &client(), &creditor), Ok(()));
+
+        let credit = BillItem { item_type: "Gutschrift".to_string(), ..vat_item(1.0, -100.0, 0.0) };
+        let negative = Bill { items: vec![vat_item(1.0, 80.0, 0.0), credit], ..scor };
+        assert_eq!(
+            validate_qr_bill(&negative, &client(), &creditor).unwrap_err(),
+            ["Total -20.00 is negative; an open amount can't settle a credit"]
+        );
+    }
+
+    #[test]
+    fn placeholder_creditor_details_are_detected() {
+        assert_eq!(
+            placeholder_creditor_fields(&placeholder_creditor(), "ch9300762011623852957"),
+            ["business name", "street", "IBAN"]
+        );
+        let mut creditor = placeholder_creditor();
+        creditor.name = "Muster GmbH".to_string();
+        assert_eq!(placeholder_creditor_fields(&creditor, PLACEHOLDER_IBAN), ["street", "IBAN"]);
+        assert!(placeholder_creditor_fields(&address("Muster GmbH"), "CH56 0483 5012 3456 7800 9").is_empty());
+    }
+
+    #[test]
+    fn cash_rounding_goes_to_the_nearest_five_centimes() {
+        assert_eq!(round_to_five_cents(10.03), 10.05);
+        assert_eq!(round_to_five_cents(10.02), 10.0);
+        assert_eq!(round_to_five_cents(10.07), 10.05);
+        assert_eq!(round_to_five_cents(10.08), 10.1);
+
+        let bill = |price: f64| Bill { items: vec![vat_item(1.0, price, 0.0)], round_to_five_cents: true, ..Bill::default() };
+        let up = bill(10.03).totals();
+        assert_eq!((up.grand_total, up.rounding), (10.05, 0.02));
+        let down = bill(10.02).totals();
+        assert_eq!((down.grand_total, down.rounding), (10.0, -0.02));
+    }
+
+    #[test]
+    fn credit_lines_reduce_the_total_but_not_below_zero_for_qr_bills() {
+        let creditor = address("Muster GmbH");
+        let credit = BillItem { item_type: "Gutschrift".to_string(), ..vat_item(1.0, -50.0, 0.0) };
+        assert!(credit.is_credit());
+
+        let positive = qr_bill(vec![vat_item(2.0, 100.0, 0.0), credit.clone()]);
+        assert_eq!(positive.total(), 150.0);
+        assert_eq!(validate_qr_bill(&positive, &client(), &creditor), Ok(()));
+
+        let negative = qr_bill(vec![vat_item(1.0, 30.0, 0.0), credit]);
+        assert_eq!(negative.total(), -20.0);
+        assert_eq!(
+            validate_qr_bill(&negative, &client(), &creditor).unwrap_err(),
+            ["Amount -20.00 is negative; the credit lines exceed the charges"]
+        );
+    }
+
+    #[test]
+    fn credit_notes_count_negatively_and_are_never_outstanding() {
+        let (_dir, db) = test_db();
+        let app = BillManagerApp::with_database(db);
+        let mut invoice = stats_bill(1, 1, 120.0, "CHF", BillStatus::Sent);
+        invoice.round_to_five_cents = true;
+
+        let mut credit_note = app.credit_note_for(&invoice);
+        assert!(credit_note.is_credit_note());
+        assert_eq!(credit_note.credited_bill_id, Some(1));
+        assert_eq!((credit_note.client_id, credit_note.items.clone()), (1, invoice.items.clone()));
+        assert!(credit_note.round_to_five_cents);
+
+        // The amount itself stays positive; only its sign from our side flips
+        assert_eq!(credit_note.total(), 120.0);
+        assert_eq!(credit_note.signed_total(), -120.0);
+        assert!(!credit_note.shows_qr_bill());
+        credit_note.include_qr_bill = true;
+        assert!(!credit_note.shows_qr_bill());
+        credit_note.status = BillStatus::Sent;
+        assert!(!credit_note.is_outstanding());
+        assert!(invoice.is_outstanding());
+    }
+
+    #[test]
+    fn invoice_fully_offset_by_a_credit_note_has_nothing_outstanding() {
+        let (_dir, db) = test_db();
+        let mut app = BillManagerApp::with_database(db);
+        let invoice = stats_bill(1, 1, 250.0, "CHF", BillStatus::Sent);
+        let credit_note = Bill { id: 2, status: BillStatus::Sent, ..app.credit_note_for(&invoice) };
+        let draft = Bill { id: 3, ..app.credit_note_for(&invoice) };
+        app.bills = vec![invoice.clone(), credit_note.clone(), draft];
+
+        // Drafts don't count as issued credit notes
+        let linked: Vec<u64> = invoice.linked_documents(&app.bills).iter().map(|b| b.id).collect();
+        assert_eq!(linked, [2]);
+        assert_eq!(credit_note.linked_documents(&app.bills)[0].id, 1);
+
+        assert_eq!(app.credited_amount(&invoice), 250.0);
+        assert_eq!(app.net_total(&invoice), 0.0);
+        assert_eq!(app.outstanding_amount(&invoice), 0.0);
+        assert!(!app.has_balance(&invoice));
+        assert!(app.client_stats(1).outstanding.is_empty());
+    }
 }
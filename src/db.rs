@@ -2,33 +2,263 @@ use diesel::prelude::*;
 use diesel::r2d2::{self, ConnectionManager, Pool};
 use diesel::sqlite::SqliteConnection;
 use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
+use std::path::{Path, PathBuf};
 
-use crate::app::{Bill, BillItem, BillStatus, Client, ItemTemplate};
+use crate::email::SmtpSettings;
+use crate::export::CsvColumn;
+use crate::app::{AmountMode, AppliedDiscount, Attachment, AuditEvent, Bill, BillItem, BillStatus, BillTemplate, Client, CurrencyConversion, Discount, DiscountTemplate, ItemTemplate, Payment, reference_problem, validate_iban};
 use crate::models::*;
 use crate::schema::*;
-use crate::types::Address;
+use crate::timezone;
+use crate::types::{Address, DocumentType, Language, ReferenceType};
 
 pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
 
 pub type DbPool = Pool<ConnectionManager<SqliteConnection>>;
 
+/// Findings of `Database::check_integrity`
+#[derive(Debug, Clone, Default)]
+pub struct IntegrityReport {
+    /// Messages of `PRAGMA integrity_check` other than "ok"
+    pub sqlite_errors: Vec<String>,
+    /// Bills whose client no longer exists
+    pub orphaned_bills: Vec<u64>,
+    /// Payment and attachment rows of bills that no longer exist
+    pub orphaned_rows: usize,
+    /// Unreadable columns and invalid IBANs or references, per bill
+    pub bill_problems: Vec<(u64, String)>,
+}
+
+impl IntegrityReport {
+    pub fn is_clean(&self) -> bool {
+        self.sqlite_errors.is_empty()
+            && self.orphaned_bills.is_empty()
+            && self.orphaned_rows == 0
+            && self.bill_problems.is_empty()
+    }
+}
+
+#[derive(Clone)]
 pub struct Database {
     pool: DbPool,
+    path: PathBuf,
 }
 
 impl Database {
     pub fn new(database_url: &str) -> Result<Self, Box<dyn Error>> {
         let manager = ConnectionManager::<SqliteConnection>::new(database_url);
+        // Every connection to ":memory:" opens a database of its own, so an in-memory
+        // database (e.g. for trying out the pipeline) has to stick to a single connection
+        let max_size = if database_url == ":memory:" { 1 } else { 10 };
         let pool = r2d2::Pool::builder()
+            .max_size(max_size)
             .build(manager)?;
 
         // Run migrations
         let mut conn = pool.get()?;
         conn.run_pending_migrations(MIGRATIONS)
             .map_err(|e| format!("Failed to run migrations: {}", e))?;
+        drop(conn);
+
+        let db = Database {
+            pool,
+            path: PathBuf::from(database_url),
+        };
+        db.extract_pdf_blobs()?;
+
+        Ok(db)
+    }
+
+    /// Directory containing the database file; PDFs are stored below it
+    pub fn data_dir(&self) -> PathBuf {
+        match self.path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn pdf_dir(&self) -> PathBuf {
+        self.data_dir().join("pdfs")
+    }
+
+    /// Moves PDFs still stored as BLOBs (from before PDFs were kept on disk) into files
+    fn extract_pdf_blobs(&self) -> Result<(), Box<dyn Error>> {
+        let mut conn = self.get_conn()?;
+
+        let blobs: Vec<(i32, Option<Vec<u8>>)> = bills::table
+            .filter(bills::pdf_data.is_not_null())
+            .select((bills::id, bills::pdf_data))
+            .load(&mut conn)?;
+
+        for (id, data) in blobs {
+            let Some(data) = data else { continue };
+            let relative_path = self.write_pdf_file(id as u64, &data)?;
+
+            diesel::update(bills::table.filter(bills::id.eq(id)))
+                .set((
+                    bills::pdf_path.eq(Some(relative_path)),
+                    bills::pdf_data.eq(None::<Vec<u8>>),
+                ))
+                .execute(&mut conn)?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes a bill's PDF to `pdfs/{id}.pdf` (via a temp file, so an existing PDF is only
+    /// replaced by a complete one) and returns the path relative to the data directory
+    fn write_pdf_file(&self, bill_id: u64, pdf_data: &[u8]) -> Result<String, Box<dyn Error>> {
+        let pdf_dir = self.pdf_dir();
+        std::fs::create_dir_all(&pdf_dir)?;
+
+        let file_name = format!("{}.pdf", bill_id);
+        let temp_path = pdf_dir.join(format!("{}.tmp", file_name));
+        std::fs::write(&temp_path, pdf_data)?;
+        std::fs::rename(&temp_path, pdf_dir.join(&file_name))?;
+
+        Ok(format!("pdfs/{}", file_name))
+    }
+
+    fn attachment_dir(&self, bill_id: u64) -> PathBuf {
+        self.data_dir().join("attachments").join(bill_id.to_string())
+    }
+
+    /// Rebuilds the database file to reclaim space left by deleted rows (e.g. PDF blobs).
+    /// Returns the file size in bytes before and after.
+    pub fn vacuum(&self) -> Result<(u64, u64), Box<dyn Error>> {
+        let size_before = std::fs::metadata(&self.path)?.len();
+
+        let mut conn = self.get_conn()?;
+        diesel::sql_query("VACUUM").execute(&mut conn)?;
+
+        let size_after = std::fs::metadata(&self.path)?.len();
+        Ok((size_before, size_after))
+    }
+
+    /// Scans for damage that loading silently papers over: SQLite corruption, bills of deleted
+    /// clients, leftovers of deleted bills, unreadable columns and invalid IBANs or references
+    pub fn check_integrity(&self) -> Result<IntegrityReport, Box<dyn Error>> {
+        let mut conn = self.get_conn()?;
+        let mut report = IntegrityReport::default();
+
+        let checks: Vec<IntegrityCheckRow> = diesel::sql_query("PRAGMA integrity_check").load(&mut conn)?;
+        report.sqlite_errors = checks
+            .into_iter()
+            .map(|row| row.integrity_check)
+            .filter(|message| message != "ok")
+            .collect();
+
+        let client_ids: HashSet<i32> = clients::table.select(clients::id).load::<i32>(&mut conn)?.into_iter().collect();
+        let bills: Vec<BillDb> = bills::table
+            .order(bills::id.asc())
+            .select(BillDb::as_select())
+            .load(&mut conn)?;
+
+        for bill in &bills {
+            let id = bill.id as u64;
+            let mut problems = Vec::new();
+
+            // Drafts may be saved before a client is chosen
+            if bill.client_id != 0 && !client_ids.contains(&bill.client_id) {
+                report.orphaned_bills.push(id);
+            }
+            if let Err(e) = serde_json::from_str::<Vec<BillItem>>(&bill.items) {
+                problems.push(format!("unreadable items: {}", e));
+            }
+            if let Some(discount) = &bill.discount
+                && let Err(e) = serde_json::from_str::<AppliedDiscount>(discount)
+            {
+                problems.push(format!("unreadable discount: {}", e));
+            }
+            if let Some(conversion) = &bill.conversion
+                && let Err(e) = serde_json::from_str::<CurrencyConversion>(conversion)
+            {
+                problems.push(format!("unreadable currency conversion: {}", e));
+            }
+            for (field, value) in [("date", &bill.date), ("due date", &bill.due_date)] {
+                if chrono::DateTime::parse_from_rfc3339(value).is_err() {
+                    problems.push(format!("invalid {} '{}'", field, value));
+                }
+            }
+            if BillStatus::parse(&bill.status).is_none() {
+                problems.push(format!("unknown status '{}'", bill.status));
+            }
+            if !bill.iban.trim().is_empty() && !validate_iban(&bill.iban) {
+                problems.push(format!("invalid IBAN '{}'", bill.iban));
+            }
+            match ReferenceType::from_code(&bill.reference_type) {
+                Some(reference_type) => problems.extend(reference_problem(reference_type, &bill.reference)),
+                None => problems.push(format!("unknown reference type '{}'", bill.reference_type)),
+            }
+
+            report.bill_problems.extend(problems.into_iter().map(|problem| (id, problem)));
+        }
+
+        let bill_ids = bills::table.select(bills::id);
+        let orphaned_payments: i64 = payments::table
+            .filter(diesel::dsl::not(payments::bill_id.eq_any(bill_ids)))
+            .count()
+            .get_result(&mut conn)?;
+        let orphaned_attachments: i64 = attachments::table
+            .filter(diesel::dsl::not(attachments::bill_id.eq_any(bill_ids)))
+            .count()
+            .get_result(&mut conn)?;
+        report.orphaned_rows = (orphaned_payments + orphaned_attachments) as usize;
+
+        Ok(report)
+    }
+
+    /// Moves bills to another client, e.g. bills whose client was lost
+    pub fn reassign_bills(&self, ids: &[u64], client_id: u64) -> Result<(), Box<dyn Error>> {
+        let mut conn = self.get_conn()?;
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            for id in ids {
+                diesel::update(bills::table.filter(bills::id.eq(*id as i32)))
+                    .set(bills::client_id.eq(client_id as i32))
+                    .execute(conn)?;
+                let details = serde_json::json!({ "client_id": client_id }).to_string();
+                log_audit(conn, "bill", *id as i32, "reassign", Some(details))?;
+            }
+            Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    /// Deletes payments and attachment rows left behind by deleted bills; returns how many
+    pub fn delete_orphaned_rows(&self) -> Result<usize, Box<dyn Error>> {
+        let mut conn = self.get_conn()?;
+
+        let deleted = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let bill_ids = bills::table.select(bills::id);
+            let orphaned_payments = payments::table
+                .filter(diesel::dsl::not(payments::bill_id.eq_any(bill_ids)))
+                .select(PaymentDb::as_select())
+                .load(conn)?;
+            let orphaned_attachments = attachments::table
+                .filter(diesel::dsl::not(attachments::bill_id.eq_any(bill_ids)))
+                .select(AttachmentDb::as_select())
+                .load(conn)?;
+
+            for row in &orphaned_payments {
+                diesel::delete(payments::table.find(row.id)).execute(conn)?;
+                log_audit(conn, "payment", row.id, "delete", serde_json::to_string(row).ok())?;
+            }
+            for row in &orphaned_attachments {
+                diesel::delete(attachments::table.find(row.id)).execute(conn)?;
+                log_audit(conn, "attachment", row.id, "delete", serde_json::to_string(row).ok())?;
+            }
+            Ok(orphaned_payments.len() + orphaned_attachments.len())
+        })?;
 
-        Ok(Database { pool })
+        Ok(deleted)
     }
 
     fn get_conn(&self) -> Result<r2d2::PooledConnection<ConnectionManager<SqliteConnection>>, Box<dyn Error>> {
@@ -61,18 +291,28 @@ impl Database {
         Ok(result)
     }
 
+    /// Reads a JSON-valued setting. A value that doesn't parse (corrupted, or written by an
+    /// incompatible version) is logged and treated as unset, so the default applies.
+    fn get_json_setting<T: serde::de::DeserializeOwned>(&self, key: &str) -> Result<Option<T>, Box<dyn Error>> {
+        let Some(json) = self.get_setting(key)? else {
+            return Ok(None);
+        };
+        match serde_json::from_str(&json) {
+            Ok(value) => Ok(Some(value)),
+            Err(e) => {
+                eprintln!("Ignoring unreadable setting '{}': {}", key, e);
+                Ok(None)
+            }
+        }
+    }
+
     pub fn save_creditor_address(&self, address: &Address) -> Result<(), Box<dyn Error>> {
         let json = serde_json::to_string(address)?;
         self.save_setting("creditor_address", &json)
     }
 
     pub fn get_creditor_address(&self) -> Result<Option<Address>, Box<dyn Error>> {
-        if let Some(json) = self.get_setting("creditor_address")? {
-            let address: Address = serde_json::from_str(&json)?;
-            Ok(Some(address))
-        } else {
-            Ok(None)
-        }
+        self.get_json_setting("creditor_address")
     }
 
     pub fn save_default_iban(&self, iban: &str) -> Result<(), Box<dyn Error>> {
@@ -83,6 +323,201 @@ impl Database {
         self.get_setting("default_iban")
     }
 
+    pub fn save_template_dir(&self, dir: &str) -> Result<(), Box<dyn Error>> {
+        self.save_setting("template_dir", dir)
+    }
+
+    pub fn get_template_dir(&self) -> Result<Option<String>, Box<dyn Error>> {
+        self.get_setting("template_dir")
+    }
+
+    pub fn save_package_dir(&self, dir: &str) -> Result<(), Box<dyn Error>> {
+        self.save_setting("package_dir", dir)
+    }
+
+    pub fn get_package_dir(&self) -> Result<Option<String>, Box<dyn Error>> {
+        self.get_setting("package_dir")
+    }
+
+    pub fn save_brand_color(&self, color: &str) -> Result<(), Box<dyn Error>> {
+        self.save_setting("brand_color", color)
+    }
+
+    pub fn get_brand_color(&self) -> Result<Option<String>, Box<dyn Error>> {
+        self.get_setting("brand_color")
+    }
+
+    /// IANA zone name dates are shown in; empty follows the system
+    pub fn save_timezone(&self, name: &str) -> Result<(), Box<dyn Error>> {
+        self.save_setting("timezone", name)
+    }
+
+    pub fn get_timezone(&self) -> Result<Option<String>, Box<dyn Error>> {
+        self.get_setting("timezone")
+    }
+
+    pub fn save_pdf_output_options(&self, tagged: bool, system_fonts: bool) -> Result<(), Box<dyn Error>> {
+        self.save_setting("pdf_tagged", if tagged { "true" } else { "false" })?;
+        self.save_setting("pdf_system_fonts", if system_fonts { "true" } else { "false" })
+    }
+
+    /// Whether PDFs are tagged and may use system fonts
+    pub fn get_pdf_output_options(&self) -> Result<(Option<bool>, Option<bool>), Box<dyn Error>> {
+        Ok((
+            self.get_setting("pdf_tagged")?.map(|v| v == "true"),
+            self.get_setting("pdf_system_fonts")?.map(|v| v == "true"),
+        ))
+    }
+
+    pub fn save_auto_generate_pdf(&self, enabled: bool) -> Result<(), Box<dyn Error>> {
+        self.save_setting("auto_generate_pdf", if enabled { "true" } else { "false" })
+    }
+
+    pub fn get_auto_generate_pdf(&self) -> Result<Option<bool>, Box<dyn Error>> {
+        Ok(self.get_setting("auto_generate_pdf")?.map(|v| v == "true"))
+    }
+
+    /// Whether the first-run setup was finished; skipping it leaves this unset
+    pub fn save_setup_completed(&self, completed: bool) -> Result<(), Box<dyn Error>> {
+        self.save_setting("setup_completed", if completed { "true" } else { "false" })
+    }
+
+    pub fn get_setup_completed(&self) -> Result<Option<bool>, Box<dyn Error>> {
+        Ok(self.get_setting("setup_completed")?.map(|v| v == "true"))
+    }
+
+    /// Lowest id for new bills, e.g. 1001 to continue the numbering of a previous tool
+    pub fn save_first_bill_number(&self, number: u64) -> Result<(), Box<dyn Error>> {
+        self.save_setting("first_bill_number", &number.to_string())
+    }
+
+    pub fn get_first_bill_number(&self) -> Result<Option<u64>, Box<dyn Error>> {
+        Ok(self.get_setting("first_bill_number")?.and_then(|v| v.parse().ok()))
+    }
+
+    pub fn save_bill_list_compact(&self, compact: bool) -> Result<(), Box<dyn Error>> {
+        self.save_setting("bill_list_compact", if compact { "true" } else { "false" })
+    }
+
+    pub fn get_bill_list_compact(&self) -> Result<Option<bool>, Box<dyn Error>> {
+        Ok(self.get_setting("bill_list_compact")?.map(|v| v == "true"))
+    }
+
+    pub fn save_default_currency(&self, currency: &str) -> Result<(), Box<dyn Error>> {
+        self.save_setting("default_currency", currency)
+    }
+
+    pub fn get_default_currency(&self) -> Result<Option<String>, Box<dyn Error>> {
+        self.get_setting("default_currency")
+    }
+
+    pub fn save_default_language(&self, language: Language) -> Result<(), Box<dyn Error>> {
+        self.save_setting("default_language", language.code())
+    }
+
+    pub fn get_default_language(&self) -> Result<Option<Language>, Box<dyn Error>> {
+        Ok(self.get_setting("default_language")?.and_then(|code| Language::from_code(&code)))
+    }
+
+    pub fn save_default_vat_rate(&self, rate: f64) -> Result<(), Box<dyn Error>> {
+        self.save_setting("default_vat_rate", &rate.to_string())
+    }
+
+    pub fn get_default_vat_rate(&self) -> Result<Option<f64>, Box<dyn Error>> {
+        Ok(self.get_setting("default_vat_rate")?.and_then(|v| v.parse().ok()))
+    }
+
+    pub fn save_default_reference_type(&self, reference_type: ReferenceType) -> Result<(), Box<dyn Error>> {
+        self.save_setting("default_reference_type", reference_type.code())
+    }
+
+    pub fn get_default_reference_type(&self) -> Result<Option<ReferenceType>, Box<dyn Error>> {
+        Ok(self.get_setting("default_reference_type")?.and_then(|code| ReferenceType::from_code(&code)))
+    }
+
+    pub fn save_quantity_decimals(&self, decimals: usize) -> Result<(), Box<dyn Error>> {
+        self.save_setting("quantity_decimals", &decimals.to_string())
+    }
+
+    pub fn get_quantity_decimals(&self) -> Result<Option<usize>, Box<dyn Error>> {
+        Ok(self.get_setting("quantity_decimals")?.and_then(|v| v.parse().ok()))
+    }
+
+    pub fn save_smtp_settings(&self, smtp: &SmtpSettings) -> Result<(), Box<dyn Error>> {
+        let json = serde_json::to_string(smtp)?;
+        self.save_setting("smtp", &json)
+    }
+
+    pub fn get_smtp_settings(&self) -> Result<Option<SmtpSettings>, Box<dyn Error>> {
+        self.get_json_setting("smtp")
+    }
+
+    pub fn save_email_cc(&self, cc: &[String]) -> Result<(), Box<dyn Error>> {
+        self.save_setting("email_cc", &serde_json::to_string(cc)?)
+    }
+
+    pub fn get_email_cc(&self) -> Result<Option<Vec<String>>, Box<dyn Error>> {
+        self.get_json_setting("email_cc")
+    }
+
+    pub fn save_email_bcc(&self, bcc: &[String]) -> Result<(), Box<dyn Error>> {
+        self.save_setting("email_bcc", &serde_json::to_string(bcc)?)
+    }
+
+    pub fn get_email_bcc(&self) -> Result<Option<Vec<String>>, Box<dyn Error>> {
+        self.get_json_setting("email_bcc")
+    }
+
+    /// Empty string means replies go to the sender address
+    pub fn save_email_reply_to(&self, reply_to: Option<&str>) -> Result<(), Box<dyn Error>> {
+        self.save_setting("email_reply_to", reply_to.unwrap_or_default())
+    }
+
+    pub fn get_email_reply_to(&self) -> Result<Option<String>, Box<dyn Error>> {
+        Ok(self.get_setting("email_reply_to")?.filter(|v| !v.is_empty()))
+    }
+
+    pub fn save_due_date_presets(&self, presets: &[i64]) -> Result<(), Box<dyn Error>> {
+        self.save_setting("due_date_presets", &serde_json::to_string(presets)?)
+    }
+
+    pub fn get_due_date_presets(&self) -> Result<Option<Vec<i64>>, Box<dyn Error>> {
+        self.get_json_setting("due_date_presets")
+    }
+
+    pub fn save_csv_columns(&self, columns: &[CsvColumn]) -> Result<(), Box<dyn Error>> {
+        self.save_setting("csv_columns", &serde_json::to_string(columns)?)
+    }
+
+    pub fn get_csv_columns(&self) -> Result<Option<Vec<CsvColumn>>, Box<dyn Error>> {
+        self.get_json_setting("csv_columns")
+    }
+
+    pub fn save_delete_pdf_with_bill(&self, enabled: bool) -> Result<(), Box<dyn Error>> {
+        self.save_setting("delete_pdf_with_bill", if enabled { "true" } else { "false" })
+    }
+
+    pub fn get_delete_pdf_with_bill(&self) -> Result<Option<bool>, Box<dyn Error>> {
+        Ok(self.get_setting("delete_pdf_with_bill")?.map(|v| v == "true"))
+    }
+
+    pub fn save_dashboard_horizon_days(&self, days: i64) -> Result<(), Box<dyn Error>> {
+        self.save_setting("dashboard_horizon_days", &days.to_string())
+    }
+
+    pub fn get_dashboard_horizon_days(&self) -> Result<Option<i64>, Box<dyn Error>> {
+        Ok(self.get_setting("dashboard_horizon_days")?.and_then(|v| v.parse().ok()))
+    }
+
+    pub fn save_additional_info_templates(&self, templates: &HashMap<Language, String>) -> Result<(), Box<dyn Error>> {
+        let json = serde_json::to_string(templates)?;
+        self.save_setting("additional_info_templates", &json)
+    }
+
+    pub fn get_additional_info_templates(&self) -> Result<Option<HashMap<Language, String>>, Box<dyn Error>> {
+        self.get_json_setting("additional_info_templates")
+    }
+
     // Client operations
     pub fn save_client(&self, client: &Client) -> Result<u64, Box<dyn Error>> {
         let mut conn = self.get_conn()?;
@@ -107,10 +542,14 @@ impl Database {
                 billing_address_country: Some(client.billing_address.country.clone()),
             };
 
-            let id = diesel::insert_into(clients::table)
-                .values(&new_client)
-                .returning(clients::id)
-                .get_result::<i32>(&mut conn)?;
+            let id = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+                let id = diesel::insert_into(clients::table)
+                    .values(&new_client)
+                    .returning(clients::id)
+                    .get_result::<i32>(conn)?;
+                log_audit(conn, "client", id, "create", serde_json::to_string(&Client { id: id as u64, ..client.clone() }).ok())?;
+                Ok(id)
+            })?;
 
             Ok(id as u64)
         } else {
@@ -134,9 +573,18 @@ impl Database {
                 billing_address_country: Some(client.billing_address.country.clone()),
             };
 
-            diesel::update(clients::table.filter(clients::id.eq(client.id as i32)))
-                .set(&client_db)
-                .execute(&mut conn)?;
+            conn.transaction::<_, diesel::result::Error, _>(|conn| {
+                let old = clients::table
+                    .find(client_db.id)
+                    .select(ClientDb::as_select())
+                    .first(conn)
+                    .optional()?;
+                diesel::update(clients::table.filter(clients::id.eq(client.id as i32)))
+                    .set(&client_db)
+                    .execute(conn)?;
+                let diff = old.and_then(|old| json_diff(&old, &client_db, &[]));
+                log_audit(conn, "client", client_db.id, "update", diff)
+            })?;
 
             Ok(client.id)
         }
@@ -150,17 +598,19 @@ impl Database {
             .load::<ClientDb>(&mut conn)?;
 
         let clients = clients_db.into_iter().map(|c| {
-            let billing_address = if c.billing_address_name.is_some()
-                && c.billing_address_postal_code.is_some()
-                && c.billing_address_city.is_some()
-                && c.billing_address_country.is_some() {
+            let billing_address = if let (Some(name), Some(postal_code), Some(city), Some(country)) = (
+                c.billing_address_name,
+                c.billing_address_postal_code,
+                c.billing_address_city,
+                c.billing_address_country,
+            ) {
                 Address {
-                    name: c.billing_address_name.unwrap(),
+                    name,
                     street: c.billing_address_street.clone(),
                     building_number: c.billing_address_building_number.clone(),
-                    postal_code: c.billing_address_postal_code.unwrap(),
-                    city: c.billing_address_city.unwrap(),
-                    country: c.billing_address_country.unwrap(),
+                    postal_code,
+                    city,
+                    country,
                 }
             } else {
                 // Use regular address as billing address for backward compatibility
@@ -197,8 +647,37 @@ impl Database {
     pub fn delete_client(&self, id: u64) -> Result<(), Box<dyn Error>> {
         let mut conn = self.get_conn()?;
 
-        diesel::delete(clients::table.filter(clients::id.eq(id as i32)))
-            .execute(&mut conn)?;
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let old = clients::table
+                .find(id as i32)
+                .select(ClientDb::as_select())
+                .first(conn)
+                .optional()?;
+            diesel::delete(clients::table.filter(clients::id.eq(id as i32)))
+                .execute(conn)?;
+            log_audit(conn, "client", id as i32, "delete", old.and_then(|old| serde_json::to_string(&old).ok()))
+        })?;
+
+        Ok(())
+    }
+
+    /// Deletes several clients in one transaction
+    pub fn delete_clients(&self, ids: &[u64]) -> Result<(), Box<dyn Error>> {
+        let mut conn = self.get_conn()?;
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            for id in ids {
+                let old = clients::table
+                    .find(*id as i32)
+                    .select(ClientDb::as_select())
+                    .first(conn)
+                    .optional()?;
+                diesel::delete(clients::table.filter(clients::id.eq(*id as i32)))
+                    .execute(conn)?;
+                log_audit(conn, "client", *id as i32, "delete", old.and_then(|old| serde_json::to_string(&old).ok()))?;
+            }
+            Ok(())
+        })?;
 
         Ok(())
     }
@@ -216,119 +695,214 @@ impl Database {
 
     // Bill operations
     pub fn save_bill(&self, bill: &Bill) -> Result<u64, Box<dyn Error>> {
+        // Read before taking the connection, a single-connection pool would wait for itself
+        let first_number = self.get_first_bill_number()?.unwrap_or(1);
         let mut conn = self.get_conn()?;
 
-        let items_json = serde_json::to_string(&bill.items)?;
-        let status_str = match bill.status {
-            BillStatus::Draft => "Draft",
-            BillStatus::Sent => "Sent",
-            BillStatus::Paid => "Paid",
-            BillStatus::Overdue => "Overdue",
-        };
+        let bill_db = bill_to_db(bill)?;
 
         if bill.id == 0 {
             // Insert new bill
-            let new_bill = NewBill {
-                client_id: bill.client_id as i32,
-                date: bill.date.to_rfc3339(),
-                due_date: bill.due_date.to_rfc3339(),
-                reference: bill.reference.clone(),
-                iban: bill.iban.clone(),
-                notes: bill.notes.clone(),
-                status: status_str.to_string(),
-                items: items_json,
-                pdf_data: bill.pdf_data.clone(),
-                pdf_created_at: bill.pdf_created_at.as_ref().map(|dt| dt.to_rfc3339()),
+            let mut new_bill = NewBill {
+                id: None,
+                client_id: bill_db.client_id,
+                date: bill_db.date,
+                due_date: bill_db.due_date,
+                reference: bill_db.reference,
+                iban: bill_db.iban,
+                notes: bill_db.notes,
+                status: bill_db.status,
+                items: bill_db.items,
+                pdf_created_at: bill_db.pdf_created_at,
+                amount_mode: bill_db.amount_mode,
+                language: bill_db.language,
+                currency: bill_db.currency,
+                pdf_hash: bill_db.pdf_hash,
+                pdf_path: bill_db.pdf_path,
+                prices_include_vat: bill_db.prices_include_vat,
+                discount: bill_db.discount,
+                reminder_level: bill_db.reminder_level,
+                last_reminder_at: bill_db.last_reminder_at,
+                reference_type: bill_db.reference_type,
+                po_number: bill_db.po_number,
+                service_from: bill_db.service_from,
+                service_to: bill_db.service_to,
+                archived_at: bill_db.archived_at,
+                follow_up: bill_db.follow_up,
+                include_qr_bill: bill_db.include_qr_bill,
+                conversion: bill_db.conversion,
+                round_to_five_cents: bill_db.round_to_five_cents,
+                document_type: bill_db.document_type,
+                credited_bill_id: bill_db.credited_bill_id,
             };
 
-            let id = diesel::insert_into(bills::table)
-                .values(&new_bill)
-                .returning(bills::id)
-                .get_result::<i32>(&mut conn)?;
+            let id = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+                if next_bill_id(conn)? < first_number {
+                    new_bill.id = Some(first_number as i32);
+                }
+                let id = diesel::insert_into(bills::table)
+                    .values(&new_bill)
+                    .returning(bills::id)
+                    .get_result::<i32>(conn)?;
+                log_audit(conn, "bill", id, "create", serde_json::to_string(&Bill { id: id as u64, ..bill.clone() }).ok())?;
+                Ok(id)
+            })?;
 
             Ok(id as u64)
         } else {
             // Update existing bill
-            let bill_db = BillDb {
-                id: bill.id as i32,
-                client_id: bill.client_id as i32,
-                date: bill.date.to_rfc3339(),
-                due_date: bill.due_date.to_rfc3339(),
-                reference: bill.reference.clone(),
-                iban: bill.iban.clone(),
-                notes: bill.notes.clone(),
-                status: status_str.to_string(),
-                items: items_json,
-                pdf_data: bill.pdf_data.clone(),
-                pdf_created_at: bill.pdf_created_at.as_ref().map(|dt| dt.to_rfc3339()),
-            };
-
-            diesel::update(bills::table.filter(bills::id.eq(bill.id as i32)))
-                .set(&bill_db)
-                .execute(&mut conn)?;
+            conn.transaction::<_, diesel::result::Error, _>(|conn| {
+                let old = bills::table
+                    .find(bill_db.id)
+                    .select(BillDb::as_select())
+                    .first(conn)
+                    .optional()?;
+                diesel::update(bills::table.filter(bills::id.eq(bill.id as i32)))
+                    .set(&bill_db)
+                    .execute(conn)?;
+                // `pdf_path` is never changed here (see `bill_to_db`)
+                let diff = old.and_then(|old| json_diff(&old, &bill_db, &["pdf_path"]));
+                log_audit(conn, "bill", bill_db.id, "update", diff)
+            })?;
 
             Ok(bill.id)
         }
     }
 
-    pub fn save_bill_pdf(&self, bill_id: u64, pdf_data: &[u8], created_at: &chrono::DateTime<chrono::Local>) -> Result<(), Box<dyn Error>> {
+    /// Sets the status of several bills in one transaction, so either all or none are updated
+    pub fn update_bill_statuses(&self, ids: &[u64], status: BillStatus) -> Result<(), Box<dyn Error>> {
         let mut conn = self.get_conn()?;
 
-        diesel::update(bills::table.filter(bills::id.eq(bill_id as i32)))
-            .set((
-                bills::pdf_data.eq(Some(pdf_data)),
-                bills::pdf_created_at.eq(Some(created_at.to_rfc3339())),
-            ))
-            .execute(&mut conn)?;
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            for id in ids {
+                diesel::update(bills::table.filter(bills::id.eq(*id as i32)))
+                    .set(bills::status.eq(status_to_db(status)))
+                    .execute(conn)?;
+                let details = serde_json::json!({ "status": status_to_db(status) }).to_string();
+                log_audit(conn, "bill", *id as i32, "status", Some(details))?;
+            }
+            Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    /// Records a sent reminder for several bills in one transaction, setting each bill's
+    /// reminder level and date; the status is left as it is
+    pub fn record_reminders(&self, reminders: &[(u64, u8)], at: &chrono::DateTime<chrono_tz::Tz>) -> Result<(), Box<dyn Error>> {
+        let mut conn = self.get_conn()?;
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            for (id, level) in reminders {
+                diesel::update(bills::table.filter(bills::id.eq(*id as i32)))
+                    .set((
+                        bills::reminder_level.eq(*level as i32),
+                        bills::last_reminder_at.eq(timezone::to_stored(at)),
+                    ))
+                    .execute(conn)?;
+                let details = serde_json::json!({ "level": level }).to_string();
+                log_audit(conn, "bill", *id as i32, "reminder", Some(details))?;
+            }
+            Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    /// Flags bills as archived for tax retention
+    pub fn mark_archived(&self, ids: &[u64], at: &chrono::DateTime<chrono_tz::Tz>) -> Result<(), Box<dyn Error>> {
+        let mut conn = self.get_conn()?;
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            for id in ids {
+                diesel::update(bills::table.filter(bills::id.eq(*id as i32)))
+                    .set(bills::archived_at.eq(timezone::to_stored(at)))
+                    .execute(conn)?;
+                log_audit(conn, "bill", *id as i32, "archive", None)?;
+            }
+            Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    /// Inserts bills keeping their ids (used for importing historical invoices), all or nothing
+    pub fn insert_bills_with_ids(&self, bills: &[Bill]) -> Result<(), Box<dyn Error>> {
+        let mut conn = self.get_conn()?;
+
+        let rows = bills.iter().map(bill_to_db).collect::<Result<Vec<_>, _>>()?;
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            for row in &rows {
+                diesel::insert_into(bills::table)
+                    .values(row)
+                    .execute(conn)?;
+                log_audit(conn, "bill", row.id, "import", serde_json::to_string(row).ok())?;
+            }
+            Ok(())
+        })?;
+
+        Ok(())
+    }
+
+    /// Writes a bill's PDF to disk and records its path, timestamp and content hash in one transaction
+    pub fn save_bill_pdf(
+        &self,
+        bill_id: u64,
+        pdf_data: &[u8],
+        created_at: &chrono::DateTime<chrono_tz::Tz>,
+        content_hash: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut conn = self.get_conn()?;
+
+        let relative_path = self.write_pdf_file(bill_id, pdf_data)?;
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let updated = diesel::update(bills::table.filter(bills::id.eq(bill_id as i32)))
+                .set((
+                    bills::pdf_path.eq(Some(&relative_path)),
+                    bills::pdf_data.eq(None::<Vec<u8>>),
+                    bills::pdf_created_at.eq(Some(timezone::to_stored(created_at))),
+                    bills::pdf_hash.eq(Some(content_hash)),
+                ))
+                .execute(conn)?;
+            if updated == 0 {
+                return Err(diesel::result::Error::NotFound);
+            }
+            let details = serde_json::json!({ "pdf_path": relative_path, "pdf_hash": content_hash }).to_string();
+            log_audit(conn, "bill", bill_id as i32, "pdf", Some(details))
+        })?;
 
         Ok(())
     }
 
+    /// Loads a bill's PDF bytes on demand, falling back to a not yet extracted BLOB
+    pub fn load_bill_pdf(&self, bill_id: u64) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+        let mut conn = self.get_conn()?;
+
+        let row: Option<(Option<String>, Option<Vec<u8>>)> = bills::table
+            .filter(bills::id.eq(bill_id as i32))
+            .select((bills::pdf_path, bills::pdf_data))
+            .first(&mut conn)
+            .optional()?;
+
+        match row {
+            Some((Some(path), _)) => Ok(Some(std::fs::read(self.data_dir().join(path))?)),
+            Some((None, data)) => Ok(data),
+            None => Ok(None),
+        }
+    }
+
     pub fn get_bill_by_id(&self, id: u64) -> Result<Option<Bill>, Box<dyn Error>> {
         let mut conn = self.get_conn()?;
 
         let bill_db: Option<BillDb> = bills::table
             .filter(bills::id.eq(id as i32))
-            .first::<BillDb>(&mut conn)
+            .select(BillDb::as_select())
+            .first(&mut conn)
             .optional()?;
 
-        if let Some(b) = bill_db {
-            let status = match b.status.as_str() {
-                "Draft" => BillStatus::Draft,
-                "Sent" => BillStatus::Sent,
-                "Paid" => BillStatus::Paid,
-                "Overdue" => BillStatus::Overdue,
-                _ => BillStatus::Draft,
-            };
-
-            let items: Vec<BillItem> = serde_json::from_str(&b.items).unwrap_or_default();
-
-            let pdf_created_at = b.pdf_created_at.and_then(|s| {
-                chrono::DateTime::parse_from_rfc3339(&s)
-                    .ok()
-                    .map(|dt| dt.with_timezone(&chrono::Local))
-            });
-
-            Ok(Some(Bill {
-                id: b.id as u64,
-                client_id: b.client_id as u64,
-                date: chrono::DateTime::parse_from_rfc3339(&b.date)
-                    .unwrap()
-                    .with_timezone(&chrono::Local),
-                due_date: chrono::DateTime::parse_from_rfc3339(&b.due_date)
-                    .unwrap()
-                    .with_timezone(&chrono::Local),
-                reference: b.reference,
-                iban: b.iban,
-                notes: b.notes,
-                status,
-                items,
-                pdf_data: b.pdf_data,
-                pdf_created_at,
-            }))
-        } else {
-            Ok(None)
-        }
+        Ok(bill_db.map(bill_from_db))
     }
 
     pub fn get_all_bills(&self) -> Result<Vec<Bill>, Box<dyn Error>> {
@@ -336,65 +910,60 @@ impl Database {
 
         let bills_db: Vec<BillDb> = bills::table
             .order(bills::date.desc())
-            .load::<BillDb>(&mut conn)?;
-
-        let bills = bills_db.into_iter().map(|b| {
-            let status = match b.status.as_str() {
-                "Draft" => BillStatus::Draft,
-                "Sent" => BillStatus::Sent,
-                "Paid" => BillStatus::Paid,
-                "Overdue" => BillStatus::Overdue,
-                _ => BillStatus::Draft,
-            };
+            .select(BillDb::as_select())
+            .load(&mut conn)?;
 
-            let items: Vec<BillItem> = serde_json::from_str(&b.items).unwrap_or_default();
-
-            let pdf_created_at = b.pdf_created_at.and_then(|s| {
-                chrono::DateTime::parse_from_rfc3339(&s)
-                    .ok()
-                    .map(|dt| dt.with_timezone(&chrono::Local))
-            });
-
-            Bill {
-                id: b.id as u64,
-                client_id: b.client_id as u64,
-                date: chrono::DateTime::parse_from_rfc3339(&b.date)
-                    .unwrap()
-                    .with_timezone(&chrono::Local),
-                due_date: chrono::DateTime::parse_from_rfc3339(&b.due_date)
-                    .unwrap()
-                    .with_timezone(&chrono::Local),
-                reference: b.reference,
-                iban: b.iban,
-                notes: b.notes,
-                status,
-                items,
-                pdf_data: b.pdf_data,
-                pdf_created_at,
-            }
-        }).collect();
+        let bills = bills_db.into_iter().map(bill_from_db).collect();
 
         Ok(bills)
     }
 
-    pub fn delete_bill(&self, id: u64) -> Result<(), Box<dyn Error>> {
+    /// Deletes a bill with its attachments and, if `delete_pdf_file` is set, its PDF file.
+    /// Removing the PDF file is best effort: a failure is returned as a warning after the
+    /// bill itself was deleted.
+    pub fn delete_bill(&self, id: u64, delete_pdf_file: bool) -> Result<Option<String>, Box<dyn Error>> {
         let mut conn = self.get_conn()?;
 
-        diesel::delete(bills::table.filter(bills::id.eq(id as i32)))
-            .execute(&mut conn)?;
-
-        Ok(())
+        let old = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let old = bills::table
+                .find(id as i32)
+                .select(BillDb::as_select())
+                .first(conn)
+                .optional()?;
+            // Foreign keys aren't enforced by SQLite by default, so remove attachments and
+            // payments explicitly
+            diesel::delete(attachments::table.filter(attachments::bill_id.eq(id as i32)))
+                .execute(conn)?;
+            diesel::delete(payments::table.filter(payments::bill_id.eq(id as i32)))
+                .execute(conn)?;
+            diesel::delete(bills::table.filter(bills::id.eq(id as i32)))
+                .execute(conn)?;
+            log_audit(conn, "bill", id as i32, "delete", old.as_ref().and_then(|old| serde_json::to_string(old).ok()))?;
+            Ok(old)
+        })?;
+
+        std::fs::remove_dir_all(self.attachment_dir(id)).ok();
+
+        let Some(pdf_path) = old.and_then(|old| old.pdf_path) else {
+            return Ok(None);
+        };
+        let pdf_path = self.data_dir().join(pdf_path);
+        if !delete_pdf_file {
+            eprintln!("Bill {} deleted, its PDF was kept at {}", id, pdf_path.display());
+            return Ok(None);
+        }
+        match std::fs::remove_file(&pdf_path) {
+            Ok(()) => Ok(None),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Ok(Some(format!("Bill #{} was deleted, but its PDF {} could not be removed: {}", id, pdf_path.display(), e))),
+        }
     }
 
+    /// Id the next new bill gets: one past the highest, but at least the first bill number
     pub fn get_next_bill_id(&self) -> Result<u64, Box<dyn Error>> {
+        let first_number = self.get_first_bill_number()?.unwrap_or(1);
         let mut conn = self.get_conn()?;
-
-        let max_id: Option<Option<i32>> = bills::table
-            .select(diesel::dsl::max(bills::id))
-            .first(&mut conn)
-            .optional()?;
-
-        Ok((max_id.flatten().unwrap_or(0) + 1) as u64)
+        Ok(next_bill_id(&mut conn)?.max(first_number))
     }
 
     // Item template operations
@@ -406,25 +975,48 @@ impl Database {
             let new_template = NewItemTemplate {
                 item_type: template.item_type.clone(),
                 unit_price: template.unit_price,
+                vat_rate: template.vat_rate,
+                unit: template.unit.clone(),
             };
 
-            let id = diesel::insert_into(item_templates::table)
-                .values(&new_template)
-                .returning(item_templates::id)
-                .get_result::<i32>(&mut conn)?;
+            let id = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+                let id = diesel::insert_into(item_templates::table)
+                    .values(&new_template)
+                    .returning(item_templates::id)
+                    .get_result::<i32>(conn)?;
+                log_audit(conn, "item_template", id, "create", serde_json::to_string(&ItemTemplate { id: id as u64, ..template.clone() }).ok())?;
+                Ok(id)
+            })?;
 
             Ok(id as u64)
         } else {
-            // Update existing template
-            let template_db = ItemTemplateDb {
-                id: template.id as i32,
-                item_type: template.item_type.clone(),
-                unit_price: template.unit_price,
-            };
-
-            diesel::update(item_templates::table.filter(item_templates::id.eq(template.id as i32)))
-                .set(&template_db)
-                .execute(&mut conn)?;
+            // Update existing template; usage stats are only changed by `record_item_template_use`
+            conn.transaction::<_, diesel::result::Error, _>(|conn| {
+                let old = item_templates::table
+                    .find(template.id as i32)
+                    .select(ItemTemplateDb::as_select())
+                    .first(conn)
+                    .optional()?;
+                diesel::update(item_templates::table.filter(item_templates::id.eq(template.id as i32)))
+                    .set((
+                        item_templates::item_type.eq(&template.item_type),
+                        item_templates::unit_price.eq(template.unit_price),
+                        item_templates::vat_rate.eq(template.vat_rate),
+                        item_templates::unit.eq(&template.unit),
+                    ))
+                    .execute(conn)?;
+                let diff = old.and_then(|old| {
+                    let new = ItemTemplateDb {
+                        item_type: template.item_type.clone(),
+                        unit_price: template.unit_price,
+                        vat_rate: template.vat_rate,
+                        unit: template.unit.clone(),
+                        ..old.clone()
+                    };
+                    json_diff(&old, &new, &[])
+                });
+                log_audit(conn, "item_template", template.id as i32, "update", diff)
+            })?;
 
             Ok(template.id)
         }
@@ -442,21 +1034,417 @@ impl Database {
                 id: t.id as u64,
                 item_type: t.item_type,
                 unit_price: t.unit_price,
+                vat_rate: t.vat_rate,
+                unit: t.unit,
+                use_count: t.use_count.max(0) as u32,
+                last_used: t.last_used.as_deref().and_then(timezone::parse_stored),
             }
         }).collect();
 
         Ok(templates)
     }
 
-    pub fn delete_item_template(&self, id: u64) -> Result<(), Box<dyn Error>> {
+    pub fn record_item_template_use(&self, id: u64, used_at: &chrono::DateTime<chrono_tz::Tz>) -> Result<(), Box<dyn Error>> {
         let mut conn = self.get_conn()?;
 
-        diesel::delete(item_templates::table.filter(item_templates::id.eq(id as i32)))
+        diesel::update(item_templates::table.filter(item_templates::id.eq(id as i32)))
+            .set((
+                item_templates::use_count.eq(item_templates::use_count + 1),
+                item_templates::last_used.eq(timezone::to_stored(used_at)),
+            ))
             .execute(&mut conn)?;
 
         Ok(())
     }
 
+    // Discount template operations
+    pub fn save_discount_template(&self, template: &DiscountTemplate) -> Result<u64, Box<dyn Error>> {
+        let mut conn = self.get_conn()?;
+        let (kind, value) = discount_to_db(&template.discount);
+
+        if template.id == 0 {
+            let new_template = NewDiscountTemplate {
+                name: template.name.clone(),
+                kind: kind.to_string(),
+                value,
+            };
+
+            let id = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+                let id = diesel::insert_into(discount_templates::table)
+                    .values(&new_template)
+                    .returning(discount_templates::id)
+                    .get_result::<i32>(conn)?;
+                log_audit(conn, "discount_template", id, "create", serde_json::to_string(&DiscountTemplate { id: id as u64, ..template.clone() }).ok())?;
+                Ok(id)
+            })?;
+
+            Ok(id as u64)
+        } else {
+            conn.transaction::<_, diesel::result::Error, _>(|conn| {
+                let old = discount_templates::table
+                    .find(template.id as i32)
+                    .select(DiscountTemplateDb::as_select())
+                    .first(conn)
+                    .optional()?;
+                let new = DiscountTemplateDb {
+                    id: template.id as i32,
+                    name: template.name.clone(),
+                    kind: kind.to_string(),
+                    value,
+                };
+                diesel::update(discount_templates::table.filter(discount_templates::id.eq(new.id)))
+                    .set((
+                        discount_templates::name.eq(&new.name),
+                        discount_templates::kind.eq(&new.kind),
+                        discount_templates::value.eq(new.value),
+                    ))
+                    .execute(conn)?;
+                let diff = old.and_then(|old| json_diff(&old, &new, &[]));
+                log_audit(conn, "discount_template", template.id as i32, "update", diff)
+            })?;
+
+            Ok(template.id)
+        }
+    }
+
+    pub fn get_all_discount_templates(&self) -> Result<Vec<DiscountTemplate>, Box<dyn Error>> {
+        let mut conn = self.get_conn()?;
+
+        let templates_db: Vec<DiscountTemplateDb> = discount_templates::table
+            .order(discount_templates::name.asc())
+            .select(DiscountTemplateDb::as_select())
+            .load(&mut conn)?;
+
+        Ok(templates_db
+            .into_iter()
+            .map(|t| DiscountTemplate {
+                id: t.id as u64,
+                name: t.name,
+                discount: match t.kind.as_str() {
+                    "amount" => Discount::Amount(t.value),
+                    _ => Discount::Percent(t.value),
+                },
+            })
+            .collect())
+    }
+
+    pub fn delete_discount_template(&self, id: u64) -> Result<(), Box<dyn Error>> {
+        let mut conn = self.get_conn()?;
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let old = discount_templates::table
+                .find(id as i32)
+                .select(DiscountTemplateDb::as_select())
+                .first(conn)
+                .optional()?;
+            diesel::delete(discount_templates::table.filter(discount_templates::id.eq(id as i32)))
+                .execute(conn)?;
+            log_audit(conn, "discount_template", id as i32, "delete", old.and_then(|old| serde_json::to_string(&old).ok()))
+        })?;
+
+        Ok(())
+    }
+
+    // Bill template operations
+    pub fn save_bill_template(&self, template: &BillTemplate) -> Result<u64, Box<dyn Error>> {
+        let mut conn = self.get_conn()?;
+        let items = serde_json::to_string(&template.items)?;
+
+        if template.id == 0 {
+            let new_template = NewBillTemplate {
+                name: template.name.clone(),
+                items,
+                notes: template.notes.clone(),
+                payment_days: template.payment_days as i32,
+            };
+
+            let id = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+                let id = diesel::insert_into(bill_templates::table)
+                    .values(&new_template)
+                    .returning(bill_templates::id)
+                    .get_result::<i32>(conn)?;
+                log_audit(conn, "bill_template", id, "create", serde_json::to_string(&BillTemplate { id: id as u64, ..template.clone() }).ok())?;
+                Ok(id)
+            })?;
+
+            Ok(id as u64)
+        } else {
+            conn.transaction::<_, diesel::result::Error, _>(|conn| {
+                let old = bill_templates::table
+                    .find(template.id as i32)
+                    .select(BillTemplateDb::as_select())
+                    .first(conn)
+                    .optional()?;
+                let new = BillTemplateDb {
+                    id: template.id as i32,
+                    name: template.name.clone(),
+                    items,
+                    notes: template.notes.clone(),
+                    payment_days: template.payment_days as i32,
+                };
+                diesel::update(bill_templates::table.filter(bill_templates::id.eq(new.id)))
+                    .set((
+                        bill_templates::name.eq(&new.name),
+                        bill_templates::items.eq(&new.items),
+                        bill_templates::notes.eq(&new.notes),
+                        bill_templates::payment_days.eq(new.payment_days),
+                    ))
+                    .execute(conn)?;
+                let diff = old.and_then(|old| json_diff(&old, &new, &[]));
+                log_audit(conn, "bill_template", template.id as i32, "update", diff)
+            })?;
+
+            Ok(template.id)
+        }
+    }
+
+    /// All bill templates by name; a template with unreadable items is loaded without items
+    pub fn get_all_bill_templates(&self) -> Result<Vec<BillTemplate>, Box<dyn Error>> {
+        let mut conn = self.get_conn()?;
+
+        let templates_db: Vec<BillTemplateDb> = bill_templates::table
+            .order(bill_templates::name.asc())
+            .select(BillTemplateDb::as_select())
+            .load(&mut conn)?;
+
+        Ok(templates_db
+            .into_iter()
+            .map(|t| BillTemplate {
+                id: t.id as u64,
+                items: serde_json::from_str(&t.items).unwrap_or_else(|e| {
+                    eprintln!("Bill template #{}: items could not be read: {}", t.id, e);
+                    Vec::new()
+                }),
+                name: t.name,
+                notes: t.notes,
+                payment_days: t.payment_days as i64,
+            })
+            .collect())
+    }
+
+    pub fn delete_bill_template(&self, id: u64) -> Result<(), Box<dyn Error>> {
+        let mut conn = self.get_conn()?;
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let old = bill_templates::table
+                .find(id as i32)
+                .select(BillTemplateDb::as_select())
+                .first(conn)
+                .optional()?;
+            diesel::delete(bill_templates::table.filter(bill_templates::id.eq(id as i32)))
+                .execute(conn)?;
+            log_audit(conn, "bill_template", id as i32, "delete", old.and_then(|old| serde_json::to_string(&old).ok()))
+        })?;
+
+        Ok(())
+    }
+
+    pub fn delete_item_template(&self, id: u64) -> Result<(), Box<dyn Error>> {
+        let mut conn = self.get_conn()?;
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let old = item_templates::table
+                .find(id as i32)
+                .select(ItemTemplateDb::as_select())
+                .first(conn)
+                .optional()?;
+            diesel::delete(item_templates::table.filter(item_templates::id.eq(id as i32)))
+                .execute(conn)?;
+            log_audit(conn, "item_template", id as i32, "delete", old.and_then(|old| serde_json::to_string(&old).ok()))
+        })?;
+
+        Ok(())
+    }
+
+    // Attachment operations
+    /// Copies `source` below the data folder and records it as an attachment of the bill
+    pub fn add_attachment(&self, bill_id: u64, source: &Path) -> Result<Attachment, Box<dyn Error>> {
+        let mut conn = self.get_conn()?;
+
+        let filename = source
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .ok_or("Not a file")?;
+        let added_at = timezone::now();
+
+        // Prefix with a unique number so attaching two files with the same name keeps both
+        let dir = self.attachment_dir(bill_id);
+        std::fs::create_dir_all(&dir)?;
+        let mut prefix = added_at.timestamp_millis();
+        let mut stored_name = format!("{}_{}", prefix, filename.replace(['/', '\\'], "_"));
+        while dir.join(&stored_name).exists() {
+            prefix += 1;
+            stored_name = format!("{}_{}", prefix, filename.replace(['/', '\\'], "_"));
+        }
+        let target = dir.join(&stored_name);
+        std::fs::copy(source, &target)?;
+
+        let new_attachment = NewAttachment {
+            bill_id: bill_id as i32,
+            mime: mime_for_filename(&filename).to_string(),
+            filename,
+            path: format!("attachments/{}/{}", bill_id, stored_name),
+            added_at: timezone::to_stored(&added_at),
+            append_to_pdf: false,
+        };
+
+        let result = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let id = diesel::insert_into(attachments::table)
+                .values(&new_attachment)
+                .returning(attachments::id)
+                .get_result::<i32>(conn)?;
+            let details = serde_json::json!({ "bill_id": bill_id, "filename": new_attachment.filename }).to_string();
+            log_audit(conn, "attachment", id, "create", Some(details))?;
+            Ok(id)
+        });
+
+        let id = match result {
+            Ok(id) => id,
+            Err(e) => {
+                std::fs::remove_file(&target).ok();
+                return Err(e.into());
+            }
+        };
+
+        Ok(Attachment {
+            id: id as u64,
+            bill_id,
+            filename: new_attachment.filename,
+            mime: new_attachment.mime,
+            path: new_attachment.path,
+            added_at: Some(added_at),
+            append_to_pdf: false,
+        })
+    }
+
+    pub fn get_attachments(&self, bill_id: u64) -> Result<Vec<Attachment>, Box<dyn Error>> {
+        let mut conn = self.get_conn()?;
+
+        let rows: Vec<AttachmentDb> = attachments::table
+            .filter(attachments::bill_id.eq(bill_id as i32))
+            .order(attachments::id.asc())
+            .select(AttachmentDb::as_select())
+            .load(&mut conn)?;
+
+        Ok(rows.into_iter().map(attachment_from_db).collect())
+    }
+
+    /// Sets whether a PDF attachment is appended to the bill's generated PDF
+    pub fn set_attachment_append(&self, id: u64, append: bool) -> Result<(), Box<dyn Error>> {
+        let mut conn = self.get_conn()?;
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            diesel::update(attachments::table.filter(attachments::id.eq(id as i32)))
+                .set(attachments::append_to_pdf.eq(append))
+                .execute(conn)?;
+            let details = serde_json::json!({ "append_to_pdf": [!append, append] }).to_string();
+            log_audit(conn, "attachment", id as i32, "update", Some(details))
+        })?;
+
+        Ok(())
+    }
+
+    /// Deletes an attachment's row and then its file
+    pub fn remove_attachment(&self, id: u64) -> Result<(), Box<dyn Error>> {
+        let mut conn = self.get_conn()?;
+
+        let row = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let row = attachments::table
+                .find(id as i32)
+                .select(AttachmentDb::as_select())
+                .first(conn)
+                .optional()?;
+            diesel::delete(attachments::table.filter(attachments::id.eq(id as i32)))
+                .execute(conn)?;
+            log_audit(conn, "attachment", id as i32, "delete", row.as_ref().and_then(|r| serde_json::to_string(r).ok()))?;
+            Ok(row)
+        })?;
+
+        if let Some(row) = row {
+            std::fs::remove_file(self.data_dir().join(row.path)).ok();
+        }
+
+        Ok(())
+    }
+
+    // Payment operations
+    /// Records payments and sets the bills in `paid_bill_ids` to Paid, all in one transaction.
+    /// Returns the ids of the new payments in the given order.
+    pub fn add_payments(&self, payments: &[Payment], paid_bill_ids: &[u64]) -> Result<Vec<u64>, Box<dyn Error>> {
+        let mut conn = self.get_conn()?;
+
+        let rows: Vec<NewPayment> = payments
+            .iter()
+            .map(|p| NewPayment {
+                bill_id: p.bill_id as i32,
+                amount: p.amount,
+                paid_on: p.paid_on.format("%Y-%m-%d").to_string(),
+                note: p.note.clone(),
+                source: p.source.clone(),
+            })
+            .collect();
+
+        let ids = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let mut ids = Vec::with_capacity(rows.len());
+            for row in &rows {
+                let id = diesel::insert_into(payments::table)
+                    .values(row)
+                    .returning(payments::id)
+                    .get_result::<i32>(conn)?;
+                log_audit(conn, "payment", id, "create", serde_json::to_string(row).ok())?;
+                ids.push(id as u64);
+            }
+            for id in paid_bill_ids {
+                diesel::update(bills::table.filter(bills::id.eq(*id as i32)))
+                    .set(bills::status.eq(status_to_db(BillStatus::Paid)))
+                    .execute(conn)?;
+                let details = serde_json::json!({ "status": status_to_db(BillStatus::Paid) }).to_string();
+                log_audit(conn, "bill", *id as i32, "status", Some(details))?;
+            }
+            Ok(ids)
+        })?;
+
+        Ok(ids)
+    }
+
+    pub fn get_all_payments(&self) -> Result<Vec<Payment>, Box<dyn Error>> {
+        let mut conn = self.get_conn()?;
+
+        let rows: Vec<PaymentDb> = payments::table
+            .order(payments::id.asc())
+            .select(PaymentDb::as_select())
+            .load(&mut conn)?;
+
+        Ok(rows.into_iter().filter_map(payment_from_db).collect())
+    }
+
+    /// Most recent audit log entries, newest first
+    pub fn get_recent_audit_events(&self, limit: i64) -> Result<Vec<AuditEvent>, Box<dyn Error>> {
+        let mut conn = self.get_conn()?;
+
+        let entries: Vec<AuditLogEntry> = audit_log::table
+            .order(audit_log::id.desc())
+            .limit(limit)
+            .select(AuditLogEntry::as_select())
+            .load(&mut conn)?;
+
+        Ok(entries.into_iter().map(audit_event_from_db).collect())
+    }
+
+    /// All recorded changes of one entity, oldest first
+    pub fn get_audit_events_for(&self, entity_type: &str, entity_id: u64) -> Result<Vec<AuditEvent>, Box<dyn Error>> {
+        let mut conn = self.get_conn()?;
+
+        let entries: Vec<AuditLogEntry> = audit_log::table
+            .filter(audit_log::entity_type.eq(entity_type))
+            .filter(audit_log::entity_id.eq(entity_id as i32))
+            .order(audit_log::id.asc())
+            .select(AuditLogEntry::as_select())
+            .load(&mut conn)?;
+
+        Ok(entries.into_iter().map(audit_event_from_db).collect())
+    }
+
     pub fn get_next_template_id(&self) -> Result<u64, Box<dyn Error>> {
         let mut conn = self.get_conn()?;
 
@@ -468,3 +1456,385 @@ impl Database {
         Ok((max_id.flatten().unwrap_or(0) + 1) as u64)
     }
 }
+
+fn payment_from_db(p: PaymentDb) -> Option<Payment> {
+    let Ok(paid_on) = chrono::NaiveDate::parse_from_str(&p.paid_on, "%Y-%m-%d") else {
+        eprintln!("Skipping payment {} with invalid date '{}'", p.id, p.paid_on);
+        return None;
+    };
+    Some(Payment {
+        id: p.id as u64,
+        bill_id: p.bill_id as u64,
+        amount: p.amount,
+        paid_on,
+        note: p.note,
+        source: p.source,
+    })
+}
+
+fn attachment_from_db(a: AttachmentDb) -> Attachment {
+    Attachment {
+        id: a.id as u64,
+        bill_id: a.bill_id as u64,
+        filename: a.filename,
+        mime: a.mime,
+        path: a.path,
+        added_at: timezone::parse_stored(&a.added_at),
+        append_to_pdf: a.append_to_pdf,
+    }
+}
+
+/// MIME type guessed from the file extension
+fn mime_for_filename(filename: &str) -> &'static str {
+    let extension = Path::new(filename)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "pdf" => "application/pdf",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "txt" => "text/plain",
+        "csv" => "text/csv",
+        "doc" => "application/msword",
+        "docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        "xls" => "application/vnd.ms-excel",
+        "xlsx" => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        "zip" => "application/zip",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Appends an entry to the audit log; called inside the transaction of the change it records
+fn log_audit(
+    conn: &mut SqliteConnection,
+    entity_type: &str,
+    entity_id: i32,
+    action: &str,
+    details: Option<String>,
+) -> QueryResult<()> {
+    diesel::insert_into(audit_log::table)
+        .values(&NewAuditLogEntry {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            entity_type: entity_type.to_string(),
+            entity_id,
+            action: action.to_string(),
+            details,
+        })
+        .execute(conn)?;
+    Ok(())
+}
+
+/// JSON object of the fields that differ between two rows, as `{"field": [old, new]}`.
+/// Returns `None` if nothing changed apart from the `ignored` fields.
+fn json_diff<T: serde::Serialize>(old: &T, new: &T, ignored: &[&str]) -> Option<String> {
+    let (Ok(serde_json::Value::Object(old)), Ok(serde_json::Value::Object(new))) =
+        (serde_json::to_value(old), serde_json::to_value(new))
+    else {
+        return None;
+    };
+
+    let changes: serde_json::Map<String, serde_json::Value> = new
+        .into_iter()
+        .filter(|(key, _)| !ignored.contains(&key.as_str()))
+        .filter_map(|(key, value)| {
+            let previous = old.get(&key).cloned().unwrap_or(serde_json::Value::Null);
+            (previous != value).then(|| (key, serde_json::json!([previous, value])))
+        })
+        .collect();
+
+    (!changes.is_empty()).then(|| serde_json::Value::Object(changes).to_string())
+}
+
+fn status_to_db(status: BillStatus) -> &'static str {
+    match status {
+        BillStatus::Draft => "Draft",
+        BillStatus::Sent => "Sent",
+        BillStatus::Paid => "Paid",
+        BillStatus::Overdue => "Overdue",
+        BillStatus::WrittenOff => "WrittenOff",
+    }
+}
+
+fn amount_mode_to_db(mode: AmountMode) -> &'static str {
+    match mode {
+        AmountMode::Fixed => "Fixed",
+        AmountMode::Open => "Open",
+    }
+}
+
+fn discount_to_db(discount: &Discount) -> (&'static str, f64) {
+    match discount {
+        Discount::Percent(value) => ("percent", *value),
+        Discount::Amount(value) => ("amount", *value),
+    }
+}
+
+fn bill_to_db(bill: &Bill) -> Result<BillDb, Box<dyn Error>> {
+    Ok(BillDb {
+        id: bill.id as i32,
+        client_id: bill.client_id as i32,
+        date: timezone::to_stored(&bill.date),
+        due_date: timezone::to_stored(&bill.due_date),
+        reference: bill.reference.clone(),
+        iban: bill.iban.clone(),
+        notes: bill.notes.clone(),
+        status: status_to_db(bill.status).to_string(),
+        items: serde_json::to_string(&bill.items)?,
+        pdf_created_at: bill.pdf_created_at.as_ref().map(timezone::to_stored),
+        amount_mode: amount_mode_to_db(bill.amount_mode).to_string(),
+        language: bill.language.code().to_string(),
+        currency: bill.currency.clone(),
+        pdf_hash: bill.pdf_hash.clone(),
+        // The PDF location is only changed by `save_bill_pdf`; `None` leaves it untouched
+        pdf_path: None,
+        prices_include_vat: bill.prices_include_vat,
+        discount: bill.discount.as_ref().map(serde_json::to_string).transpose()?,
+        reminder_level: bill.reminder_level as i32,
+        last_reminder_at: bill.last_reminder_at.as_ref().map(timezone::to_stored),
+        reference_type: bill.reference_type.code().to_string(),
+        po_number: bill.po_number.clone(),
+        service_from: bill.service_from.map(|d| d.format("%Y-%m-%d").to_string()),
+        service_to: bill.service_to.map(|d| d.format("%Y-%m-%d").to_string()),
+        archived_at: bill.archived_at.as_ref().map(timezone::to_stored),
+        follow_up: bill.follow_up.as_ref().map(timezone::to_stored),
+        include_qr_bill: bill.include_qr_bill,
+        conversion: bill.conversion.as_ref().map(serde_json::to_string).transpose()?,
+        round_to_five_cents: bill.round_to_five_cents,
+        document_type: bill.document_type.code().to_string(),
+        credited_bill_id: bill.credited_bill_id.map(|id| id as i32),
+    })
+}
+
+fn bill_from_db(b: BillDb) -> Bill {
+    // Unknown strings (e.g. written by a newer version) show up as drafts instead of failing the load
+    let status = BillStatus::parse(&b.status).unwrap_or_else(|| {
+        eprintln!("Unknown status '{}' on bill {}, treating it as Draft", b.status, b.id);
+        BillStatus::Draft
+    });
+
+    let amount_mode = match b.amount_mode.as_str() {
+        "Open" => AmountMode::Open,
+        _ => AmountMode::Fixed,
+    };
+
+    let mut load_problems = Vec::new();
+
+    // A corrupt row must not keep the other bills from loading; it is flagged for repair instead
+    let items: Vec<BillItem> = serde_json::from_str(&b.items).unwrap_or_else(|e| {
+        eprintln!("Unreadable items on bill {}: {}", b.id, e);
+        load_problems.push(format!("Items could not be read ({}) and were left empty", e));
+        Vec::new()
+    });
+    let date = parse_bill_date(&b.date);
+    let due_date = parse_bill_date(&b.due_date);
+    if date.is_none() {
+        eprintln!("Invalid date '{}' on bill {}", b.date, b.id);
+        load_problems.push(format!("Invalid date '{}'", b.date));
+    }
+    if due_date.is_none() {
+        eprintln!("Invalid due date '{}' on bill {}", b.due_date, b.id);
+        load_problems.push(format!("Invalid due date '{}'", b.due_date));
+    }
+    // Fall back to the other date, or today, so the bill can be opened and fixed
+    let date = date.or(due_date).unwrap_or_else(timezone::now);
+    let due_date = due_date.unwrap_or(date);
+
+    let pdf_created_at = b.pdf_created_at.as_deref().and_then(timezone::parse_stored);
+
+    Bill {
+        id: b.id as u64,
+        client_id: b.client_id as u64,
+        date,
+        due_date,
+        reference: b.reference,
+        iban: b.iban,
+        notes: b.notes,
+        status,
+        amount_mode,
+        language: Language::from_code(&b.language).unwrap_or_default(),
+        currency: b.currency,
+        pdf_hash: b.pdf_hash,
+        has_pdf: b.pdf_path.is_some(),
+        prices_include_vat: b.prices_include_vat,
+        discount: b.discount.and_then(|json| serde_json::from_str(&json).ok()),
+        reminder_level: b.reminder_level.clamp(0, u8::MAX as i32) as u8,
+        last_reminder_at: b.last_reminder_at.as_deref().and_then(timezone::parse_stored),
+        reference_type: ReferenceType::from_code(&b.reference_type).unwrap_or_default(),
+        po_number: b.po_number,
+        service_from: b.service_from.as_deref().and_then(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").ok()),
+        service_to: b.service_to.as_deref().and_then(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").ok()),
+        archived_at: b.archived_at.as_deref().and_then(timezone::parse_stored),
+        follow_up: b.follow_up.as_deref().and_then(timezone::parse_stored),
+        include_qr_bill: b.include_qr_bill,
+        conversion: b.conversion.and_then(|json| serde_json::from_str(&json).ok()),
+        round_to_five_cents: b.round_to_five_cents,
+        document_type: DocumentType::from_code(&b.document_type).unwrap_or_default(),
+        credited_bill_id: b.credited_bill_id.map(|id| id as u64),
+        items,
+        pdf_created_at,
+        load_problems,
+    }
+}
+
+fn audit_event_from_db(e: AuditLogEntry) -> AuditEvent {
+    AuditEvent {
+        timestamp: timezone::parse_stored(&e.timestamp),
+        entity_type: e.entity_type,
+        entity_id: e.entity_id as u64,
+        action: e.action,
+        details: e.details,
+    }
+}
+
+/// One past the highest bill id, as SQLite would assign it
+fn next_bill_id(conn: &mut SqliteConnection) -> Result<u64, diesel::result::Error> {
+    let max_id: Option<i32> = bills::table
+        .select(diesel::dsl::max(bills::id))
+        .first(conn)?;
+    Ok((max_id.unwrap_or(0) + 1) as u64)
+}
+
+/// RFC 3339 timestamp of a bill column, in the display zone
+fn parse_bill_date(value: &str) -> Option<chrono::DateTime<chrono_tz::Tz>> {
+    timezone::parse_stored(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Empty database in a temporary folder, deleted when the folder is dropped
+    fn test_db() -> (tempfile::TempDir, Database) {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Database::new(dir.path().join("bills.db").to_str().unwrap()).expect("Failed to open test database");
+        (dir, db)
+    }
+
+    #[test]
+    fn recording_a_template_use_increments_its_count() {
+        let (_dir, db) = test_db();
+        let template = ItemTemplate {
+            item_type: "Consulting".to_string(),
+            unit_price: 150.0,
+            ..ItemTemplate::default()
+        };
+        let id = db.save_item_template(&template).unwrap();
+
+        let used_at = crate::timezone::now();
+        db.record_item_template_use(id, &used_at).unwrap();
+        db.record_item_template_use(id, &used_at).unwrap();
+
+        let saved = db.get_all_item_templates().unwrap().into_iter().find(|t| t.id == id).unwrap();
+        assert_eq!(saved.use_count, 2);
+        assert_eq!(saved.last_used.map(|dt| dt.timestamp()), Some(used_at.timestamp()));
+    }
+
+    /// Saves a client with one bill and returns the bill's id
+    fn save_test_bill(db: &Database) -> u64 {
+        let client_id = db.save_client(&Client { name: "Muster AG".to_string(), ..Client::default() }).unwrap();
+        db.save_bill(&Bill { client_id, ..Bill::default() }).unwrap()
+    }
+
+    #[test]
+    fn orphaned_rows_are_deleted_with_an_audit_entry_each() {
+        let (dir, db) = test_db();
+        let bill_id = save_test_bill(&db);
+        let source = dir.path().join("receipt.pdf");
+        std::fs::write(&source, b"%PDF-1.7 receipt").unwrap();
+        let attachment = db.add_attachment(bill_id, &source).unwrap();
+
+        // Left behind by a bill deleted outside the app
+        diesel::delete(bills::table.find(bill_id as i32)).execute(&mut db.get_conn().unwrap()).unwrap();
+        assert_eq!(db.delete_orphaned_rows().unwrap(), 1);
+
+        assert!(db.get_attachments(bill_id).unwrap().is_empty());
+        let events = db.get_audit_events_for("attachment", attachment.id).unwrap();
+        assert_eq!(events.last().map(|e| e.action.as_str()), Some("delete"));
+        assert_eq!(db.delete_orphaned_rows().unwrap(), 0);
+    }
+
+    #[test]
+    fn attachments_can_be_added_listed_and_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Database::new(dir.path().join("bills.db").to_str().unwrap()).unwrap();
+        let bill_id = save_test_bill(&db);
+
+        let source = dir.path().join("receipt.pdf");
+        std::fs::write(&source, b"%PDF-1.7 receipt").unwrap();
+        let attachment = db.add_attachment(bill_id, &source).unwrap();
+        let stored = db.data_dir().join(&attachment.path);
+        assert_eq!(attachment.filename, "receipt.pdf");
+        assert_eq!(attachment.mime, "application/pdf");
+        assert_eq!(std::fs::read(&stored).unwrap(), b"%PDF-1.7 receipt");
+
+        // A second file with the same name is kept next to the first
+        let second = db.add_attachment(bill_id, &source).unwrap();
+        assert_ne!(second.path, attachment.path);
+        let listed = db.get_attachments(bill_id).unwrap();
+        assert_eq!(listed.iter().map(|a| a.id).collect::<Vec<_>>(), [attachment.id, second.id]);
+
+        db.remove_attachment(attachment.id).unwrap();
+        assert_eq!(db.get_attachments(bill_id).unwrap().iter().map(|a| a.id).collect::<Vec<_>>(), [second.id]);
+        assert!(!stored.exists());
+    }
+
+    #[test]
+    fn item_template_vat_rate_and_unit_round_trip() {
+        let (_dir, db) = test_db();
+        let template = ItemTemplate {
+            item_type: "Beratung".to_string(),
+            unit_price: 180.0,
+            vat_rate: 8.1,
+            unit: "h".to_string(),
+            ..ItemTemplate::default()
+        };
+        let id = db.save_item_template(&template).unwrap();
+
+        let saved = db.get_all_item_templates().unwrap().into_iter().find(|t| t.id == id).unwrap();
+        assert_eq!(saved, ItemTemplate { id, ..template.clone() });
+
+        // Updates keep them too
+        db.save_item_template(&ItemTemplate { id, vat_rate: 2.6, unit: "Stk.".to_string(), ..template }).unwrap();
+        let updated = db.get_all_item_templates().unwrap().into_iter().find(|t| t.id == id).unwrap();
+        assert_eq!((updated.vat_rate, updated.unit.as_str()), (2.6, "Stk."));
+    }
+
+    #[test]
+    fn unreadable_bill_columns_are_flagged_instead_of_failing_the_load() {
+        let (_dir, db) = test_db();
+        let bill_id = save_test_bill(&db);
+        save_test_bill(&db);
+
+        diesel::update(bills::table.filter(bills::id.eq(bill_id as i32)))
+            .set((bills::items.eq("[{not json"), bills::date.eq("yesterday")))
+            .execute(&mut db.get_conn().unwrap())
+            .unwrap();
+
+        let bills = db.get_all_bills().unwrap();
+        assert_eq!(bills.len(), 2);
+        let broken = bills.iter().find(|b| b.id == bill_id).unwrap();
+        assert!(broken.items.is_empty());
+        assert_eq!(broken.load_problems.len(), 2);
+        assert!(broken.load_problems[0].starts_with("Items could not be read"));
+        assert_eq!(broken.load_problems[1], "Invalid date 'yesterday'");
+        // The fallback date is the readable due date
+        assert_eq!(broken.date, broken.due_date);
+        assert!(bills.iter().any(|b| b.id != bill_id && b.load_problems.is_empty()));
+    }
+
+    #[test]
+    fn first_bill_number_sets_the_floor_of_new_ids() {
+        let (_dir, db) = test_db();
+        db.save_first_bill_number(1001).unwrap();
+        assert_eq!(db.get_next_bill_id().unwrap(), 1001);
+
+        assert_eq!(save_test_bill(&db), 1001);
+        assert_eq!(save_test_bill(&db), 1002);
+        assert_eq!(db.get_next_bill_id().unwrap(), 1003);
+    }
+}
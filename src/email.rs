@@ -0,0 +1,212 @@
+use lettre::message::header::ContentType;
+use lettre::message::{Attachment, Mailbox, MultiPart, SinglePart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::app::{Bill, Client};
+use crate::types::Address;
+
+pub const DEFAULT_SUBJECT: &str = "Rechnung {invoice_number}";
+pub const DEFAULT_BODY: &str = "Guten Tag\n\n\
+Im Anhang finden Sie die Rechnung {invoice_number} über {currency} {total}, zahlbar bis {due_date}.\n\n\
+Freundliche Grüsse\n{company}";
+pub const DEFAULT_REMINDER_SUBJECT: &str = "Zahlungserinnerung zu Rechnung {invoice_number}";
+pub const DEFAULT_REMINDER_BODY: &str = "Guten Tag\n\n\
+Unsere Rechnung {invoice_number} über {currency} {total} war am {due_date} fällig. Im Anhang finden Sie die Zahlungserinnerung. \
+Sollte sich Ihre Zahlung mit dieser Nachricht gekreuzt haben, betrachten Sie sie bitte als gegenstandslos.\n\n\
+Freundliche Grüsse\n{company}";
+
+/// How the connection to the SMTP server is secured
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SmtpSecurity {
+    /// Plain connection upgraded with STARTTLS, usually port 587
+    #[default]
+    StartTls,
+    /// TLS from the start, usually port 465
+    Tls,
+    /// Unencrypted, only for local relays
+    None,
+}
+
+impl SmtpSecurity {
+    pub const ALL: [SmtpSecurity; 3] = [SmtpSecurity::StartTls, SmtpSecurity::Tls, SmtpSecurity::None];
+
+    pub fn default_port(&self) -> u16 {
+        match self {
+            SmtpSecurity::StartTls => 587,
+            SmtpSecurity::Tls => 465,
+            SmtpSecurity::None => 25,
+        }
+    }
+}
+
+impl std::fmt::Display for SmtpSecurity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SmtpSecurity::StartTls => write!(f, "STARTTLS"),
+            SmtpSecurity::Tls => write!(f, "TLS"),
+            SmtpSecurity::None => write!(f, "None"),
+        }
+    }
+}
+
+/// SMTP server, sender account and message texts for invoice emails
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SmtpSettings {
+    pub host: String,
+    pub port: u16,
+    pub security: SmtpSecurity,
+    /// Empty to send without authentication
+    pub username: String,
+    pub password: String,
+    pub from: String,
+    /// Subject template, see `render_email_text` for placeholders
+    pub subject: String,
+    /// Body template, see `render_email_text` for placeholders
+    pub body: String,
+    /// Subject template of payment reminder emails
+    pub reminder_subject: String,
+    /// Body template of payment reminder emails
+    pub reminder_body: String,
+    /// Pause between two emails of a batch, so the server doesn't throttle or reject them
+    pub batch_delay_secs: u64,
+}
+
+impl Default for SmtpSettings {
+    fn default() -> Self {
+        SmtpSettings {
+            host: String::new(),
+            port: SmtpSecurity::default().default_port(),
+            security: SmtpSecurity::default(),
+            username: String::new(),
+            password: String::new(),
+            from: String::new(),
+            subject: DEFAULT_SUBJECT.to_string(),
+            body: DEFAULT_BODY.to_string(),
+            reminder_subject: DEFAULT_REMINDER_SUBJECT.to_string(),
+            reminder_body: DEFAULT_REMINDER_BODY.to_string(),
+            batch_delay_secs: 5,
+        }
+    }
+}
+
+impl SmtpSettings {
+    pub fn is_configured(&self) -> bool {
+        !self.host.trim().is_empty() && !self.from.trim().is_empty()
+    }
+
+    fn transport(&self) -> Result<SmtpTransport, String> {
+        let host = self.host.trim();
+        let builder = match self.security {
+            SmtpSecurity::StartTls => SmtpTransport::starttls_relay(host),
+            SmtpSecurity::Tls => SmtpTransport::relay(host),
+            SmtpSecurity::None => Ok(SmtpTransport::builder_dangerous(host)),
+        }
+        .map_err(|e| format!("Invalid SMTP server {}: {}", host, e))?
+        .port(self.port)
+        .timeout(Some(Duration::from_secs(30)));
+
+        let builder = if self.username.trim().is_empty() {
+            builder
+        } else {
+            builder.credentials(Credentials::new(self.username.trim().to_string(), self.password.clone()))
+        };
+        Ok(builder.build())
+    }
+}
+
+/// An invoice email with all recipients and texts resolved
+#[derive(Debug, Clone)]
+pub struct OutgoingEmail {
+    pub to: String,
+    pub cc: Vec<String>,
+    pub bcc: Vec<String>,
+    pub reply_to: Option<String>,
+    pub subject: String,
+    pub body: String,
+    pub attachment_name: String,
+    pub attachment: Vec<u8>,
+}
+
+/// Non-empty, trimmed entries of an address list setting
+pub fn address_list(entries: &[String]) -> Vec<String> {
+    entries
+        .iter()
+        .map(|e| e.trim())
+        .filter(|e| !e.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Fills the placeholders {invoice_number}, {client_name}, {currency}, {total}, {due_date},
+/// {reference} and {company} of an email subject or body
+pub fn render_email_text(template: &str, bill: &Bill, client: &Client, creditor: &Address) -> String {
+    template
+        .replace("{invoice_number}", &bill.id.to_string())
+        .replace("{client_name}", &client.name)
+        .replace("{currency}", &bill.currency)
+        .replace("{total}", &format!("{:.2}", bill.total()))
+        .replace("{due_date}", &bill.due_date.format("%d.%m.%Y").to_string())
+        .replace("{reference}", &bill.reference)
+        .replace("{company}", &creditor.name)
+}
+
+fn mailbox(address: &str, role: &str) -> Result<Mailbox, String> {
+    address
+        .trim()
+        .parse()
+        .map_err(|e| format!("Invalid {} address '{}': {}", role, address, e))
+}
+
+/// Connects to the SMTP server and logs in without sending anything; blocks until done
+pub fn test_connection(settings: &SmtpSettings) -> Result<(), String> {
+    if settings.host.trim().is_empty() {
+        return Err("No SMTP server is set".to_string());
+    }
+    match settings.transport()?.test_connection() {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(format!("{} did not accept the connection", settings.host.trim())),
+        Err(e) => Err(format!("Failed to connect to {}: {}", settings.host.trim(), e)),
+    }
+}
+
+/// Sends an email with its PDF attachment; blocks until the server accepted or rejected it
+pub fn send(settings: &SmtpSettings, email: &OutgoingEmail) -> Result<(), String> {
+    if !settings.is_configured() {
+        return Err("SMTP server and sender address must be set in Settings".to_string());
+    }
+
+    let mut builder = Message::builder()
+        .from(mailbox(&settings.from, "sender")?)
+        .to(mailbox(&email.to, "recipient")?)
+        .subject(email.subject.clone());
+    for cc in &email.cc {
+        builder = builder.cc(mailbox(cc, "CC")?);
+    }
+    for bcc in &email.bcc {
+        builder = builder.bcc(mailbox(bcc, "BCC")?);
+    }
+    if let Some(reply_to) = &email.reply_to {
+        builder = builder.reply_to(mailbox(reply_to, "reply-to")?);
+    }
+
+    let message = builder
+        .multipart(
+            MultiPart::mixed()
+                .singlepart(SinglePart::plain(email.body.clone()))
+                .singlepart(
+                    Attachment::new(email.attachment_name.clone())
+                        .body(email.attachment.clone(), ContentType::parse("application/pdf").unwrap()),
+                ),
+        )
+        .map_err(|e| format!("Failed to build email: {}", e))?;
+
+    settings
+        .transport()?
+        .send(&message)
+        .map_err(|e| format!("Failed to send email via {}: {}", settings.host.trim(), e))?;
+    Ok(())
+}
@@ -0,0 +1,526 @@
+use chrono::{Datelike, Timelike};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::Path;
+
+use crate::app::{AmountMode, Bill, Client, Payment};
+
+/// Format marker of a shared client file
+pub const CLIENT_EXPORT_FORMAT: &str = "bill-manager/client";
+/// Version of the shared client format; raise it on incompatible changes only, new optional
+/// client fields are read by older versions' successors through `#[serde(default)]`
+pub const CLIENT_EXPORT_VERSION: u32 = 1;
+
+/// A single client as shared with someone else
+#[derive(Debug, Serialize)]
+struct ClientExport<'a> {
+    format: &'static str,
+    version: u32,
+    client: &'a Client,
+}
+
+/// Several clients in one file, same format marker as a single client
+#[derive(Debug, Serialize)]
+struct ClientsExport<'a> {
+    format: &'static str,
+    version: u32,
+    clients: &'a [Client],
+}
+
+/// Format marker of an anonymized data export
+pub const ANONYMIZED_EXPORT_FORMAT: &str = "bill-manager/anonymized";
+pub const ANONYMIZED_EXPORT_VERSION: u32 = 1;
+
+/// Clients, bills and payments with the client details replaced, for bug reports
+#[derive(Debug, Serialize)]
+struct AnonymizedExport {
+    format: &'static str,
+    version: u32,
+    clients: Vec<Client>,
+    bills: Vec<Bill>,
+    payments: Vec<Payment>,
+}
+
+/// A column of the bill CSV export; the key is used as header and stored in the settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CsvColumn {
+    Id,
+    Client,
+    Date,
+    DueDate,
+    Status,
+    Currency,
+    Subtotal,
+    Discount,
+    Vat,
+    Total,
+    Paid,
+    Reference,
+    PoNumber,
+    Notes,
+}
+
+impl CsvColumn {
+    pub const ALL: [CsvColumn; 14] = [
+        CsvColumn::Id,
+        CsvColumn::Client,
+        CsvColumn::Date,
+        CsvColumn::DueDate,
+        CsvColumn::Status,
+        CsvColumn::Currency,
+        CsvColumn::Subtotal,
+        CsvColumn::Discount,
+        CsvColumn::Vat,
+        CsvColumn::Total,
+        CsvColumn::Paid,
+        CsvColumn::Reference,
+        CsvColumn::PoNumber,
+        CsvColumn::Notes,
+    ];
+
+    /// Header written to the file
+    pub fn key(&self) -> &'static str {
+        match self {
+            CsvColumn::Id => "id",
+            CsvColumn::Client => "client",
+            CsvColumn::Date => "date",
+            CsvColumn::DueDate => "due_date",
+            CsvColumn::Status => "status",
+            CsvColumn::Currency => "currency",
+            CsvColumn::Subtotal => "subtotal",
+            CsvColumn::Discount => "discount",
+            CsvColumn::Vat => "vat",
+            CsvColumn::Total => "total",
+            CsvColumn::Paid => "paid",
+            CsvColumn::Reference => "reference",
+            CsvColumn::PoNumber => "po_number",
+            CsvColumn::Notes => "notes",
+        }
+    }
+
+    /// Name shown in the settings
+    pub fn label(&self) -> &'static str {
+        match self {
+            CsvColumn::Id => "Invoice number",
+            CsvColumn::Client => "Client",
+            CsvColumn::Date => "Date",
+            CsvColumn::DueDate => "Due date",
+            CsvColumn::Status => "Status",
+            CsvColumn::Currency => "Currency",
+            CsvColumn::Subtotal => "Subtotal",
+            CsvColumn::Discount => "Discount",
+            CsvColumn::Vat => "VAT",
+            CsvColumn::Total => "Total",
+            CsvColumn::Paid => "Paid",
+            CsvColumn::Reference => "Reference",
+            CsvColumn::PoNumber => "PO number",
+            CsvColumn::Notes => "Notes",
+        }
+    }
+}
+
+/// Columns of the export before they were configurable
+pub const DEFAULT_CSV_COLUMNS: [CsvColumn; 10] = [
+    CsvColumn::Id,
+    CsvColumn::Client,
+    CsvColumn::Date,
+    CsvColumn::DueDate,
+    CsvColumn::Status,
+    CsvColumn::Currency,
+    CsvColumn::Total,
+    CsvColumn::Reference,
+    CsvColumn::PoNumber,
+    CsvColumn::Notes,
+];
+
+/// Named column sets offered in the settings
+pub const CSV_PRESETS: [(&str, &[CsvColumn]); 3] = [
+    ("Standard", &DEFAULT_CSV_COLUMNS),
+    (
+        "Accounting",
+        &[
+            CsvColumn::Id,
+            CsvColumn::Date,
+            CsvColumn::Client,
+            CsvColumn::Currency,
+            CsvColumn::Subtotal,
+            CsvColumn::Discount,
+            CsvColumn::Vat,
+            CsvColumn::Total,
+            CsvColumn::Paid,
+            CsvColumn::Status,
+        ],
+    ),
+    ("Full", &CsvColumn::ALL),
+];
+
+/// Writes the given bills, in the given order, to a CSV file with the given columns.
+/// `client_name` resolves the client shown for each bill, `paid` the amount paid so far.
+pub fn write_bills_csv<'a>(
+    path: &Path,
+    bills: &'a [Bill],
+    columns: &[CsvColumn],
+    client_name: impl Fn(u64) -> &'a str,
+    paid: impl Fn(u64) -> f64,
+) -> Result<usize, String> {
+    let mut writer = csv::Writer::from_path(path)
+        .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+
+    writer
+        .write_record(columns.iter().map(CsvColumn::key))
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+
+    for bill in bills {
+        // Open-amount bills have no fixed amounts to report
+        let fixed = bill.amount_mode == AmountMode::Fixed;
+        // Credit notes reduce revenue, so their amounts are exported as negative
+        let sign = if bill.is_credit_note() { -1.0 } else { 1.0 };
+        let amount = |value: f64| if fixed { format!("{:.2}", sign * value) } else { String::new() };
+        let totals = bill.totals();
+
+        let record = columns.iter().map(|column| match column {
+            CsvColumn::Id => bill.id.to_string(),
+            CsvColumn::Client => client_name(bill.client_id).to_string(),
+            CsvColumn::Date => bill.date.format("%Y-%m-%d").to_string(),
+            CsvColumn::DueDate => bill.due_date.format("%Y-%m-%d").to_string(),
+            CsvColumn::Status => bill.status.to_string(),
+            CsvColumn::Currency => bill.currency.clone(),
+            CsvColumn::Subtotal => amount(totals.subtotal),
+            CsvColumn::Discount => amount(totals.discount),
+            CsvColumn::Vat => amount(totals.vat.iter().map(|(_, vat)| vat).sum()),
+            CsvColumn::Total => amount(totals.grand_total),
+            CsvColumn::Paid => format!("{:.2}", paid(bill.id)),
+            CsvColumn::Reference => bill.reference.clone(),
+            CsvColumn::PoNumber => bill.po_number.clone(),
+            CsvColumn::Notes => bill.notes.clone(),
+        });
+        writer
+            .write_record(record)
+            .map_err(|e| format!("Failed to write bill #{}: {}", bill.id, e))?;
+    }
+
+    writer
+        .flush()
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+
+    Ok(bills.len())
+}
+
+/// One archived bill in the manifest written next to its PDF
+#[derive(Debug, Serialize)]
+pub struct ArchiveManifestRow {
+    pub id: u64,
+    pub client: String,
+    pub date: String,
+    pub due_date: String,
+    pub status: String,
+    pub currency: String,
+    pub total: String,
+    pub reference: String,
+    pub file: String,
+    /// SHA-256 of the PDF file, to show it wasn't altered later
+    pub sha256: String,
+}
+
+pub fn write_archive_manifest(path: &Path, rows: &[ArchiveManifestRow]) -> Result<(), String> {
+    let mut writer = csv::Writer::from_path(path)
+        .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    for row in rows {
+        writer
+            .serialize(row)
+            .map_err(|e| format!("Failed to write bill #{}: {}", row.id, e))?;
+    }
+    writer
+        .flush()
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Writes one client, including both addresses, to a JSON file
+pub fn write_client_json(path: &Path, client: &Client) -> Result<(), String> {
+    let export = ClientExport {
+        format: CLIENT_EXPORT_FORMAT,
+        version: CLIENT_EXPORT_VERSION,
+        client,
+    };
+    let json = serde_json::to_string_pretty(&export)
+        .map_err(|e| format!("Failed to serialize client: {}", e))?;
+    std::fs::write(path, json)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Writes several clients to one JSON file, readable by the client import
+pub fn write_clients_json(path: &Path, clients: &[Client]) -> Result<(), String> {
+    let export = ClientsExport {
+        format: CLIENT_EXPORT_FORMAT,
+        version: CLIENT_EXPORT_VERSION,
+        clients,
+    };
+    let json = serde_json::to_string_pretty(&export)
+        .map_err(|e| format!("Failed to serialize clients: {}", e))?;
+    std::fs::write(path, json)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Writes clients, bills and payments to JSON with names, addresses, contact details, item
+/// descriptions and free text replaced by placeholders. Ids, amounts, dates and references are kept, and
+/// each client gets the same placeholder wherever it appears, so the data stays consistent.
+pub fn write_anonymized_json(path: &Path, clients: &[Client], bills: &[Bill], payments: &[Payment]) -> Result<(), String> {
+    let mut sorted: Vec<&Client> = clients.iter().collect();
+    sorted.sort_by_key(|c| c.id);
+    let clients = sorted.iter().enumerate().map(|(idx, client)| anonymize_client(client, idx + 1)).collect();
+
+    let bills = bills.iter().map(|bill| {
+        let mut bill = bill.clone();
+        if !bill.notes.is_empty() {
+            bill.notes = format!("Notes of bill {}", bill.id);
+        }
+        if !bill.po_number.is_empty() {
+            bill.po_number = format!("PO-{}", bill.id);
+        }
+        let id = bill.id;
+        for (idx, item) in bill.items.iter_mut().enumerate() {
+            item.item_type = format!("Item {}.{}", id, idx + 1);
+            if !item.note.is_empty() {
+                item.note = format!("Note {}.{}", id, idx + 1);
+            }
+        }
+        bill
+    }).collect();
+
+    let payments = payments.iter().map(|payment| {
+        let mut payment = payment.clone();
+        if !payment.note.is_empty() {
+            payment.note = format!("Payment {}", payment.id);
+        }
+        payment
+    }).collect();
+
+    let export = AnonymizedExport {
+        format: ANONYMIZED_EXPORT_FORMAT,
+        version: ANONYMIZED_EXPORT_VERSION,
+        clients,
+        bills,
+        payments,
+    };
+    let json = serde_json::to_string_pretty(&export)
+        .map_err(|e| format!("Failed to serialize data: {}", e))?;
+    std::fs::write(path, json)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Replaces a client's identifying fields with numbered placeholders; countries are kept
+/// since they change how addresses and QR bills are handled
+fn anonymize_client(client: &Client, n: usize) -> Client {
+    let name = format!("Client {}", n);
+    let address = |address: &crate::types::Address, name: &str| crate::types::Address {
+        name: if address.name.is_empty() { String::new() } else { name.to_string() },
+        street: address.street.as_ref().map(|s| if s.is_empty() { String::new() } else { format!("Street {}", n) }),
+        building_number: address.building_number.as_ref().map(|b| if b.is_empty() { String::new() } else { n.to_string() }),
+        postal_code: if address.postal_code.is_empty() { String::new() } else { format!("{:04}", 1000 + n % 9000) },
+        city: if address.city.is_empty() { String::new() } else { format!("City {}", n) },
+        country: address.country.clone(),
+    };
+    Client {
+        id: client.id,
+        address: address(&client.address, &name),
+        billing_address: address(&client.billing_address, &name),
+        email: if client.email.is_empty() { String::new() } else { format!("client{}@example.com", n) },
+        phone: if client.phone.is_empty() { String::new() } else { format!("+41 00 000 {:02} {:02}", n / 100 % 100, n % 100) },
+        name,
+    }
+}
+
+/// Escapes a TEXT value as required by RFC 5545
+fn ical_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Folds a content line after 75 octets, never inside a UTF-8 character, and ends it with CRLF
+fn ical_line(out: &mut String, line: &str) {
+    let mut octets = 0;
+    for c in line.chars() {
+        if octets + c.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            // The leading space of a continuation line counts towards its length
+            octets = 1;
+        }
+        out.push(c);
+        octets += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
+
+/// Writes an iCalendar file with one event per bill on its due date.
+/// Due dates at midnight, the usual case, become all-day events; others are timed events
+/// in UTC. The UID is derived from the bill id, so importing again updates the events.
+pub fn write_due_ical<'a>(
+    path: &Path,
+    bills: &'a [&'a Bill],
+    client_name: impl Fn(u64) -> &'a str,
+) -> Result<usize, String> {
+    let stamp = chrono::Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+    let mut out = String::new();
+    ical_line(&mut out, "BEGIN:VCALENDAR");
+    ical_line(&mut out, "VERSION:2.0");
+    ical_line(&mut out, "PRODID:-//Bill Manager//Due dates//EN");
+    ical_line(&mut out, "CALSCALE:GREGORIAN");
+
+    for bill in bills {
+        let amount = match bill.amount_mode {
+            AmountMode::Fixed => format!("{} {:.2}", bill.currency, bill.total()),
+            AmountMode::Open => format!("{}, amount open", bill.currency),
+        };
+        let mut description = format!("Amount: {}", amount);
+        if !bill.reference.is_empty() {
+            description.push_str(&format!("\nReference: {}", bill.reference));
+        }
+
+        ical_line(&mut out, "BEGIN:VEVENT");
+        ical_line(&mut out, &format!("UID:bill-{}-due@bill-manager", bill.id));
+        ical_line(&mut out, &format!("DTSTAMP:{}", stamp));
+        if bill.due_date.num_seconds_from_midnight() == 0 {
+            let day = bill.due_date.date_naive();
+            ical_line(&mut out, &format!("DTSTART;VALUE=DATE:{}", day.format("%Y%m%d")));
+            ical_line(&mut out, &format!("DTEND;VALUE=DATE:{}", (day + chrono::Days::new(1)).format("%Y%m%d")));
+            ical_line(&mut out, "TRANSP:TRANSPARENT");
+        } else {
+            let start = bill.due_date.with_timezone(&chrono::Utc).format("%Y%m%dT%H%M%SZ");
+            ical_line(&mut out, &format!("DTSTART:{}", start));
+        }
+        let summary = format!("Invoice {} due – {}", bill.id, client_name(bill.client_id));
+        ical_line(&mut out, &format!("SUMMARY:{}", ical_text(&summary)));
+        ical_line(&mut out, &format!("DESCRIPTION:{}", ical_text(&description)));
+        ical_line(&mut out, "END:VEVENT");
+    }
+
+    ical_line(&mut out, "END:VCALENDAR");
+    std::fs::write(path, out)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(bills.len())
+}
+
+/// Writes files into a ZIP archive. Entries are stored uncompressed: the archive is meant for
+/// PDFs, which are compressed already.
+pub fn write_zip(path: &Path, files: &[(String, Vec<u8>)]) -> Result<(), String> {
+    let now = crate::timezone::now();
+    let dos_time = ((now.hour() << 11) | (now.minute() << 5) | (now.second() / 2)) as u16;
+    let dos_date = (((now.year().clamp(1980, 2107) - 1980) as u32) << 9 | (now.month() << 5) | now.day()) as u16;
+
+    let mut archive: Vec<u8> = Vec::new();
+    let mut central_directory: Vec<u8> = Vec::new();
+
+    for (name, data) in files {
+        let offset = u32::try_from(archive.len()).map_err(|_| "ZIP archive too large".to_string())?;
+        let size = u32::try_from(data.len()).map_err(|_| format!("{} is too large for a ZIP archive", name))?;
+        let crc = crc32fast::hash(data);
+
+        // Fields shared by the local header and the central directory entry: version needed,
+        // flags (UTF-8 names), method (stored), time, date, CRC, sizes, name length
+        let mut common = Vec::new();
+        common.extend_from_slice(&20u16.to_le_bytes());
+        common.extend_from_slice(&0x0800u16.to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes());
+        common.extend_from_slice(&dos_time.to_le_bytes());
+        common.extend_from_slice(&dos_date.to_le_bytes());
+        common.extend_from_slice(&crc.to_le_bytes());
+        common.extend_from_slice(&size.to_le_bytes());
+        common.extend_from_slice(&size.to_le_bytes());
+        common.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes());
+
+        archive.extend_from_slice(&0x04034b50u32.to_le_bytes());
+        archive.extend_from_slice(&common);
+        archive.extend_from_slice(name.as_bytes());
+        archive.extend_from_slice(data);
+
+        central_directory.extend_from_slice(&0x02014b50u32.to_le_bytes());
+        central_directory.extend_from_slice(&20u16.to_le_bytes());
+        central_directory.extend_from_slice(&common);
+        // Comment length, disk number, internal and external attributes
+        central_directory.extend_from_slice(&[0u8; 10]);
+        central_directory.extend_from_slice(&offset.to_le_bytes());
+        central_directory.extend_from_slice(name.as_bytes());
+    }
+
+    let directory_offset = archive.len() as u32;
+    let entries = files.len() as u16;
+    archive.extend_from_slice(&central_directory);
+    archive.extend_from_slice(&0x06054b50u32.to_le_bytes());
+    archive.extend_from_slice(&[0u8; 4]);
+    archive.extend_from_slice(&entries.to_le_bytes());
+    archive.extend_from_slice(&entries.to_le_bytes());
+    archive.extend_from_slice(&(central_directory.len() as u32).to_le_bytes());
+    archive.extend_from_slice(&directory_offset.to_le_bytes());
+    archive.extend_from_slice(&0u16.to_le_bytes());
+
+    std::fs::File::create(path)
+        .and_then(|mut file| file.write_all(&archive))
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::BillItem;
+    use crate::types::Address;
+
+    #[test]
+    fn anonymized_export_contains_no_client_text() {
+        let address = Address::new(
+            "Muster AG".to_string(),
+            Some("Bahnhofstrasse".to_string()),
+            Some("17".to_string()),
+            "8001".to_string(),
+            "Zürich".to_string(),
+            "CH".to_string(),
+        );
+        let client = Client {
+            id: 3,
+            name: "Muster AG".to_string(),
+            address: address.clone(),
+            billing_address: address,
+            email: "info@muster.ch".to_string(),
+            phone: "+41 44 123 45 67".to_string(),
+        };
+        let item = BillItem {
+            item_type: "Website relaunch Muster".to_string(),
+            note: "Meeting with Mrs. Keller".to_string(),
+            quantity: 2.0,
+            unit_price: 150.0,
+            ..BillItem::default()
+        };
+        let bill = Bill {
+            id: 12,
+            client_id: 3,
+            items: vec![item],
+            notes: "Thanks for the tour of Bahnhofstrasse".to_string(),
+            po_number: "MUSTER-4711".to_string(),
+            ..Bill::default()
+        };
+        let payment = Payment {
+            id: 1,
+            bill_id: 12,
+            amount: 300.0,
+            paid_on: bill.date.date_naive(),
+            note: "Paid by Keller".to_string(),
+            source: None,
+        };
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("anonymized.json");
+        write_anonymized_json(&path, &[client], &[bill], &[payment]).unwrap();
+        let json = std::fs::read_to_string(&path).unwrap();
+
+        for secret in ["Muster", "Bahnhofstrasse", "8001", "Zürich", "info@", "123 45 67", "Keller", "Website", "4711"] {
+            assert!(!json.contains(secret), "{} is in the export:\n{}", secret, json);
+        }
+        for placeholder in ["Client 1", "Item 12.1", "Note 12.1", "Notes of bill 12", "PO-12"] {
+            assert!(json.contains(placeholder), "{} is missing from the export", placeholder);
+        }
+    }
+}
@@ -0,0 +1,399 @@
+use chrono::{DateTime, NaiveDate};
+use chrono_tz::Tz;
+use serde::Deserialize;
+use std::path::Path;
+
+use crate::app::{BillItem, Client};
+use crate::export::{CLIENT_EXPORT_FORMAT, CLIENT_EXPORT_VERSION};
+
+/// A client file written by `write_client_json` or `write_clients_json`
+#[derive(Debug, Deserialize)]
+struct ClientImport {
+    format: String,
+    version: u32,
+    #[serde(default)]
+    client: Option<Client>,
+    #[serde(default)]
+    clients: Vec<Client>,
+}
+
+/// Reads a file of one or several shared clients; ids are reset so the clients are stored as
+/// new records
+pub fn read_clients_json(path: &Path) -> Result<Vec<Client>, String> {
+    let json = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let import: ClientImport = serde_json::from_str(&json)
+        .map_err(|e| format!("{} is not a client file: {}", path.display(), e))?;
+
+    if import.format != CLIENT_EXPORT_FORMAT {
+        return Err(format!("{} is not a client file (format '{}')", path.display(), import.format));
+    }
+    if import.version > CLIENT_EXPORT_VERSION {
+        return Err(format!(
+            "{} was written by a newer version (format version {}), please update",
+            path.display(),
+            import.version
+        ));
+    }
+
+    let clients: Vec<Client> = import.client
+        .into_iter()
+        .chain(import.clients)
+        .map(|client| Client { id: 0, ..client })
+        .collect();
+    if clients.is_empty() {
+        return Err(format!("{} contains no clients", path.display()));
+    }
+    Ok(clients)
+}
+
+/// A historical bill to import with its original id
+#[derive(Debug, Clone, Deserialize)]
+pub struct BillImportRecord {
+    pub id: u64,
+    pub client_id: u64,
+    pub date: String,
+    pub due_date: String,
+    #[serde(default)]
+    pub reference: String,
+    #[serde(default)]
+    pub iban: String,
+    #[serde(default)]
+    pub notes: String,
+    pub status: String,
+    #[serde(default)]
+    pub items: Vec<BillItem>,
+}
+
+/// One CSV line; consecutive lines with the same `id` form one bill with several items
+#[derive(Debug, Deserialize)]
+struct BillCsvRow {
+    id: u64,
+    client_id: u64,
+    date: String,
+    due_date: String,
+    #[serde(default)]
+    reference: String,
+    #[serde(default)]
+    iban: String,
+    #[serde(default)]
+    notes: String,
+    status: String,
+    #[serde(default)]
+    item_type: String,
+    #[serde(default)]
+    quantity: Option<f64>,
+    #[serde(default)]
+    unit_price: Option<f64>,
+    #[serde(default)]
+    item_note: String,
+    #[serde(default)]
+    vat_rate: Option<f64>,
+    #[serde(default)]
+    unit: String,
+}
+
+/// Reads bill records from a `.json` (array of records) or `.csv` file
+pub fn read_bill_records(path: &Path) -> Result<Vec<BillImportRecord>, String> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "json" => {
+            let json = std::fs::read_to_string(path)
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            serde_json::from_str(&json).map_err(|e| format!("Invalid JSON: {}", e))
+        }
+        "csv" => read_bill_records_csv(path),
+        _ => Err("Unsupported file type, expected .json or .csv".to_string()),
+    }
+}
+
+fn read_bill_records_csv(path: &Path) -> Result<Vec<BillImportRecord>, String> {
+    let mut reader = csv::Reader::from_path(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+    let mut records: Vec<BillImportRecord> = Vec::new();
+    for (idx, row) in reader.deserialize::<BillCsvRow>().enumerate() {
+        let row = row.map_err(|e| format!("CSV line {}: {}", idx + 2, e))?;
+
+        let item = (!row.item_type.is_empty() || row.unit_price.is_some()).then(|| BillItem {
+            item_type: row.item_type.clone(),
+            quantity: row.quantity.unwrap_or(1.0),
+            unit_price: row.unit_price.unwrap_or(0.0),
+            note: row.item_note.clone(),
+            vat_rate: row.vat_rate.unwrap_or(0.0),
+            unit: row.unit.clone(),
+        });
+
+        match records.last_mut() {
+            Some(last) if last.id == row.id => last.items.extend(item),
+            _ => records.push(BillImportRecord {
+                id: row.id,
+                client_id: row.client_id,
+                date: row.date,
+                due_date: row.due_date,
+                reference: row.reference,
+                iban: row.iban,
+                notes: row.notes,
+                status: row.status,
+                items: item.into_iter().collect(),
+            }),
+        }
+    }
+
+    Ok(records)
+}
+
+/// Parses an RFC 3339 timestamp or a plain `YYYY-MM-DD` date
+pub fn parse_import_date(value: &str) -> Option<DateTime<Tz>> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+        return Some(dt.with_timezone(&crate::timezone::zone()));
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()?
+        .and_hms_opt(0, 0, 0)?
+        .and_local_timezone(crate::timezone::zone())
+        .single()
+}
+
+/// A credit booked on a bank statement
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatementEntry {
+    pub amount: f64,
+    pub currency: String,
+    pub booking_date: Option<NaiveDate>,
+    /// Structured creditor reference (QRR or SCOR) given by the payer
+    pub reference: Option<String>,
+    pub debtor: String,
+    /// Unstructured remittance information
+    pub remittance: String,
+    /// Identifies the entry within the statement, to recognize a statement imported twice
+    pub entry_ref: String,
+}
+
+/// The credits of an ISO 20022 camt.053 bank statement
+#[derive(Debug, Clone)]
+pub struct BankStatement {
+    /// Message id of the statement file
+    pub id: String,
+    pub credits: Vec<StatementEntry>,
+}
+
+/// First child element with the given local name, following a path of names
+fn camt_node<'a, 'input>(node: roxmltree::Node<'a, 'input>, path: &[&str]) -> Option<roxmltree::Node<'a, 'input>> {
+    path.iter().try_fold(node, |node, name| node.children().find(|n| n.has_tag_name(*name)))
+}
+
+fn camt_text<'a>(node: roxmltree::Node<'a, '_>, path: &[&str]) -> Option<&'a str> {
+    camt_node(node, path)?.text().map(str::trim).filter(|t| !t.is_empty())
+}
+
+/// Amount and currency of an `Amt` element
+fn camt_amount(node: roxmltree::Node) -> Result<(f64, String), String> {
+    let text = node.text().unwrap_or("").trim();
+    let amount = text.parse::<f64>().map_err(|_| format!("Invalid amount '{}'", text))?;
+    Ok((amount, node.attribute("Ccy").unwrap_or("").to_string()))
+}
+
+/// Date of a `Dt` or `DtTm` child, e.g. of `BookgDt`
+fn camt_date(node: roxmltree::Node) -> Option<NaiveDate> {
+    let text = camt_text(node, &["Dt"]).or_else(|| camt_text(node, &["DtTm"]))?;
+    NaiveDate::parse_from_str(text.get(..10)?, "%Y-%m-%d").ok()
+}
+
+/// Reads the credit entries of a camt.053 file. Batch bookings are split into their
+/// transactions; debits, reversals and pending entries are skipped.
+pub fn read_camt053(path: &Path) -> Result<BankStatement, String> {
+    let xml = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    parse_camt053(&xml).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+pub fn parse_camt053(xml: &str) -> Result<BankStatement, String> {
+    let doc = roxmltree::Document::parse(xml).map_err(|e| format!("Not an XML file: {}", e))?;
+    let report = doc
+        .descendants()
+        .find(|n| n.has_tag_name("BkToCstmrStmt"))
+        .ok_or("Not a camt.053 bank statement")?;
+
+    let statements: Vec<_> = report.children().filter(|n| n.has_tag_name("Stmt")).collect();
+    let id = camt_text(report, &["GrpHdr", "MsgId"])
+        .or_else(|| statements.first().and_then(|s| camt_text(*s, &["Id"])))
+        .unwrap_or("")
+        .to_string();
+
+    let mut credits = Vec::new();
+    for (stmt_idx, statement) in statements.iter().enumerate() {
+        for (entry_idx, entry) in statement.children().filter(|n| n.has_tag_name("Ntry")).enumerate() {
+            let status = camt_text(entry, &["Sts", "Cd"]).or_else(|| camt_text(entry, &["Sts"]));
+            if camt_text(entry, &["CdtDbtInd"]) != Some("CRDT")
+                || camt_text(entry, &["RvslInd"]) == Some("true")
+                || matches!(status, Some("PDNG" | "INFO"))
+            {
+                continue;
+            }
+
+            let entry_no = format!("{}.{}", stmt_idx + 1, entry_idx + 1);
+            let (entry_amount, entry_currency) = camt_node(entry, &["Amt"])
+                .ok_or_else(|| format!("Entry {} has no amount", entry_no))
+                .and_then(|amt| camt_amount(amt).map_err(|e| format!("Entry {}: {}", entry_no, e)))?;
+            let booking_date = camt_node(entry, &["BookgDt"])
+                .or_else(|| camt_node(entry, &["ValDt"]))
+                .and_then(camt_date);
+            let entry_ref = camt_text(entry, &["AcctSvcrRef"])
+                .or_else(|| camt_text(entry, &["NtryRef"]))
+                .map(str::to_string)
+                .unwrap_or(entry_no.clone());
+
+            let transactions: Vec<_> = camt_node(entry, &["NtryDtls"])
+                .map(|details| details.descendants().filter(|n| n.has_tag_name("TxDtls")).collect())
+                .unwrap_or_default();
+            if transactions.is_empty() {
+                credits.push(StatementEntry {
+                    amount: entry_amount,
+                    currency: entry_currency,
+                    booking_date,
+                    reference: None,
+                    debtor: String::new(),
+                    remittance: camt_text(entry, &["AddtlNtryInf"]).unwrap_or("").to_string(),
+                    entry_ref,
+                });
+                continue;
+            }
+
+            let single = transactions.len() == 1;
+            for (tx_idx, tx) in transactions.iter().enumerate() {
+                let tx_amount = camt_node(*tx, &["Amt"]).or_else(|| camt_node(*tx, &["AmtDtls", "TxAmt", "Amt"]));
+                let (amount, currency) = match tx_amount {
+                    Some(amt) => camt_amount(amt).map_err(|e| format!("Entry {}: {}", entry_no, e))?,
+                    None if single => (entry_amount, entry_currency.clone()),
+                    None => return Err(format!("Entry {}: transaction {} has no amount", entry_no, tx_idx + 1)),
+                };
+
+                let remittance: Vec<&str> = camt_node(*tx, &["RmtInf"])
+                    .map(|info| {
+                        info.children()
+                            .filter(|n| n.has_tag_name("Ustrd"))
+                            .filter_map(|n| n.text())
+                            .map(str::trim)
+                            .collect()
+                    })
+                    .unwrap_or_default();
+
+                credits.push(StatementEntry {
+                    amount,
+                    currency,
+                    booking_date,
+                    reference: camt_text(*tx, &["RmtInf", "Strd", "CdtrRefInf", "Ref"]).map(str::to_string),
+                    debtor: camt_text(*tx, &["RltdPties", "Dbtr", "Nm"])
+                        .or_else(|| camt_text(*tx, &["RltdPties", "Dbtr", "Pty", "Nm"]))
+                        .unwrap_or("")
+                        .to_string(),
+                    remittance: remittance.join(" "),
+                    entry_ref: if single {
+                        entry_ref.clone()
+                    } else {
+                        format!("{}/{}", entry_ref, tx_idx + 1)
+                    },
+                });
+            }
+        }
+    }
+
+    Ok(BankStatement { id, credits })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A camt.053 document with one statement holding the given `Ntry` elements
+    fn statement(entries: &str) -> String {
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<Document xmlns="urn:iso:std:iso:20022:tech:xsd:camt.053.001.04">
+  <BkToCstmrStmt>
+    <GrpHdr><MsgId>MSG-2025-03</MsgId></GrpHdr>
+    <Stmt><Id>STMT-1</Id>{}</Stmt>
+  </BkToCstmrStmt>
+</Document>"#,
+            entries
+        )
+    }
+
+    fn entry(indicator: &str, extra: &str, details: &str) -> String {
+        format!(
+            r#"<Ntry>
+  <Amt Ccy="CHF">300.00</Amt><CdtDbtInd>{}</CdtDbtInd>{}
+  <Sts><Cd>BOOK</Cd></Sts>
+  <BookgDt><Dt>2025-03-14</Dt></BookgDt>
+  <AcctSvcrRef>REF-{}</AcctSvcrRef>
+  <NtryDtls>{}</NtryDtls>
+</Ntry>"#,
+            indicator, extra, indicator, details
+        )
+    }
+
+    fn transaction(amount: Option<&str>, reference: &str) -> String {
+        format!(
+            r#"<TxDtls>{}
+  <RltdPties><Dbtr><Nm>Muster AG</Nm></Dbtr></RltdPties>
+  <RmtInf><Ustrd>Rechnung</Ustrd><Strd><CdtrRefInf><Ref>{}</Ref></CdtrRefInf></Strd></RmtInf>
+</TxDtls>"#,
+            amount.map(|a| format!("<Amt Ccy=\"CHF\">{}</Amt>", a)).unwrap_or_default(),
+            reference
+        )
+    }
+
+    #[test]
+    fn single_credit_carries_its_structured_reference() {
+        let xml = statement(&entry("CRDT", "", &transaction(None, "RF18539007547034")));
+        let parsed = parse_camt053(&xml).unwrap();
+
+        assert_eq!(parsed.id, "MSG-2025-03");
+        assert_eq!(parsed.credits, [StatementEntry {
+            amount: 300.0,
+            currency: "CHF".to_string(),
+            booking_date: NaiveDate::from_ymd_opt(2025, 3, 14),
+            reference: Some("RF18539007547034".to_string()),
+            debtor: "Muster AG".to_string(),
+            remittance: "Rechnung".to_string(),
+            entry_ref: "REF-CRDT".to_string(),
+        }]);
+    }
+
+    #[test]
+    fn batch_bookings_split_into_transactions_with_distinct_refs() {
+        let details = transaction(Some("120.00"), "RF18539007547034") + &transaction(Some("180.00"), "RF712025Y421K4201");
+        let parsed = parse_camt053(&statement(&entry("CRDT", "", &details))).unwrap();
+
+        let split: Vec<_> = parsed.credits.iter().map(|c| (c.amount, c.entry_ref.as_str())).collect();
+        assert_eq!(split, [(120.0, "REF-CRDT/1"), (180.0, "REF-CRDT/2")]);
+        assert_eq!(parsed.credits[1].reference.as_deref(), Some("RF712025Y421K4201"));
+    }
+
+    #[test]
+    fn debits_reversals_and_pending_entries_are_skipped() {
+        let details = transaction(None, "RF18539007547034");
+        let entries = [
+            entry("DBIT", "", &details),
+            entry("CRDT", "<RvslInd>true</RvslInd>", &details),
+            entry("CRDT", "", &details).replace("<Cd>BOOK</Cd>", "<Cd>PDNG</Cd>"),
+        ];
+        let parsed = parse_camt053(&statement(&entries.concat())).unwrap();
+
+        assert!(parsed.credits.is_empty(), "{:?}", parsed.credits);
+    }
+
+    #[test]
+    fn batch_transaction_without_an_amount_is_an_error() {
+        let details = transaction(Some("120.00"), "RF18539007547034") + &transaction(None, "RF712025Y421K4201");
+        let error = parse_camt053(&statement(&entry("CRDT", "", &details))).unwrap_err();
+
+        assert_eq!(error, "Entry 1.1: transaction 2 has no amount");
+    }
+}
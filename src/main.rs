@@ -1,9 +1,15 @@
 mod app;
 mod db;
+mod email;
+mod export;
+mod import;
 mod models;
 mod pdf;
+mod print;
 mod schema;
+mod timezone;
 mod types;
+mod words;
 mod ui;
 
 use app::BillManagerApp;
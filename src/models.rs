@@ -1,4 +1,5 @@
 use diesel::prelude::*;
+use serde::Serialize;
 use crate::schema::*;
 
 // Database models (for Diesel)
@@ -10,7 +11,7 @@ pub struct Setting {
     pub value: String,
 }
 
-#[derive(Queryable, Selectable, Identifiable, AsChangeset, Debug, Clone)]
+#[derive(Queryable, Selectable, Identifiable, AsChangeset, Serialize, Debug, Clone)]
 #[diesel(table_name = clients)]
 pub struct ClientDb {
     pub id: i32,
@@ -51,7 +52,8 @@ pub struct NewClient {
     pub billing_address_country: Option<String>,
 }
 
-#[derive(Queryable, Selectable, Identifiable, AsChangeset, Debug, Clone)]
+/// Bill row without the legacy `pdf_data` BLOB column, which is only read on demand
+#[derive(Queryable, Selectable, Identifiable, Insertable, AsChangeset, Serialize, Debug, Clone)]
 #[diesel(table_name = bills)]
 pub struct BillDb {
     pub id: i32,
@@ -63,13 +65,34 @@ pub struct BillDb {
     pub notes: String,
     pub status: String,
     pub items: String,
-    pub pdf_data: Option<Vec<u8>>,
     pub pdf_created_at: Option<String>,
+    pub amount_mode: String,
+    pub language: String,
+    pub currency: String,
+    pub pdf_hash: Option<String>,
+    pub pdf_path: Option<String>,
+    pub prices_include_vat: bool,
+    pub discount: Option<String>,
+    pub reminder_level: i32,
+    pub last_reminder_at: Option<String>,
+    pub reference_type: String,
+    pub po_number: String,
+    pub service_from: Option<String>,
+    pub service_to: Option<String>,
+    pub archived_at: Option<String>,
+    pub follow_up: Option<String>,
+    pub include_qr_bill: bool,
+    pub conversion: Option<String>,
+    pub round_to_five_cents: bool,
+    pub document_type: String,
+    pub credited_bill_id: Option<i32>,
 }
 
 #[derive(Insertable, Debug, Clone)]
 #[diesel(table_name = bills)]
 pub struct NewBill {
+    /// Set only to lift the id to the configured first invoice number; `None` lets SQLite pick
+    pub id: Option<i32>,
     pub client_id: i32,
     pub date: String,
     pub due_date: String,
@@ -78,16 +101,39 @@ pub struct NewBill {
     pub notes: String,
     pub status: String,
     pub items: String,
-    pub pdf_data: Option<Vec<u8>>,
     pub pdf_created_at: Option<String>,
+    pub amount_mode: String,
+    pub language: String,
+    pub currency: String,
+    pub pdf_hash: Option<String>,
+    pub pdf_path: Option<String>,
+    pub prices_include_vat: bool,
+    pub discount: Option<String>,
+    pub reminder_level: i32,
+    pub last_reminder_at: Option<String>,
+    pub reference_type: String,
+    pub po_number: String,
+    pub service_from: Option<String>,
+    pub service_to: Option<String>,
+    pub archived_at: Option<String>,
+    pub follow_up: Option<String>,
+    pub include_qr_bill: bool,
+    pub conversion: Option<String>,
+    pub round_to_five_cents: bool,
+    pub document_type: String,
+    pub credited_bill_id: Option<i32>,
 }
 
-#[derive(Queryable, Selectable, Identifiable, AsChangeset, Debug, Clone)]
+#[derive(Queryable, Selectable, Identifiable, AsChangeset, Serialize, Debug, Clone)]
 #[diesel(table_name = item_templates)]
 pub struct ItemTemplateDb {
     pub id: i32,
     pub item_type: String,
     pub unit_price: f64,
+    pub use_count: i32,
+    pub last_used: Option<String>,
+    pub vat_rate: f64,
+    pub unit: String,
 }
 
 #[derive(Insertable, Debug, Clone)]
@@ -95,4 +141,114 @@ pub struct ItemTemplateDb {
 pub struct NewItemTemplate {
     pub item_type: String,
     pub unit_price: f64,
+    pub vat_rate: f64,
+    pub unit: String,
+}
+
+#[derive(Queryable, Selectable, Serialize, Debug, Clone)]
+#[diesel(table_name = bill_templates)]
+pub struct BillTemplateDb {
+    pub id: i32,
+    pub name: String,
+    /// JSON array of the template's bill items
+    pub items: String,
+    pub notes: String,
+    pub payment_days: i32,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = bill_templates)]
+pub struct NewBillTemplate {
+    pub name: String,
+    pub items: String,
+    pub notes: String,
+    pub payment_days: i32,
+}
+
+#[derive(Queryable, Selectable, Serialize, Debug, Clone)]
+#[diesel(table_name = discount_templates)]
+pub struct DiscountTemplateDb {
+    pub id: i32,
+    pub name: String,
+    pub kind: String,
+    pub value: f64,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = discount_templates)]
+pub struct NewDiscountTemplate {
+    pub name: String,
+    pub kind: String,
+    pub value: f64,
+}
+
+#[derive(Queryable, Selectable, Debug, Clone)]
+#[diesel(table_name = audit_log)]
+pub struct AuditLogEntry {
+    pub timestamp: String,
+    pub entity_type: String,
+    pub entity_id: i32,
+    pub action: String,
+    pub details: Option<String>,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = audit_log)]
+pub struct NewAuditLogEntry {
+    pub timestamp: String,
+    pub entity_type: String,
+    pub entity_id: i32,
+    pub action: String,
+    pub details: Option<String>,
+}
+
+#[derive(Queryable, Selectable, Serialize, Debug, Clone)]
+#[diesel(table_name = attachments)]
+pub struct AttachmentDb {
+    pub id: i32,
+    pub bill_id: i32,
+    pub filename: String,
+    pub mime: String,
+    pub path: String,
+    pub added_at: String,
+    pub append_to_pdf: bool,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = attachments)]
+pub struct NewAttachment {
+    pub bill_id: i32,
+    pub filename: String,
+    pub mime: String,
+    pub path: String,
+    pub added_at: String,
+    pub append_to_pdf: bool,
+}
+
+#[derive(Queryable, Selectable, Serialize, Debug, Clone)]
+#[diesel(table_name = payments)]
+pub struct PaymentDb {
+    pub id: i32,
+    pub bill_id: i32,
+    pub amount: f64,
+    pub paid_on: String,
+    pub note: String,
+    pub source: Option<String>,
+}
+
+#[derive(Insertable, Serialize, Debug, Clone)]
+#[diesel(table_name = payments)]
+pub struct NewPayment {
+    pub bill_id: i32,
+    pub amount: f64,
+    pub paid_on: String,
+    pub note: String,
+    pub source: Option<String>,
+}
+
+/// One line of `PRAGMA integrity_check` output
+#[derive(QueryableByName, Debug)]
+pub struct IntegrityCheckRow {
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    pub integrity_check: String,
 }
@@ -1,21 +1,79 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::sync::LazyLock;
+use std::sync::{LazyLock, Mutex};
+use std::time::SystemTime;
 
-use crate::app::{Bill, Client};
-use crate::types::Address;
+use crate::app::{AmountMode, Bill, BillTotals, Client};
+use crate::types::{Address, DocumentType, Language, ReferenceType};
 
+use serde::Serialize;
+use sha2::{Digest, Sha256};
 use text_placeholder::Template;
-use typst::diag::{FileError, FileResult, PackageError, PackageResult};
+use typst::diag::{FileError, FileResult, PackageError, PackageResult, Severity, SourceDiagnostic};
 use typst::foundations::{Bytes, Datetime};
-use typst::syntax::{FileId, Source, VirtualPath, package::PackageSpec};
+use typst::syntax::{FileId, Source, Span, VirtualPath, package::PackageSpec};
 use typst::text::{Font, FontBook};
 use typst::utils::LazyHash;
 use typst::{Library, LibraryExt, World};
 use typst_kit::fonts::{FontSearcher, FontSlot};
 use typst_pdf::PdfOptions;
 
+/// Maximum length of the unstructured message (additional information) on a QR bill
+pub const MAX_ADDITIONAL_INFO_LEN: usize = 140;
+
+/// File name of the invoice template inside the template directory
+pub const TEMPLATE_FILE: &str = "qr_bill.tpl";
+
+/// Accent color of invoices until a brand color is chosen
+pub const DEFAULT_ACCENT_COLOR: &str = "#000000";
+
+/// Environment variable that makes PDF generation keep the generated Typst source
+/// as `typst-debug.typ` in the data folder, for debugging templates
+pub const DEBUG_SOURCE_ENV: &str = "BILL_MANAGER_TYPST_DEBUG";
+
+/// Built-in template and logo, used when the template directory doesn't provide them
+const BUNDLED_TEMPLATE: &str = include_str!("../templates/qr_bill.tpl");
+const BUNDLED_LOGO: &[u8] = include_bytes!("../templates/logo.svg");
+
+/// Settings that influence how an invoice is rendered
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct InvoiceOptions {
+    /// Additional-information text with `{due_date}`, `{reference}` and `{invoice_number}` placeholders
+    pub additional_info_template: String,
+    /// Directory with `qr_bill.tpl` and the files it references, e.g. the logo
+    pub template_dir: PathBuf,
+    /// Brand color for headings and rules, `#rrggbb`; invalid values use `DEFAULT_ACCENT_COLOR`
+    pub accent_color: String,
+    /// Pre-bundled Typst packages laid out as `<namespace>/<name>/<version>/`, checked before
+    /// the package cache. Doesn't change the rendered invoice, so it's not part of the hash.
+    #[serde(skip)]
+    pub package_dir: Option<PathBuf>,
+    /// PDF files whose pages follow the invoice, in this order. Their location depends on the
+    /// data folder, so the hash covers `appended_hashes` instead.
+    #[serde(skip)]
+    pub appended_pdfs: Vec<PathBuf>,
+    /// Attachment id and SHA-256 of each appended PDF, so that flagging, unflagging or
+    /// replacing one makes the stored PDF stale. Left out while empty to keep older hashes.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub appended_hashes: Vec<(u64, String)>,
+    /// Renders the bill as a payment reminder of this level (0 for the invoice itself).
+    /// Reminders aren't stored, so it's not part of the hash either.
+    #[serde(skip)]
+    pub reminder_level: u8,
+    /// Writes accessibility tags into the PDF; off gives somewhat smaller files. The visible
+    /// invoice stays the same, so it's not part of the hash.
+    #[serde(skip)]
+    pub tagged_pdf: bool,
+    /// Also looks for fonts installed on the system. Off restricts the invoice to the bundled
+    /// fonts and those in the template directory, so no unexpected fallback fonts get embedded.
+    #[serde(skip)]
+    pub system_fonts: bool,
+    /// Where to write the generated Typst source for inspection; usually unset
+    #[serde(skip)]
+    pub debug_source: Option<PathBuf>,
+}
+
 static LIBRARY: LazyLock<LazyHash<Library>> = LazyLock::new(|| {
     LazyHash::new(Library::builder().build())
 });
@@ -24,44 +82,56 @@ static LIBRARY: LazyLock<LazyHash<Library>> = LazyLock::new(|| {
 struct TypstWorld {
     source: Source,
     main_id: FileId,
+    package_dir: Option<PathBuf>,
     package_cache: PathBuf,
     template_dir: PathBuf,
+    /// Attachment PDFs served as `__appended/<index>.pdf`
+    appended_pdfs: Vec<PathBuf>,
     book: LazyHash<FontBook>,
     fonts: Vec<FontSlot>,
 }
 
 impl TypstWorld {
-    fn new(source_text: String) -> Self {
+    fn new(
+        source_text: String,
+        template_dir: PathBuf,
+        package_dir: Option<PathBuf>,
+        appended_pdfs: Vec<PathBuf>,
+        system_fonts: bool,
+    ) -> Self {
         let main_id = FileId::new(None, VirtualPath::new("main.typ"));
         let source = Source::new(main_id, source_text);
 
-        // Use system cache directory for packages
-        let package_cache = dirs::cache_dir()
-            .unwrap_or_else(|| PathBuf::from("."))
-            .join("typst")
-            .join("packages");
+        let package_cache = package_cache_dir();
 
-        // Template directory for local files
-        let template_dir = PathBuf::from("templates");
-
-        let fonts = FontSearcher::new().include_system_fonts(true).search();
+        // Fonts next to the template let it bring its own typeface, e.g. Roboto
+        let fonts = FontSearcher::new()
+            .include_system_fonts(system_fonts)
+            .search_with([&template_dir]);
         let book = LazyHash::new(fonts.book);
 
         Self {
             source,
             main_id,
+            package_dir,
             package_cache,
             template_dir,
+            appended_pdfs,
             fonts: fonts.fonts,
             book,
         }
     }
 
     fn resolve_package(&self, spec: &PackageSpec) -> PackageResult<PathBuf> {
-        let package_dir = self.package_cache
-            .join(spec.namespace.as_str())
-            .join(spec.name.as_str())
-            .join(spec.version.to_string());
+        // Bundled packages take precedence so invoices render the same without internet access
+        if let Some(local_dir) = &self.package_dir {
+            let package_dir = package_subdir(local_dir, spec);
+            if package_dir.exists() {
+                return Ok(package_dir);
+            }
+        }
+
+        let package_dir = package_subdir(&self.package_cache, spec);
 
         if package_dir.exists() {
             Ok(package_dir)
@@ -72,10 +142,7 @@ impl TypstWorld {
     }
 
     fn download_package(&self, spec: &PackageSpec) -> PackageResult<PathBuf> {
-        let package_dir = self.package_cache
-            .join(spec.namespace.as_str())
-            .join(spec.name.as_str())
-            .join(spec.version.to_string());
+        let package_dir = package_subdir(&self.package_cache, spec);
 
         if package_dir.exists() {
             return Ok(package_dir);
@@ -93,21 +160,84 @@ impl TypstWorld {
             spec.version
         );
 
+        let local_hint = match &self.package_dir {
+            Some(local_dir) => format!(
+                "\nOr place it in the local package directory at: {}",
+                package_subdir(local_dir, spec).display()
+            ),
+            None => String::new(),
+        };
+
         // For now, return an error with instructions
         Err(PackageError::Other(Some(
             format!(
                 "Package '{}' not found in cache. Please download it manually:\n\
                  1. Download from: {}\n\
-                 2. Extract to: {}\n\
+                 2. Extract to: {}{}\n\
                  Or run: typst compile (with the CLI) to auto-download packages",
                 spec,
                 url,
-                package_dir.display()
+                package_dir.display(),
+                local_hint
             ).into()
         )))
     }
 }
 
+/// Location of a package inside a package root: `<root>/<namespace>/<name>/<version>`
+/// Typst's package cache in the system cache directory, where downloaded packages are kept
+fn package_cache_dir() -> PathBuf {
+    dirs::cache_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("typst")
+        .join("packages")
+}
+
+/// Directories the packages imported by `source` are read from, resolved as `TypstWorld`
+/// does: the bundled package directory first, then the cache. Packages not available yet
+/// are left out.
+fn resolved_package_roots(source: &str, package_dir: Option<&Path>) -> Vec<PathBuf> {
+    let cache = package_cache_dir();
+    let mut roots: Vec<PathBuf> = source
+        .split('"')
+        .filter(|s| s.starts_with('@'))
+        .filter_map(|s| s.parse::<PackageSpec>().ok())
+        .filter_map(|spec| {
+            package_dir
+                .into_iter()
+                .chain([cache.as_path()])
+                .map(|root| package_subdir(root, &spec))
+                .find(|dir| dir.exists())
+        })
+        .collect();
+    roots.sort();
+    roots.dedup();
+    roots
+}
+
+/// All files below `dir`, including those in subfolders, sorted by path
+fn files_below(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in fs::read_dir(&dir).into_iter().flatten().flatten() {
+            match entry.file_type() {
+                Ok(kind) if kind.is_dir() => pending.push(entry.path()),
+                Ok(_) => files.push(entry.path()),
+                Err(_) => {}
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+fn package_subdir(root: &Path, spec: &PackageSpec) -> PathBuf {
+    root.join(spec.namespace.as_str())
+        .join(spec.name.as_str())
+        .join(spec.version.to_string())
+}
+
 impl World for TypstWorld {
     fn library(&self) -> &LazyHash<Library> {
         &LIBRARY
@@ -127,7 +257,7 @@ impl World for TypstWorld {
         } else if let Some(package_spec) = id.package() {
             // Handle package files
             let package_dir = self.resolve_package(package_spec)
-                .map_err(|e| FileError::Package(e))?;
+                .map_err(FileError::Package)?;
 
             let file_path = package_dir.join(id.vpath().as_rootless_path());
 
@@ -144,7 +274,7 @@ impl World for TypstWorld {
         if let Some(package_spec) = id.package() {
             // Handle package files
             let package_dir = self.resolve_package(package_spec)
-                .map_err(|e| FileError::Package(e))?;
+                .map_err(FileError::Package)?;
 
             let file_path = package_dir.join(id.vpath().as_rootless_path());
 
@@ -152,14 +282,24 @@ impl World for TypstWorld {
                 .map_err(|_| FileError::NotFound(file_path))?;
 
             Ok(Bytes::new(data))
+        } else if let Some(file_path) = appended_index(id.vpath().as_rootless_path())
+            .and_then(|idx| self.appended_pdfs.get(idx))
+        {
+            fs::read(file_path)
+                .map(Bytes::new)
+                .map_err(|_| FileError::NotFound(file_path.clone()))
         } else {
             // Handle local files relative to template directory
             let file_path = self.template_dir.join(id.vpath().as_rootless_path());
 
-            let data = fs::read(&file_path)
-                .map_err(|_| FileError::NotFound(file_path))?;
-
-            Ok(Bytes::new(data))
+            match fs::read(&file_path) {
+                Ok(data) => Ok(Bytes::new(data)),
+                // The bundled template references the logo, so it must work without one on disk
+                Err(_) if id.vpath().as_rootless_path() == Path::new("logo.svg") => {
+                    Ok(Bytes::new(BUNDLED_LOGO))
+                }
+                Err(_) => Err(FileError::NotFound(file_path)),
+            }
         }
     }
 
@@ -172,61 +312,596 @@ impl World for TypstWorld {
     }
 }
 
+/// Virtual directory the appended attachment PDFs are read from
+const APPENDED_DIR: &str = "__appended";
+
+/// Index of an appended PDF from its virtual path, e.g. 1 for `__appended/1.pdf`
+fn appended_index(vpath: &Path) -> Option<usize> {
+    vpath.strip_prefix(APPENDED_DIR).ok()?.file_stem()?.to_str()?.parse().ok()
+}
+
+/// Markup putting each page of the appended PDFs on a page of its own after the invoice.
+/// The pages are embedded as vector graphics, so the result is one merged document.
+fn appended_pages(paths: &[PathBuf]) -> Result<String, String> {
+    let mut markup = String::new();
+    for (idx, path) in paths.iter().enumerate() {
+        let data = fs::read(path)
+            .map_err(|e| format!("Failed to read attachment {}: {}", path.display(), e))?;
+        let pdf = hayro_syntax::Pdf::new(std::sync::Arc::new(data))
+            .map_err(|e| format!("Attachment {} is not a readable PDF: {:?}", path.display(), e))?;
+        for page in 1..=pdf.pages().len() {
+            markup.push_str(&format!(
+                "\n#page(margin: 0pt)[#image(\"/{}/{}.pdf\", page: {}, width: 100%, height: 100%, fit: \"contain\")]\n",
+                APPENDED_DIR, idx, page
+            ));
+        }
+    }
+    Ok(markup)
+}
+
 pub fn generate_bill_pdf(
     bill: &Bill,
     client: &Client,
     creditor: &Address,
+    options: &InvoiceOptions,
 ) -> Result<Vec<u8>, String> {
-    let typst_content = create_typst_invoice(bill, client, creditor);
+    let mut typst_content = create_typst_invoice(bill, client, creditor, options)?;
+    typst_content.push_str(&appended_pages(&options.appended_pdfs)?);
+
+    // Only a debugging aid, so a failed write must not stop the invoice
+    if let Some(path) = &options.debug_source {
+        match fs::write(path, &typst_content) {
+            Ok(()) => eprintln!("Typst content written to: {}", path.display()),
+            Err(e) => eprintln!("Failed to write Typst debug file {}: {}", path.display(), e),
+        }
+    }
 
-    // Write typst content to temp file for inspection
-    let temp_path = Path::new("typst-debug.typ");
-    fs::write(&temp_path, &typst_content)
-        .map_err(|e| format!("Failed to write debug file: {}", e))?;
-    eprintln!("Typst content written to: {}", temp_path.display());
+    let key = compile_cache_key(&typst_content, options);
+    if let Some(pdf_data) = cached_pdf(&key) {
+        return Ok(pdf_data);
+    }
 
-    let world = TypstWorld::new(typst_content);
+    let world = TypstWorld::new(
+        typst_content,
+        options.template_dir.clone(),
+        options.package_dir.clone(),
+        options.appended_pdfs.clone(),
+        options.system_fonts,
+    );
 
     let result = typst::compile(&world);
     let document = result.output
-        .map_err(|errors| format!("Typst compilation failed: {:?}", errors))?;
+        .map_err(|errors| format!("Typst compilation failed:\n{}", format_diagnostics(&world, &errors)))?;
 
-    let pdf_data = typst_pdf::pdf(&document, &PdfOptions::default())
+    // Fonts are always subset to the glyphs used; typst-pdf has no option to embed them fully
+    let pdf_options = PdfOptions {
+        tagged: options.tagged_pdf,
+        ..PdfOptions::default()
+    };
+    let pdf_data = typst_pdf::pdf(&document, &pdf_options)
         .map_err(|e| format!("PDF generation failed: {:?}", e))?;
 
+    cache_pdf(key, pdf_data.clone());
     Ok(pdf_data)
 }
 
-fn create_typst_invoice(bill: &Bill, client: &Client, creditor: &Address) -> String {
-    let template_str = fs::read_to_string("templates/qr_bill.tpl").unwrap();
+/// Number of compiled PDFs kept in memory
+const COMPILE_CACHE_SIZE: usize = 16;
+
+/// Recently compiled PDFs by `compile_cache_key`, least recently used first
+static COMPILE_CACHE: Mutex<VecDeque<(String, Vec<u8>)>> = Mutex::new(VecDeque::new());
+
+/// Size and modification time of a file
+type FileStamp = (u64, Option<SystemTime>);
+
+/// Content hash of each file read by `compile_cache_key`, with the stamp it was computed at
+static FILE_HASHES: Mutex<BTreeMap<PathBuf, (FileStamp, [u8; 32])>> = Mutex::new(BTreeMap::new());
+
+/// SHA-256 of the generated Typst source, the output options and the files it can read: those
+/// below the template directory, in the imported packages and the appended PDFs. Any change
+/// to them leads to a new key. Files are only read again once their size or modification
+/// time changed.
+fn compile_cache_key(source: &str, options: &InvoiceOptions) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(source.as_bytes());
+    hasher.update([options.tagged_pdf as u8, options.system_fonts as u8]);
+
+    let package_files = resolved_package_roots(source, options.package_dir.as_deref())
+        .into_iter()
+        .flat_map(|root| files_below(&root));
+    let files: Vec<PathBuf> = files_below(&options.template_dir)
+        .into_iter()
+        .chain(package_files)
+        .chain(options.appended_pdfs.iter().cloned())
+        .collect();
+    for path in &files {
+        hasher.update(path.to_string_lossy().as_bytes());
+        hasher.update(file_hash(path));
+    }
+
+    hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// SHA-256 of a file's content, reused from `FILE_HASHES` while its metadata is unchanged.
+/// Missing or unreadable files hash like empty ones.
+fn file_hash(path: &Path) -> [u8; 32] {
+    let stamp: FileStamp = fs::metadata(path).map(|m| (m.len(), m.modified().ok())).unwrap_or_default();
+    if let Some((known, hash)) = FILE_HASHES.lock().unwrap().get(path)
+        && *known == stamp
+    {
+        return *hash;
+    }
+
+    let hash: [u8; 32] = Sha256::digest(fs::read(path).unwrap_or_default()).into();
+    FILE_HASHES.lock().unwrap().insert(path.to_path_buf(), (stamp, hash));
+    hash
+}
+
+fn cached_pdf(key: &str) -> Option<Vec<u8>> {
+    let mut cache = COMPILE_CACHE.lock().unwrap();
+    let idx = cache.iter().position(|(k, _)| k == key)?;
+    // Move the hit to the back so it is evicted last
+    let entry = cache.remove(idx)?;
+    let pdf_data = entry.1.clone();
+    cache.push_back(entry);
+    Some(pdf_data)
+}
+
+fn cache_pdf(key: String, pdf_data: Vec<u8>) {
+    let mut cache = COMPILE_CACHE.lock().unwrap();
+    cache.retain(|(k, _)| *k != key);
+    if cache.len() >= COMPILE_CACHE_SIZE {
+        cache.pop_front();
+    }
+    cache.push_back((key, pdf_data));
+}
+
+/// Turns Typst diagnostics into readable lines with file, line/column and the offending source
+fn format_diagnostics(world: &TypstWorld, diagnostics: &[SourceDiagnostic]) -> String {
+    diagnostics
+        .iter()
+        .map(|diagnostic| {
+            let severity = match diagnostic.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+            };
+            let mut message = match describe_span(world, diagnostic.span) {
+                Some((location, snippet)) => {
+                    format!("{} at {}: {}\n    {}", severity, location, diagnostic.message, snippet)
+                }
+                None => format!("{}: {}", severity, diagnostic.message),
+            };
+            for hint in &diagnostic.hints {
+                message.push_str(&format!("\n    hint: {}", hint));
+            }
+            message
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Location ("file:line:column") and source text of a span, if it can be resolved
+fn describe_span(world: &TypstWorld, span: Span) -> Option<(String, String)> {
+    let id = span.id()?;
+    let source = world.source(id).ok()?;
+    let range = source.range(span)?;
+    let (line, column) = source.lines().byte_to_line_column(range.start)?;
+
+    let file = match id.package() {
+        Some(package) => format!("{}/{}", package, id.vpath().as_rootless_path().display()),
+        None if id == world.main_id => "invoice template".to_string(),
+        None => id.vpath().as_rootless_path().display().to_string(),
+    };
+
+    // Only show the first line of long spans
+    let snippet: String = source.text()[range]
+        .lines()
+        .next()
+        .unwrap_or("")
+        .trim()
+        .chars()
+        .take(80)
+        .collect();
+
+    Some((format!("{}:{}:{}", file, line + 1, column + 1), snippet))
+}
+
+/// SHA-256 over everything that ends up on the invoice, used to detect stale PDFs.
+/// PDF metadata and the status are excluded since they don't affect the rendered document.
+pub fn invoice_content_hash(bill: &Bill, client: &Client, creditor: &Address, options: &InvoiceOptions) -> String {
+    let mut bill = bill.clone();
+    bill.has_pdf = false;
+    bill.pdf_created_at = None;
+    bill.pdf_hash = None;
+    bill.status = crate::app::BillStatus::Draft;
+    bill.reminder_level = 0;
+    bill.last_reminder_at = None;
+    bill.archived_at = None;
+    bill.follow_up = None;
+
+    let content = serde_json::to_vec(&(&bill, client, creditor, options)).unwrap_or_default();
+    Sha256::digest(&content)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Expands the placeholders of an additional-information template for a bill
+pub fn render_additional_info(template: &str, bill: &Bill) -> String {
+    template
+        .replace("{due_date}", &bill.due_date.format("%d.%m.%Y").to_string())
+        .replace("{reference}", &bill.reference)
+        .replace("{invoice_number}", &bill.id.to_string())
+}
+
+/// Parses a `#rrggbb` or `#rgb` color, with or without the `#`
+pub fn parse_hex_color(hex: &str) -> Option<[u8; 3]> {
+    let digits = hex.trim().trim_start_matches('#');
+    if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let digits = match digits.len() {
+        3 => digits.chars().flat_map(|c| [c, c]).collect(),
+        6 => digits.to_string(),
+        _ => return None,
+    };
+    let channel = |i: usize| u8::from_str_radix(&digits[i..i + 2], 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+pub fn hex_color(rgb: [u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", rgb[0], rgb[1], rgb[2])
+}
+
+/// The accent color as passed to the template, falling back to the default if invalid
+fn accent_color(setting: &str) -> String {
+    parse_hex_color(setting)
+        .map(hex_color)
+        .unwrap_or_else(|| DEFAULT_ACCENT_COLOR.to_string())
+}
+
+/// Reads the invoice template from `template_dir`, falling back to the bundled one if the
+/// directory has no template file
+pub fn load_template(template_dir: &Path) -> Result<String, String> {
+    let path = template_dir.join(TEMPLATE_FILE);
+    if !path.exists() {
+        return Ok(BUNDLED_TEMPLATE.to_string());
+    }
+    fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read invoice template {}: {}", path.display(), e))
+}
+
+/// Writes the bundled template into `template_dir`. A template already there is kept
+/// next to it as `qr_bill.tpl.bak`, so local edits can still be recovered.
+pub fn write_default_template(template_dir: &Path) -> Result<PathBuf, String> {
+    fs::create_dir_all(template_dir)
+        .map_err(|e| format!("Failed to create template directory {}: {}", template_dir.display(), e))?;
+
+    let path = template_dir.join(TEMPLATE_FILE);
+    if path.exists() {
+        let backup = path.with_extension("tpl.bak");
+        fs::rename(&path, &backup)
+            .map_err(|e| format!("Failed to back up {} to {}: {}", path.display(), backup.display(), e))?;
+    }
+    fs::write(&path, BUNDLED_TEMPLATE)
+        .map_err(|e| format!("Failed to write invoice template {}: {}", path.display(), e))?;
+    Ok(path)
+}
+
+/// Re-reads the invoice template and checks it for Typst syntax errors.
+/// Placeholders are filled with dummy values since only the markup itself is checked.
+/// Returns the path of the template that was checked, or `None` for the bundled one.
+pub fn check_template(template_dir: &Path) -> Result<Option<PathBuf>, String> {
+    let template_str = load_template(template_dir)?;
+    let path = template_dir.join(TEMPLATE_FILE);
+    let path = path.exists().then_some(path);
+
+    let filled = Template::new(&template_str)
+        .fill_with_function(|key| {
+            Some(match key {
+                "amount" | "table-rows" => "0".into(),
+                "table-contents" => "[]".into(),
+                _ => "x".into(),
+            })
+        })
+        .map_err(|e| format!("Invalid placeholder in template: {}", e))?;
+
+    let source = Source::detached(filled);
+    let errors: Vec<String> = source
+        .root()
+        .errors()
+        .into_iter()
+        .map(|error| {
+            let line = source
+                .range(error.span)
+                .and_then(|range| source.lines().byte_to_line(range.start))
+                .map(|line| format!("line {}: ", line + 1))
+                .unwrap_or_default();
+            format!("{}{}", line, error.message)
+        })
+        .collect();
+
+    if errors.is_empty() {
+        Ok(path)
+    } else {
+        Err(format!("Template has syntax errors:\n{}", errors.join("\n")))
+    }
+}
+
+/// Escapes a value for use inside a Typst string literal (`"…"`). Templates show such values
+/// in markup as `#"{{client-name}}"`, so names with `#`, `[` or `$` are printed as typed.
+pub fn typst_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Escapes characters with a meaning in Typst markup so `text` is rendered as typed
+fn escape_markup(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '#' | '[' | ']' | '$' | '*' | '_' | '`' | '<' | '>' | '@' | '=' | '-' | '+' | '/' | '~' | '"' | '\'') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Converts the inline markdown of one line (`**bold**`, `*italic*`, `_italic_`, `` `code` ``)
+fn markdown_inline_to_typst(text: &str) -> String {
+    let mut out = String::new();
+    let mut rest = text;
+    let mut previous: Option<char> = None;
+
+    while !rest.is_empty() {
+        // Underscores inside words (snake_case) aren't emphasis
+        let delimiter = ["**", "__", "*", "_", "`"]
+            .into_iter()
+            .filter(|d| !d.starts_with('_') || !previous.is_some_and(char::is_alphanumeric))
+            .find(|d| rest.starts_with(d));
+
+        // Only treat a delimiter as markup if it is closed later on the same line
+        if let Some(delimiter) = delimiter
+            && let Some(end) = rest[delimiter.len()..].find(delimiter).filter(|&end| end > 0)
+        {
+            let inner = &rest[delimiter.len()..delimiter.len() + end];
+            match delimiter {
+                "**" | "__" => out.push_str(&format!("#strong[{}];", markdown_inline_to_typst(inner))),
+                "`" => out.push_str(&format!("#raw(\"{}\");", typst_escape(inner))),
+                _ => out.push_str(&format!("#emph[{}];", markdown_inline_to_typst(inner))),
+            }
+            rest = &rest[2 * delimiter.len() + end..];
+            previous = delimiter.chars().last();
+            continue;
+        }
+
+        let c = rest.chars().next().unwrap();
+        out.push_str(&escape_markup(&c.to_string()));
+        rest = &rest[c.len_utf8()..];
+        previous = Some(c);
+    }
+
+    out
+}
+
+/// Renders the basic markdown used in bill notes as Typst markup: bullet and numbered lists,
+/// bold, italic and code. Line breaks are kept, a blank line starts a new paragraph, and all
+/// other text is escaped so it can't break the template.
+pub fn markdown_to_typst(markdown: &str) -> String {
+    let lines: Vec<&str> = markdown.lines().map(str::trim_end).collect();
+    let mut out = String::new();
+
+    for (idx, line) in lines.iter().enumerate() {
+        let trimmed = line.trim_start();
+        let indent = &line[..line.len() - trimmed.len()];
+
+        match markdown_list_item(trimmed) {
+            _ if trimmed.is_empty() => {}
+            Some((marker, item)) => {
+                out.push_str(&format!("{}{} {}", indent, marker, markdown_inline_to_typst(item)));
+            }
+            None => {
+                out.push_str(&markdown_inline_to_typst(trimmed));
+
+                // Keep the line break when the paragraph continues on the next line
+                let continues = lines.get(idx + 1).is_some_and(|next| {
+                    let next = next.trim_start();
+                    !next.is_empty() && markdown_list_item(next).is_none()
+                });
+                if continues {
+                    out.push_str(" \\");
+                }
+            }
+        }
+        out.push('\n');
+    }
+
+    out
+}
+
+/// Typst list marker and text of a markdown bullet (`- `, `* `, `+ `) or numbered (`1. `) item
+fn markdown_list_item(line: &str) -> Option<(&'static str, &str)> {
+    if let Some(item) = ["- ", "* ", "+ "].iter().find_map(|bullet| line.strip_prefix(bullet)) {
+        return Some(("-", item));
+    }
+
+    line.split_once(". ")
+        .filter(|(number, _)| !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()))
+        .map(|(_, item)| ("+", item))
+}
+
+/// Table rows following the items: discount, VAT lines, rounding and the amount due. Net prices
+/// list the VAT before the total; gross prices show the included VAT below it. Also returns the
+/// index of the grand-total row among them, which gets the heavy border.
+fn totals_rows(totals: &BillTotals, language: Language, document_type: DocumentType) -> (String, usize) {
+    let row = |label: String, amount: f64| format!(", table.cell(colspan: 4)[{}], [{:.2}]", label, amount);
+    let mut rows = Vec::new();
+
+    let net_vat = !totals.vat_included && !totals.vat.is_empty();
+    if net_vat {
+        rows.push(row(
+            format!("{} ({})", language.subtotal_label(), language.excluding_vat_label()),
+            totals.subtotal,
+        ));
+    } else if totals.discount != 0.0 {
+        rows.push(row(language.subtotal_label().to_string(), totals.subtotal));
+    }
+
+    if totals.discount != 0.0 {
+        let label = match &totals.discount_name {
+            Some(name) if !name.is_empty() => format!("{} #\"{}\"", language.discount_label(), typst_escape(name)),
+            _ => language.discount_label().to_string(),
+        };
+        rows.push(row(label, -totals.discount));
+    }
+
+    if net_vat {
+        for (rate, amount) in &totals.vat {
+            rows.push(row(format!("{} {}%", language.vat_label(), rate), *amount));
+        }
+    }
+
+    if totals.rounding != 0.0 {
+        rows.push(row(language.rounding_label().to_string(), totals.rounding));
+    }
+
+    let grand_total_index = rows.len();
+    rows.push(row(format!("*{}*", document_type.total_label(language)), totals.grand_total));
+
+    if totals.vat_included {
+        for (rate, amount) in &totals.vat {
+            rows.push(row(format!("_{} {}%_", language.including_vat_label(), rate), *amount));
+        }
+        rows.push(format!(", table.cell(colspan: 5)[_{}_]", language.prices_include_vat_note()));
+    }
+
+    (rows.concat(), grand_total_index)
+}
+
+fn create_typst_invoice(bill: &Bill, client: &Client, creditor: &Address, options: &InvoiceOptions) -> Result<String, String> {
+    let template_str = load_template(&options.template_dir)?;
 
     let tpl = Template::new(&template_str);
 
-    let amount_str = bill.total().to_string();
-    let table_rows = (bill.items.len()+1).to_string();
+    // An open amount is passed as `none` so the payment part shows an empty amount box
+    let amount_str = match bill.amount_mode {
+        AmountMode::Fixed => bill.qr_amount().to_string(),
+        AmountMode::Open => "none".to_string(),
+    };
+    let amount_words = match bill.amount_mode {
+        AmountMode::Fixed => crate::words::amount_to_words(bill.total(), bill.language, &bill.currency),
+        AmountMode::Open => String::new(),
+    };
 
     let mut table_contents = bill.items.iter().fold(String::new(), |mut all, item| {
         if !all.is_empty() {
             all.push_str(", ");
         }
-        all.push_str(&format!("[{}], [{}], [{}], [{:.2}], [{:.2}]", item.note, item.item_type, item.quantity, item.unit_price, item.total()));
+        // Credit lines are labelled and printed in red so they aren't mistaken for charges
+        let credit = item.is_credit();
+        let cell = |content: String| if credit { format!("[#text(fill: red)[{}]]", content) } else { format!("[{}]", content) };
+        let note = if credit {
+            format!("{} ({})", item.note, bill.language.credit_label()).trim().to_string()
+        } else {
+            item.note.clone()
+        };
+        let cells = [
+            format!("#\"{}\"", typst_escape(&note)),
+            format!("#\"{}\"", typst_escape(&item.item_type)),
+            format!("#\"{}\"", typst_escape(format!("{} {}", item.quantity, item.unit).trim())),
+            format!("{:.2}", item.unit_price),
+            format!("{:.2}", item.total()),
+        ];
+        all.push_str(&cells.map(cell).join(", "));
         all
     });
 
-    table_contents.push_str(&format!(", table.cell(colspan: 4)[*Zu unseren Gunsten*], [{:.2}]", bill.total()));
+    // Row with the heavy border: the grand total, counting the header and the items before it
+    let mut total_row = bill.items.len() + 1;
+    if bill.amount_mode == AmountMode::Fixed {
+        let (rows, grand_total_index) = totals_rows(&bill.totals(), bill.language, bill.document_type);
+        table_contents.push_str(&rows);
+        total_row += grand_total_index;
+        if let Some(conversion) = &bill.conversion {
+            table_contents.push_str(&format!(
+                ", table.cell(colspan: 4)[_{} #\"{}\" (1 #\"{}\" = {:.4} #\"{}\")_], [{:.2}]",
+                bill.language.payable_in_label(),
+                typst_escape(&conversion.currency),
+                typst_escape(&bill.currency),
+                conversion.rate,
+                typst_escape(&conversion.currency),
+                bill.qr_amount()
+            ));
+        }
+    }
+
+    let mut additional_info = render_additional_info(&options.additional_info_template, bill);
+    if additional_info.chars().count() > MAX_ADDITIONAL_INFO_LEN {
+        eprintln!(
+            "Warning: additional information exceeds {} characters and was truncated: {}",
+            MAX_ADDITIONAL_INFO_LEN, additional_info
+        );
+        additional_info = additional_info.chars().take(MAX_ADDITIONAL_INFO_LEN).collect();
+    }
 
-    let additional_info = &format!("Zahlbar bis {}", bill.due_date.format("%d.%m.%Y"));
+    let table_rows = total_row.to_string();
+    let notes = markdown_to_typst(&bill.notes);
+    let service_period = match (bill.service_from, bill.service_to) {
+        (Some(from), Some(to)) => format!(
+            "{}: {} – {}",
+            bill.language.service_period_label(),
+            from.format("%d.%m.%Y"),
+            to.format("%d.%m.%Y")
+        ),
+        _ => String::new(),
+    };
+    let reference = match bill.reference_type {
+        ReferenceType::None => "",
+        _ => bill.reference.as_str(),
+    };
+    // Invoices keep their untitled layout; credit notes name themselves and the refunded invoice
+    let document_title = match (bill.document_type, bill.credited_bill_id) {
+        (DocumentType::Invoice, _) => String::new(),
+        (DocumentType::CreditNote, Some(credited)) => format!(
+            "#text(size: 14pt, weight: \"bold\")[{} {} {}]",
+            bill.document_type.title(bill.language),
+            bill.language.credited_invoice_label(),
+            credited
+        ),
+        (DocumentType::CreditNote, None) => format!(
+            "#text(size: 14pt, weight: \"bold\")[{}]",
+            bill.document_type.title(bill.language)
+        ),
+    };
+    let reminder = match options.reminder_level {
+        0 => String::new(),
+        level => format!("#text(size: 14pt, weight: \"bold\")[{}]", bill.language.reminder_title(level)),
+    };
 
-    let vars = HashMap::from([
+    // User-entered values are placed inside Typst string literals, so they are escaped for
+    // that context; the table, amount and notes are markup generated here
+    let text_vars = [
         ("account", bill.iban.as_str()),
+        ("payable-to-label", bill.language.payable_to_label()),
         ("creditor-name", creditor.name.as_str()),
         ("creditor-street", creditor.street.as_deref().unwrap_or("")),
         ("creditor-building", creditor.building_number.as_deref().unwrap_or("")),
         ("creditor-postal-code", creditor.postal_code.as_str()),
         ("creditor-city", creditor.city.as_str()),
         ("creditor-country", creditor.country.as_str()),
-        ("amount", amount_str.as_str()),
-        ("currency", "CHF"),
+        // The currency of `amount`, i.e. of the QR payment part
+        ("currency", bill.qr_currency()),
+        ("invoice-currency", bill.currency.as_str()),
         ("client-name", client.name.as_str()),
         ("client-street", client.address.street.as_deref().unwrap_or("")),
         ("client-building", client.address.building_number.as_deref().unwrap_or("")),
@@ -239,12 +914,371 @@ fn create_typst_invoice(bill: &Bill, client: &Client, creditor: &Address) -> Str
         ("debtor-postal-code", client.billing_address.postal_code.as_str()),
         ("debtor-city", client.billing_address.city.as_str()),
         ("debtor-country", client.billing_address.country.as_str()),
-        ("reference-type", "SCOR"),
-        ("reference", bill.reference.as_str()),
+        ("reference-type", bill.reference_type.code()),
+        ("reference", reference),
+        ("po-number", bill.po_number.trim()),
+        ("po-number-label", bill.language.po_number_label()),
+        ("service-period", service_period.as_str()),
+        ("amount-words", amount_words.as_str()),
         ("additional-info", additional_info.as_str()),
-        ("table-contents", table_contents.as_str()),
-        ("table-rows", table_rows.as_str())
-    ]);
+    ];
+    let escaped: Vec<(&str, String)> = text_vars
+        .iter()
+        .map(|(key, value)| (*key, typst_escape(value)))
+        .collect();
+
+    let mut vars: HashMap<&str, &str> = escaped
+        .iter()
+        .map(|(key, value)| (*key, value.as_str()))
+        .collect();
+    let accent = accent_color(&options.accent_color);
+    vars.insert("accent-color", accent.as_str());
+    vars.insert("qr-bill", if bill.shows_qr_bill() { "true" } else { "false" });
+    vars.insert("amount", amount_str.as_str());
+    vars.insert("notes", notes.as_str());
+    vars.insert("document-title", document_title.as_str());
+    vars.insert("reminder", reminder.as_str());
+    vars.insert("table-contents", table_contents.as_str());
+    vars.insert("table-rows", table_rows.as_str());
+
+    Ok(tpl.fill_with_hashmap(&vars))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::BillItem;
+
+    /// Packages for offline tests; payqr-swiss there is a stand-in without a QR code
+    fn test_packages() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("packages")
+    }
+
+    fn test_options(template_dir: &Path) -> InvoiceOptions {
+        InvoiceOptions {
+            additional_info_template: Language::De.default_additional_info().to_string(),
+            template_dir: template_dir.to_path_buf(),
+            package_dir: Some(test_packages()),
+            tagged_pdf: true,
+            ..InvoiceOptions::default()
+        }
+    }
+
+    fn test_address(name: &str) -> Address {
+        Address::new(
+            name.to_string(),
+            Some("Bahnhofstrasse".to_string()),
+            Some("1".to_string()),
+            "8001".to_string(),
+            "Zürich".to_string(),
+            "CH".to_string(),
+        )
+    }
+
+    fn test_client(name: &str) -> Client {
+        Client {
+            id: 1,
+            name: name.to_string(),
+            address: test_address(name),
+            billing_address: test_address(name),
+            ..Client::default()
+        }
+    }
+
+    fn test_bill(items: Vec<BillItem>) -> Bill {
+        Bill {
+            id: 1,
+            client_id: 1,
+            items,
+            iban: "CH93 0076 2011 6238 5295 7".to_string(),
+            reference: Bill::generate_scor_reference(1, 1, 2024),
+            ..Bill::default()
+        }
+    }
+
+    fn item(item_type: &str, quantity: f64, unit_price: f64) -> BillItem {
+        BillItem {
+            item_type: item_type.to_string(),
+            quantity,
+            unit_price,
+            ..BillItem::default()
+        }
+    }
+
+    #[test]
+    fn load_template_falls_back_to_the_bundled_template() {
+        let empty = tempfile::tempdir().unwrap();
+        assert_eq!(load_template(empty.path()).unwrap(), BUNDLED_TEMPLATE);
+        assert_eq!(check_template(empty.path()), Ok(None));
+    }
+
+    #[test]
+    fn generates_a_pdf_without_external_templates() {
+        let empty = tempfile::tempdir().unwrap();
+        let bill = test_bill(vec![item("Beratung", 2.0, 150.0)]);
+
+        let pdf = generate_bill_pdf(&bill, &test_client("Muster AG"), &test_address("Beispiel GmbH"), &test_options(empty.path()))
+            .unwrap();
+        assert!(pdf.starts_with(b"%PDF"));
+        // Nothing is written next to the template or into the working directory
+        assert_eq!(fs::read_dir(empty.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn resolves_packages_from_the_bundled_directory() {
+        let empty = tempfile::tempdir().unwrap();
+        let world = TypstWorld::new(String::new(), empty.path().to_path_buf(), Some(test_packages()), Vec::new(), false);
+        let spec: PackageSpec = "@preview/payqr-swiss:0.4.0".parse().unwrap();
+
+        let package_dir = world.resolve_package(&spec).unwrap();
+        assert_eq!(package_dir, test_packages().join("preview").join("payqr-swiss").join("0.4.0"));
+        assert!(package_dir.join("typst.toml").is_file());
+    }
+
+    #[test]
+    fn heavy_border_marks_the_grand_total_row() {
+        let items = vec![
+            BillItem { vat_rate: 8.1, ..item("Beratung", 2.0, 150.0) },
+            BillItem { vat_rate: 2.6, ..item("Bücher", 1.0, 40.0) },
+        ];
+        let empty = tempfile::tempdir().unwrap();
+        let source = create_typst_invoice(&test_bill(items), &test_client("Muster AG"), &test_address("Beispiel GmbH"), &test_options(empty.path()))
+            .unwrap();
+
+        // Header, two items, then subtotal and two VAT lines before the total
+        assert!(source.contains("y == 6"), "{}", source);
+    }
+
+    /// Fails with the syntax errors if `markup` isn't valid Typst markup
+    fn assert_parses(markup: &str) {
+        let source = Source::detached(markup);
+        let errors: Vec<_> = source.root().errors().into_iter().map(|e| e.message).collect();
+        assert!(errors.is_empty(), "{:?} in {:?}", errors, markup);
+    }
+
+    #[test]
+    fn markdown_notes_become_typst_markup() {
+        assert_eq!(markdown_to_typst("**Danke** für *Ihren* Auftrag"), "#strong[Danke]; für #emph[Ihren]; Auftrag\n");
+        assert_eq!(markdown_to_typst("- Analyse\n- Umsetzung"), "- Analyse\n- Umsetzung\n");
+        assert_eq!(markdown_to_typst("1. Anzahlung\n2. Rest"), "+ Anzahlung\n+ Rest\n");
+        assert_eq!(markdown_to_typst("Zeile eins\nZeile zwei\n\nAbsatz"), "Zeile eins \\\nZeile zwei\n\nAbsatz\n");
+        assert_eq!(markdown_to_typst("Feld `user_id` und snake_case"), "Feld #raw(\"user_id\"); und snake\\_case\n");
+    }
+
+    #[test]
+    fn adversarial_notes_still_parse() {
+        let notes = [
+            "C:\\Users\\name\\ und ein \\",
+            "[unclosed #let x = ( $a^2 ] ] [[",
+            "- #panic(\"boom\")\n* **[**\n3. `\\`",
+            "#set page(width: 1pt) = Heading <label> @ref ~ // comment /* block",
+            "**bold *nested `code\\\" _under",
+            "\"quoted\" 'single' $$ ``` ***",
+        ];
+        for note in notes {
+            let markup = markdown_to_typst(note);
+            assert_parses(&markup);
+            // Every `#` of the input is escaped, so nothing of it turns into code
+            let unescaped = markup.replace("\\#", "");
+            assert!(!["#panic", "#set", "#let"].iter().any(|code| unescaped.contains(code)), "{}", markup);
+        }
+    }
+
+    /// Client name and item text with every character that means something in Typst
+    const METACHARACTERS: &str = "Müller #1 [AG] $x$ \\ \"Q\" */_ <a> @b = - + / ~";
+
+    #[test]
+    fn escaped_values_are_plain_typst_strings() {
+        assert_eq!(typst_escape("a\\b\"c\nd\te"), "a\\\\b\\\"c\\nd\\te");
+        assert_eq!(typst_escape("#[]$"), "#[]$");
+
+        for value in [METACHARACTERS, "\\", "\"", "ends with \\", "line\r\nbreak"] {
+            assert_parses(&format!("#\"{}\"", typst_escape(value)));
+            assert_parses(&format!("#if \"{}\" != \"\" [x]", typst_escape(value)));
+        }
+    }
+
+    #[test]
+    fn compiles_a_bill_with_typst_metacharacters() {
+        let mut bill = test_bill(vec![BillItem { note: METACHARACTERS.to_string(), ..item(METACHARACTERS, 1.0, 99.0) }]);
+        bill.notes = METACHARACTERS.to_string();
+        bill.po_number = METACHARACTERS.to_string();
+        let mut creditor = test_address(METACHARACTERS);
+        creditor.city = METACHARACTERS.to_string();
+
+        let empty = tempfile::tempdir().unwrap();
+        let pdf = generate_bill_pdf(&bill, &test_client(METACHARACTERS), &creditor, &test_options(empty.path())).unwrap();
+        assert!(pdf.starts_with(b"%PDF"));
+    }
+
+    #[test]
+    fn open_amount_bills_compile_without_an_amount() {
+        let empty = tempfile::tempdir().unwrap();
+        let options = test_options(empty.path());
+        let scor = Bill { amount_mode: AmountMode::Open, ..test_bill(vec![item("Beratung", 2.0, 150.0)]) };
+        let qrr = Bill {
+            iban: "CH44 3199 9123 0008 8901 2".to_string(),
+            reference_type: ReferenceType::Qrr,
+            reference: Bill::generate_qrr_reference(1, 1, 2024),
+            ..scor.clone()
+        };
+
+        for bill in [scor, qrr] {
+            let source = create_typst_invoice(&bill, &test_client("Muster AG"), &test_address("Muster GmbH"), &options).unwrap();
+            assert!(source.contains("amount: none,"), "{:?}", bill.reference_type);
+            assert_eq!(source.matches("amount:").count(), 1);
+            let pdf = generate_bill_pdf(&bill, &test_client("Muster AG"), &test_address("Muster GmbH"), &options).unwrap();
+            assert!(pdf.starts_with(b"%PDF"));
+        }
+    }
+
+    #[test]
+    fn invalid_accent_colors_fall_back_to_the_default() {
+        assert_eq!(accent_color("#1A2b3C"), "#1a2b3c");
+        assert_eq!(accent_color(" #abc "), "#aabbcc");
+        for invalid in ["nope", "", "#12345", "#ggg000", "123456789"] {
+            assert_eq!(accent_color(invalid), DEFAULT_ACCENT_COLOR, "{:?}", invalid);
+        }
+    }
+
+    fn page_count(pdf_data: Vec<u8>) -> usize {
+        hayro_syntax::Pdf::new(std::sync::Arc::new(pdf_data)).expect("Generated PDF is not readable").pages().len()
+    }
+
+    #[test]
+    fn appended_attachment_pages_follow_the_invoice() {
+        let dir = tempfile::tempdir().unwrap();
+        let options = test_options(dir.path());
+        let bill = test_bill(vec![item("Beratung", 2.0, 150.0)]);
+        let invoice = generate_bill_pdf(&bill, &test_client("Muster AG"), &test_address("Muster GmbH"), &options).unwrap();
+        assert_eq!(page_count(invoice), 1);
+
+        // A two-page attachment, compiled the same way as an invoice
+        let world = TypstWorld::new("Receipt\n#pagebreak()\nContract".to_string(), dir.path().to_path_buf(), None, Vec::new(), false);
+        let document = typst::compile(&world).output.unwrap();
+        let attachment = dir.path().join("attachment.pdf");
+        fs::write(&attachment, typst_pdf::pdf(&document, &PdfOptions::default()).unwrap()).unwrap();
+
+        let options = InvoiceOptions { appended_pdfs: vec![attachment], ..options };
+        let merged = generate_bill_pdf(&bill, &test_client("Muster AG"), &test_address("Muster GmbH"), &options).unwrap();
+        assert_eq!(page_count(merged), 3);
+    }
+
+    #[test]
+    fn saved_bill_round_trips_into_a_pdf() {
+        let db = crate::db::Database::new(":memory:").unwrap();
+        let client_id = db.save_client(&Client { id: 0, ..test_client("Muster AG") }).unwrap();
+        let mut items = vec![item("Beratung", 2.5, 180.0), item("Material", 3.0, 19.90)];
+        items[0].unit = "h".to_string();
+        items[1].vat_rate = 8.1;
+        let bill_id = db.save_bill(&Bill { id: 0, client_id, ..test_bill(items) }).unwrap();
+
+        let client = db.get_all_clients().unwrap().into_iter().find(|c| c.id == client_id).unwrap();
+        let bill = db.get_bill_by_id(bill_id).unwrap().unwrap();
+        assert_eq!(client.billing_address, test_address("Muster AG"));
+        assert_eq!(bill.items.len(), 2);
+        assert_eq!(bill.items[0].unit, "h");
+        // 450.00 + 59.70, plus 8.1% VAT on the material
+        assert_eq!(bill.total(), 514.54);
 
-    tpl.fill_with_hashmap(&vars)
+        let dir = tempfile::tempdir().unwrap();
+        let options = test_options(dir.path());
+        // The bundled template with the offline payqr-swiss stand-in, no network access
+        assert!(options.package_dir.as_ref().unwrap().join("preview/payqr-swiss/0.4.0/typst.toml").is_file());
+        let pdf_data = generate_bill_pdf(&bill, &client, &test_address("Muster GmbH"), &options).unwrap();
+        assert!(pdf_data.starts_with(b"%PDF"));
+    }
+
+    #[test]
+    fn unchanged_invoices_are_served_from_the_compile_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let options = test_options(dir.path());
+        let (client, creditor) = (test_client("Cache AG"), test_address("Muster GmbH"));
+        let bill = test_bill(vec![item("Cached line", 1.0, 42.0)]);
+
+        let first = generate_bill_pdf(&bill, &client, &creditor, &options).unwrap();
+        let source = create_typst_invoice(&bill, &client, &creditor, &options).unwrap();
+        let key = compile_cache_key(&source, &options);
+        assert_eq!(cached_pdf(&key), Some(first));
+
+        // A second call returns the cached bytes instead of compiling again
+        cache_pdf(key.clone(), b"cached".to_vec());
+        assert_eq!(generate_bill_pdf(&bill, &client, &creditor, &options).unwrap(), b"cached");
+
+        // Files in template subfolders and in the imported packages are part of the key
+        fs::create_dir(dir.path().join("fonts")).unwrap();
+        fs::write(dir.path().join("fonts").join("note.txt"), "v1").unwrap();
+        let with_subfolder = compile_cache_key(&source, &options);
+        assert_ne!(with_subfolder, key);
+
+        let packages = tempfile::tempdir().unwrap();
+        let package = packages.path().join("preview/payqr-swiss/0.4.0");
+        fs::create_dir_all(&package).unwrap();
+        for file in ["typst.toml", "lib.typ"] {
+            fs::copy(test_packages().join("preview/payqr-swiss/0.4.0").join(file), package.join(file)).unwrap();
+        }
+        let copied = InvoiceOptions { package_dir: Some(packages.path().to_path_buf()), ..options.clone() };
+        let copied_key = compile_cache_key(&source, &copied);
+        fs::write(package.join("lib.typ"), "#let swiss-qr-bill(..args) = []").unwrap();
+        assert_ne!(compile_cache_key(&source, &copied), copied_key);
+    }
+
+    #[test]
+    fn files_are_rehashed_only_after_their_metadata_changed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("logo.svg");
+        fs::write(&path, "<svg>one</svg>").unwrap();
+        let modified = fs::metadata(&path).unwrap().modified().unwrap();
+        let first = file_hash(&path);
+
+        // Same size and modification time: the stored hash is used without reading the file
+        fs::write(&path, "<svg>two</svg>").unwrap();
+        fs::File::options().write(true).open(&path).unwrap().set_modified(modified).unwrap();
+        assert_eq!(file_hash(&path), first);
+
+        let later = modified + std::time::Duration::from_secs(1);
+        fs::File::options().write(true).open(&path).unwrap().set_modified(later).unwrap();
+        assert_ne!(file_hash(&path), first);
+        assert_eq!(file_hash(&path), <[u8; 32]>::from(Sha256::digest("<svg>two</svg>")));
+    }
+
+    #[test]
+    fn embedded_fonts_are_subsets() {
+        let dir = tempfile::tempdir().unwrap();
+        let bill = test_bill(vec![item("Beratung", 4.0, 150.0)]);
+        let pdf_data = generate_bill_pdf(&bill, &test_client("Muster AG"), &test_address("Muster GmbH"), &test_options(dir.path())).unwrap();
+
+        use hayro_syntax::object::{Array, Dict, Name, dict::keys};
+        let pdf = hayro_syntax::Pdf::new(std::sync::Arc::new(pdf_data)).expect("Generated PDF is not readable");
+        let mut fonts = Vec::new();
+        for page in pdf.pages().iter() {
+            let resources = page.resources();
+            for font in resources.fonts.keys().filter_map(|key| resources.fonts.get::<Dict>(key)) {
+                // Type0 fonts name their CID font again, with the same prefix
+                let descendants = font.get::<Array>(keys::DESCENDANT_FONTS).map(|a| a.iter::<Dict>().collect::<Vec<_>>());
+                for dict in std::iter::once(font).chain(descendants.into_iter().flatten()) {
+                    fonts.extend(dict.get::<Name>(keys::BASE_FONT).map(|name| name.as_str().to_string()));
+                }
+            }
+        }
+        assert!(!fonts.is_empty());
+        // Subsets are named with six capital letters and a plus sign, e.g. `SVFBIW+`
+        for font in &fonts {
+            let prefix = font.split_once('+').map_or("", |(prefix, _)| prefix);
+            assert!(prefix.len() == 6 && prefix.chars().all(|c| c.is_ascii_uppercase()), "{} is not a subset", font);
+        }
+    }
+
+    #[test]
+    fn untagged_pdfs_are_smaller() {
+        let dir = tempfile::tempdir().unwrap();
+        let tagged = test_options(dir.path());
+        let untagged = InvoiceOptions { tagged_pdf: false, ..tagged.clone() };
+        let (client, creditor) = (test_client("Muster AG"), test_address("Muster GmbH"));
+        let bill = test_bill(vec![item("Beratung", 4.0, 150.0), item("Reisezeit", 1.5, 90.0)]);
+
+        let tagged_size = generate_bill_pdf(&bill, &client, &creditor, &tagged).unwrap().len();
+        let untagged_size = generate_bill_pdf(&bill, &client, &creditor, &untagged).unwrap().len();
+        assert!(untagged_size < tagged_size, "untagged {} bytes, tagged {} bytes", untagged_size, tagged_size);
+    }
 }
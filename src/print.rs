@@ -0,0 +1,50 @@
+use std::path::Path;
+use std::process::Command;
+
+/// Sends a PDF file to the default printer with the platform's print command:
+/// `lp` (CUPS) on Linux and macOS, the shell's print verb on Windows
+pub fn print_pdf(path: &Path) -> Result<(), String> {
+    let output = print_command(path)
+        .output()
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => {
+                "No print system found. Save the PDF and print it from a PDF viewer instead.".to_string()
+            }
+            _ => format!("Failed to start printing: {}", e),
+        })?;
+
+    if output.status.success() {
+        return Ok(());
+    }
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let message = stderr.trim();
+    let lower = message.to_lowercase();
+    if lower.contains("no default destination") || lower.contains("no destinations") || lower.contains("no printer") {
+        Err("No printer is set up. Add a printer in the system settings or choose a default one.".to_string())
+    } else if message.is_empty() {
+        Err(format!("Printing failed ({})", output.status))
+    } else {
+        Err(format!("Printing failed: {}", message))
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+fn print_command(path: &Path) -> Command {
+    let mut command = Command::new("lp");
+    command.arg("--").arg(path);
+    command
+}
+
+#[cfg(target_os = "windows")]
+fn print_command(path: &Path) -> Command {
+    // Prints through the application registered for PDFs, which fails if there is none
+    let mut command = Command::new("powershell");
+    command
+        .args(["-NoProfile", "-NonInteractive", "-Command"])
+        .arg(format!(
+            "Start-Process -FilePath '{}' -Verb Print -ErrorAction Stop",
+            path.display().to_string().replace('\'', "''")
+        ));
+    command
+}
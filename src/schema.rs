@@ -1,5 +1,28 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    audit_log (id) {
+        id -> Integer,
+        timestamp -> Text,
+        entity_type -> Text,
+        entity_id -> Integer,
+        action -> Text,
+        details -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    attachments (id) {
+        id -> Integer,
+        bill_id -> Integer,
+        filename -> Text,
+        mime -> Text,
+        path -> Text,
+        added_at -> Text,
+        append_to_pdf -> Bool,
+    }
+}
+
 diesel::table! {
     bills (id) {
         id -> Integer,
@@ -13,6 +36,26 @@ diesel::table! {
         items -> Text,
         pdf_data -> Nullable<Binary>,
         pdf_created_at -> Nullable<Text>,
+        amount_mode -> Text,
+        language -> Text,
+        currency -> Text,
+        pdf_hash -> Nullable<Text>,
+        pdf_path -> Nullable<Text>,
+        prices_include_vat -> Bool,
+        discount -> Nullable<Text>,
+        reminder_level -> Integer,
+        last_reminder_at -> Nullable<Text>,
+        reference_type -> Text,
+        po_number -> Text,
+        service_from -> Nullable<Text>,
+        service_to -> Nullable<Text>,
+        archived_at -> Nullable<Text>,
+        follow_up -> Nullable<Text>,
+        include_qr_bill -> Bool,
+        conversion -> Nullable<Text>,
+        round_to_five_cents -> Bool,
+        document_type -> Text,
+        credited_bill_id -> Nullable<Integer>,
     }
 }
 
@@ -37,11 +80,45 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    bill_templates (id) {
+        id -> Integer,
+        name -> Text,
+        items -> Text,
+        notes -> Text,
+        payment_days -> Integer,
+    }
+}
+
+diesel::table! {
+    discount_templates (id) {
+        id -> Integer,
+        name -> Text,
+        kind -> Text,
+        value -> Double,
+    }
+}
+
 diesel::table! {
     item_templates (id) {
         id -> Integer,
         item_type -> Text,
         unit_price -> Double,
+        use_count -> Integer,
+        last_used -> Nullable<Text>,
+        vat_rate -> Double,
+        unit -> Text,
+    }
+}
+
+diesel::table! {
+    payments (id) {
+        id -> Integer,
+        bill_id -> Integer,
+        amount -> Double,
+        paid_on -> Text,
+        note -> Text,
+        source -> Nullable<Text>,
     }
 }
 
@@ -52,11 +129,18 @@ diesel::table! {
     }
 }
 
+diesel::joinable!(attachments -> bills (bill_id));
 diesel::joinable!(bills -> clients (client_id));
+diesel::joinable!(payments -> bills (bill_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    attachments,
+    audit_log,
+    bill_templates,
     bills,
     clients,
+    discount_templates,
     item_templates,
+    payments,
     settings,
 );
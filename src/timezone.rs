@@ -0,0 +1,118 @@
+use std::sync::{OnceLock, RwLock};
+
+use chrono::{DateTime, FixedOffset, TimeZone, Utc};
+use chrono_tz::Tz;
+
+/// Process-wide copy of `BillManagerApp::timezone` for code without access to the app, like
+/// database rows and deserialized files; `None` follows the system
+static CONFIGURED: RwLock<Option<Tz>> = RwLock::new(None);
+
+/// The system's zone, falling back to UTC if its name is unknown
+pub fn system_zone() -> Tz {
+    static SYSTEM: OnceLock<Tz> = OnceLock::new();
+    *SYSTEM.get_or_init(|| {
+        iana_time_zone::get_timezone()
+            .ok()
+            .and_then(|name| name.parse().ok())
+            .unwrap_or(Tz::UTC)
+    })
+}
+
+/// The zone dates are displayed and entered in
+pub fn zone() -> Tz {
+    CONFIGURED.read().unwrap().unwrap_or_else(system_zone)
+}
+
+/// Sets the display zone from an IANA name like `Europe/Zurich`; empty follows the system
+pub fn set_zone(name: &str) -> Result<(), String> {
+    let tz = parse_zone(name)?;
+    *CONFIGURED.write().unwrap() = tz;
+    Ok(())
+}
+
+/// Checks an IANA zone name; empty means the system zone
+pub fn parse_zone(name: &str) -> Result<Option<Tz>, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Ok(None);
+    }
+    name.parse::<Tz>()
+        .map(Some)
+        .map_err(|_| format!("Unknown time zone '{}'", name))
+}
+
+pub fn now() -> DateTime<Tz> {
+    Utc::now().with_timezone(&zone())
+}
+
+/// Converts a timestamp into the display zone
+pub fn in_zone<T: TimeZone>(dt: &DateTime<T>) -> DateTime<Tz> {
+    dt.with_timezone(&zone())
+}
+
+/// Parses a stored RFC 3339 timestamp into the display zone
+pub fn parse_stored(value: &str) -> Option<DateTime<Tz>> {
+    parse_stored_in(value, zone())
+}
+
+/// Parses a stored RFC 3339 timestamp into the given zone
+pub fn parse_stored_in(value: &str, tz: Tz) -> Option<DateTime<Tz>> {
+    DateTime::parse_from_rfc3339(value).ok().map(|dt| dt.with_timezone(&tz))
+}
+
+/// Formats a timestamp for storage, always in UTC
+pub fn to_stored<T: TimeZone>(dt: &DateTime<T>) -> String {
+    dt.with_timezone(&Utc).to_rfc3339()
+}
+
+/// Serde support for `DateTime<Tz>` fields; chrono only deserializes fixed zones
+pub mod serde_zoned {
+    use super::*;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(dt: &DateTime<Tz>, serializer: S) -> Result<S::Ok, S::Error> {
+        dt.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<DateTime<Tz>, D::Error> {
+        DateTime::<FixedOffset>::deserialize(deserializer).map(|dt| in_zone(&dt))
+    }
+
+    pub mod option {
+        use super::*;
+
+        pub fn serialize<S: Serializer>(dt: &Option<DateTime<Tz>>, serializer: S) -> Result<S::Ok, S::Error> {
+            dt.serialize(serializer)
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<DateTime<Tz>>, D::Error> {
+            Option::<DateTime<FixedOffset>>::deserialize(deserializer).map(|dt| dt.map(|dt| in_zone(&dt)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn stored_utc_timestamps_are_shown_in_the_configured_zone() {
+        let stored = "2024-12-31T23:30:00+00:00";
+        let local = |name: &str| parse_stored_in(stored, parse_zone(name).unwrap().unwrap()).unwrap();
+
+        let zurich = local("Europe/Zurich");
+        let new_york = local("America/New_York");
+
+        // New Year in Zurich, still the old year in New York
+        assert_eq!(zurich.date_naive(), NaiveDate::from_ymd_opt(2025, 1, 1).unwrap());
+        assert_eq!(zurich.format("%H:%M").to_string(), "00:30");
+        assert_eq!(new_york.date_naive(), NaiveDate::from_ymd_opt(2024, 12, 31).unwrap());
+        assert_eq!(new_york.format("%H:%M").to_string(), "18:30");
+        // Same instant, stored back unchanged
+        assert_eq!(zurich, new_york);
+        assert_eq!(to_stored(&zurich), stored);
+        assert_eq!(parse_zone(" "), Ok(None));
+        assert!(parse_zone("Mars/Olympus").is_err());
+    }
+}
@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct Address {
     pub name: String,
     pub street: Option<String>,
@@ -28,4 +28,332 @@ impl Address {
             country,
         }
     }
+
+    /// Returns the labels of required fields that are empty
+    pub fn missing_fields(&self) -> Vec<&'static str> {
+        let mut missing = Vec::new();
+        if self.name.trim().is_empty() {
+            missing.push("name");
+        }
+        if self.postal_code.trim().is_empty() {
+            missing.push("postal code");
+        }
+        if self.city.trim().is_empty() {
+            missing.push("city");
+        }
+        if self.country.trim().is_empty() {
+            missing.push("country");
+        }
+        missing
+    }
+}
+
+/// Invoice language, used for texts rendered on the invoice and QR bill
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum Language {
+    #[default]
+    De,
+    Fr,
+    It,
+    En,
+}
+
+impl Language {
+    pub const ALL: [Language; 4] = [Language::De, Language::Fr, Language::It, Language::En];
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "De" => Some(Language::De),
+            "Fr" => Some(Language::Fr),
+            "It" => Some(Language::It),
+            "En" => Some(Language::En),
+            _ => None,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Language::De => "De",
+            Language::Fr => "Fr",
+            Language::It => "It",
+            Language::En => "En",
+        }
+    }
+
+    /// Default text for the QR bill's additional information field
+    pub fn default_additional_info(&self) -> &'static str {
+        match self {
+            Language::De => "Zahlbar bis {due_date}",
+            Language::Fr => "Payable jusqu'au {due_date}",
+            Language::It => "Pagabile entro il {due_date}",
+            Language::En => "Payable by {due_date}",
+        }
+    }
+
+    /// Label of the client's purchase order number on the invoice
+    pub fn po_number_label(&self) -> &'static str {
+        match self {
+            Language::De => "Bestellnummer",
+            Language::Fr => "Numéro de commande",
+            Language::It => "Numero d'ordine",
+            Language::En => "PO number",
+        }
+    }
+
+    /// Label of the service period line on the invoice
+    pub fn service_period_label(&self) -> &'static str {
+        match self {
+            Language::De => "Leistungszeitraum",
+            Language::Fr => "Période de prestation",
+            Language::It => "Periodo di prestazione",
+            Language::En => "Service period",
+        }
+    }
+
+    /// Marks credit and refund lines on the invoice
+    pub fn credit_label(&self) -> &'static str {
+        match self {
+            Language::De => "Gutschrift",
+            Language::Fr => "Avoir",
+            Language::It => "Accredito",
+            Language::En => "Credit",
+        }
+    }
+
+    /// Introduces the invoice a credit note refers to, followed by its number
+    pub fn credited_invoice_label(&self) -> &'static str {
+        match self {
+            Language::De => "zu Rechnung Nr.",
+            Language::Fr => "concernant la facture n°",
+            Language::It => "relativa alla fattura n.",
+            Language::En => "for invoice no.",
+        }
+    }
+
+    pub fn rounding_label(&self) -> &'static str {
+        match self {
+            Language::De => "Rundungsdifferenz",
+            Language::Fr => "Différence d'arrondi",
+            Language::It => "Differenza di arrotondamento",
+            Language::En => "Rounding difference",
+        }
+    }
+
+    /// Sum of the item lines before discount and VAT
+    pub fn subtotal_label(&self) -> &'static str {
+        match self {
+            Language::De => "Zwischensumme",
+            Language::Fr => "Sous-total",
+            Language::It => "Subtotale",
+            Language::En => "Subtotal",
+        }
+    }
+
+    /// Name of the value added tax, followed by the rate on VAT lines
+    pub fn vat_label(&self) -> &'static str {
+        match self {
+            Language::De => "MWST",
+            Language::Fr => "TVA",
+            Language::It => "IVA",
+            Language::En => "VAT",
+        }
+    }
+
+    /// Marks the subtotal of net prices, before VAT is added
+    pub fn excluding_vat_label(&self) -> &'static str {
+        match self {
+            Language::De => "exkl. MWST",
+            Language::Fr => "hors TVA",
+            Language::It => "IVA esclusa",
+            Language::En => "excl. VAT",
+        }
+    }
+
+    /// Introduces the VAT contained in gross prices, followed by the rate
+    pub fn including_vat_label(&self) -> &'static str {
+        match self {
+            Language::De => "inkl. MWST",
+            Language::Fr => "dont TVA",
+            Language::It => "di cui IVA",
+            Language::En => "incl. VAT",
+        }
+    }
+
+    /// Note below the totals of a bill with gross prices
+    pub fn prices_include_vat_note(&self) -> &'static str {
+        match self {
+            Language::De => "Alle Preise inkl. MWST",
+            Language::Fr => "Tous les prix TVA incluse",
+            Language::It => "Tutti i prezzi IVA inclusa",
+            Language::En => "All prices incl. VAT",
+        }
+    }
+
+    pub fn discount_label(&self) -> &'static str {
+        match self {
+            Language::De => "Rabatt",
+            Language::Fr => "Rabais",
+            Language::It => "Sconto",
+            Language::En => "Discount",
+        }
+    }
+
+    /// Label of the account line on invoices without a QR payment part
+    pub fn payable_to_label(&self) -> &'static str {
+        match self {
+            Language::De => "Zahlbar auf",
+            Language::Fr => "Payable sur",
+            Language::It => "Pagabile su",
+            Language::En => "Payable to",
+        }
+    }
+
+    /// Introduces the currency of a converted QR amount
+    pub fn payable_in_label(&self) -> &'static str {
+        match self {
+            Language::De => "Zahlbar in",
+            Language::Fr => "Payable en",
+            Language::It => "Pagabile in",
+            Language::En => "Payable in",
+        }
+    }
+
+    /// Heading of a payment reminder of the given level
+    pub fn reminder_title(&self, level: u8) -> &'static str {
+        match (self, level) {
+            (Language::De, 1) => "Zahlungserinnerung",
+            (Language::De, 2) => "2. Mahnung",
+            (Language::De, _) => "Letzte Mahnung",
+            (Language::Fr, 1) => "Rappel de paiement",
+            (Language::Fr, 2) => "2e rappel",
+            (Language::Fr, _) => "Dernier rappel",
+            (Language::It, 1) => "Promemoria di pagamento",
+            (Language::It, 2) => "2° sollecito",
+            (Language::It, _) => "Ultimo sollecito",
+            (Language::En, 1) => "Payment reminder",
+            (Language::En, 2) => "Second reminder",
+            (Language::En, _) => "Final reminder",
+        }
+    }
+}
+
+impl std::fmt::Display for Language {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Language::De => write!(f, "Deutsch"),
+            Language::Fr => write!(f, "Français"),
+            Language::It => write!(f, "Italiano"),
+            Language::En => write!(f, "English"),
+        }
+    }
+}
+
+/// Payment reference scheme of the QR bill
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ReferenceType {
+    /// ISO 11649 creditor reference (`RF…`), used with a regular IBAN
+    #[default]
+    Scor,
+    /// 27-digit QR reference, only valid with a QR-IBAN
+    Qrr,
+    /// No structured reference
+    None,
+}
+
+impl ReferenceType {
+    pub const ALL: [ReferenceType; 3] = [ReferenceType::Scor, ReferenceType::Qrr, ReferenceType::None];
+
+    /// Code used by the QR-bill standard, also stored in the database
+    pub fn code(&self) -> &'static str {
+        match self {
+            ReferenceType::Scor => "SCOR",
+            ReferenceType::Qrr => "QRR",
+            ReferenceType::None => "NON",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "SCOR" => Some(ReferenceType::Scor),
+            "QRR" => Some(ReferenceType::Qrr),
+            "NON" => Some(ReferenceType::None),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for ReferenceType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReferenceType::Scor => write!(f, "SCOR (creditor reference)"),
+            ReferenceType::Qrr => write!(f, "QRR (QR reference)"),
+            ReferenceType::None => write!(f, "None"),
+        }
+    }
+}
+
+/// Kind of document a bill is printed as
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum DocumentType {
+    #[default]
+    Invoice,
+    /// Refund of an earlier invoice; the amount is owed to the client
+    CreditNote,
+}
+
+impl DocumentType {
+    pub const ALL: [DocumentType; 2] = [DocumentType::Invoice, DocumentType::CreditNote];
+
+    /// Code stored in the database
+    pub fn code(&self) -> &'static str {
+        match self {
+            DocumentType::Invoice => "invoice",
+            DocumentType::CreditNote => "credit_note",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "invoice" => Some(DocumentType::Invoice),
+            "credit_note" => Some(DocumentType::CreditNote),
+            _ => None,
+        }
+    }
+
+    /// Heading printed on the document
+    pub fn title(&self, language: Language) -> &'static str {
+        match (self, language) {
+            (DocumentType::Invoice, Language::De) => "Rechnung",
+            (DocumentType::Invoice, Language::Fr) => "Facture",
+            (DocumentType::Invoice, Language::It) => "Fattura",
+            (DocumentType::Invoice, Language::En) => "Invoice",
+            (DocumentType::CreditNote, Language::De) => "Gutschrift",
+            (DocumentType::CreditNote, Language::Fr) => "Note de crédit",
+            (DocumentType::CreditNote, Language::It) => "Nota di credito",
+            (DocumentType::CreditNote, Language::En) => "Credit note",
+        }
+    }
+
+    /// Label of the grand total: owed by the client on invoices, to the client on credit notes
+    pub fn total_label(&self, language: Language) -> &'static str {
+        match (self, language) {
+            (DocumentType::Invoice, Language::De) => "Zu unseren Gunsten",
+            (DocumentType::Invoice, Language::Fr) => "En notre faveur",
+            (DocumentType::Invoice, Language::It) => "A nostro favore",
+            (DocumentType::Invoice, Language::En) => "Amount due",
+            (DocumentType::CreditNote, Language::De) => "Zu Ihren Gunsten",
+            (DocumentType::CreditNote, Language::Fr) => "En votre faveur",
+            (DocumentType::CreditNote, Language::It) => "A vostro favore",
+            (DocumentType::CreditNote, Language::En) => "In your favour",
+        }
+    }
+}
+
+impl std::fmt::Display for DocumentType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DocumentType::Invoice => write!(f, "Invoice"),
+            DocumentType::CreditNote => write!(f, "Credit note"),
+        }
+    }
 }
@@ -1,14 +1,27 @@
 use eframe::egui;
 use chrono::Datelike;
 
-use crate::app::{Bill, BillItem, BillManagerApp, BillStatus, Client, ItemTemplate, Tab, validate_iban};
+use crate::app::{AmountMode, AppliedDiscount, Attachment, Bill, BillFilter, BillItem, BillTemplate, CurrencyConversion, BillSortColumn, BillTotals, BillManagerApp, DEFAULT_DUE_DATE_PRESETS, BillStatus, Client, Discount, DiscountTemplate, FormKind, SettingsReset, SetupWizard, ItemTemplate, MatchConfidence, PIECE_UNITS, RESTORED_CLIENT_NAME, QR_CURRENCIES, check_reference_type, find_duplicate_references, format_bill_ids, format_reference, placeholder_creditor_fields, reference_problem, Tab, parse_amount_bound, round_cents, round_to, validate_email, validate_iban};
+use crate::export::{CSV_PRESETS, CsvColumn};
+use crate::pdf::{render_additional_info, MAX_ADDITIONAL_INFO_LEN};
+use crate::types::{DocumentType, Language, ReferenceType};
 
 impl eframe::App for BillManagerApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.poll_pdf_jobs();
+        self.poll_email_job();
+        self.poll_smtp_test();
+        self.poll_reminder_batch();
+        let batch_running = self.reminder_batch.as_ref().is_some_and(|b| !b.finished);
+        if self.pdf_jobs_running > 0 || self.email_job.is_some() || self.smtp_test_job.is_some() || batch_running {
+            ctx.request_repaint_after(std::time::Duration::from_millis(200));
+        }
+
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             ui.horizontal(|ui| {
                 ui.heading("Bill Manager");
                 ui.separator();
+                ui.selectable_value(&mut self.selected_tab, Tab::Dashboard, "Dashboard");
                 ui.selectable_value(&mut self.selected_tab, Tab::Bills, "Bills");
                 ui.selectable_value(&mut self.selected_tab, Tab::Clients, "Clients");
                 ui.selectable_value(&mut self.selected_tab, Tab::ItemTemplates, "Item Templates");
@@ -18,6 +31,7 @@ impl eframe::App for BillManagerApp {
 
         egui::CentralPanel::default().show(ctx, |ui| {
             match self.selected_tab {
+                Tab::Dashboard => show_dashboard_tab(self, ui),
                 Tab::Clients => show_clients_tab(self, ui),
                 Tab::Bills => show_bills_tab(self, ui),
                 Tab::ItemTemplates => show_item_templates_tab(self, ui),
@@ -30,720 +44,3885 @@ impl eframe::App for BillManagerApp {
             show_client_form_window(self, ctx);
         }
 
-        if self.show_bill_form {
-            show_bill_form_window(self, ctx);
+        let editor_keys: Vec<u64> = self.bill_editors.iter().map(|e| e.key).collect();
+        for key in editor_keys {
+            show_bill_form_window(self, ctx, key);
         }
 
         if self.show_template_form {
             show_template_form_window(self, ctx);
         }
+
+        if self.editing_bill_template.is_some() {
+            show_bill_template_dialog(self, ctx);
+        }
+
+        if let Some(kind) = self.pending_discard {
+            show_discard_changes_dialog(self, ctx, kind);
+        }
+
+        if self.pending_pdf_overwrite.is_some() {
+            show_overwrite_pdf_dialog(self, ctx);
+        }
+
+        if !self.pending_client_import.is_empty() {
+            show_client_import_dialog(self, ctx);
+        }
+
+        if self.pending_client_delete.is_some() {
+            show_client_delete_dialog(self, ctx);
+        }
+
+        if let Some(reset) = self.pending_settings_reset {
+            show_settings_reset_dialog(self, ctx, reset);
+        }
+
+        if self.pending_email.is_some() {
+            show_email_preview_dialog(self, ctx);
+        }
+
+        if self.pending_payment.is_some() {
+            show_record_payment_dialog(self, ctx);
+        }
+
+        if self.reconcile_report.is_some() {
+            show_reconcile_report_dialog(self, ctx);
+        }
+
+        if self.pdf_error.is_some() {
+            show_pdf_error_dialog(self, ctx);
+        }
+
+        if self.setup_wizard.is_some() {
+            show_setup_wizard(self, ctx);
+        }
+
+        show_toast(self, ctx);
+
+        // Settings are saved once typing pauses, and right away when leaving the tab or closing
+        let leaving = self.selected_tab != Tab::Settings || ctx.input(|i| i.viewport().close_requested());
+        if let Some(wait) = self.flush_settings(leaving) {
+            ctx.request_repaint_after(wait);
+        }
     }
 }
 
-fn show_clients_tab(app: &mut BillManagerApp, ui: &mut egui::Ui) {
-    ui.heading("Clients");
+fn show_dashboard_tab(app: &mut BillManagerApp, ui: &mut egui::Ui) {
+    ui.heading("Dashboard");
     ui.separator();
 
-    if ui.button("➕ Add Client").clicked() {
-        app.editing_client = Some(Client::default());
-        app.show_client_form = true;
-    }
-
-    ui.add_space(10.0);
+    let mut bill_to_open: Option<Bill> = None;
+    let mut record_reminder: Option<Vec<u64>> = None;
+    let mut email_reminders = false;
 
     egui::ScrollArea::vertical().show(ui, |ui| {
-        for client in app.clients.clone().iter() {
-            ui.group(|ui| {
-                ui.horizontal(|ui| {
-                    ui.vertical(|ui| {
-                        ui.strong(&client.name);
-                        ui.label(format!("{}, {}", client.address.city, client.address.country));
-                        ui.label(&client.email);
-                        ui.label(&client.phone);
-                    });
+        ui.group(|ui| {
+            ui.strong("Totals");
+            ui.separator();
+
+            // Written-off bills are listed on their own, never as paid or outstanding
+            let rows = [
+                ("Outstanding", app.totals_by_currency(Bill::is_outstanding)),
+                ("Paid", app.totals_by_currency(|b| b.status == BillStatus::Paid)),
+                ("Written off", app.totals_by_currency(|b| b.status == BillStatus::WrittenOff)),
+            ];
+            egui::Grid::new("dashboard_totals").num_columns(2).spacing([20.0, 4.0]).show(ui, |ui| {
+                for (label, totals) in rows {
+                    ui.label(label);
+                    if totals.is_empty() {
+                        ui.weak("–");
+                    } else {
+                        ui.label(
+                            totals.iter()
+                                .map(|(currency, total)| format!("{} {:.2}", currency, total))
+                                .collect::<Vec<_>>()
+                                .join(", "),
+                        );
+                    }
+                    ui.end_row();
+                }
+            });
+        });
 
-                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                        if ui.button("🗑 Delete").clicked() {
-                            app.delete_client(client.id);
-                        }
-                        if ui.button("✏ Edit").clicked() {
-                            app.editing_client = Some(client.clone());
-                            app.show_client_form = true;
+        ui.add_space(10.0);
+
+        ui.group(|ui| {
+            ui.horizontal(|ui| {
+                ui.strong("Coming due");
+                let mut horizon = app.dashboard_horizon_days;
+                for days in [7, 14, 30] {
+                    ui.selectable_value(&mut horizon, days, format!("{} days", days));
+                }
+                if horizon != app.dashboard_horizon_days {
+                    app.dashboard_horizon_days = horizon;
+                    app.mark_settings_dirty();
+                }
+
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui.button("📅 Export to calendar…")
+                        .on_hover_text("Due dates of all sent, unpaid bills as an .ics file")
+                        .clicked()
+                        && let Some(path) = rfd::FileDialog::new()
+                            .add_filter("iCalendar", &["ics"])
+                            .set_file_name("due-dates.ics")
+                            .save_file()
+                    {
+                        match app.export_due_ical(&path) {
+                            Ok(count) => app.show_toast(format!("Exported {} due dates to {}", count, path.display())),
+                            Err(e) => app.show_toast(e),
                         }
-                    });
+                    }
                 });
             });
-            ui.add_space(5.0);
-        }
-    });
-}
+            ui.separator();
 
-fn show_bills_tab(app: &mut BillManagerApp, ui: &mut egui::Ui) {
-    ui.heading("Bills");
-    ui.separator();
+            let upcoming = app.upcoming_due_bills(app.dashboard_horizon_days);
+            if upcoming.is_empty() {
+                ui.weak("Nothing due in this period");
+            }
+            for bill in upcoming {
+                let days = bill.days_until_due();
+                let when = match days {
+                    0 => "due today".to_string(),
+                    1 => "due tomorrow".to_string(),
+                    d => format!("due in {} days", d),
+                };
+                if dashboard_bill_row(ui, app, bill, &when).clicked() {
+                    bill_to_open = Some(bill.clone());
+                }
+            }
+        });
 
-    if ui.button("➕ Create Bill").clicked() {
-        let mut new_bill = Bill::default();
-        new_bill.iban = app.default_iban.clone();
-        // Generate SCOR reference with next bill ID (temporary, will be updated on save)
-        let year = chrono::Local::now().year();
-        new_bill.reference = Bill::generate_scor_reference(app.next_bill_id, 0, year);
-        app.editing_bill = Some(new_bill);
-        app.show_bill_form = true;
-    }
+        ui.add_space(10.0);
 
-    ui.add_space(10.0);
+        ui.group(|ui| {
+            ui.strong("Follow up");
+            ui.separator();
 
-    let mut bill_to_delete: Option<u64> = None;
-    let mut bill_to_edit: Option<Bill> = None;
-    let mut bill_to_generate_pdf: Option<u64> = None;
-    let mut bill_to_save_pdf: Option<u64> = None;
-    let mut status_changes: Vec<(u64, BillStatus)> = Vec::new();
+            let follow_ups = app.follow_up_bills();
+            if follow_ups.is_empty() {
+                ui.weak("No follow-ups due");
+            }
+            let today = crate::timezone::now().date_naive();
+            let mut clear_follow_up: Option<u64> = None;
+            for bill in follow_ups {
+                let Some(follow_up) = bill.follow_up else {
+                    continue;
+                };
+                let when = match (today - follow_up.date_naive()).num_days() {
+                    0 => "follow up today".to_string(),
+                    days => format!("follow up {} days ago", days),
+                };
+                ui.horizontal(|ui| {
+                    if ui.small_button("✔").on_hover_text("Done, clear the follow-up date").clicked() {
+                        clear_follow_up = Some(bill.id);
+                    }
+                    if dashboard_bill_row(ui, app, bill, &when).clicked() {
+                        bill_to_open = Some(bill.clone());
+                    }
+                });
+            }
+            if let Some(bill_id) = clear_follow_up {
+                app.set_follow_up(bill_id, None);
+            }
+        });
 
-    // Fetch bills from database
-    let bills = app.get_bills().unwrap_or_default();
+        ui.add_space(10.0);
 
-    egui::ScrollArea::vertical().show(ui, |ui| {
-        for bill in bills.iter() {
-            let client_name = app.get_client(bill.client_id)
-                .map(|c| c.name.clone())
-                .unwrap_or_else(|| "Unknown Client".to_string());
+        ui.group(|ui| {
+            ui.strong("Overdue");
+            ui.separator();
 
-            ui.group(|ui| {
+            let overdue: Vec<Bill> = app.overdue_bills().into_iter().cloned().collect();
+            if overdue.is_empty() {
+                ui.weak("No overdue bills");
+            } else {
                 ui.horizontal(|ui| {
-                    ui.vertical(|ui| {
-                        ui.horizontal(|ui| {
-                            ui.strong(format!("Bill #{}", bill.id));
-                            ui.label("-");
-                            ui.label(&client_name);
-                        });
-                        ui.label(format!("Date: {}", bill.date.format("%Y-%m-%d")));
-                        ui.label(format!("Due: {}", bill.due_date.format("%Y-%m-%d")));
-                        ui.label(format!("Total: CHF {:.2}", bill.total()));
-
-                        // Status dropdown
-                        ui.horizontal(|ui| {
-                            ui.label("Status:");
-                            let mut current_status = bill.status;
-                            egui::ComboBox::from_id_salt(format!("status_{}", bill.id))
-                                .selected_text(format!("{}", current_status))
-                                .show_ui(ui, |ui| {
-                                    if ui.selectable_value(&mut current_status, BillStatus::Draft, "Draft").clicked() {
-                                        status_changes.push((bill.id, BillStatus::Draft));
-                                    }
-                                    if ui.selectable_value(&mut current_status, BillStatus::Sent, "Sent").clicked() {
-                                        status_changes.push((bill.id, BillStatus::Sent));
-                                    }
-                                    if ui.selectable_value(&mut current_status, BillStatus::Paid, "Paid").clicked() {
-                                        status_changes.push((bill.id, BillStatus::Paid));
-                                    }
-                                    if ui.selectable_value(&mut current_status, BillStatus::Overdue, "Overdue").clicked() {
-                                        status_changes.push((bill.id, BillStatus::Overdue));
-                                    }
-                                });
-                        });
+                    let all_selected = overdue.iter().all(|b| app.selected_overdue.contains(&b.id));
+                    let mut select_all = all_selected;
+                    if ui.checkbox(&mut select_all, "Select all").changed() {
+                        if select_all {
+                            app.selected_overdue.extend(overdue.iter().map(|b| b.id));
+                        } else {
+                            app.selected_overdue.clear();
+                        }
+                    }
 
-                        // PDF creation date
-                        if let Some(pdf_created_at) = &bill.pdf_created_at {
-                            ui.label(format!("PDF: {}", pdf_created_at.format("%Y-%m-%d %H:%M")));
+                    let selected: Vec<u64> = overdue
+                        .iter()
+                        .map(|b| b.id)
+                        .filter(|id| app.selected_overdue.contains(id))
+                        .collect();
+                    ui.add_enabled_ui(!selected.is_empty(), |ui| {
+                        if ui.button(format!("📨 Record reminder ({})", selected.len())).clicked() {
+                            record_reminder = Some(selected);
                         }
                     });
+                    ui.checkbox(&mut app.reminder_pdfs, "Also create reminder PDFs (ZIP)");
+
+                    let idle = app.email_job.is_none() && app.reminder_batch.as_ref().is_none_or(|b| b.finished);
+                    if ui.add_enabled(idle, egui::Button::new("✉ Email all reminders"))
+                        .on_hover_text("Email the next reminder to every overdue client with an email address")
+                        .clicked()
+                    {
+                        email_reminders = true;
+                    }
+                });
+            }
 
-                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                        if ui.button("🗑 Delete").clicked() {
-                            bill_to_delete = Some(bill.id);
-                        }
-                        if ui.button("✏ Edit").clicked() {
-                            bill_to_edit = Some(bill.clone());
+            let mut dismiss_batch = false;
+            if let Some(batch) = &app.reminder_batch {
+                ui.horizontal(|ui| {
+                    if batch.finished {
+                        ui.label(batch.report());
+                        if ui.small_button("✖").clicked() {
+                            dismiss_batch = true;
                         }
-
-                        // PDF buttons
-                        let pdf_exists = bill.pdf_data.is_some();
-
-                        if pdf_exists {
-                            // Save PDF button (green)
-                            let save_button = egui::Button::new("💾 Save PDF")
-                                .fill(egui::Color32::from_rgb(60, 150, 60));
-                            if ui.add(save_button).clicked() {
-                                bill_to_save_pdf = Some(bill.id);
-                            }
-
-                            // Regenerate PDF button (red)
-                            let regen_button = egui::Button::new("🔄 Regenerate")
-                                .fill(egui::Color32::from_rgb(180, 60, 60));
-                            if ui.add(regen_button).clicked() {
-                                bill_to_generate_pdf = Some(bill.id);
-                            }
+                    } else {
+                        ui.spinner();
+                        ui.label(format!(
+                            "Sending reminders {}/{}…",
+                            batch.sent.len() + batch.failed.len(),
+                            batch.total
+                        ));
+                    }
+                });
+            }
+            if dismiss_batch {
+                app.reminder_batch = None;
+            }
+            for bill in &overdue {
+                let mut when = format!("{} days overdue", -bill.days_until_due());
+                if let Some(reminded) = bill.last_reminder_at {
+                    when.push_str(&format!(", reminder {} sent {}", bill.reminder_level, reminded.format("%Y-%m-%d")));
+                }
+                ui.horizontal(|ui| {
+                    let mut selected = app.selected_overdue.contains(&bill.id);
+                    if ui.checkbox(&mut selected, "").changed() {
+                        if selected {
+                            app.selected_overdue.insert(bill.id);
                         } else {
-                            // Generate PDF button (default)
-                            if ui.button("📄 Generate PDF").clicked() {
-                                bill_to_generate_pdf = Some(bill.id);
-                            }
+                            app.selected_overdue.remove(&bill.id);
                         }
-                    });
+                    }
+                    if dashboard_bill_row(ui, app, bill, &when).clicked() {
+                        bill_to_open = Some(bill.clone());
+                    }
                 });
-            });
-            ui.add_space(5.0);
-        }
+            }
+        });
     });
 
-    if let Some(id) = bill_to_delete {
-        app.delete_bill(id);
+    if let Some(bill) = bill_to_open {
+        app.open_bill_form(bill);
     }
-    if let Some(bill) = bill_to_edit {
-        app.editing_bill = Some(bill);
-        app.show_bill_form = true;
+
+    if email_reminders && let Err(e) = app.start_reminder_batch() {
+        app.pdf_error = Some(format!("Reminders could not be emailed:\n{}", e));
     }
-    if let Some(bill_id) = bill_to_generate_pdf {
-        match app.generate_pdf(bill_id) {
-            Ok(_) => {
-                println!("PDF generated successfully");
-            }
-            Err(e) => {
-                app.bill_error = Some(format!("Failed to generate PDF: {}", e));
-                println!("Failed to generate PDF: {}", e)
+
+    if let Some(ids) = record_reminder {
+        let zip_path = if app.reminder_pdfs {
+            match rfd::FileDialog::new()
+                .add_filter("ZIP", &["zip"])
+                .set_file_name(format!("reminders_{}.zip", crate::timezone::now().format("%Y-%m-%d")))
+                .save_file()
+            {
+                Some(path) => Some(path),
+                // Cancelled: record nothing
+                None => return,
             }
+        } else {
+            None
+        };
+        match app.record_reminders(&ids, zip_path.as_deref()) {
+            Ok(report) => app.show_toast(report),
+            Err(e) => app.pdf_error = Some(e),
         }
     }
-    if let Some(bill_id) = bill_to_save_pdf {
-        match app.save_pdf_to_file(bill_id) {
-            Ok(Some(path)) => {
-                println!("PDF saved successfully to: {}", path.display());
-                // Open the PDF with xdg-open
-                std::process::Command::new("xdg-open")
-                    .arg(&path)
-                    .spawn()
-                    .ok();
-            }
-            Ok(None) => {
-                println!("PDF save cancelled");
+}
+
+/// One clickable line in a dashboard list
+fn dashboard_bill_row(ui: &mut egui::Ui, app: &BillManagerApp, bill: &Bill, when: &str) -> egui::Response {
+    let client_name = app.get_client(bill.client_id)
+        .map(|c| c.name.as_str())
+        .unwrap_or("Unknown Client");
+    ui.selectable_label(
+        false,
+        format!(
+            "Bill #{} – {} – {} {:.2} – {} ({})",
+            bill.id,
+            client_name,
+            bill.currency,
+            bill.total(),
+            bill.due_date.format("%Y-%m-%d"),
+            when
+        ),
+    )
+}
+
+fn show_bill_filter_bar(app: &mut BillManagerApp, ui: &mut egui::Ui) {
+    let filter = &mut app.bill_filter;
+
+    ui.horizontal(|ui| {
+        ui.label("🔍");
+        ui.add(egui::TextEdit::singleline(&mut filter.text).hint_text("Client, reference, notes…").desired_width(180.0));
+
+        egui::ComboBox::from_id_salt("bill_filter_status")
+            .selected_text(filter.status.map(|s| s.to_string()).unwrap_or_else(|| "All statuses".to_string()))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut filter.status, None, "All statuses");
+                for status in BillStatus::ALL {
+                    ui.selectable_value(&mut filter.status, Some(status), status.to_string());
+                }
+            });
+
+        ui.label("Total:");
+        amount_bound_input(ui, &mut filter.min_total, "min");
+        ui.label("–");
+        amount_bound_input(ui, &mut filter.max_total, "max");
+
+        if filter.is_active() && ui.button("✖ Clear filters").clicked() {
+            *filter = BillFilter::default();
+        }
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Date:");
+        date_bound_input(ui, &mut filter.date_from, "bill_filter_from", "from");
+        ui.label("–");
+        date_bound_input(ui, &mut filter.date_to, "bill_filter_to", "to");
+
+        let today = crate::timezone::now().date_naive();
+        if ui.small_button("This month").clicked() {
+            filter.set_this_month(today);
+        }
+        if ui.small_button("This quarter").clicked() {
+            filter.set_this_quarter(today);
+        }
+        if ui.small_button("This year").clicked() {
+            filter.set_this_year(today);
+        }
+    });
+}
+
+/// Date picker for an optional date bound; unset bounds show a button to enable them
+fn date_bound_input(ui: &mut egui::Ui, value: &mut Option<chrono::NaiveDate>, id: &str, label: &str) {
+    match value {
+        Some(date) => {
+            ui.add(egui_extras::DatePickerButton::new(date).id_salt(id));
+            if ui.small_button("✖").on_hover_text(format!("Clear \"{}\" date", label)).clicked() {
+                *value = None;
             }
-            Err(e) => {
-                app.bill_error = Some(format!("Failed to save PDF: {}", e));
+        }
+        None => {
+            if ui.button(format!("{}…", label)).clicked() {
+                *value = Some(crate::timezone::now().date_naive());
             }
         }
     }
+}
 
-    // Apply status changes
-    for (bill_id, new_status) in status_changes {
-        app.update_bill_status(bill_id, new_status);
+/// Text field for an optional amount bound, highlighted when it can't be parsed
+fn amount_bound_input(ui: &mut egui::Ui, value: &mut String, hint: &str) {
+    let invalid = !value.trim().is_empty() && parse_amount_bound(value).is_none();
+    let mut edit = egui::TextEdit::singleline(value).hint_text(hint).desired_width(70.0);
+    if invalid {
+        edit = edit.text_color(egui::Color32::RED);
     }
+    ui.add(edit);
 }
 
-fn show_settings_tab(app: &mut BillManagerApp, ui: &mut egui::Ui) {
-    ui.heading("Settings");
+fn show_clients_tab(app: &mut BillManagerApp, ui: &mut egui::Ui) {
+    ui.heading("Clients");
     ui.separator();
 
-    ui.add_space(10.0);
-
-    let mut settings_changed = false;
-
-    ui.group(|ui| {
-        ui.strong("Your Business Information");
-        ui.separator();
-
-        ui.horizontal(|ui| {
-            ui.label("Company Name:");
-            if ui.text_edit_singleline(&mut app.creditor_address.name).changed() {
-                settings_changed = true;
-            }
-        });
+    ui.horizontal(|ui| {
+        if ui.button("➕ Add Client").clicked() {
+            app.open_client_form(Client::default());
+        }
 
-        ui.horizontal(|ui| {
-            ui.label("Street:");
-            let mut street = app.creditor_address.street.clone().unwrap_or_default();
-            let response = ui.text_edit_singleline(&mut street);
-            app.creditor_address.street = Some(street);
-            if response.changed() {
-                settings_changed = true;
+        if ui.button("📥 Import clients…").clicked()
+            && let Some(path) = rfd::FileDialog::new()
+                .add_filter("Client", &["json"])
+                .pick_file()
+        {
+            // Clients matching existing ones are asked about one by one
+            match crate::import::read_clients_json(&path) {
+                Ok(clients) => {
+                    let report = app.import_clients(clients);
+                    app.show_toast(report);
+                }
+                Err(e) => app.show_toast(format!("Import failed:\n{}", e)),
             }
-        });
+        }
 
-        ui.horizontal(|ui| {
-            ui.label("Building Number:");
-            let mut building = app.creditor_address.building_number.clone().unwrap_or_default();
-            let response = ui.text_edit_singleline(&mut building);
-            app.creditor_address.building_number = Some(building);
-            if response.changed() {
-                settings_changed = true;
-            }
-        });
+        ui.separator();
+        ui.label("🔍");
+        ui.add(egui::TextEdit::singleline(&mut app.client_filter).hint_text("Name, city, email…").desired_width(180.0));
+    });
 
-        ui.horizontal(|ui| {
-            ui.label("Postal Code:");
-            if ui.text_edit_singleline(&mut app.creditor_address.postal_code).changed() {
-                settings_changed = true;
-            }
-        });
+    let visible: Vec<Client> = app.filtered_clients().into_iter().cloned().collect();
 
-        ui.horizontal(|ui| {
-            ui.label("City:");
-            if ui.text_edit_singleline(&mut app.creditor_address.city).changed() {
-                settings_changed = true;
+    ui.horizontal(|ui| {
+        let all_selected = !visible.is_empty() && visible.iter().all(|c| app.selected_clients.contains(&c.id));
+        let mut select_all = all_selected;
+        if ui.checkbox(&mut select_all, "Select all").changed() {
+            if select_all {
+                app.selected_clients.extend(visible.iter().map(|c| c.id));
+            } else {
+                app.selected_clients.clear();
             }
-        });
+        }
 
-        ui.horizontal(|ui| {
-            ui.label("Country:");
-            if ui.text_edit_singleline(&mut app.creditor_address.country).changed() {
-                settings_changed = true;
-            }
-        });
+        if !app.selected_clients.is_empty() {
+            ui.label(format!("{} selected", app.selected_clients.len()));
 
-        ui.horizontal(|ui| {
-            ui.label("Default IBAN:");
-            if ui.text_edit_singleline(&mut app.default_iban).changed() {
-                settings_changed = true;
+            if ui.button("🗑 Delete selected").clicked() {
+                app.pending_client_delete = Some(app.selected_clients.iter().copied().collect());
             }
 
-            // Show validation status
-            if !app.default_iban.is_empty() {
-                if validate_iban(&app.default_iban) {
-                    ui.colored_label(egui::Color32::from_rgb(60, 150, 60), "✓ Valid");
-                } else {
-                    ui.colored_label(egui::Color32::from_rgb(180, 60, 60), "✗ Invalid IBAN");
+            if ui.button("📤 Export selected…").clicked()
+                && let Some(path) = rfd::FileDialog::new()
+                    .add_filter("Client", &["json"])
+                    .set_file_name("clients.json")
+                    .save_file()
+            {
+                let clients: Vec<Client> = app.clients
+                    .iter()
+                    .filter(|c| app.selected_clients.contains(&c.id))
+                    .cloned()
+                    .collect();
+                match crate::export::write_clients_json(&path, &clients) {
+                    Ok(()) => app.show_toast(format!("Exported {} clients to {}", clients.len(), path.display())),
+                    Err(e) => app.show_toast(e),
                 }
             }
-        });
+        }
     });
 
-    if settings_changed {
-        app.save_settings();
-    }
-}
-
-fn show_client_form_window(app: &mut BillManagerApp, ctx: &egui::Context) {
-    let mut open = true;
-    egui::Window::new("Client Details")
-        .open(&mut open)
-        .resizable(true)
-        .show(ctx, |ui| {
-            if let Some(client) = &mut app.editing_client {
-                ui.horizontal(|ui| {
-                    ui.label("Name:");
-                    ui.text_edit_singleline(&mut client.name);
-                });
+    ui.add_space(10.0);
 
+    egui::ScrollArea::vertical().show(ui, |ui| {
+        for client in &visible {
+            ui.group(|ui| {
                 ui.horizontal(|ui| {
-                    ui.label("Email:");
-                    ui.text_edit_singleline(&mut client.email);
-                });
+                    let mut selected = app.selected_clients.contains(&client.id);
+                    if ui.checkbox(&mut selected, "").changed() {
+                        if selected {
+                            app.selected_clients.insert(client.id);
+                        } else {
+                            app.selected_clients.remove(&client.id);
+                        }
+                    }
 
-                ui.horizontal(|ui| {
+                    ui.vertical(|ui| {
+                        ui.strong(&client.name);
+                        ui.label(format!("{}, {}", client.address.city, client.address.country));
+                        ui.label(&client.email);
+                        ui.label(&client.phone);
+                    });
+
+                    let stats = app.client_stats(client.id);
+                    ui.vertical(|ui| {
+                        match stats.client_since {
+                            Some(since) => ui.weak(format!("Client since {}", since.format("%d.%m.%Y"))),
+                            None => ui.weak("No bills yet"),
+                        };
+                        if stats.bill_count > 0 {
+                            ui.weak(match stats.bill_count {
+                                1 => "1 bill".to_string(),
+                                n => format!("{} bills", n),
+                            });
+                            for (currency, total) in &stats.billed {
+                                ui.weak(format!("{} {:.2} billed", currency, total));
+                            }
+                            for (currency, balance) in &stats.outstanding {
+                                ui.weak(format!("{} {:.2} outstanding", currency, balance));
+                            }
+                        }
+                    });
+
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        let has_bills = app.client_has_bills(client.id);
+                        if ui.add_enabled(!has_bills, egui::Button::new("🗑 Delete"))
+                            .on_disabled_hover_text("Clients with bills can't be deleted")
+                            .clicked()
+                            && let Err(e) = app.delete_client(client.id)
+                        {
+                            app.show_toast(e);
+                        }
+                        if ui.button("✏ Edit").clicked() {
+                            app.open_client_form(client.clone());
+                        }
+                        if ui.button("📤 Export").on_hover_text("Save this client as a file to share").clicked()
+                            && let Some(path) = rfd::FileDialog::new()
+                                .add_filter("Client", &["json"])
+                                .set_file_name(format!("{}.json", client.name.replace(['/', '\\'], "_")))
+                                .save_file()
+                            && let Err(e) = crate::export::write_client_json(&path, client)
+                        {
+                            app.show_toast(e);
+                        }
+                    });
+                });
+            });
+            ui.add_space(5.0);
+        }
+    });
+}
+
+fn show_bills_tab(app: &mut BillManagerApp, ui: &mut egui::Ui) {
+    ui.heading("Bills");
+    ui.separator();
+
+    ui.horizontal(|ui| {
+        if ui.button("➕ Create Bill").clicked() {
+            let new_bill = app.new_bill();
+            app.open_bill_form(new_bill);
+        }
+
+        let mut from_template: Option<BillTemplate> = None;
+        ui.add_enabled_ui(!app.bill_templates.is_empty(), |ui| {
+            ui.menu_button("📋 New from template", |ui| {
+                for template in &app.bill_templates {
+                    if ui.button(&template.name).clicked() {
+                        from_template = Some(template.clone());
+                    }
+                }
+            })
+            .response
+            .on_disabled_hover_text("Save a bill as template from its form first");
+        });
+        if let Some(template) = from_template {
+            let new_bill = app.bill_from_template(&template);
+            app.open_bill_form(new_bill);
+        }
+
+        if ui.button("🏦 Import bank statement…")
+            .on_hover_text("Match the credits of a camt.053 file to bills by their QR/SCOR reference")
+            .clicked()
+            && let Some(path) = rfd::FileDialog::new()
+                .add_filter("camt.053", &["xml"])
+                .pick_file()
+        {
+            match app.import_camt053(&path) {
+                Ok(report) => app.reconcile_report = Some(report),
+                Err(e) => app.bill_error = Some(format!("Bank statement import failed: {}", e)),
+            }
+        }
+    });
+
+    // Errors from list actions (e.g. PDF generation) are shown here as well
+    if let Some(error) = app.bill_error.clone() {
+        ui.horizontal(|ui| {
+            ui.colored_label(egui::Color32::RED, error);
+            if ui.small_button("✖").clicked() {
+                app.bill_error = None;
+            }
+        });
+    }
+
+    ui.add_space(10.0);
+
+    let mut actions = BillRowActions::default();
+    let mut status_changes: Vec<(u64, BillStatus)> = Vec::new();
+    let mut bulk_status: Option<BillStatus> = None;
+
+    show_bill_filter_bar(app, ui);
+
+    // Fetch bills from database, narrowed by the filter
+    let bills = app.filtered_bills().unwrap_or_default();
+
+    let summary = app.outstanding_summary(&bills);
+    egui::TopBottomPanel::bottom("bills_outstanding").show_inside(ui, |ui| {
+        let amounts = |totals: &[(String, f64)]| {
+            totals.iter()
+                .map(|(currency, total)| format!("{} {:.2}", currency, total))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        ui.horizontal(|ui| {
+            if summary.count == 0 {
+                ui.weak("Nothing outstanding in this view");
+                return;
+            }
+            ui.strong(format!("{} outstanding: {}", summary.count, amounts(&summary.outstanding)));
+            if summary.overdue_count > 0 {
+                ui.separator();
+                ui.colored_label(
+                    egui::Color32::from_rgb(180, 60, 60),
+                    format!("{} overdue: {}", summary.overdue_count, amounts(&summary.overdue)),
+                );
+            }
+        });
+    });
+
+    ui.horizontal(|ui| {
+        ui.label(format!("{} bills shown", bills.len()));
+        if app.pdf_jobs_running > 0 {
+            ui.spinner();
+            ui.label("Generating PDFs…");
+        }
+        if ui.button("📤 Export current view (CSV)…").clicked()
+            && let Some(path) = rfd::FileDialog::new()
+                .add_filter("CSV", &["csv"])
+                .set_file_name("bills.csv")
+                .save_file()
+        {
+            match app.export_bills_csv(&bills, &path) {
+                Ok(count) => app.show_toast(format!("Exported {} bills to {}", count, path.display())),
+                Err(e) => app.bill_error = Some(e),
+            }
+        }
+
+        ui.separator();
+        if ui.checkbox(&mut app.bill_list_compact, "Compact")
+            .on_hover_text("One short line per bill with the actions in a menu")
+            .changed()
+        {
+            app.mark_settings_dirty();
+        }
+        if app.bill_list_compact {
+            if ui.button("⏷ Expand all").clicked() {
+                app.expanded_bills.extend(bills.iter().map(|b| b.id));
+            }
+            if ui.button("⏶ Collapse all").clicked() {
+                app.expanded_bills.clear();
+            }
+        }
+    });
+
+    // Bulk actions on selected bills
+    ui.horizontal(|ui| {
+        if ui.button("☑ Select all visible").clicked() {
+            app.selected_bills.extend(bills.iter().map(|b| b.id));
+        }
+
+        if !app.selected_bills.is_empty() {
+            if ui.button("☐ Clear selection").clicked() {
+                app.selected_bills.clear();
+            }
+
+            ui.label(format!("{} selected", app.selected_bills.len()));
+
+            egui::ComboBox::from_id_salt("bulk_status")
+                .selected_text("Set status →")
+                .show_ui(ui, |ui| {
+                    for status in BillStatus::ALL {
+                        if ui.selectable_label(false, format!("{}", status)).clicked() {
+                            bulk_status = Some(status);
+                        }
+                    }
+                });
+        }
+    });
+
+    ui.add_space(5.0);
+
+    let mut sort_by: Option<BillSortColumn> = None;
+    let sort_header = |ui: &mut egui::Ui, label: &str, column: BillSortColumn, sort_by: &mut Option<BillSortColumn>| {
+        let arrow = match (app.bill_sort == column, app.bill_sort_ascending) {
+            (true, true) => " ⏶",
+            (true, false) => " ⏷",
+            (false, _) => "",
+        };
+        if ui.add(egui::Button::new(egui::RichText::new(format!("{}{}", label, arrow)).strong()).frame(false)).clicked() {
+            *sort_by = Some(column);
+        }
+    };
+
+    // The compact list leaves out the bill date and folds the actions into a menu
+    let compact = app.bill_list_compact;
+    let mut table = egui_extras::TableBuilder::new(ui)
+        .striped(true)
+        .resizable(true)
+        .cell_layout(egui::Layout::left_to_right(egui::Align::Center))
+        .column(egui_extras::Column::exact(24.0))
+        .column(egui_extras::Column::initial(if compact { 70.0 } else { 50.0 }).at_least(40.0))
+        .column(egui_extras::Column::initial(160.0).at_least(80.0).clip(true));
+    if !compact {
+        table = table.column(egui_extras::Column::initial(90.0).at_least(70.0));
+    }
+    table
+        .column(egui_extras::Column::initial(90.0).at_least(70.0))
+        .column(egui_extras::Column::initial(110.0).at_least(70.0))
+        .column(egui_extras::Column::initial(100.0).at_least(80.0))
+        .column(egui_extras::Column::remainder().at_least(if compact { 40.0 } else { 280.0 }))
+        .header(24.0, |mut header| {
+            header.col(|_ui| {});
+            header.col(|ui| sort_header(ui, "ID", BillSortColumn::Id, &mut sort_by));
+            header.col(|ui| sort_header(ui, "Client", BillSortColumn::Client, &mut sort_by));
+            if !compact {
+                header.col(|ui| sort_header(ui, "Date", BillSortColumn::Date, &mut sort_by));
+            }
+            header.col(|ui| sort_header(ui, "Due", BillSortColumn::Due, &mut sort_by));
+            header.col(|ui| sort_header(ui, "Total", BillSortColumn::Total, &mut sort_by));
+            header.col(|ui| sort_header(ui, "Status", BillSortColumn::Status, &mut sort_by));
+            header.col(|ui| {
+                ui.strong("Actions");
+            });
+        })
+        .body(|body| {
+            let heights = bills
+                .iter()
+                .map(|b| if compact && !app.expanded_bills.contains(&b.id) { 22.0 } else { 28.0 })
+                .collect::<Vec<f32>>();
+            body.heterogeneous_rows(heights.into_iter(), |mut row| {
+                let bill = &bills[row.index()];
+                let client_name = app.get_client(bill.client_id)
+                    .map(|c| c.name.clone())
+                    .unwrap_or_else(|| "Unknown Client".to_string());
+                let expanded = !compact || app.expanded_bills.contains(&bill.id);
+
+                row.col(|ui| {
+                    let mut selected = app.selected_bills.contains(&bill.id);
+                    if ui.checkbox(&mut selected, "").changed() {
+                        if selected {
+                            app.selected_bills.insert(bill.id);
+                        } else {
+                            app.selected_bills.remove(&bill.id);
+                        }
+                    }
+                });
+                row.col(|ui| {
+                    if compact {
+                        let arrow = if expanded { "⏷" } else { "⏵" };
+                        if ui.add(egui::Button::new(arrow).frame(false)).on_hover_text("Show all actions").clicked() {
+                            if expanded {
+                                app.expanded_bills.remove(&bill.id);
+                            } else {
+                                app.expanded_bills.insert(bill.id);
+                            }
+                        }
+                    }
+                    ui.strong(format!("#{}", bill.id));
+                    if bill.is_credit_note() {
+                        let credited = bill.credited_bill_id.map(|id| format!(" for invoice #{}", id)).unwrap_or_default();
+                        ui.label("↩").on_hover_text(format!("Credit note{}", credited));
+                    }
+                    if let Some(archived_at) = bill.archived_at {
+                        ui.label("🔒").on_hover_text(format!("Archived {}", archived_at.format("%Y-%m-%d")));
+                    }
+                    if !bill.load_problems.is_empty() {
+                        ui.colored_label(egui::Color32::RED, "⚠")
+                            .on_hover_text(format!("Needs repair:\n{}", bill.load_problems.join("\n")));
+                    }
+                });
+                row.col(|ui| {
+                    ui.label(&client_name);
+                });
+                if !compact {
+                    row.col(|ui| {
+                        ui.label(bill.date.format("%Y-%m-%d").to_string());
+                    });
+                }
+                row.col(|ui| {
+                    ui.label(bill.due_date.format("%Y-%m-%d").to_string());
+                });
+                row.col(|ui| {
+                    match bill.amount_mode {
+                        AmountMode::Fixed => ui.label(format!("{} {:.2}", bill.currency, bill.signed_total())),
+                        AmountMode::Open => ui.label("open"),
+                    };
+                    let credit_notes = bill.linked_documents(&app.bills);
+                    if !bill.is_credit_note() && !credit_notes.is_empty() {
+                        let ids: Vec<String> = credit_notes.iter().map(|b| format!("#{}", b.id)).collect();
+                        ui.weak(format!("net {:.2}", app.net_total(bill)))
+                            .on_hover_text(format!("Less credit notes {}", ids.join(", ")));
+                    }
+                    let paid = app.paid_amount(bill.id);
+                    if paid > 0.0 && bill.status != BillStatus::Paid {
+                        ui.label("◐").on_hover_text(format!(
+                            "{} {:.2} paid, {:.2} outstanding",
+                            bill.currency,
+                            paid,
+                            app.outstanding_amount(bill)
+                        ));
+                    }
+                });
+                row.col(|ui| {
+                    let mut current_status = bill.status;
+                    egui::ComboBox::from_id_salt(format!("status_{}", bill.id))
+                        .selected_text(format!("{}", current_status))
+                        .show_ui(ui, |ui| {
+                            for status in BillStatus::ALL {
+                                if ui.selectable_value(&mut current_status, status, status.to_string()).clicked() {
+                                    status_changes.push((bill.id, status));
+                                }
+                            }
+                        });
+                });
+                row.col(|ui| {
+                    if expanded {
+                        bill_row_actions(ui, app, bill, &mut actions);
+                    } else {
+                        ui.menu_button("⋯", |ui| bill_row_actions(ui, app, bill, &mut actions));
+                    }
+                });
+            });
+        });
+
+    if let Some(column) = sort_by {
+        app.sort_bills_by(column);
+    }
+
+    if let Some(id) = actions.delete {
+        app.delete_bill(id);
+        app.selected_bills.remove(&id);
+    }
+    if let Some(bill) = actions.edit {
+        app.open_bill_form(bill);
+    }
+    if let Some(bill_id) = actions.generate_pdf {
+        match app.generate_pdf(bill_id) {
+            Ok(_) => {
+                println!("PDF generated successfully");
+            }
+            Err(e) => {
+                app.pdf_error = Some(format!("Failed to generate PDF:\n{}", e));
+                println!("Failed to generate PDF: {}", e)
+            }
+        }
+    }
+    if let Some(bill_id) = actions.print {
+        match app.print_bill(bill_id) {
+            Ok(()) => app.show_toast(format!("Bill #{} sent to the printer", bill_id)),
+            Err(e) => app.pdf_error = Some(format!("Bill #{} could not be printed:\n{}", bill_id, e)),
+        }
+    }
+    if let Some(bill_id) = actions.email
+        && let Err(e) = app.email_bill(bill_id)
+    {
+        app.pdf_error = Some(format!("Bill #{} could not be emailed:\n{}", bill_id, e));
+    }
+    if let Some(bill_id) = actions.record_payment {
+        app.open_payment_dialog(bill_id);
+    }
+    if let Some(invoice) = actions.credit_note {
+        let credit_note = app.credit_note_for(&invoice);
+        app.open_bill_form(credit_note);
+    }
+    if let Some(bill_id) = actions.regenerate_pdf
+        && let Err(e) = app.regenerate_pdf(bill_id)
+    {
+        app.pdf_error = Some(format!("Failed to regenerate PDF, previous PDF kept:\n{}", e));
+    }
+    if let Some(bill_id) = actions.save_pdf {
+        match app.pick_pdf_save_path(bill_id) {
+            Ok(Some(path)) if path.exists() => {
+                app.pending_pdf_overwrite = Some((bill_id, path));
+            }
+            Ok(Some(path)) => write_pdf_and_open(app, bill_id, &path),
+            Ok(None) => {
+                println!("PDF save cancelled");
+            }
+            Err(e) => {
+                app.bill_error = Some(format!("Failed to save PDF: {}", e));
+            }
+        }
+    }
+
+    // Apply status changes
+    for (bill_id, new_status) in status_changes {
+        app.update_bill_status(bill_id, new_status);
+    }
+
+    if let Some(new_status) = bulk_status {
+        let ids: Vec<u64> = app.selected_bills.iter().copied().collect();
+        match app.update_bill_statuses(&ids, new_status) {
+            Ok(()) => app.selected_bills.clear(),
+            Err(e) => app.bill_error = Some(e),
+        }
+    }
+}
+
+/// Buttons clicked in a bill row, applied after the list is drawn
+#[derive(Default)]
+struct BillRowActions {
+    delete: Option<u64>,
+    edit: Option<Bill>,
+    generate_pdf: Option<u64>,
+    regenerate_pdf: Option<u64>,
+    email: Option<u64>,
+    print: Option<u64>,
+    record_payment: Option<u64>,
+    save_pdf: Option<u64>,
+    credit_note: Option<Bill>,
+}
+
+fn bill_row_actions(ui: &mut egui::Ui, app: &BillManagerApp, bill: &Bill, actions: &mut BillRowActions) {
+    if ui.button("✏ Edit").clicked() {
+        actions.edit = Some(bill.clone());
+    }
+
+    // PDF buttons
+    let pdf_exists = bill.has_pdf;
+
+    if pdf_exists {
+        // Save PDF button (green)
+        let save_button = egui::Button::new("💾 Save PDF")
+            .fill(egui::Color32::from_rgb(60, 150, 60));
+        let response = ui.add(save_button);
+        let response = match &bill.pdf_created_at {
+            Some(created_at) => response.on_hover_text(format!("PDF: {}", created_at.format("%Y-%m-%d %H:%M"))),
+            None => response,
+        };
+        if response.clicked() {
+            actions.save_pdf = Some(bill.id);
+        }
+
+        // Regenerate PDF button (red when the bill changed since generation)
+        let regen_button = if app.is_pdf_stale(bill) {
+            egui::Button::new("⚠ Regenerate")
+                .fill(egui::Color32::from_rgb(180, 60, 60))
+        } else {
+            egui::Button::new("🔄 Regenerate")
+        };
+        if ui.add(regen_button).clicked() {
+            actions.regenerate_pdf = Some(bill.id);
+        }
+    } else {
+        // Generate PDF button (default); drafts have to be finalized first
+        let is_draft = bill.status == BillStatus::Draft;
+        if ui.add_enabled(!is_draft, egui::Button::new("📄 Generate PDF"))
+            .on_disabled_hover_text("Finalize the draft before generating its PDF")
+            .clicked()
+        {
+            actions.generate_pdf = Some(bill.id);
+        }
+    }
+
+    if ui.button("🖨").on_hover_text("Print").clicked() {
+        actions.print = Some(bill.id);
+    }
+
+    let sending = app.email_job.as_ref().is_some_and(|(id, _)| *id == bill.id);
+    if sending {
+        ui.spinner();
+    } else if ui.add_enabled(app.email_job.is_none(), egui::Button::new("✉"))
+        .on_hover_text("Email the PDF to the client")
+        .clicked()
+    {
+        actions.email = Some(bill.id);
+    }
+
+    if !matches!(bill.status, BillStatus::Paid | BillStatus::WrittenOff)
+        && !bill.is_credit_note()
+        && ui.button("💰").on_hover_text("Record Payment").clicked()
+    {
+        actions.record_payment = Some(bill.id);
+    }
+
+    if !bill.is_credit_note()
+        && bill.status != BillStatus::Draft
+        && ui.button("↩").on_hover_text("Create a credit note for this invoice").clicked()
+    {
+        actions.credit_note = Some(bill.clone());
+    }
+
+    if ui.button("🗑").on_hover_text("Delete").clicked() {
+        actions.delete = Some(bill.id);
+    }
+}
+
+fn show_settings_tab(app: &mut BillManagerApp, ui: &mut egui::Ui) {
+    ui.heading("Settings");
+    ui.separator();
+
+    egui::ScrollArea::vertical().show(ui, |ui| show_settings_content(app, ui));
+}
+
+fn show_settings_content(app: &mut BillManagerApp, ui: &mut egui::Ui) {
+    ui.add_space(10.0);
+
+    let mut settings_changed = false;
+
+    ui.group(|ui| {
+        ui.strong("Your Business Information");
+        ui.separator();
+
+        let placeholders = placeholder_creditor_fields(&app.creditor_address, &app.default_iban);
+        if !placeholders.is_empty() {
+            ui.colored_label(
+                egui::Color32::from_rgb(180, 60, 60),
+                format!("⚠ Still the example {}; PDFs are refused until you enter your own", placeholders.join(", ")),
+            );
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Company Name:");
+            if ui.text_edit_singleline(&mut app.creditor_address.name).changed() {
+                settings_changed = true;
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Street:");
+            let mut street = app.creditor_address.street.clone().unwrap_or_default();
+            let response = ui.text_edit_singleline(&mut street);
+            app.creditor_address.street = Some(street);
+            if response.changed() {
+                settings_changed = true;
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Building Number:");
+            let mut building = app.creditor_address.building_number.clone().unwrap_or_default();
+            let response = ui.text_edit_singleline(&mut building);
+            app.creditor_address.building_number = Some(building);
+            if response.changed() {
+                settings_changed = true;
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Postal Code:");
+            if ui.text_edit_singleline(&mut app.creditor_address.postal_code).changed() {
+                settings_changed = true;
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("City:");
+            if ui.text_edit_singleline(&mut app.creditor_address.city).changed() {
+                settings_changed = true;
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Country:");
+            if ui.text_edit_singleline(&mut app.creditor_address.country).changed() {
+                settings_changed = true;
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Default IBAN:");
+            if ui.text_edit_singleline(&mut app.default_iban).changed() {
+                settings_changed = true;
+            }
+
+            // Show validation status
+            if !app.default_iban.is_empty() {
+                if validate_iban(&app.default_iban) {
+                    ui.colored_label(egui::Color32::from_rgb(60, 150, 60), "✓ Valid");
+                } else {
+                    ui.colored_label(egui::Color32::from_rgb(180, 60, 60), "✗ Invalid IBAN");
+                }
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Default Reference:");
+            egui::ComboBox::from_id_salt("default_reference_type")
+                .selected_text(app.default_reference_type.to_string())
+                .show_ui(ui, |ui| {
+                    for reference_type in ReferenceType::ALL {
+                        if ui.selectable_value(&mut app.default_reference_type, reference_type, reference_type.to_string()).changed() {
+                            settings_changed = true;
+                        }
+                    }
+                });
+            if validate_iban(&app.default_iban)
+                && let Err(e) = check_reference_type(app.default_reference_type, &app.default_iban)
+            {
+                ui.colored_label(egui::Color32::from_rgb(180, 60, 60), format!("✗ {} (default IBAN)", e));
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Default Currency:");
+            let response = ui.add(egui::TextEdit::singleline(&mut app.default_currency).desired_width(50.0));
+            if response.changed() {
+                app.default_currency = app.default_currency.trim().to_uppercase();
+                settings_changed = true;
+            }
+            if !QR_CURRENCIES.contains(&app.default_currency.as_str()) {
+                ui.colored_label(
+                    egui::Color32::from_rgb(180, 60, 60),
+                    format!("✗ QR bill supports {} only", QR_CURRENCIES.join("/")),
+                );
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Default Language:");
+            egui::ComboBox::from_id_salt("default_language")
+                .selected_text(format!("{}", app.default_language))
+                .show_ui(ui, |ui| {
+                    for language in Language::ALL {
+                        if ui.selectable_value(&mut app.default_language, language, format!("{}", language)).changed() {
+                            settings_changed = true;
+                        }
+                    }
+                });
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Time Zone:");
+            let system = crate::timezone::system_zone();
+            let mut selected: Option<String> = None;
+            egui::ComboBox::from_id_salt("timezone")
+                .selected_text(if app.timezone.is_empty() {
+                    format!("System ({})", system.name())
+                } else {
+                    app.timezone.clone()
+                })
+                .height(300.0)
+                .show_ui(ui, |ui| {
+                    if ui.selectable_label(app.timezone.is_empty(), format!("System ({})", system.name())).clicked() {
+                        selected = Some(String::new());
+                    }
+                    for tz in chrono_tz::TZ_VARIANTS {
+                        if ui.selectable_label(app.timezone == tz.name(), tz.name()).clicked() {
+                            selected = Some(tz.name().to_string());
+                        }
+                    }
+                });
+            if let Some(name) = selected {
+                match app.set_timezone(&name) {
+                    Ok(()) => settings_changed = true,
+                    Err(e) => app.settings_message = Some(e),
+                }
+            }
+            ui.weak("Dates are shown and entered in this zone and stored in UTC");
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Default VAT Rate:");
+            let response = ui.add(
+                egui::DragValue::new(&mut app.default_vat_rate)
+                    .speed(0.1)
+                    .range(0.0..=100.0)
+                    .suffix("%"),
+            );
+            if response.changed() {
+                settings_changed = true;
+            }
+            ui.weak("Used for new items; existing items keep their rate");
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Due Date Buttons:");
+            let mut remove: Option<usize> = None;
+            for (idx, days) in app.due_date_presets.iter_mut().enumerate() {
+                if ui.add(egui::DragValue::new(days).range(1..=365).prefix("+").suffix("d")).changed() {
+                    settings_changed = true;
+                }
+                if ui.small_button("✖").on_hover_text("Remove").clicked() {
+                    remove = Some(idx);
+                }
+            }
+            if let Some(idx) = remove {
+                app.due_date_presets.remove(idx);
+                settings_changed = true;
+            }
+            if ui.small_button("➕").clicked() {
+                let next = app.due_date_presets.last().map_or(14, |d| d + 30);
+                app.due_date_presets.push(next);
+                settings_changed = true;
+            }
+            if ui.small_button("Reset").on_hover_text("Back to +7d and +30d").clicked() {
+                app.due_date_presets = DEFAULT_DUE_DATE_PRESETS.to_vec();
+                settings_changed = true;
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("First Invoice Number:");
+            let mut number = app.first_bill_number;
+            if ui.add(egui::DragValue::new(&mut number).range(1..=i32::MAX as u64)).changed() {
+                app.set_first_bill_number(number);
+                settings_changed = true;
+            }
+            ui.weak(format!("The next bill gets #{}", app.next_bill_id));
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Quantity Decimals:");
+            if ui.add(egui::DragValue::new(&mut app.quantity_decimals).range(0..=4)).changed() {
+                settings_changed = true;
+            }
+            ui.weak(format!("Quantities in pieces ({}) are always whole numbers", PIECE_UNITS.join(", ")));
+        });
+
+        ui.separator();
+        if ui.button("↺ Reset to defaults…")
+            .on_hover_text("Creditor, IBAN, currency, language, VAT, reference type and format settings")
+            .clicked()
+        {
+            app.pending_settings_reset = Some(SettingsReset::Settings);
+        }
+    });
+
+    ui.add_space(10.0);
+
+    ui.group(|ui| {
+        ui.strong("QR Bill Additional Information");
+        ui.label("Placeholders: {due_date}, {reference}, {invoice_number}");
+        ui.separator();
+
+        // Sample bill used to check the expanded length against the QR-bill limit
+        let sample_bill = Bill {
+            id: app.next_bill_id,
+            reference: Bill::generate_scor_reference(app.next_bill_id, 1, crate::timezone::now().year()),
+            ..Bill::default()
+        };
+
+        for language in Language::ALL {
+            ui.horizontal(|ui| {
+                ui.label(format!("{}:", language));
+                let mut text = app.additional_info_template(language).to_string();
+                if ui.text_edit_singleline(&mut text).changed() {
+                    app.additional_info_templates.insert(language, text.clone());
+                    settings_changed = true;
+                }
+
+                let length = render_additional_info(&text, &sample_bill).chars().count();
+                if length > MAX_ADDITIONAL_INFO_LEN {
+                    ui.colored_label(
+                        egui::Color32::from_rgb(180, 60, 60),
+                        format!("⚠ {}/{} characters, will be truncated", length, MAX_ADDITIONAL_INFO_LEN),
+                    );
+                }
+            });
+        }
+    });
+
+    ui.add_space(10.0);
+
+    ui.group(|ui| {
+        ui.strong("Invoice Template");
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            ui.label("Template Directory:");
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut app.template_dir)
+                    .hint_text("templates next to the database")
+                    .desired_width(300.0),
+            );
+            if response.changed() {
+                settings_changed = true;
+            }
+            if ui.button("📁 Browse…").clicked()
+                && let Some(dir) = rfd::FileDialog::new().pick_folder()
+            {
+                app.template_dir = dir.display().to_string();
+                settings_changed = true;
+            }
+        });
+
+        let template_path = app.resolved_template_dir().join(crate::pdf::TEMPLATE_FILE);
+        if template_path.exists() {
+            ui.weak(format!("Using {}", template_path.display()));
+        } else {
+            ui.weak(format!("{} not found, using the built-in template", template_path.display()));
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Local Package Directory:");
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut app.package_dir)
+                    .hint_text("optional, e.g. for offline use")
+                    .desired_width(300.0),
+            );
+            if response.changed() {
+                settings_changed = true;
+            }
+            if ui.button("📁 Browse…").clicked()
+                && let Some(dir) = rfd::FileDialog::new().pick_folder()
+            {
+                app.package_dir = dir.display().to_string();
+                settings_changed = true;
+            }
+        });
+        ui.weak("Packages are looked up as <namespace>/<name>/<version>, e.g. preview/payqr-swiss/0.4.0, before the Typst package cache");
+
+        ui.horizontal(|ui| {
+            ui.label("Brand Color:");
+            let mut rgb = crate::pdf::parse_hex_color(&app.brand_color)
+                .or_else(|| crate::pdf::parse_hex_color(crate::pdf::DEFAULT_ACCENT_COLOR))
+                .unwrap_or_default();
+            if ui.color_edit_button_srgb(&mut rgb).changed() {
+                app.brand_color = crate::pdf::hex_color(rgb);
+                settings_changed = true;
+            }
+            if ui.add(egui::TextEdit::singleline(&mut app.brand_color).desired_width(80.0)).changed() {
+                settings_changed = true;
+            }
+            if crate::pdf::parse_hex_color(&app.brand_color).is_none() {
+                ui.colored_label(
+                    egui::Color32::RED,
+                    format!("Not a hex color like #1f4e79, {} is used", crate::pdf::DEFAULT_ACCENT_COLOR),
+                );
+            } else {
+                ui.weak("Headings and rules on the invoice, as accent-color in templates");
+            }
+        });
+
+        if ui.checkbox(&mut app.auto_generate_pdf, "Auto-generate PDF on save").changed() {
+            settings_changed = true;
+        }
+
+        ui.horizontal(|ui| {
+            settings_changed |= ui.checkbox(&mut app.pdf_tagged, "Accessibility tags")
+                .on_hover_text("Turn off for somewhat smaller PDFs")
+                .changed();
+            settings_changed |= ui.checkbox(&mut app.pdf_system_fonts, "Use system fonts")
+                .on_hover_text("Turn off to embed only the bundled fonts and those in the template folder")
+                .changed();
+            ui.weak("Fonts are always embedded as subsets of the characters used");
+        });
+        if !app.pdf_tagged {
+            ui.colored_label(
+                egui::Color32::from_rgb(200, 120, 0),
+                "⚠ Without tags, screen readers can't follow the invoice's structure",
+            );
+        }
+
+        if ui.checkbox(&mut app.delete_pdf_with_bill, "Delete PDF files with bill")
+            .on_hover_text("Turn off if you archive the files in the PDF folder separately")
+            .changed()
+        {
+            settings_changed = true;
+        }
+
+        ui.horizontal(|ui| {
+            // The template is read on every generation; this re-checks it after editing
+            if ui.button("🔄 Reload template").clicked() {
+                app.settings_message = Some(match crate::pdf::check_template(&app.resolved_template_dir()) {
+                    Ok(Some(path)) => format!("Template reloaded from {}, no syntax errors found", path.display()),
+                    Ok(None) => "Built-in template reloaded".to_string(),
+                    Err(e) => e,
+                });
+            }
+            if ui.button("↺ Restore default template…")
+                .on_hover_text("Writes the built-in template to the template folder")
+                .clicked()
+            {
+                app.pending_settings_reset = Some(SettingsReset::Template);
+            }
+        });
+
+        ui.horizontal(|ui| {
+            if ui.button("🧪 Generate sample invoice").clicked() {
+                match app.generate_sample_pdf() {
+                    Ok(path) => {
+                        app.settings_message = Some(format!("Sample invoice written to {}", path.display()));
+                        if let Err(e) = open::that(&path) {
+                            app.pdf_error = Some(format!("Failed to open {}: {}", path.display(), e));
+                        }
+                    }
+                    Err(e) => app.pdf_error = Some(format!("Sample invoice could not be generated:\n{}", e)),
+                }
+            }
+            ui.weak("Renders made-up data with the settings above, without creating a bill");
+        });
+    });
+
+    ui.add_space(10.0);
+
+    ui.group(|ui| {
+        ui.strong("Email (SMTP)");
+        ui.separator();
+
+        egui::Grid::new("smtp_settings").num_columns(2).show(ui, |ui| {
+            ui.label("Server:");
+            ui.horizontal(|ui| {
+                settings_changed |= ui.add(
+                    egui::TextEdit::singleline(&mut app.smtp.host).hint_text("smtp.example.com"),
+                ).changed();
+                ui.label("Port:");
+                settings_changed |= ui.add(egui::DragValue::new(&mut app.smtp.port).range(1..=65535)).changed();
+                egui::ComboBox::from_id_salt("smtp_security")
+                    .selected_text(app.smtp.security.to_string())
+                    .show_ui(ui, |ui| {
+                        for security in crate::email::SmtpSecurity::ALL {
+                            if ui.selectable_value(&mut app.smtp.security, security, security.to_string()).changed() {
+                                app.smtp.port = security.default_port();
+                                settings_changed = true;
+                            }
+                        }
+                    });
+            });
+            ui.end_row();
+
+            ui.label("Username:");
+            settings_changed |= ui.add(
+                egui::TextEdit::singleline(&mut app.smtp.username).hint_text("empty for no login"),
+            ).changed();
+            ui.end_row();
+
+            ui.label("Password:");
+            settings_changed |= ui.add(egui::TextEdit::singleline(&mut app.smtp.password).password(true))
+                .on_hover_text("Stored unencrypted in the database")
+                .changed();
+            ui.end_row();
+
+            ui.label("From:");
+            ui.horizontal(|ui| {
+                settings_changed |= ui.text_edit_singleline(&mut app.smtp.from).changed();
+                email_validation_label(ui, &app.smtp.from);
+            });
+            ui.end_row();
+
+            ui.label("Reply-To:");
+            ui.horizontal(|ui| {
+                let mut reply_to = app.email_reply_to.clone().unwrap_or_default();
+                if ui.add(egui::TextEdit::singleline(&mut reply_to).hint_text("sender address")).changed() {
+                    app.email_reply_to = (!reply_to.trim().is_empty()).then_some(reply_to);
+                    settings_changed = true;
+                }
+                email_validation_label(ui, app.email_reply_to.as_deref().unwrap_or_default());
+            });
+            ui.end_row();
+
+            ui.label("CC:");
+            settings_changed |= email_list_edit(ui, &mut app.email_cc);
+            ui.end_row();
+
+            ui.label("BCC:");
+            settings_changed |= email_list_edit(ui, &mut app.email_bcc);
+            ui.end_row();
+
+            ui.label("Subject:");
+            settings_changed |= ui.add(egui::TextEdit::singleline(&mut app.smtp.subject).desired_width(400.0)).changed();
+            ui.end_row();
+
+            ui.label("Message:");
+            settings_changed |= ui.add(egui::TextEdit::multiline(&mut app.smtp.body).desired_width(400.0).desired_rows(6)).changed();
+            ui.end_row();
+
+            ui.label("Reminder subject:");
+            settings_changed |= ui.add(egui::TextEdit::singleline(&mut app.smtp.reminder_subject).desired_width(400.0)).changed();
+            ui.end_row();
+
+            ui.label("Reminder message:");
+            settings_changed |= ui.add(egui::TextEdit::multiline(&mut app.smtp.reminder_body).desired_width(400.0).desired_rows(6)).changed();
+            ui.end_row();
+
+            ui.label("Batch delay:");
+            ui.horizontal(|ui| {
+                settings_changed |= ui.add(egui::DragValue::new(&mut app.smtp.batch_delay_secs).range(0..=600).suffix(" s")).changed();
+                ui.weak("between emails sent in one go");
+            });
+            ui.end_row();
+        });
+        ui.horizontal(|ui| {
+            if app.smtp_test_job.is_some() {
+                ui.spinner();
+                ui.label("Connecting…");
+            } else if ui.button("🔌 Test connection")
+                .on_hover_text("Connect and log in with these settings without sending an email")
+                .clicked()
+            {
+                app.start_smtp_test();
+            }
+        });
+        ui.weak("Placeholders: {invoice_number}, {client_name}, {currency}, {total}, {due_date}, {reference}, {company}");
+    });
+
+    ui.add_space(10.0);
+
+    ui.group(|ui| {
+        ui.strong("Data");
+        ui.separator();
+
+        let locations = app.data_locations();
+        egui::Grid::new("data_locations").num_columns(3).show(ui, |ui| {
+            for (label, path) in &locations {
+                ui.label(format!("{}:", label));
+                ui.add(egui::Label::new(egui::RichText::new(path.display().to_string()).monospace()).selectable(true));
+                if ui.small_button("📋").on_hover_text("Copy path").clicked() {
+                    ui.ctx().copy_text(path.display().to_string());
+                }
+                ui.end_row();
+            }
+        });
+
+        if ui.button("📂 Open data folder").clicked()
+            && let Some((_, data_dir)) = locations.first()
+            && let Err(e) = open::that(data_dir)
+        {
+            app.settings_message = Some(format!("Failed to open {}: {}", data_dir.display(), e));
+        }
+
+        ui.separator();
+
+        if ui.button("📥 Import bills (JSON/CSV)…").clicked()
+            && let Some(path) = rfd::FileDialog::new()
+                .add_filter("Bills", &["json", "csv"])
+                .pick_file()
+        {
+            let result = crate::import::read_bill_records(&path)
+                .and_then(|records| app.import_bills(records));
+            app.settings_message = Some(match result {
+                Ok(count) => format!("Imported {} bills", count),
+                Err(e) => format!("Import failed:\n{}", e),
+            });
+        }
+
+        if ui.button("📤 Export all bills (CSV)…").clicked()
+            && let Some(path) = rfd::FileDialog::new()
+                .add_filter("CSV", &["csv"])
+                .set_file_name("bills.csv")
+                .save_file()
+        {
+            let result = app.get_bills()
+                .and_then(|bills| app.export_bills_csv(&bills, &path));
+            app.settings_message = Some(match result {
+                Ok(count) => format!("Exported {} bills to {}", count, path.display()),
+                Err(e) => format!("Export failed:\n{}", e),
+            });
+        }
+
+        egui::CollapsingHeader::new("CSV export columns")
+            .id_salt("csv_columns")
+            .show(ui, |ui| {
+                settings_changed |= show_csv_columns(app, ui);
+            });
+
+        if ui.button("🕶 Export anonymized copy…")
+            .on_hover_text("Clients, bills and payments without names, addresses or notes, e.g. for a bug report")
+            .clicked()
+            && let Some(path) = rfd::FileDialog::new()
+                .add_filter("JSON", &["json"])
+                .set_file_name("bill-manager-anonymized.json")
+                .save_file()
+        {
+            let result = crate::export::write_anonymized_json(&path, &app.clients, &app.bills, &app.payments);
+            app.settings_message = Some(match result {
+                Ok(()) => format!("Exported an anonymized copy to {}", path.display()),
+                Err(e) => format!("Export failed:\n{}", e),
+            });
+        }
+
+        if ui.button("🔎 Find duplicate references").clicked() {
+            let duplicates = find_duplicate_references(&app.bills);
+            app.settings_message = Some(if duplicates.is_empty() {
+                "No two bills share a reference".to_string()
+            } else {
+                format!(
+                    "{} references are used more than once:\n{}",
+                    duplicates.len(),
+                    duplicates.iter()
+                        .map(|(reference, ids)| format!("{}: {}", reference, format_bill_ids(ids)))
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                )
+            });
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Archive year:");
+            ui.add(egui::DragValue::new(&mut app.archive_year).range(2000..=2100));
+            if ui.button("🗄 Archive…")
+                .on_hover_text("Export the year's PDFs and a manifest into a folder and flag the bills as archived")
+                .clicked()
+                && let Some(dir) = rfd::FileDialog::new().pick_folder()
+            {
+                let year = app.archive_year;
+                app.settings_message = Some(match app.archive_year(year, &dir) {
+                    Ok(report) => report,
+                    Err(e) => format!("Archiving failed:\n{}", e),
+                });
+            }
+        });
+
+        if ui.button("🩺 Check database")
+            .on_hover_text("Look for corruption, bills without client and unreadable or invalid bill data")
+            .clicked()
+        {
+            match app.check_integrity() {
+                Ok(report) => app.integrity_report = Some(report),
+                Err(e) => app.settings_message = Some(e),
+            }
+        }
+        show_integrity_report(app, ui);
+
+        if let Some(job) = &app.vacuum_job {
+            match job.try_recv() {
+                Ok(result) => {
+                    app.settings_message = Some(match result {
+                        Ok((before, after)) => format!(
+                            "Database compacted: {} → {}",
+                            format_file_size(before),
+                            format_file_size(after)
+                        ),
+                        Err(e) => e,
+                    });
+                    app.vacuum_job = None;
+                }
+                Err(std::sync::mpsc::TryRecvError::Empty) => {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Compacting database…");
+                    });
+                    ui.ctx().request_repaint_after(std::time::Duration::from_millis(200));
+                }
+                Err(std::sync::mpsc::TryRecvError::Disconnected) => {
+                    app.settings_message = Some("Compacting the database was aborted".to_string());
+                    app.vacuum_job = None;
+                }
+            }
+        } else if ui.button("🗜 Compact database").clicked() {
+            app.start_vacuum();
+        }
+
+        if let Some(message) = &app.settings_message {
+            ui.label(message);
+        }
+    });
+
+    ui.add_space(10.0);
+
+    egui::CollapsingHeader::new("Recent Activity")
+        .id_salt("audit_log")
+        .show(ui, |ui| {
+            match app.recent_audit_events(100) {
+                Ok(events) if events.is_empty() => {
+                    ui.weak("No changes recorded yet");
+                }
+                Ok(events) => {
+                    egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                        egui::Grid::new("audit_log_grid").striped(true).num_columns(4).show(ui, |ui| {
+                            for event in &events {
+                                let timestamp = event.timestamp
+                                    .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+                                    .unwrap_or_default();
+                                ui.label(timestamp);
+                                ui.label(format!("{} #{}", event.entity_type, event.entity_id));
+                                ui.label(&event.action);
+                                match &event.details {
+                                    Some(details) => {
+                                        let preview: String = details.chars().take(60).collect();
+                                        ui.weak(preview).on_hover_text(details);
+                                    }
+                                    None => {
+                                        ui.label("");
+                                    }
+                                }
+                                ui.end_row();
+                            }
+                        });
+                    });
+                }
+                Err(e) => {
+                    ui.colored_label(egui::Color32::RED, e);
+                }
+            }
+        });
+
+    if settings_changed {
+        app.mark_settings_dirty();
+    }
+}
+
+/// Column choice and order of the bill CSV export; returns whether it changed
+fn show_csv_columns(app: &mut BillManagerApp, ui: &mut egui::Ui) -> bool {
+    let mut changed = false;
+
+    ui.horizontal(|ui| {
+        ui.label("Preset:");
+        for (name, columns) in CSV_PRESETS {
+            if ui.selectable_label(app.csv_columns == columns, name).clicked() {
+                app.csv_columns = columns.to_vec();
+                changed = true;
+            }
+        }
+    });
+
+    let mut move_up: Option<usize> = None;
+    let mut remove: Option<usize> = None;
+    egui::Grid::new("csv_columns_grid").num_columns(3).show(ui, |ui| {
+        for (idx, column) in app.csv_columns.iter().enumerate() {
+            ui.label(format!("{}. {}", idx + 1, column.label()));
+            ui.horizontal(|ui| {
+                if ui.add_enabled(idx > 0, egui::Button::new("⏶").small()).on_hover_text("Move up").clicked() {
+                    move_up = Some(idx);
+                }
+                if ui.add_enabled(idx + 1 < app.csv_columns.len(), egui::Button::new("⏷").small())
+                    .on_hover_text("Move down")
+                    .clicked()
+                {
+                    move_up = Some(idx + 1);
+                }
+            });
+            // At least one column has to stay
+            if ui.add_enabled(app.csv_columns.len() > 1, egui::Button::new("✖").small())
+                .on_hover_text("Remove")
+                .clicked()
+            {
+                remove = Some(idx);
+            }
+            ui.end_row();
+        }
+    });
+    if let Some(idx) = move_up {
+        app.csv_columns.swap(idx - 1, idx);
+        changed = true;
+    }
+    if let Some(idx) = remove {
+        app.csv_columns.remove(idx);
+        changed = true;
+    }
+
+    let missing: Vec<CsvColumn> = CsvColumn::ALL
+        .into_iter()
+        .filter(|c| !app.csv_columns.contains(c))
+        .collect();
+    if !missing.is_empty() {
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Add:");
+            for column in missing {
+                if ui.small_button(format!("➕ {}", column.label())).clicked() {
+                    app.csv_columns.push(column);
+                    changed = true;
+                }
+            }
+        });
+    }
+
+    changed
+}
+
+/// Findings of the database check with the available repairs
+fn show_integrity_report(app: &mut BillManagerApp, ui: &mut egui::Ui) {
+    let Some(report) = &app.integrity_report else {
+        return;
+    };
+    let mut dismiss = false;
+    let mut reassign: Option<Vec<u64>> = None;
+    let mut remove_leftovers = false;
+
+    ui.group(|ui| {
+        ui.horizontal(|ui| {
+            if report.is_clean() {
+                ui.colored_label(egui::Color32::from_rgb(60, 150, 60), "✔ No problems found");
+            } else {
+                ui.strong("Database check");
+            }
+            if ui.small_button("✖").clicked() {
+                dismiss = true;
+            }
+        });
+
+        for message in &report.sqlite_errors {
+            ui.colored_label(egui::Color32::RED, format!("SQLite: {}", message));
+        }
+        if !report.sqlite_errors.is_empty() {
+            ui.label("The database file is damaged; restore a backup if you have one.");
+        }
+
+        if !report.orphaned_bills.is_empty() {
+            ui.horizontal(|ui| {
+                ui.label(format!("{} belong to a client that no longer exists", format_bill_ids(&report.orphaned_bills)));
+                if ui.button("Reassign").on_hover_text(format!("Move them to a client \"{}\"", RESTORED_CLIENT_NAME)).clicked() {
+                    reassign = Some(report.orphaned_bills.clone());
+                }
+            });
+        }
+
+        if report.orphaned_rows > 0 {
+            ui.horizontal(|ui| {
+                ui.label(format!("{} payments or attachments belong to deleted bills", report.orphaned_rows));
+                if ui.button("Remove").clicked() {
+                    remove_leftovers = true;
+                }
+            });
+        }
+
+        if !report.bill_problems.is_empty() {
+            ui.label("Fix these in the bill form:");
+            egui::ScrollArea::vertical().id_salt("integrity_problems").max_height(200.0).show(ui, |ui| {
+                for (bill_id, problem) in &report.bill_problems {
+                    ui.label(format!("Bill #{}: {}", bill_id, problem));
+                }
+            });
+        }
+    });
+
+    if dismiss {
+        app.integrity_report = None;
+    }
+    let repair = match (reassign, remove_leftovers) {
+        (Some(ids), _) => Some(app.reassign_orphaned_bills(&ids)),
+        (None, true) => Some(app.remove_orphaned_rows()),
+        (None, false) => None,
+    };
+    if let Some(result) = repair {
+        app.settings_message = Some(result.unwrap_or_else(|e| e));
+        app.integrity_report = app.check_integrity().ok();
+    }
+}
+
+/// Marks a non-empty email address as valid or invalid
+fn email_validation_label(ui: &mut egui::Ui, email: &str) {
+    if email.trim().is_empty() {
+        return;
+    }
+    if validate_email(email) {
+        ui.colored_label(egui::Color32::from_rgb(60, 150, 60), "✓");
+    } else {
+        ui.colored_label(egui::Color32::from_rgb(180, 60, 60), "✗ Invalid address");
+    }
+}
+
+/// Comma-separated address list; entries are kept untrimmed so typing isn't disturbed
+fn email_list_edit(ui: &mut egui::Ui, addresses: &mut Vec<String>) -> bool {
+    ui.horizontal(|ui| {
+        let mut text = addresses.join(",");
+        let changed = ui.add(egui::TextEdit::singleline(&mut text).hint_text("comma-separated")).changed();
+        if changed {
+            *addresses = if text.trim().is_empty() {
+                Vec::new()
+            } else {
+                text.split(',').map(str::to_string).collect()
+            };
+        }
+        let invalid: Vec<String> = crate::email::address_list(addresses)
+            .into_iter()
+            .filter(|a| !validate_email(a))
+            .collect();
+        if !invalid.is_empty() {
+            ui.colored_label(egui::Color32::from_rgb(180, 60, 60), format!("✗ Invalid: {}", invalid.join(", ")));
+        }
+        changed
+    })
+    .inner
+}
+
+fn show_client_form_window(app: &mut BillManagerApp, ctx: &egui::Context) {
+    let mut open = true;
+    let mut close_requested = false;
+    let mut save_client = false;
+    let window = egui::Window::new("Client Details")
+        .open(&mut open)
+        .resizable(true)
+        .show(ctx, |ui| {
+            if let Some(client) = &mut app.editing_client {
+                if let Some(error) = &app.client_error {
+                    ui.colored_label(egui::Color32::RED, error);
+                    ui.separator();
+                }
+
+                ui.horizontal(|ui| {
+                    required_label(ui, "Name:");
+                    ui.text_edit_singleline(&mut client.name);
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("Email:");
+                    ui.text_edit_singleline(&mut client.email);
+                });
+
+                ui.horizontal(|ui| {
                     ui.label("Phone:");
                     ui.text_edit_singleline(&mut client.phone);
                 });
 
-                ui.separator();
-                ui.strong("Contact Address");
+                ui.separator();
+                ui.strong("Contact Address");
+
+                ui.horizontal(|ui| {
+                    ui.label("Street:");
+                    let mut street = client.address.street.clone().unwrap_or_default();
+                    ui.text_edit_singleline(&mut street);
+                    client.address.street = Some(street);
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("Building Number:");
+                    let mut building = client.address.building_number.clone().unwrap_or_default();
+                    ui.text_edit_singleline(&mut building);
+                    client.address.building_number = Some(building);
+                });
+
+                ui.horizontal(|ui| {
+                    required_label(ui, "Postal Code:");
+                    ui.text_edit_singleline(&mut client.address.postal_code);
+                });
+
+                ui.horizontal(|ui| {
+                    required_label(ui, "City:");
+                    ui.text_edit_singleline(&mut client.address.city);
+                });
+
+                ui.horizontal(|ui| {
+                    required_label(ui, "Country:");
+                    ui.text_edit_singleline(&mut client.address.country);
+                });
+
+                ui.separator();
+                ui.strong("Billing Address");
+
+                ui.horizontal(|ui| {
+                    required_label(ui, "Name:");
+                    ui.text_edit_singleline(&mut client.billing_address.name);
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("Street:");
+                    let mut street = client.billing_address.street.clone().unwrap_or_default();
+                    ui.text_edit_singleline(&mut street);
+                    client.billing_address.street = Some(street);
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("Building Number:");
+                    let mut building = client.billing_address.building_number.clone().unwrap_or_default();
+                    ui.text_edit_singleline(&mut building);
+                    client.billing_address.building_number = Some(building);
+                });
+
+                ui.horizontal(|ui| {
+                    required_label(ui, "Postal Code:");
+                    ui.text_edit_singleline(&mut client.billing_address.postal_code);
+                });
+
+                ui.horizontal(|ui| {
+                    required_label(ui, "City:");
+                    ui.text_edit_singleline(&mut client.billing_address.city);
+                });
+
+                ui.horizontal(|ui| {
+                    required_label(ui, "Country:");
+                    ui.text_edit_singleline(&mut client.billing_address.country);
+                });
+
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    if ui.button("💾 Save").clicked() {
+                        save_client = true;
+                    }
+
+                    if ui.button("❌ Cancel").clicked() {
+                        close_requested = true;
+                    }
+                });
+            }
+        });
+
+    let (enter, escape) = form_shortcuts(ctx, window.map(|w| w.response.layer_id));
+    save_client |= enter;
+    close_requested |= escape;
+
+    if save_client && let Some(client) = app.editing_client.clone() {
+        // Keep the form open with the list of missing fields until they are filled in
+        match client.validate() {
+            Ok(()) => {
+                if client.id == 0 {
+                    app.add_client(client);
+                } else {
+                    app.update_client(client);
+                }
+                app.close_form(FormKind::Client);
+            }
+            Err(problems) => app.client_error = Some(problems.join("\n")),
+        }
+    }
+
+    if close_requested || !open {
+        app.request_close_form(FormKind::Client);
+    }
+}
+
+fn show_bill_form_window(app: &mut BillManagerApp, ctx: &egui::Context, key: u64) {
+    // The editor is taken out while its window is drawn, so the rest of the app stays readable
+    let Some(index) = app.bill_editors.iter().position(|e| e.key == key) else {
+        return;
+    };
+    let mut editor = app.bill_editors.remove(index);
+
+    let mut open = true;
+    let mut save_bill = false;
+    let mut save_and_generate = false;
+    let mut save_draft = false;
+    let mut finalize = false;
+    let mut cancel_bill = false;
+    let mut save_as_template = false;
+    let mut template_to_edit: Option<(ItemTemplate, usize)> = None;
+    let mut generate_reference = false;
+
+    let client_name = app.get_client(editor.bill.client_id)
+        .map(|c| c.name.clone())
+        .unwrap_or_else(|| "Select Client".to_string());
+    let clients = app.clients.clone();
+    let item_templates = app.templates_by_popularity();
+
+    // Items of the chosen client's previous bill, offered as a starting point for new bills
+    let previous_items = Some(&editor.bill)
+        .filter(|bill| bill.id == 0 && bill.client_id != 0)
+        .and_then(|bill| app.last_bill_for_client(bill.client_id))
+        .map(|last| (last.id, last.items.clone()));
+    let mut used_template: Option<u64> = None;
+    let default_vat_rate = app.default_vat_rate;
+    let discount_templates = app.discount_templates.clone();
+    let quantity_decimals = app.quantity_decimals;
+    let due_date_presets = app.due_date_presets.clone();
+    let auto_generate_pdf = app.auto_generate_pdf;
+    let duplicate_references = app.bills_with_reference(&editor.bill.reference, editor.bill.id);
+    let credit_notes: Vec<String> = editor.bill
+        .linked_documents(&app.bills)
+        .iter()
+        .filter(|b| b.is_credit_note())
+        .map(|b| format!("#{} ({} {:.2})", b.id, b.currency, b.total()))
+        .collect();
+    // Invoices a credit note can refer to, as (id, client, label)
+    let invoices: Vec<(u64, u64, String)> = app.bills
+        .iter()
+        .filter(|b| !b.is_credit_note() && b.id != editor.bill.id)
+        .map(|b| (b.id, b.client_id, format!("#{} – {} – {} {:.2}", b.id, b.date.format("%Y-%m-%d"), b.currency, b.total())))
+        .collect();
+    let payments: Vec<_> = app.payments
+        .iter()
+        .filter(|p| p.bill_id == editor.bill.id && editor.bill.id != 0)
+        .cloned()
+        .collect();
+    let mut add_attachments = false;
+    let mut open_attachment: Option<Attachment> = None;
+    let mut save_attachment: Option<Attachment> = None;
+    let mut remove_attachment: Option<u64> = None;
+    let mut append_attachment: Option<(u64, bool)> = None;
+
+    let title = if editor.bill.id == 0 {
+        "New Bill".to_string()
+    } else {
+        format!("Bill #{}", editor.bill.id)
+    };
+    // Cascade new windows so they do not cover each other exactly
+    let offset = (key % 8) as f32 * 24.0;
+
+    let window = egui::Window::new(title)
+        .id(egui::Id::new(("bill_form", key)))
+        .open(&mut open)
+        .resizable(true)
+        .default_width(600.0)
+        .default_pos(egui::pos2(160.0 + offset, 60.0 + offset))
+        .show(ctx, |ui| {
+            let bill = &mut editor.bill;
+
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                // Display error message if present
+                if let Some(error) = &editor.error {
+                    ui.colored_label(egui::Color32::RED, error);
+                    ui.separator();
+                }
+
+                if let Some(archived_at) = bill.archived_at {
+                    ui.colored_label(
+                        egui::Color32::from_rgb(200, 120, 0),
+                        format!(
+                            "🔒 Archived on {} for tax retention; changes no longer match the archived PDF",
+                            archived_at.format("%Y-%m-%d")
+                        ),
+                    );
+                    ui.separator();
+                }
+
+                if !bill.load_problems.is_empty() {
+                    for problem in &bill.load_problems {
+                        ui.colored_label(egui::Color32::RED, format!("⚠ {}", problem));
+                    }
+                    ui.label("Check the bill and save it to repair the stored data.");
+                    ui.separator();
+                }
+
+                ui.horizontal(|ui| {
+                    ui.label("Client:");
+                    if client_picker(
+                        ui,
+                        &mut bill.client_id,
+                        &clients,
+                        &client_name,
+                        &mut editor.client_picker_query,
+                        &mut editor.client_picker_index,
+                    ) {
+                        // Clear error when client is selected
+                        editor.error = None;
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("Document:");
+                    egui::ComboBox::from_id_salt("document_type")
+                        .selected_text(bill.document_type.to_string())
+                        .show_ui(ui, |ui| {
+                            for document_type in DocumentType::ALL {
+                                ui.selectable_value(&mut bill.document_type, document_type, document_type.to_string());
+                            }
+                        });
+
+                    if bill.is_credit_note() {
+                        ui.label("refunds");
+                        let selected = bill.credited_bill_id
+                            .map_or_else(|| "Select invoice".to_string(), |id| format!("Invoice #{}", id));
+                        egui::ComboBox::from_id_salt("credited_bill")
+                            .selected_text(selected)
+                            .show_ui(ui, |ui| {
+                                for (id, _, label) in invoices.iter().filter(|(_, client_id, _)| *client_id == bill.client_id) {
+                                    ui.selectable_value(&mut bill.credited_bill_id, Some(*id), label);
+                                }
+                            });
+                    } else if !credit_notes.is_empty() {
+                        ui.weak(format!("Credited by {}", credit_notes.join(", ")));
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("Bill Date:");
+                    let mut date = bill.date.date_naive();
+                    if ui.add(egui_extras::DatePickerButton::new(&mut date).id_salt("bill_date")).changed() {
+                        // Midnight can fall into a DST gap, so take the earliest valid instant
+                        bill.date = date.and_time(chrono::NaiveTime::MIN)
+                            .and_local_timezone(app.zone())
+                            .earliest()
+                            .unwrap_or_else(crate::timezone::now);
+                    }
+                    if ui.small_button("Today").clicked() {
+                        bill.date = crate::timezone::now();
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("Due Date:");
+                    let mut due_date_str = bill.due_date.format("%Y-%m-%d").to_string();
+                    if ui.text_edit_singleline(&mut due_date_str).changed() {
+                        // Try to parse the date
+                        if let Ok(naive_date) = chrono::NaiveDate::parse_from_str(&due_date_str, "%Y-%m-%d") {
+                            bill.due_date = naive_date.and_hms_opt(0, 0, 0)
+                                .unwrap()
+                                .and_local_timezone(app.zone())
+                                .unwrap();
+                        }
+                    }
+                    for days in &due_date_presets {
+                        if ui.button(format!("+{}d", days)).clicked() {
+                            bill.due_date += chrono::Duration::days(*days);
+                        }
+                    }
+                });
+
+                if bill.date.date_naive() > bill.due_date.date_naive() {
+                    ui.colored_label(egui::Color32::from_rgb(200, 120, 0), "⚠ The bill date is after the due date");
+                }
+
+                ui.horizontal(|ui| {
+                    let mut has_follow_up = bill.follow_up.is_some();
+                    if ui.checkbox(&mut has_follow_up, "Follow up on:").changed() {
+                        bill.follow_up = has_follow_up.then(|| bill.due_date + chrono::Duration::days(7));
+                    }
+                    if let Some(follow_up) = &mut bill.follow_up {
+                        let mut date = follow_up.date_naive();
+                        if ui.add(egui_extras::DatePickerButton::new(&mut date).id_salt("follow_up")).changed() {
+                            *follow_up = date.and_time(chrono::NaiveTime::MIN)
+                                .and_local_timezone(app.zone())
+                                .earliest()
+                                .unwrap_or_else(crate::timezone::now);
+                        }
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    let mut has_period = bill.service_from.is_some() && bill.service_to.is_some();
+                    if ui.checkbox(&mut has_period, "Service period:").changed() {
+                        if has_period {
+                            let today = bill.date.date_naive();
+                            bill.service_from = Some(today.with_day(1).unwrap_or(today));
+                            bill.service_to = Some(today);
+                        } else {
+                            bill.service_from = None;
+                            bill.service_to = None;
+                        }
+                    }
+                    if let (Some(from), Some(to)) = (&mut bill.service_from, &mut bill.service_to) {
+                        ui.add(egui_extras::DatePickerButton::new(from).id_salt("service_from"));
+                        ui.label("–");
+                        ui.add(egui_extras::DatePickerButton::new(to).id_salt("service_to"));
+                        if from > to {
+                            ui.colored_label(egui::Color32::from_rgb(200, 120, 0), "⚠ Ends before it starts");
+                        }
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("Reference:");
+                    egui::ComboBox::from_id_salt("bill_reference_type")
+                        .selected_text(bill.reference_type.code())
+                        .show_ui(ui, |ui| {
+                            for reference_type in ReferenceType::ALL {
+                                ui.selectable_value(&mut bill.reference_type, reference_type, reference_type.to_string());
+                            }
+                        });
+                    ui.add_enabled(bill.reference_type != ReferenceType::None, egui::TextEdit::singleline(&mut bill.reference));
+                    if ui.button("🔄 Generate").clicked() {
+                        generate_reference = true;
+                    }
+                });
+                if validate_iban(&bill.iban)
+                    && let Err(e) = check_reference_type(bill.reference_type, &bill.iban)
+                {
+                    ui.colored_label(egui::Color32::from_rgb(200, 120, 0), format!("⚠ {}", e));
+                }
+                // Checked on every frame, so a freshly generated reference is judged right away
+                if bill.reference_type != ReferenceType::None && !bill.reference.trim().is_empty() {
+                    let formatted = format_reference(bill.reference_type, &bill.reference);
+                    if let Some(problem) = reference_problem(bill.reference_type, &bill.reference) {
+                        ui.colored_label(egui::Color32::from_rgb(180, 60, 60), format!("✗ {}: {}", formatted, problem));
+                    } else if !duplicate_references.is_empty() {
+                        ui.colored_label(
+                            egui::Color32::from_rgb(180, 60, 60),
+                            format!("✗ {} is also used by {}", formatted, format_bill_ids(&duplicate_references)),
+                        );
+                    } else {
+                        ui.colored_label(egui::Color32::from_rgb(60, 150, 60), format!("✓ {} is valid and unique", formatted));
+                    }
+                }
+
+                ui.horizontal(|ui| {
+                    ui.label("PO Number:");
+                    ui.add(egui::TextEdit::singleline(&mut bill.po_number).hint_text("client's purchase order, optional"));
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("IBAN:");
+                    ui.text_edit_singleline(&mut bill.iban);
+
+                    // Show validation status
+                    if !bill.iban.is_empty() {
+                        if validate_iban(&bill.iban) {
+                            ui.colored_label(egui::Color32::from_rgb(60, 150, 60), "✓ Valid");
+                        } else {
+                            ui.colored_label(egui::Color32::from_rgb(180, 60, 60), "✗ Invalid IBAN");
+                        }
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("Status:");
+                    egui::ComboBox::from_id_salt("status_select")
+                        .selected_text(format!("{}", bill.status))
+                        .show_ui(ui, |ui| {
+                            for status in BillStatus::ALL {
+                                ui.selectable_value(&mut bill.status, status, status.to_string());
+                            }
+                        });
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("Language:");
+                    egui::ComboBox::from_id_salt("language_select")
+                        .selected_text(format!("{}", bill.language))
+                        .show_ui(ui, |ui| {
+                            for language in Language::ALL {
+                                ui.selectable_value(&mut bill.language, language, format!("{}", language));
+                            }
+                        });
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("Currency:");
+                    let response = ui.add(egui::TextEdit::singleline(&mut bill.currency).desired_width(50.0));
+                    if response.changed() {
+                        bill.currency = bill.currency.trim().to_uppercase();
+                        if bill.conversion.as_ref().is_some_and(|c| c.currency == bill.currency) {
+                            bill.conversion = None;
+                        }
+                        // Only CHF and EUR can be paid with a QR bill
+                        bill.include_qr_bill = QR_CURRENCIES.contains(&bill.qr_currency());
+                    }
+                    if bill.include_qr_bill && !QR_CURRENCIES.contains(&bill.qr_currency()) {
+                        ui.colored_label(
+                            egui::Color32::from_rgb(180, 60, 60),
+                            format!("✗ QR bill supports {} only", QR_CURRENCIES.join("/")),
+                        );
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("Amount:");
+                    egui::ComboBox::from_id_salt("amount_mode_select")
+                        .selected_text(format!("{}", bill.amount_mode))
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut bill.amount_mode, AmountMode::Fixed, "Fixed");
+                            ui.selectable_value(&mut bill.amount_mode, AmountMode::Open, "Open");
+                        });
+                    if bill.amount_mode == AmountMode::Open {
+                        ui.label("Payer fills in the amount on the QR bill");
+                    }
+                });
+
+                ui.add_enabled_ui(bill.include_qr_bill, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("QR payment in:");
+                        let mut qr_currency = bill.conversion.as_ref().map(|c| c.currency.clone());
+                        egui::ComboBox::from_id_salt("qr_currency_select")
+                            .selected_text(bill.qr_currency().to_string())
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut qr_currency, None, format!("{} (bill currency)", bill.currency));
+                                for currency in QR_CURRENCIES.iter().filter(|c| **c != bill.currency) {
+                                    ui.selectable_value(&mut qr_currency, Some(currency.to_string()), *currency);
+                                }
+                            });
+                        if qr_currency != bill.conversion.as_ref().map(|c| c.currency.clone()) {
+                            let rate = bill.conversion.as_ref().map_or(1.0, |c| c.rate);
+                            bill.conversion = qr_currency.map(|currency| CurrencyConversion { currency, rate });
+                        }
+
+                        if let Some(conversion) = &mut bill.conversion {
+                            ui.label(format!("1 {} =", bill.currency));
+                            ui.add(egui::DragValue::new(&mut conversion.rate).speed(0.0001).range(0.0001..=f64::MAX).max_decimals(6));
+                            ui.label(&conversion.currency);
+                        }
+                        if bill.conversion.is_some() && bill.amount_mode == AmountMode::Fixed {
+                            ui.weak(format!("QR amount {} {:.2}", bill.qr_currency(), bill.qr_amount()));
+                        }
+                    });
+                });
+
+                ui.add_enabled(!bill.is_credit_note(), egui::Checkbox::new(&mut bill.include_qr_bill, "Include QR payment part"))
+                    .on_hover_text("Turn off for a plain invoice, e.g. for clients paying by card or in other currencies")
+                    .on_disabled_hover_text("Credit notes have no payment part");
+
+                ui.checkbox(&mut bill.prices_include_vat, "Prices include VAT")
+                    .on_hover_text("Unit prices are gross; the VAT is backed out instead of added");
+
+                ui.checkbox(&mut bill.round_to_five_cents, "Round total to 5 centimes")
+                    .on_hover_text("Swiss cash rounding; the difference is shown as its own line on the invoice");
+
+                ui.horizontal(|ui| {
+                    ui.label("Discount:");
+                    let selected = bill.discount
+                        .as_ref()
+                        .map(|d| format!("{} ({})", d.name, d.discount))
+                        .unwrap_or_else(|| "None".to_string());
+                    egui::ComboBox::from_id_salt("bill_discount")
+                        .selected_text(selected)
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut bill.discount, None, "None");
+                            for template in &discount_templates {
+                                let applied = AppliedDiscount::from(template);
+                                let label = format!("{} ({})", applied.name, applied.discount);
+                                ui.selectable_value(&mut bill.discount, Some(applied), label);
+                            }
+                        });
+                    if discount_templates.is_empty() && bill.discount.is_none() {
+                        ui.weak("Define discounts in the Item Templates tab");
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("Notes:");
+                    ui.text_edit_multiline(&mut bill.notes);
+                });
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.strong("Items");
+                    if let Some((last_id, items)) = &previous_items
+                        && ui.button(format!("📋 Copy items from last invoice (#{})", last_id)).clicked()
+                    {
+                        bill.items = items.clone();
+                    }
+                });
+
+                let mut item_to_remove: Option<usize> = None;
+                let items_count = bill.items.len();
+                let currency = bill.currency.clone();
+
+                for (idx, item) in bill.items.iter_mut().enumerate() {
+                    ui.group(|ui| {
+                        ui.horizontal(|ui| {
+                            ui.label("Type:");
+                            ui.text_edit_singleline(&mut item.item_type);
+
+                            // Add template button if templates exist
+                            if !item_templates.is_empty() {
+                                egui::ComboBox::from_id_salt(format!("template_{}", idx))
+                                    .selected_text("📋")
+                                    .show_ui(ui, |ui| {
+                                        for template in &item_templates {
+                                            ui.horizontal(|ui| {
+                                                if ui.button(&template.item_type).clicked() {
+                                                    item.item_type = template.item_type.clone();
+                                                    item.unit_price = template.unit_price;
+                                                    item.vat_rate = template.vat_rate;
+                                                    item.unit = template.unit.clone();
+                                                    used_template = Some(template.id);
+                                                }
+                                                if ui.small_button("✎").on_hover_text("Edit template").clicked() {
+                                                    template_to_edit = Some((template.clone(), idx));
+                                                    ui.close();
+                                                }
+                                            });
+                                        }
+                                    });
+                            }
+                        });
+
+                        ui.horizontal(|ui| {
+                            ui.label("Quantity:");
+                            let decimals = item.quantity_decimals(quantity_decimals);
+                            let response = ui.add(
+                                egui::DragValue::new(&mut item.quantity)
+                                    .speed(if decimals == 0 { 0.2 } else { 0.1 })
+                                    .fixed_decimals(decimals)
+                                    .update_while_editing(false),
+                            );
+                            if response.changed() {
+                                item.quantity = round_to(item.quantity, decimals);
+                            }
+                            ui.add(egui::TextEdit::singleline(&mut item.unit).hint_text("unit").desired_width(40.0));
+
+                            ui.label("Unit Price:");
+                            if ui.add(price_drag_value(&mut item.unit_price)).changed() {
+                                item.unit_price = round_cents(item.unit_price);
+                            }
+
+                            ui.label("VAT:");
+                            ui.add(egui::DragValue::new(&mut item.vat_rate).speed(0.1).range(0.0..=100.0).suffix("%"));
+
+                            if item.is_credit() {
+                                ui.colored_label(egui::Color32::RED, format!("Credit: {} {:.2}", currency, item.total()));
+                            } else {
+                                ui.label(format!("Total: {} {:.2}", currency, item.total()));
+                            }
+
+                            if items_count > 1 && ui.button("🗑").clicked() {
+                                item_to_remove = Some(idx);
+                            }
+                        });
+
+                        // Note field
+                        ui.horizontal(|ui| {
+                            ui.label("Note:");
+                            ui.text_edit_singleline(&mut item.note);
+                        });
+                    });
+                }
+
+                if let Some(idx) = item_to_remove {
+                    bill.items.remove(idx);
+                }
+
+                ui.horizontal(|ui| {
+                    if ui.button("➕ Add Item").clicked() {
+                        bill.items.push(BillItem::with_vat_rate(default_vat_rate));
+                    }
+
+                    if !item_templates.is_empty() {
+                        egui::ComboBox::from_id_salt("add_from_template")
+                            .selected_text("📋 Add from Template")
+                            .show_ui(ui, |ui| {
+                                for template in &item_templates {
+                                    if ui.button(&template.item_type).clicked() {
+                                        bill.items.push(template.to_bill_item());
+                                        used_template = Some(template.id);
+                                    }
+                                }
+                            });
+                    }
+                });
+
+                if bill.amount_mode == AmountMode::Fixed {
+                    ui.separator();
+                    show_totals_panel(ui, &bill.totals(), &bill.currency);
+                }
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.strong("Attachments");
+                    if bill.id != 0 && ui.button("📎 Add…").clicked() {
+                        add_attachments = true;
+                    }
+                });
+                if bill.id == 0 {
+                    ui.label("Save the bill to attach receipts or contracts.");
+                } else if editor.attachments.is_empty() {
+                    ui.label("No attachments");
+                }
+                for attachment in &editor.attachments {
+                    ui.horizontal(|ui| {
+                        ui.label(&attachment.filename)
+                            .on_hover_text(&attachment.mime);
+                        if let Some(added_at) = attachment.added_at {
+                            ui.weak(added_at.format("%d.%m.%Y").to_string());
+                        }
+                        if ui.small_button("Open").clicked() {
+                            open_attachment = Some(attachment.clone());
+                        }
+                        if ui.small_button("Save…").clicked() {
+                            save_attachment = Some(attachment.clone());
+                        }
+                        if ui.small_button("🗑").on_hover_text("Remove attachment").clicked() {
+                            remove_attachment = Some(attachment.id);
+                        }
+                        if attachment.is_pdf() {
+                            let mut append = attachment.append_to_pdf;
+                            if ui.checkbox(&mut append, "Append to PDF")
+                                .on_hover_text("Add its pages after the invoice when the PDF is generated")
+                                .changed()
+                            {
+                                append_attachment = Some((attachment.id, append));
+                            }
+                        }
+                    });
+                }
+
+                if !payments.is_empty() {
+                    ui.separator();
+                    ui.strong("Payments");
+                    for payment in &payments {
+                        ui.horizontal(|ui| {
+                            ui.label(payment.paid_on.format("%d.%m.%Y").to_string());
+                            ui.label(format!("{} {:.2}", bill.currency, payment.amount));
+                            if !payment.note.is_empty() {
+                                ui.weak(&payment.note);
+                            }
+                            if let Some(source) = &payment.source {
+                                ui.weak("🏦").on_hover_text(format!("Imported from bank statement {}", source));
+                            }
+                        });
+                    }
+                }
+
+                if !editor.history.is_empty() {
+                    ui.separator();
+                    egui::CollapsingHeader::new("🕓 History")
+                        .id_salt("bill_history")
+                        .show(ui, |ui| {
+                            ui.horizontal(|ui| {
+                                ui.add(
+                                    egui::TextEdit::singleline(&mut editor.history_filter)
+                                        .hint_text("Search, e.g. reminder")
+                                        .desired_width(180.0),
+                                );
+                                ui.checkbox(&mut editor.history_show_details, "Edits and PDFs");
+                            });
+                            let needle = editor.history_filter.trim().to_lowercase();
+                            let shown: Vec<_> = editor.history
+                                .iter()
+                                .filter(|e| editor.history_show_details || !e.is_detail)
+                                .filter(|e| needle.is_empty() || e.label.to_lowercase().contains(&needle))
+                                .collect();
+                            if shown.is_empty() {
+                                ui.weak("Nothing matches");
+                            }
+                            for (idx, entry) in shown.iter().enumerate() {
+                                if idx > 0 {
+                                    ui.weak("│");
+                                }
+                                ui.horizontal(|ui| {
+                                    if entry.is_detail {
+                                        ui.weak("○");
+                                    } else {
+                                        ui.label("●");
+                                    }
+                                    ui.weak(entry.at.format("%d.%m.%Y %H:%M").to_string());
+                                    ui.label(&entry.label);
+                                });
+                            }
+                        });
+                }
+
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    if bill.status == BillStatus::Draft {
+                        if ui.button("💾 Save Draft")
+                            .on_hover_text("Keep the bill as a draft, even if it is incomplete")
+                            .clicked()
+                        {
+                            save_draft = true;
+                        }
+                        if ui.button("✅ Finalize")
+                            .on_hover_text("Check the bill and mark it as sent")
+                            .clicked()
+                        {
+                            finalize = true;
+                        }
+                    } else {
+                        if ui.button("💾 Save").clicked() {
+                            save_bill = true;
+                        }
+
+                        // With auto-generation every save already produces the PDF
+                        if !auto_generate_pdf && ui.button("💾📄 Save & Generate").clicked() {
+                            save_and_generate = true;
+                        }
+                    }
+
+                    if ui.button("❌ Cancel").clicked() {
+                        cancel_bill = true;
+                    }
+
+                    if ui.button("📋 Save as template…")
+                        .on_hover_text("Keep the lines, notes and payment term for new bills")
+                        .clicked()
+                    {
+                        save_as_template = true;
+                    }
+                });
+            });
+        });
+
+    if let Some(window) = &window
+        && app.raise_bill_editor == Some(key)
+    {
+        ctx.move_to_top(window.response.layer_id);
+        app.raise_bill_editor = None;
+    }
+
+    if save_as_template {
+        app.editing_bill_template = Some(BillManagerApp::bill_template_from(&editor.bill));
+        app.bill_template_error = None;
+    }
+
+    if generate_reference {
+        let bill = &editor.bill;
+        let year = crate::timezone::now().year();
+        let bill_id = if bill.id == 0 { app.next_bill_id } else { bill.id };
+        editor.bill.reference = app.unique_reference(bill.reference_type, bill_id, bill.client_id, year);
+    }
+
+    let mut bill_to_save: Option<Bill> = None;
+    if save_draft {
+        // Drafts are stored as they are, so half-finished bills aren't lost
+        editor.bill.status = BillStatus::Draft;
+        editor.error = None;
+        bill_to_save = Some(editor.bill.clone());
+    } else if save_bill || save_and_generate || finalize {
+        let problems = app.finalize_problems(&editor.bill);
+        if problems.is_empty() {
+            if finalize {
+                editor.bill.status = BillStatus::Sent;
+            }
+            editor.error = None;
+            bill_to_save = Some(editor.bill.clone());
+        } else {
+            // Keep the bill form open until the problems are fixed
+            editor.error = Some(problems.join("\n"));
+        }
+    }
+
+    let bill_id = editor.bill.id;
+    app.bill_editors.insert(index, editor);
+    let set_error = |app: &mut BillManagerApp, error: String| {
+        if let Some(editor) = app.bill_editor_mut(key) {
+            editor.error = Some(error);
+        }
+    };
+
+    if let Some(mut bill) = bill_to_save {
+        if bill.id == 0 {
+            bill.id = app.add_bill(bill.clone());
+        } else {
+            app.update_bill(bill.clone());
+        }
+        app.close_form(FormKind::Bill(key));
+
+        if save_and_generate && let Err(e) = app.generate_pdf(bill.id) {
+            // The bill is saved; keep the form open on it so the problem can be fixed
+            app.open_bill_form(bill);
+            app.pdf_error = Some(format!("Bill saved, but PDF generation failed:\n{}", e));
+        }
+    }
+
+    if add_attachments
+        && let Some(paths) = rfd::FileDialog::new().set_title("Attach files").pick_files()
+    {
+        for path in paths {
+            if let Err(e) = app.add_attachment(bill_id, &path) {
+                set_error(app, e);
+            }
+        }
+    }
+
+    if let Some(attachment) = open_attachment
+        && let Err(e) = open::that(app.attachment_path(&attachment))
+    {
+        set_error(app, format!("Failed to open {}: {}", attachment.filename, e));
+    }
+
+    if let Some(attachment) = save_attachment
+        && let Some(target) = rfd::FileDialog::new()
+            .set_file_name(&attachment.filename)
+            .save_file()
+        && let Err(e) = std::fs::copy(app.attachment_path(&attachment), &target)
+    {
+        set_error(app, format!("Failed to save {}: {}", attachment.filename, e));
+    }
+
+    if let Some((id, append)) = append_attachment
+        && let Err(e) = app.set_attachment_append(id, append)
+    {
+        set_error(app, e);
+    }
+    if let Some(id) = remove_attachment
+        && let Err(e) = app.remove_attachment(id)
+    {
+        set_error(app, e);
+    }
 
-                ui.horizontal(|ui| {
-                    ui.label("Street:");
-                    let mut street = client.address.street.clone().unwrap_or_default();
-                    ui.text_edit_singleline(&mut street);
-                    client.address.street = Some(street);
-                });
+    if let Some(id) = used_template {
+        app.record_template_use(id);
+    }
+
+    if let Some((template, idx)) = template_to_edit {
+        app.open_template_form_for_line(template, key, idx);
+    }
+
+    if cancel_bill || !open {
+        app.request_close_form(FormKind::Bill(key));
+    }
+}
+
+/// Enter (save) and Esc (cancel) for a form window with single-line fields only; a multiline
+/// field would need Enter for new lines. Only the frontmost window reacts, and not while a popup
+/// such as a combo box is open, since the keys belong to it then.
+fn form_shortcuts(ctx: &egui::Context, window: Option<egui::LayerId>) -> (bool, bool) {
+    if window.is_none() || window != ctx.top_layer_id() || egui::Popup::is_any_open(ctx) {
+        return (false, false);
+    }
+
+    ctx.input(|i| {
+        (
+            i.key_pressed(egui::Key::Enter) && i.modifiers.is_none(),
+            i.key_pressed(egui::Key::Escape),
+        )
+    })
+}
+
+/// Price input kept to whole cents; a click allows typing the exact value
+fn price_drag_value(value: &mut f64) -> egui::DragValue<'_> {
+    egui::DragValue::new(value)
+        .speed(0.1)
+        .fixed_decimals(2)
+        .update_while_editing(false)
+}
+
+/// Form label with a red asterisk marking a required field
+fn required_label(ui: &mut egui::Ui, text: &str) {
+    ui.label(text);
+    ui.colored_label(egui::Color32::RED, "*");
+}
+
+/// Subtotal, adjustments and grand total of a bill, laid out like on the invoice
+fn show_totals_panel(ui: &mut egui::Ui, totals: &BillTotals, currency: &str) {
+    egui::Grid::new("bill_totals")
+        .num_columns(2)
+        .spacing([40.0, 4.0])
+        .show(ui, |ui| {
+            ui.label("Subtotal");
+            ui.label(format!("{} {:.2}", currency, totals.subtotal));
+            ui.end_row();
+
+            if totals.discount != 0.0 {
+                match &totals.discount_name {
+                    Some(name) if !name.is_empty() => ui.label(format!("Discount ({})", name)),
+                    _ => ui.label("Discount"),
+                };
+                ui.label(format!("{} -{:.2}", currency, totals.discount));
+                ui.end_row();
+            }
+
+            for (rate, amount) in &totals.vat {
+                if totals.vat_included {
+                    ui.label(format!("incl. VAT {}%", rate));
+                } else {
+                    ui.label(format!("VAT {}%", rate));
+                }
+                ui.label(format!("{} {:.2}", currency, amount));
+                ui.end_row();
+            }
+
+            if totals.rounding != 0.0 {
+                ui.label("Rounding difference");
+                ui.label(format!("{} {:+.2}", currency, totals.rounding));
+                ui.end_row();
+            }
+
+            ui.strong("Grand Total");
+            ui.strong(format!("{} {:.2}", currency, totals.grand_total));
+            ui.end_row();
+        });
+}
+
+fn format_file_size(bytes: u64) -> String {
+    if bytes >= 1024 * 1024 {
+        format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+    } else {
+        format!("{:.1} KB", bytes as f64 / 1024.0)
+    }
+}
+
+/// Client combo with a typeahead filter on name/city, arrow-key navigation and Enter to select.
+/// Returns true when a client was picked.
+fn client_picker(
+    ui: &mut egui::Ui,
+    client_id: &mut u64,
+    clients: &[Client],
+    selected_text: &str,
+    query: &mut String,
+    highlighted: &mut usize,
+) -> bool {
+    let mut picked = false;
+
+    egui::ComboBox::from_id_salt("client_select")
+        .selected_text(selected_text)
+        .close_behavior(egui::PopupCloseBehavior::CloseOnClickOutside)
+        .show_ui(ui, |ui| {
+            let response = ui.add(
+                egui::TextEdit::singleline(query).hint_text("Search name or city…"),
+            );
+            if !response.has_focus() && !response.lost_focus() {
+                response.request_focus();
+            }
+            if response.changed() {
+                *highlighted = 0;
+            }
+
+            let needle = query.to_lowercase();
+            let matches: Vec<&Client> = clients
+                .iter()
+                .filter(|c| {
+                    needle.is_empty()
+                        || c.name.to_lowercase().contains(&needle)
+                        || c.address.city.to_lowercase().contains(&needle)
+                })
+                .collect();
+
+            if matches.is_empty() {
+                ui.weak("No matching clients");
+                return;
+            }
+
+            let (down, up, enter) = ui.input(|i| {
+                (
+                    i.key_pressed(egui::Key::ArrowDown),
+                    i.key_pressed(egui::Key::ArrowUp),
+                    i.key_pressed(egui::Key::Enter),
+                )
+            });
+            if down {
+                *highlighted = (*highlighted + 1).min(matches.len() - 1);
+            }
+            if up {
+                *highlighted = highlighted.saturating_sub(1);
+            }
+            *highlighted = (*highlighted).min(matches.len() - 1);
+
+            let mut chosen: Option<u64> = None;
+            for (idx, client) in matches.iter().enumerate() {
+                let label = format!("{} ({})", client.name, client.address.city);
+                let item = ui.selectable_label(idx == *highlighted, label);
+                if idx == *highlighted && (down || up) {
+                    item.scroll_to_me(None);
+                }
+                if item.clicked() {
+                    chosen = Some(client.id);
+                }
+            }
+            if enter {
+                chosen = Some(matches[*highlighted].id);
+            }
+
+            if let Some(id) = chosen {
+                *client_id = id;
+                query.clear();
+                *highlighted = 0;
+                picked = true;
+                ui.close();
+            }
+        });
+
+    picked
+}
+
+fn show_item_templates_tab(app: &mut BillManagerApp, ui: &mut egui::Ui) {
+    ui.heading("Item Templates");
+    ui.separator();
+
+    if ui.button("➕ Add Template").clicked() {
+        app.open_template_form(ItemTemplate {
+            vat_rate: app.default_vat_rate,
+            ..ItemTemplate::default()
+        });
+    }
+
+    ui.add_space(10.0);
 
+    egui::ScrollArea::vertical().show(ui, |ui| {
+        for template in app.item_templates.clone().iter() {
+            ui.group(|ui| {
                 ui.horizontal(|ui| {
-                    ui.label("Building Number:");
-                    let mut building = client.address.building_number.clone().unwrap_or_default();
-                    ui.text_edit_singleline(&mut building);
-                    client.address.building_number = Some(building);
+                    ui.vertical(|ui| {
+                        ui.strong(&template.item_type);
+                        let unit = if template.unit.is_empty() { String::new() } else { format!(" / {}", template.unit) };
+                        ui.label(format!("{} {:.2}{}, VAT {}%", app.default_currency, template.unit_price, unit, template.vat_rate));
+                        let usage = match template.last_used {
+                            Some(last_used) => format!("Used {} times, last on {}", template.use_count, last_used.format("%Y-%m-%d")),
+                            None => "Not used yet".to_string(),
+                        };
+                        ui.weak(usage);
+                    });
+
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui.button("🗑 Delete").clicked() {
+                            app.delete_item_template(template.id);
+                        }
+                        if ui.button("⧉ Duplicate").on_hover_text("Edit a copy, e.g. with another price").clicked() {
+                            app.duplicate_item_template(template);
+                        }
+                        if ui.button("✏ Edit").clicked() {
+                            app.open_template_form(template.clone());
+                        }
+                    });
                 });
+            });
+            ui.add_space(5.0);
+        }
+
+        ui.add_space(10.0);
+        show_discount_templates(app, ui);
+
+        ui.add_space(10.0);
+        show_bill_templates(app, ui);
+    });
+}
+
+/// Saved invoice skeletons; they are edited in their own dialog
+fn show_bill_templates(app: &mut BillManagerApp, ui: &mut egui::Ui) {
+    ui.heading("Bill Templates");
+    ui.separator();
+
+    if ui.button("➕ Add Bill Template").clicked() {
+        app.editing_bill_template = Some(BillTemplate {
+            items: vec![BillItem::with_vat_rate(app.default_vat_rate)],
+            ..BillTemplate::default()
+        });
+        app.bill_template_error = None;
+    }
+
+    let mut edit: Option<BillTemplate> = None;
+    let mut delete: Option<u64> = None;
+
+    for template in &app.bill_templates {
+        ui.horizontal(|ui| {
+            ui.strong(&template.name);
+            ui.label(match template.items.len() {
+                1 => "1 line".to_string(),
+                n => format!("{} lines", n),
+            });
+            ui.weak(format!("due in {} days", template.payment_days));
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if ui.button("🗑 Delete").clicked() {
+                    delete = Some(template.id);
+                }
+                if ui.button("✏ Edit").clicked() {
+                    edit = Some(template.clone());
+                }
+            });
+        });
+    }
+
+    if let Some(template) = edit {
+        app.editing_bill_template = Some(template);
+        app.bill_template_error = None;
+    }
+    if let Some(id) = delete {
+        app.delete_bill_template(id);
+    }
+}
+
+fn show_bill_template_dialog(app: &mut BillManagerApp, ctx: &egui::Context) {
+    let Some(template) = &mut app.editing_bill_template else {
+        return;
+    };
+    let mut save = false;
+    let mut cancel = false;
+    let quantity_decimals = app.quantity_decimals;
+    let default_vat_rate = app.default_vat_rate;
+
+    egui::Modal::new(egui::Id::new("bill_template")).show(ctx, |ui| {
+        ui.set_width(560.0);
+        ui.heading(if template.id == 0 { "New bill template" } else { "Edit bill template" });
+        if let Some(error) = &app.bill_template_error {
+            ui.colored_label(egui::Color32::RED, error);
+        }
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            required_label(ui, "Name:");
+            ui.text_edit_singleline(&mut template.name);
+        });
+        ui.horizontal(|ui| {
+            ui.label("Payment term:");
+            ui.add(egui::DragValue::new(&mut template.payment_days).range(0..=365).suffix(" days"));
+        });
+
+        ui.add_space(5.0);
+        ui.strong("Items");
+        let mut remove: Option<usize> = None;
+        egui::Grid::new("bill_template_items").num_columns(4).spacing([8.0, 4.0]).show(ui, |ui| {
+            for (idx, item) in template.items.iter_mut().enumerate() {
+                ui.add(egui::TextEdit::singleline(&mut item.item_type).hint_text("Description").desired_width(220.0));
+                ui.add(egui::DragValue::new(&mut item.quantity).speed(0.1).range(0.0..=f64::MAX).max_decimals(quantity_decimals));
+                ui.add(egui::DragValue::new(&mut item.unit_price).speed(0.1).fixed_decimals(2));
+                if ui.small_button("🗑").clicked() {
+                    remove = Some(idx);
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(idx) = remove {
+            template.items.remove(idx);
+        }
+        if ui.button("➕ Add line").clicked() {
+            template.items.push(BillItem::with_vat_rate(default_vat_rate));
+        }
+
+        ui.add_space(5.0);
+        ui.label("Notes:");
+        ui.add(egui::TextEdit::multiline(&mut template.notes).desired_rows(3).desired_width(f32::INFINITY));
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button("💾 Save").clicked() {
+                save = true;
+            }
+            if ui.button("❌ Cancel").clicked() {
+                cancel = true;
+            }
+        });
+    });
+
+    if save && let Some(template) = app.editing_bill_template.clone() {
+        match app.save_bill_template(template) {
+            Ok(()) => {
+                app.editing_bill_template = None;
+                app.bill_template_error = None;
+                app.show_toast("Bill template saved".to_string());
+            }
+            Err(e) => app.bill_template_error = Some(e),
+        }
+    }
+    if cancel {
+        app.editing_bill_template = None;
+        app.bill_template_error = None;
+    }
+}
+
+/// Named discounts (promotions) that can be applied to a whole bill, edited inline
+fn show_discount_templates(app: &mut BillManagerApp, ui: &mut egui::Ui) {
+    ui.heading("Discounts");
+    ui.separator();
+
+    if app.editing_discount.is_none() && ui.button("➕ Add Discount").clicked() {
+        app.editing_discount = Some(DiscountTemplate::default());
+        app.discount_error = None;
+    }
+
+    let mut save = false;
+    let mut cancel = false;
+    let mut edit: Option<DiscountTemplate> = None;
+    let mut delete: Option<u64> = None;
+
+    if let Some(template) = app.editing_discount.as_mut() {
+        ui.group(|ui| {
+            if let Some(error) = &app.discount_error {
+                ui.colored_label(egui::Color32::RED, error);
+            }
+            ui.horizontal(|ui| {
+                required_label(ui, "Name:");
+                ui.text_edit_singleline(&mut template.name);
+
+                let is_percent = matches!(template.discount, Discount::Percent(_));
+                egui::ComboBox::from_id_salt("discount_kind")
+                    .selected_text(if is_percent { "Percent" } else { "Amount" })
+                    .show_ui(ui, |ui| {
+                        let value = match template.discount {
+                            Discount::Percent(value) | Discount::Amount(value) => value,
+                        };
+                        if ui.selectable_label(is_percent, "Percent").clicked() {
+                            template.discount = Discount::Percent(value);
+                        }
+                        if ui.selectable_label(!is_percent, "Amount").clicked() {
+                            template.discount = Discount::Amount(value);
+                        }
+                    });
+
+                match &mut template.discount {
+                    Discount::Percent(value) => {
+                        ui.add(egui::DragValue::new(value).speed(0.1).range(0.0..=100.0).suffix("%"));
+                    }
+                    Discount::Amount(value) => {
+                        ui.add(egui::DragValue::new(value).speed(0.1).range(0.0..=f64::MAX).fixed_decimals(2));
+                    }
+                }
+
+                if ui.button("💾 Save").clicked() {
+                    save = true;
+                }
+                if ui.button("❌ Cancel").clicked() {
+                    cancel = true;
+                }
+            });
+        });
+    }
+
+    for template in &app.discount_templates {
+        if app.editing_discount.as_ref().is_some_and(|t| t.id == template.id) {
+            continue;
+        }
+        ui.horizontal(|ui| {
+            ui.strong(&template.name);
+            ui.label(template.discount.to_string());
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if ui.button("🗑 Delete").clicked() {
+                    delete = Some(template.id);
+                }
+                if ui.button("✏ Edit").clicked() {
+                    edit = Some(template.clone());
+                }
+            });
+        });
+    }
+
+    if save && let Some(template) = app.editing_discount.clone() {
+        match app.save_discount_template(template) {
+            Ok(()) => {
+                app.editing_discount = None;
+                app.discount_error = None;
+            }
+            Err(e) => app.discount_error = Some(e),
+        }
+    }
+    if cancel {
+        app.editing_discount = None;
+        app.discount_error = None;
+    }
+    if let Some(template) = edit {
+        app.editing_discount = Some(template);
+        app.discount_error = None;
+    }
+    if let Some(id) = delete {
+        app.delete_discount_template(id);
+    }
+}
+
+fn show_template_form_window(app: &mut BillManagerApp, ctx: &egui::Context) {
+    let mut open = true;
+    let mut close_requested = false;
+    let mut save_template = false;
+    let currency_prefix = format!("{} ", app.default_currency);
+    let window = egui::Window::new("Item Template")
+        .open(&mut open)
+        .resizable(true)
+        .show(ctx, |ui| {
+            if let Some(template) = &mut app.editing_template {
+                if let Some(error) = &app.template_error {
+                    ui.colored_label(egui::Color32::RED, error);
+                    ui.separator();
+                }
 
                 ui.horizontal(|ui| {
-                    ui.label("Postal Code:");
-                    ui.text_edit_singleline(&mut client.address.postal_code);
+                    required_label(ui, "Type:");
+                    ui.text_edit_singleline(&mut template.item_type);
                 });
 
                 ui.horizontal(|ui| {
-                    ui.label("City:");
-                    ui.text_edit_singleline(&mut client.address.city);
+                    ui.label("Unit Price:");
+                    if ui.add(price_drag_value(&mut template.unit_price).prefix(currency_prefix.as_str())).changed() {
+                        template.unit_price = round_cents(template.unit_price);
+                    }
+                    ui.label("per");
+                    ui.add(egui::TextEdit::singleline(&mut template.unit).hint_text("unit, e.g. h").desired_width(60.0));
                 });
 
                 ui.horizontal(|ui| {
-                    ui.label("Country:");
-                    ui.text_edit_singleline(&mut client.address.country);
+                    ui.label("VAT:");
+                    ui.add(egui::DragValue::new(&mut template.vat_rate).speed(0.1).range(0.0..=100.0).suffix("%"));
                 });
 
+                if app.template_source_line.is_some() {
+                    ui.checkbox(&mut app.update_line_from_template, "Also update the bill line with this price, unit and VAT rate");
+                }
+
                 ui.separator();
-                ui.strong("Billing Address");
 
                 ui.horizontal(|ui| {
-                    ui.label("Name:");
-                    ui.text_edit_singleline(&mut client.billing_address.name);
-                });
+                    if ui.button("💾 Save").clicked() {
+                        save_template = true;
+                    }
 
-                ui.horizontal(|ui| {
-                    ui.label("Street:");
-                    let mut street = client.billing_address.street.clone().unwrap_or_default();
-                    ui.text_edit_singleline(&mut street);
-                    client.billing_address.street = Some(street);
+                    if ui.button("❌ Cancel").clicked() {
+                        close_requested = true;
+                    }
                 });
+            }
+        });
 
-                ui.horizontal(|ui| {
-                    ui.label("Building Number:");
-                    let mut building = client.billing_address.building_number.clone().unwrap_or_default();
-                    ui.text_edit_singleline(&mut building);
-                    client.billing_address.building_number = Some(building);
-                });
+    let (enter, escape) = form_shortcuts(ctx, window.map(|w| w.response.layer_id));
+    save_template |= enter;
+    close_requested |= escape;
 
-                ui.horizontal(|ui| {
-                    ui.label("Postal Code:");
-                    ui.text_edit_singleline(&mut client.billing_address.postal_code);
-                });
+    if save_template && let Some(template) = app.editing_template.clone() {
+        match template.validate() {
+            Ok(()) => {
+                if app.update_line_from_template {
+                    app.apply_template_to_source_line(&template);
+                }
+                if template.id == 0 {
+                    app.add_item_template(template);
+                } else {
+                    app.update_item_template(template);
+                }
+                app.close_form(FormKind::Template);
+            }
+            Err(problems) => app.template_error = Some(problems.join("\n")),
+        }
+    }
 
-                ui.horizontal(|ui| {
-                    ui.label("City:");
-                    ui.text_edit_singleline(&mut client.billing_address.city);
-                });
+    if close_requested || !open {
+        app.request_close_form(FormKind::Template);
+    }
+}
 
-                ui.horizontal(|ui| {
-                    ui.label("Country:");
-                    ui.text_edit_singleline(&mut client.billing_address.country);
-                });
+/// How long a toast stays on screen
+const TOAST_DURATION: std::time::Duration = std::time::Duration::from_secs(6);
 
-                ui.separator();
+fn show_toast(app: &mut BillManagerApp, ctx: &egui::Context) {
+    let Some((message, raised_at)) = &app.toast else {
+        return;
+    };
+
+    let remaining = TOAST_DURATION.saturating_sub(raised_at.elapsed());
+    if remaining.is_zero() {
+        app.toast = None;
+        return;
+    }
 
+    let mut dismissed = false;
+    egui::Area::new(egui::Id::new("toast"))
+        .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-10.0, -10.0))
+        .order(egui::Order::Foreground)
+        .show(ctx, |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                ui.set_max_width(350.0);
                 ui.horizontal(|ui| {
-                    if ui.button("💾 Save").clicked() {
-                        let client = app.editing_client.take().unwrap();
-                        if client.id == 0 {
-                            app.add_client(client);
-                        } else {
-                            app.update_client(client);
-                        }
-                        app.show_client_form = false;
+                    ui.colored_label(egui::Color32::from_rgb(200, 120, 0), message);
+                    if ui.small_button("✖").clicked() {
+                        dismissed = true;
                     }
+                });
+            });
+        });
+
+    if dismissed {
+        app.toast = None;
+    } else {
+        ctx.request_repaint_after(remaining);
+    }
+}
+
+/// Writes a bill's PDF to `path` and opens it in the default viewer
+fn write_pdf_and_open(app: &mut BillManagerApp, bill_id: u64, path: &std::path::Path) {
+    match app.write_pdf_to_path(bill_id, path) {
+        Ok(()) => {
+            app.show_toast(format!("PDF saved to {}", path.display()));
+            if let Err(e) = open::that(path) {
+                app.bill_error = Some(format!("Failed to open {}: {}", path.display(), e));
+            }
+        }
+        Err(e) => {
+            app.bill_error = Some(e);
+        }
+    }
+}
+
+fn show_overwrite_pdf_dialog(app: &mut BillManagerApp, ctx: &egui::Context) {
+    let Some((bill_id, path)) = app.pending_pdf_overwrite.clone() else {
+        return;
+    };
+    let mut overwrite = false;
+    let mut cancel = false;
 
-                    if ui.button("❌ Cancel").clicked() {
-                        app.editing_client = None;
-                        app.show_client_form = false;
-                    }
-                });
+    egui::Modal::new(egui::Id::new("overwrite_pdf")).show(ctx, |ui| {
+        ui.heading("Replace existing file?");
+        ui.label(format!("{} already exists.", path.display()));
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button("Replace").clicked() {
+                overwrite = true;
+            }
+            if ui.button("Cancel").clicked() {
+                cancel = true;
             }
         });
+    });
 
-    if !open {
-        app.editing_client = None;
-        app.show_client_form = false;
+    if overwrite {
+        app.pending_pdf_overwrite = None;
+        write_pdf_and_open(app, bill_id, &path);
+    } else if cancel {
+        app.pending_pdf_overwrite = None;
     }
 }
 
-fn show_bill_form_window(app: &mut BillManagerApp, ctx: &egui::Context) {
-    let mut open = true;
-    let mut save_bill = false;
-    let mut cancel_bill = false;
-
-    // Get data before borrowing mutably
-    let client_name = if let Some(bill) = &app.editing_bill {
-        app.get_client(bill.client_id)
-            .map(|c| c.name.clone())
-            .unwrap_or_else(|| "Select Client".to_string())
-    } else {
-        "Select Client".to_string()
+fn show_email_preview_dialog(app: &mut BillManagerApp, ctx: &egui::Context) {
+    let Some((bill_id, email)) = &mut app.pending_email else {
+        return;
     };
-    let clients = app.clients.clone();
-    let item_templates = app.item_templates.clone();
+    let mut send = false;
+    let mut cancel = false;
 
-    egui::Window::new("Bill Details")
-        .open(&mut open)
-        .resizable(true)
-        .default_width(600.0)
-        .show(ctx, |ui| {
-            if let Some(bill) = &mut app.editing_bill {
+    egui::Modal::new(egui::Id::new("email_preview")).show(ctx, |ui| {
+        ui.set_width(520.0);
+        ui.heading(format!("Email bill #{}", bill_id));
+        ui.separator();
 
-                egui::ScrollArea::vertical().show(ui, |ui| {
-                    // Display error message if present
-                    if let Some(error) = &app.bill_error {
-                        ui.colored_label(egui::Color32::RED, error);
-                        ui.separator();
-                    }
+        egui::Grid::new("email_preview_grid").num_columns(2).show(ui, |ui| {
+            ui.label("To:");
+            ui.label(&email.to);
+            ui.end_row();
+            if !email.cc.is_empty() {
+                ui.label("CC:");
+                ui.label(email.cc.join(", "));
+                ui.end_row();
+            }
+            if !email.bcc.is_empty() {
+                ui.label("BCC:");
+                ui.label(email.bcc.join(", "));
+                ui.end_row();
+            }
+            if let Some(reply_to) = &email.reply_to {
+                ui.label("Reply-To:");
+                ui.label(reply_to);
+                ui.end_row();
+            }
+            ui.label("Subject:");
+            ui.add(egui::TextEdit::singleline(&mut email.subject).desired_width(f32::INFINITY));
+            ui.end_row();
+            ui.label("Attachment:");
+            ui.label(format!("📎 {} ({})", email.attachment_name, format_file_size(email.attachment.len() as u64)));
+            ui.end_row();
+        });
 
-                    ui.horizontal(|ui| {
-                        ui.label("Client:");
-                        egui::ComboBox::from_id_salt("client_select")
-                            .selected_text(&client_name)
-                            .show_ui(ui, |ui| {
-                                for client in &clients {
-                                    if ui.selectable_value(
-                                        &mut bill.client_id,
-                                        client.id,
-                                        &client.name,
-                                    ).clicked() {
-                                        // Clear error when client is selected
-                                        app.bill_error = None;
-                                    }
-                                }
-                            });
-                    });
+        ui.add_space(4.0);
+        egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+            ui.add(egui::TextEdit::multiline(&mut email.body).desired_width(f32::INFINITY).desired_rows(10));
+        });
+        ui.weak("Changes here only apply to this email");
 
-                    ui.horizontal(|ui| {
-                        ui.label("Bill Date:");
-                        let date_str = bill.date.format("%Y-%m-%d").to_string();
-                        ui.label(&date_str);
-                    });
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button("✉ Send").clicked() {
+                send = true;
+            }
+            if ui.button("Cancel").clicked() || ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                cancel = true;
+            }
+        });
+    });
 
-                    ui.horizontal(|ui| {
-                        ui.label("Due Date:");
-                        let mut due_date_str = bill.due_date.format("%Y-%m-%d").to_string();
-                        if ui.text_edit_singleline(&mut due_date_str).changed() {
-                            // Try to parse the date
-                            if let Ok(naive_date) = chrono::NaiveDate::parse_from_str(&due_date_str, "%Y-%m-%d") {
-                                bill.due_date = naive_date.and_hms_opt(0, 0, 0)
-                                    .unwrap()
-                                    .and_local_timezone(chrono::Local)
-                                    .unwrap();
-                            }
-                        }
-                        if ui.button("+7d").clicked() {
-                            bill.due_date = bill.due_date + chrono::Duration::days(7);
-                        }
-                        if ui.button("+30d").clicked() {
-                            bill.due_date = bill.due_date + chrono::Duration::days(30);
-                        }
-                    });
+    if send {
+        app.send_pending_email();
+    } else if cancel {
+        app.pending_email = None;
+    }
+}
 
-                    ui.horizontal(|ui| {
-                        ui.label("Reference:");
-                        ui.text_edit_singleline(&mut bill.reference);
-                        if ui.button("🔄 Generate").clicked() {
-                            let year = chrono::Local::now().year();
-                            let bill_id = if bill.id == 0 { app.next_bill_id } else { bill.id };
-                            bill.reference = Bill::generate_scor_reference(bill_id, bill.client_id, year);
-                        }
-                    });
+fn show_setup_wizard(app: &mut BillManagerApp, ctx: &egui::Context) {
+    let Some(mut wizard) = app.setup_wizard.take() else {
+        return;
+    };
+    let mut skip = false;
+    let mut finish = false;
 
-                    ui.horizontal(|ui| {
-                        ui.label("IBAN:");
-                        ui.text_edit_singleline(&mut bill.iban);
+    egui::Modal::new(egui::Id::new("setup_wizard")).show(ctx, |ui| {
+        ui.set_width(420.0);
+        ui.heading("Welcome to Bill Manager");
+        ui.weak(format!("Step {} of {}", wizard.step + 1, SetupWizard::STEPS));
+        ui.separator();
 
-                        // Show validation status
-                        if !bill.iban.is_empty() {
-                            if validate_iban(&bill.iban) {
-                                ui.colored_label(egui::Color32::from_rgb(60, 150, 60), "✓ Valid");
-                            } else {
-                                ui.colored_label(egui::Color32::from_rgb(180, 60, 60), "✗ Invalid IBAN");
+        match wizard.step {
+            0 => {
+                ui.label("Your business appears as creditor on every invoice and QR bill.");
+                egui::Grid::new("setup_creditor").num_columns(2).show(ui, |ui| {
+                    required_label(ui, "Name:");
+                    ui.text_edit_singleline(&mut wizard.creditor.name);
+                    ui.end_row();
+                    ui.label("Street:");
+                    ui.text_edit_singleline(wizard.creditor.street.get_or_insert_with(String::new));
+                    ui.end_row();
+                    ui.label("Number:");
+                    ui.text_edit_singleline(wizard.creditor.building_number.get_or_insert_with(String::new));
+                    ui.end_row();
+                    required_label(ui, "Postal Code:");
+                    ui.text_edit_singleline(&mut wizard.creditor.postal_code);
+                    ui.end_row();
+                    required_label(ui, "City:");
+                    ui.text_edit_singleline(&mut wizard.creditor.city);
+                    ui.end_row();
+                    required_label(ui, "Country:");
+                    ui.text_edit_singleline(&mut wizard.creditor.country);
+                    ui.end_row();
+                });
+            }
+            1 => {
+                ui.label("Payments go to this account. New bills use the currency and language below.");
+                egui::Grid::new("setup_payment").num_columns(2).show(ui, |ui| {
+                    required_label(ui, "IBAN:");
+                    ui.text_edit_singleline(&mut wizard.iban);
+                    ui.end_row();
+                    ui.label("Currency:");
+                    egui::ComboBox::from_id_salt("setup_currency")
+                        .selected_text(&wizard.currency)
+                        .show_ui(ui, |ui| {
+                            for currency in QR_CURRENCIES {
+                                ui.selectable_value(&mut wizard.currency, currency.to_string(), currency);
                             }
-                        }
-                    });
-
-                    ui.horizontal(|ui| {
-                        ui.label("Status:");
-                        egui::ComboBox::from_id_salt("status_select")
-                            .selected_text(format!("{}", bill.status))
-                            .show_ui(ui, |ui| {
-                                ui.selectable_value(&mut bill.status, BillStatus::Draft, "Draft");
-                                ui.selectable_value(&mut bill.status, BillStatus::Sent, "Sent");
-                                ui.selectable_value(&mut bill.status, BillStatus::Paid, "Paid");
-                                ui.selectable_value(
-                                    &mut bill.status,
-                                    BillStatus::Overdue,
-                                    "Overdue",
-                                );
-                            });
-                    });
-
-                    ui.horizontal(|ui| {
-                        ui.label("Notes:");
-                        ui.text_edit_multiline(&mut bill.notes);
-                    });
-
-                    ui.separator();
-                    ui.strong("Items");
-
-                    let mut item_to_remove: Option<usize> = None;
-                    let items_count = bill.items.len();
+                        });
+                    ui.end_row();
+                    ui.label("Language:");
+                    egui::ComboBox::from_id_salt("setup_language")
+                        .selected_text(format!("{}", wizard.language))
+                        .show_ui(ui, |ui| {
+                            for language in Language::ALL {
+                                ui.selectable_value(&mut wizard.language, language, format!("{}", language));
+                            }
+                        });
+                    ui.end_row();
+                });
+            }
+            _ => {
+                ui.label("Invoices will be issued by:");
+                let creditor = &wizard.creditor;
+                let street = [creditor.street.as_deref(), creditor.building_number.as_deref()]
+                    .into_iter()
+                    .flatten()
+                    .filter(|s| !s.is_empty())
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                ui.strong(&creditor.name);
+                if !street.is_empty() {
+                    ui.label(street);
+                }
+                ui.label(format!("{}-{} {}", creditor.country, creditor.postal_code, creditor.city));
+                ui.add_space(5.0);
+                ui.label(format!("Paid to {}, in {} by default, invoices in {}", wizard.iban.trim(), wizard.currency, wizard.language));
+                ui.add_space(5.0);
+                ui.weak("Everything can be changed later in Settings.");
+            }
+        }
 
-                    for (idx, item) in bill.items.iter_mut().enumerate() {
-                        ui.group(|ui| {
-                            ui.horizontal(|ui| {
-                                ui.label("Type:");
-                                ui.text_edit_singleline(&mut item.item_type);
-
-                                // Add template button if templates exist
-                                if !item_templates.is_empty() {
-                                    egui::ComboBox::from_id_salt(format!("template_{}", idx))
-                                        .selected_text("📋")
-                                        .show_ui(ui, |ui| {
-                                            for template in &item_templates {
-                                                if ui.button(&template.item_type).clicked() {
-                                                    item.item_type = template.item_type.clone();
-                                                    item.unit_price = template.unit_price;
-                                                }
-                                            }
-                                        });
-                                }
-                            });
+        if let Some(error) = &wizard.error {
+            ui.colored_label(egui::Color32::RED, error);
+        }
 
-                            ui.horizontal(|ui| {
-                                ui.label("Quantity:");
-                                ui.add(egui::DragValue::new(&mut item.quantity).speed(0.1));
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button("Skip for now").on_hover_text("Asked again at the next start").clicked() {
+                skip = true;
+            }
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if wizard.step + 1 == SetupWizard::STEPS {
+                    if ui.button("✔ Finish").clicked() {
+                        finish = true;
+                    }
+                } else if ui.button("Next ⏵").clicked() {
+                    let problems = wizard.step_problems();
+                    if problems.is_empty() {
+                        wizard.step += 1;
+                        wizard.error = None;
+                    } else {
+                        wizard.error = Some(problems.join("\n"));
+                    }
+                }
+                if wizard.step > 0 && ui.button("⏴ Back").clicked() {
+                    wizard.step -= 1;
+                    wizard.error = None;
+                }
+            });
+        });
+    });
 
-                                ui.label("Unit Price:");
-                                ui.add(egui::DragValue::new(&mut item.unit_price).speed(0.1));
+    if skip {
+        return;
+    }
+    if finish {
+        match app.complete_setup(&wizard) {
+            Ok(()) => {
+                app.show_toast("Setup saved".to_string());
+                return;
+            }
+            Err(e) => wizard.error = Some(e),
+        }
+    }
+    app.setup_wizard = Some(wizard);
+}
 
-                                ui.label(format!("Total: CHF {:.2}", item.total()));
+fn show_settings_reset_dialog(app: &mut BillManagerApp, ctx: &egui::Context, reset: SettingsReset) {
+    let mut confirm = false;
+    let mut cancel = false;
+
+    egui::Modal::new(egui::Id::new("settings_reset")).show(ctx, |ui| {
+        match reset {
+            SettingsReset::Template => {
+                let path = app.resolved_template_dir().join(crate::pdf::TEMPLATE_FILE);
+                ui.heading("Restore default template?");
+                ui.label(format!("The built-in invoice template will be written to {}.", path.display()));
+                if path.exists() {
+                    ui.label("The current template is kept as a .bak file next to it.");
+                }
+            }
+            SettingsReset::Settings => {
+                ui.heading("Reset settings?");
+                ui.label("Your business address, IBAN, default currency, language, VAT rate, reference type, \
+                    quantity decimals, additional information texts and due-date buttons go back to their defaults.");
+                ui.label("Email, folder and PDF settings are kept.");
+            }
+        }
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button("↺ Reset").clicked() {
+                confirm = true;
+            }
+            if ui.button("Cancel").clicked() {
+                cancel = true;
+            }
+        });
+    });
 
-                                if items_count > 1 && ui.button("🗑").clicked() {
-                                    item_to_remove = Some(idx);
-                                }
-                            });
+    if confirm {
+        app.pending_settings_reset = None;
+        match reset {
+            SettingsReset::Template => match app.restore_default_template() {
+                Ok(path) => app.settings_message = Some(format!("Default template written to {}", path.display())),
+                Err(e) => app.settings_message = Some(e),
+            },
+            SettingsReset::Settings => {
+                app.reset_settings();
+                app.show_toast("Settings reset to defaults".to_string());
+            }
+        }
+    } else if cancel {
+        app.pending_settings_reset = None;
+    }
+}
 
-                            // Note field
-                            ui.horizontal(|ui| {
-                                ui.label("Note:");
-                                ui.text_edit_singleline(&mut item.note);
-                            });
-                        });
-                    }
+fn show_client_delete_dialog(app: &mut BillManagerApp, ctx: &egui::Context) {
+    let Some(ids) = app.pending_client_delete.clone() else {
+        return;
+    };
+    let with_bills = ids.iter().filter(|id| app.client_has_bills(**id)).count();
+    let mut confirm = false;
+    let mut cancel = false;
+
+    egui::Modal::new(egui::Id::new("client_delete")).show(ctx, |ui| {
+        ui.heading("Delete clients?");
+        ui.label(format!("{} selected clients will be deleted.", ids.len() - with_bills));
+        if with_bills > 0 {
+            ui.label(format!("{} of the selected clients have bills and will be kept.", with_bills));
+        }
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button("🗑 Delete").clicked() {
+                confirm = true;
+            }
+            if ui.button("Cancel").clicked() {
+                cancel = true;
+            }
+        });
+    });
 
-                    if let Some(idx) = item_to_remove {
-                        bill.items.remove(idx);
-                    }
+    if confirm {
+        app.pending_client_delete = None;
+        match app.delete_clients(&ids) {
+            Ok(report) => app.show_toast(report),
+            Err(e) => app.show_toast(e),
+        }
+    } else if cancel {
+        app.pending_client_delete = None;
+    }
+}
 
-                    ui.horizontal(|ui| {
-                        if ui.button("➕ Add Item").clicked() {
-                            bill.items.push(BillItem::default());
-                        }
+fn show_client_import_dialog(app: &mut BillManagerApp, ctx: &egui::Context) {
+    let Some((client, existing_id)) = app.pending_client_import.first().cloned() else {
+        return;
+    };
+    let existing_name = app.get_client(existing_id)
+        .map(|c| c.name.clone())
+        .unwrap_or_default();
+    let mut merge = false;
+    let mut create = false;
+    let mut cancel = false;
+
+    egui::Modal::new(egui::Id::new("client_import")).show(ctx, |ui| {
+        ui.heading("Client already exists?");
+        ui.label(format!(
+            "{} ({} {}) matches the existing client {}.",
+            client.name, client.address.postal_code, client.address.city, existing_name
+        ));
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button("Merge into existing").on_hover_text("Update the existing client; empty imported fields keep their values").clicked() {
+                merge = true;
+            }
+            if ui.button("Create new").clicked() {
+                create = true;
+            }
+            if ui.button("Cancel").clicked() {
+                cancel = true;
+            }
+        });
+    });
 
-                        if !item_templates.is_empty() {
-                            egui::ComboBox::from_id_salt("add_from_template")
-                                .selected_text("📋 Add from Template")
-                                .show_ui(ui, |ui| {
-                                    for template in &item_templates {
-                                        if ui.button(&template.item_type).clicked() {
-                                            bill.items.push(template.to_bill_item());
-                                        }
-                                    }
-                                });
-                        }
-                    });
+    if merge {
+        app.pending_client_import.remove(0);
+        app.merge_client(existing_id, client);
+        app.show_toast(format!("Merged into client {}", existing_name));
+    } else if create {
+        app.pending_client_import.remove(0);
+        let name = client.name.clone();
+        app.add_client(client);
+        app.show_toast(format!("Imported client {}", name));
+    } else if cancel {
+        app.pending_client_import.remove(0);
+    }
+}
 
-                    ui.separator();
-                    ui.strong(format!("Total: CHF {:.2}", bill.total()));
+fn show_record_payment_dialog(app: &mut BillManagerApp, ctx: &egui::Context) {
+    let Some(mut payment) = app.pending_payment.take() else {
+        return;
+    };
+    let Some(bill) = app.bills.iter().find(|b| b.id == payment.bill_id) else {
+        return;
+    };
+    let currency = bill.currency.clone();
+    let outstanding = app.outstanding_amount(bill);
+    let mut confirm = false;
+    let mut cancel = false;
+
+    egui::Modal::new(egui::Id::new("record_payment")).show(ctx, |ui| {
+        ui.set_width(360.0);
+        ui.heading(format!("💰 Record payment for bill #{}", payment.bill_id));
+        ui.label(format!("Outstanding: {} {:.2}", currency, outstanding));
+        ui.separator();
 
-                    ui.separator();
+        egui::Grid::new("record_payment_grid").num_columns(2).show(ui, |ui| {
+            ui.label("Amount:");
+            ui.horizontal(|ui| {
+                ui.label(&currency);
+                ui.add(egui::DragValue::new(&mut payment.amount).speed(0.05).range(0.0..=f64::MAX).fixed_decimals(2));
+            });
+            ui.end_row();
+            ui.label("Date:");
+            ui.add(egui_extras::DatePickerButton::new(&mut payment.paid_on).id_salt("payment_date"));
+            ui.end_row();
+            ui.label("Note:");
+            ui.text_edit_singleline(&mut payment.note);
+            ui.end_row();
+        });
 
-                    ui.horizontal(|ui| {
-                        if ui.button("💾 Save").clicked() {
-                            save_bill = true;
-                        }
+        let valid = payment.amount > 0.0;
+        if !valid {
+            ui.colored_label(egui::Color32::RED, "The amount must be greater than 0");
+        } else if payment.amount > outstanding + 0.005 {
+            ui.colored_label(
+                egui::Color32::from_rgb(200, 120, 0),
+                format!("⚠ Overpaid by {} {:.2}", currency, round_cents(payment.amount - outstanding.max(0.0))),
+            );
+        }
 
-                        if ui.button("❌ Cancel").clicked() {
-                            cancel_bill = true;
-                        }
-                    });
-                });
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.add_enabled(valid, egui::Button::new("✔ Record")).clicked() {
+                confirm = true;
+            }
+            if ui.button("Cancel").clicked() || ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                cancel = true;
             }
         });
+    });
 
-    if save_bill {
-        let bill = app.editing_bill.as_ref().unwrap();
-
-        // Validate that a client is selected
-        if bill.client_id == 0 || app.get_client(bill.client_id).is_none() {
-            // Show error message - keep the bill form open
-            app.bill_error = Some("Please select a client before saving the bill.".to_string());
-        } else if !bill.iban.is_empty() && !validate_iban(&bill.iban) {
-            // Validate IBAN if provided
-            app.bill_error = Some("Invalid IBAN format. Please correct the IBAN before saving.".to_string());
-        } else {
-            // Valid client selected and IBAN is valid, proceed with save
-            app.bill_error = None;
-            let bill = app.editing_bill.take().unwrap();
-            if bill.id == 0 {
-                app.add_bill(bill);
-            } else {
-                app.update_bill(bill);
-            }
-            app.show_bill_form = false;
+    if confirm {
+        let bill_id = payment.bill_id;
+        match app.record_payment(payment) {
+            Ok(true) => app.show_toast(format!("Payment recorded, bill #{} is paid", bill_id)),
+            Ok(false) => app.show_toast(format!("Payment recorded for bill #{}", bill_id)),
+            Err(e) => app.bill_error = Some(e),
         }
-    }
-
-    if cancel_bill || !open {
-        app.editing_bill = None;
-        app.show_bill_form = false;
-        app.bill_error = None;
+    } else if !cancel {
+        app.pending_payment = Some(payment);
     }
 }
 
-fn show_item_templates_tab(app: &mut BillManagerApp, ui: &mut egui::Ui) {
-    ui.heading("Item Templates");
-    ui.separator();
+fn show_reconcile_report_dialog(app: &mut BillManagerApp, ctx: &egui::Context) {
+    let Some(mut report) = app.reconcile_report.take() else {
+        return;
+    };
+    let mut confirm = false;
+    let mut cancel = false;
+
+    // Bills a payment can still be assigned to by hand
+    let candidates: Vec<(u64, String)> = app.bills
+        .iter()
+        .filter(|b| !matches!(b.status, BillStatus::Paid | BillStatus::WrittenOff))
+        .map(|b| {
+            let client_name = app.get_client(b.client_id).map(|c| c.name.as_str()).unwrap_or("Unknown Client");
+            (b.id, format!("#{} {} – {} {:.2}", b.id, client_name, b.currency, app.outstanding_amount(b)))
+        })
+        .collect();
+
+    egui::Modal::new(egui::Id::new("reconcile_report")).show(ctx, |ui| {
+        ui.set_max_width(900.0);
+        ui.heading("Review bank statement");
+        if !report.statement_id.is_empty() {
+            ui.label(format!("Statement {}", report.statement_id));
+        }
+        if report.already_imported > 0 {
+            ui.label(format!("{} entries were imported before and are skipped", report.already_imported));
+        }
+        if report.proposals.is_empty() {
+            ui.label("No new credits in this statement.");
+        }
 
-    if ui.button("➕ Add Template").clicked() {
-        app.editing_template = Some(ItemTemplate::default());
-        app.show_template_form = true;
-    }
+        egui::ScrollArea::vertical().max_height(420.0).show(ui, |ui| {
+            egui::Grid::new("reconcile_grid").striped(true).num_columns(7).show(ui, |ui| {
+                ui.label("");
+                ui.strong("Booked");
+                ui.strong("Amount");
+                ui.strong("Reference / payer");
+                ui.strong("Bill");
+                ui.strong("Match");
+                ui.strong("");
+                ui.end_row();
+
+                for (idx, proposal) in report.proposals.iter_mut().enumerate() {
+                    let entry = &proposal.entry;
+                    ui.add_enabled(proposal.bill_id.is_some(), egui::Checkbox::without_text(&mut proposal.accepted));
+                    ui.label(entry.booking_date.map(|d| d.format("%Y-%m-%d").to_string()).unwrap_or_default());
+                    ui.label(format!("{} {:.2}", entry.currency, entry.amount));
+                    let details = [entry.reference.as_deref().unwrap_or(""), &entry.debtor, &entry.remittance]
+                        .into_iter()
+                        .filter(|s| !s.is_empty())
+                        .collect::<Vec<_>>()
+                        .join(" – ");
+                    ui.add(egui::Label::new(&details).truncate()).on_hover_text(&details);
+
+                    let selected_text = proposal.bill_id
+                        .map(|id| candidates
+                            .iter()
+                            .find(|(c, _)| *c == id)
+                            .map(|(_, label)| label.clone())
+                            .unwrap_or_else(|| format!("#{}", id)))
+                        .unwrap_or_else(|| "Assign…".to_string());
+                    let mut assigned = proposal.bill_id;
+                    egui::ComboBox::from_id_salt(("reconcile_bill", idx))
+                        .width(220.0)
+                        .selected_text(selected_text)
+                        .show_ui(ui, |ui| {
+                            for (id, label) in &candidates {
+                                ui.selectable_value(&mut assigned, Some(*id), label);
+                            }
+                        });
+                    if assigned != proposal.bill_id
+                        && let Some(bill) = assigned.and_then(|id| app.bills.iter().find(|b| b.id == id))
+                    {
+                        proposal.bill_id = assigned;
+                        proposal.confidence = MatchConfidence::Manual;
+                        proposal.warning = app.payment_warning(&proposal.entry, bill, app.outstanding_amount(bill));
+                        proposal.accepted = true;
+                    }
 
-    ui.add_space(10.0);
+                    ui.label(proposal.confidence.to_string());
+                    match &proposal.warning {
+                        Some(warning) => ui.colored_label(egui::Color32::from_rgb(200, 120, 0), format!("⚠ {}", warning)),
+                        None => ui.label(""),
+                    };
+                    ui.end_row();
+                }
+            });
+        });
 
-    egui::ScrollArea::vertical().show(ui, |ui| {
-        for template in app.item_templates.clone().iter() {
-            ui.group(|ui| {
-                ui.horizontal(|ui| {
-                    ui.vertical(|ui| {
-                        ui.strong(&template.item_type);
-                        ui.label(format!("CHF {:.2}", template.unit_price));
-                    });
+        ui.separator();
+        let accepted = report.proposals.iter().filter(|p| p.accepted && p.bill_id.is_some()).count();
+        ui.horizontal(|ui| {
+            if ui.add_enabled(accepted > 0, egui::Button::new(format!("✔ Record {} payments", accepted))).clicked() {
+                confirm = true;
+            }
+            if ui.button("Cancel").clicked() {
+                cancel = true;
+            }
+        });
+    });
 
-                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                        if ui.button("🗑 Delete").clicked() {
-                            app.delete_item_template(template.id);
-                        }
-                        if ui.button("✏ Edit").clicked() {
-                            app.editing_template = Some(template.clone());
-                            app.show_template_form = true;
-                        }
-                    });
-                });
-            });
-            ui.add_space(5.0);
+    if confirm {
+        match app.apply_reconciliation(&report) {
+            Ok(summary) => app.show_toast(summary),
+            Err(e) => app.bill_error = Some(e),
         }
-    });
+    } else if !cancel {
+        app.reconcile_report = Some(report);
+    }
 }
 
-fn show_template_form_window(app: &mut BillManagerApp, ctx: &egui::Context) {
-    let mut open = true;
-    egui::Window::new("Item Template")
-        .open(&mut open)
-        .resizable(true)
-        .show(ctx, |ui| {
-            if let Some(template) = &mut app.editing_template {
-                ui.horizontal(|ui| {
-                    ui.label("Type:");
-                    ui.text_edit_singleline(&mut template.item_type);
-                });
+fn show_pdf_error_dialog(app: &mut BillManagerApp, ctx: &egui::Context) {
+    let mut close = false;
 
-                ui.horizontal(|ui| {
-                    ui.label("Unit Price:");
-                    ui.add(egui::DragValue::new(&mut template.unit_price).speed(0.1).prefix("CHF "));
-                });
+    egui::Modal::new(egui::Id::new("pdf_error")).show(ctx, |ui| {
+        ui.heading("PDF generation failed");
+        ui.separator();
+        egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+            if let Some(error) = &app.pdf_error {
+                ui.add(egui::Label::new(egui::RichText::new(error).monospace()).wrap());
+            }
+        });
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button("📋 Copy").clicked()
+                && let Some(error) = &app.pdf_error
+            {
+                ui.ctx().copy_text(error.clone());
+            }
+            if ui.button("Close").clicked() {
+                close = true;
+            }
+        });
+    });
 
-                ui.separator();
+    if close {
+        app.pdf_error = None;
+    }
+}
 
-                ui.horizontal(|ui| {
-                    if ui.button("💾 Save").clicked() {
-                        let template = app.editing_template.take().unwrap();
-                        if template.id == 0 {
-                            app.add_item_template(template);
-                        } else {
-                            app.update_item_template(template);
-                        }
-                        app.show_template_form = false;
-                    }
+fn show_discard_changes_dialog(app: &mut BillManagerApp, ctx: &egui::Context, kind: FormKind) {
+    let mut discard = false;
+    let mut keep_editing = false;
 
-                    if ui.button("❌ Cancel").clicked() {
-                        app.editing_template = None;
-                        app.show_template_form = false;
-                    }
-                });
+    egui::Modal::new(egui::Id::new("discard_changes")).show(ctx, |ui| {
+        ui.heading("Discard changes?");
+        ui.label("The form has unsaved changes that will be lost.");
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button("🗑 Discard").clicked() {
+                discard = true;
+            }
+            if ui.button("✏ Keep editing").clicked() {
+                keep_editing = true;
             }
         });
+    });
 
-    if !open {
-        app.editing_template = None;
-        app.show_template_form = false;
+    if discard {
+        app.close_form(kind);
+    } else if keep_editing {
+        app.pending_discard = None;
     }
 }
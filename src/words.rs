@@ -0,0 +1,261 @@
+use crate::types::Language;
+
+const DE_UNITS: [&str; 20] = [
+    "null", "eins", "zwei", "drei", "vier", "fünf", "sechs", "sieben", "acht", "neun",
+    "zehn", "elf", "zwölf", "dreizehn", "vierzehn", "fünfzehn", "sechzehn", "siebzehn", "achtzehn", "neunzehn",
+];
+const DE_TENS: [&str; 10] = [
+    "", "", "zwanzig", "dreissig", "vierzig", "fünfzig", "sechzig", "siebzig", "achtzig", "neunzig",
+];
+
+const FR_UNITS: [&str; 17] = [
+    "zéro", "un", "deux", "trois", "quatre", "cinq", "six", "sept", "huit", "neuf",
+    "dix", "onze", "douze", "treize", "quatorze", "quinze", "seize",
+];
+// Swiss French: septante, huitante, nonante
+const FR_TENS: [&str; 10] = [
+    "", "dix", "vingt", "trente", "quarante", "cinquante", "soixante", "septante", "huitante", "nonante",
+];
+
+const IT_UNITS: [&str; 20] = [
+    "zero", "uno", "due", "tre", "quattro", "cinque", "sei", "sette", "otto", "nove",
+    "dieci", "undici", "dodici", "tredici", "quattordici", "quindici", "sedici", "diciassette", "diciotto", "diciannove",
+];
+const IT_TENS: [&str; 10] = [
+    "", "", "venti", "trenta", "quaranta", "cinquanta", "sessanta", "settanta", "ottanta", "novanta",
+];
+
+const EN_UNITS: [&str; 20] = [
+    "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine",
+    "ten", "eleven", "twelve", "thirteen", "fourteen", "fifteen", "sixteen", "seventeen", "eighteen", "nineteen",
+];
+const EN_TENS: [&str; 10] = [
+    "", "", "twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety",
+];
+
+/// German number as one word; a trailing one is "eins" ("einhunderteins")
+fn german(n: u64) -> String {
+    // "eins" becomes "ein" in front of another word part
+    let prefix = |n: u64| {
+        let words = german(n);
+        words.strip_suffix("eins").map(|w| format!("{}ein", w)).unwrap_or(words)
+    };
+    let rest = |n: u64| if n == 0 { String::new() } else { german(n) };
+
+    match n {
+        0..20 => DE_UNITS[n as usize].to_string(),
+        20..100 => match n % 10 {
+            0 => DE_TENS[(n / 10) as usize].to_string(),
+            u => format!("{}und{}", prefix(u), DE_TENS[(n / 10) as usize]),
+        },
+        100..1000 => format!("{}hundert{}", prefix(n / 100), rest(n % 100)),
+        1000..1_000_000 => format!("{}tausend{}", prefix(n / 1000), rest(n % 1000)),
+        _ => {
+            let millions = match n / 1_000_000 {
+                1 => "eine Million".to_string(),
+                m => format!("{} Millionen", german(m)),
+            };
+            match n % 1_000_000 {
+                0 => millions,
+                r => format!("{} {}", millions, german(r)),
+            }
+        }
+    }
+}
+
+/// French number; "cent" takes the plural only at the end of the number, not before "mille"
+fn french(n: u64) -> String {
+    fn below_thousand(n: u64, before_mille: bool) -> String {
+        match n {
+            0..17 => FR_UNITS[n as usize].to_string(),
+            17..20 => format!("dix-{}", FR_UNITS[(n % 10) as usize]),
+            20..100 => match n % 10 {
+                0 => FR_TENS[(n / 10) as usize].to_string(),
+                1 => format!("{} et un", FR_TENS[(n / 10) as usize]),
+                u => format!("{}-{}", FR_TENS[(n / 10) as usize], FR_UNITS[u as usize]),
+            },
+            _ => {
+                let hundreds = match n / 100 {
+                    1 => "cent".to_string(),
+                    h if n.is_multiple_of(100) && !before_mille => format!("{} cents", FR_UNITS[h as usize]),
+                    h => format!("{} cent", FR_UNITS[h as usize]),
+                };
+                match n % 100 {
+                    0 => hundreds,
+                    r => format!("{} {}", hundreds, below_thousand(r, false)),
+                }
+            }
+        }
+    }
+    let rest = |r: u64| if r == 0 { String::new() } else { format!(" {}", french(r)) };
+
+    match n {
+        0..1000 => below_thousand(n, false),
+        1000..1_000_000 => {
+            let thousands = match n / 1000 {
+                1 => "mille".to_string(),
+                t => format!("{} mille", below_thousand(t, true)),
+            };
+            format!("{}{}", thousands, rest(n % 1000))
+        }
+        _ => {
+            let millions = match n / 1_000_000 {
+                1 => "un million".to_string(),
+                m => format!("{} millions", french(m)),
+            };
+            format!("{}{}", millions, rest(n % 1_000_000))
+        }
+    }
+}
+
+/// Italian number as one word, with the usual elisions ("ventuno", "centottanta", "ventitré")
+fn italian(n: u64) -> String {
+    // A final "tre" in a compound is written "tré"
+    let tail = |r: u64| match r {
+        0 => String::new(),
+        3 => "tré".to_string(),
+        r => italian(r),
+    };
+
+    match n {
+        0..20 => IT_UNITS[n as usize].to_string(),
+        20..100 => {
+            let tens = IT_TENS[(n / 10) as usize];
+            match n % 10 {
+                0 => tens.to_string(),
+                u @ (1 | 8) => format!("{}{}", &tens[..tens.len() - 1], IT_UNITS[u as usize]),
+                u => format!("{}{}", tens, tail(u)),
+            }
+        }
+        100..1000 => {
+            let hundreds = match n / 100 {
+                1 => "cento".to_string(),
+                h => format!("{}cento", IT_UNITS[h as usize]),
+            };
+            let rest = tail(n % 100);
+            if rest.starts_with("ott") {
+                format!("{}{}", &hundreds[..hundreds.len() - 1], rest)
+            } else {
+                format!("{}{}", hundreds, rest)
+            }
+        }
+        1000..1_000_000 => match n / 1000 {
+            1 => format!("mille{}", tail(n % 1000)),
+            t => format!("{}mila{}", italian(t), tail(n % 1000)),
+        },
+        _ => {
+            let millions = match n / 1_000_000 {
+                1 => "un milione".to_string(),
+                m => format!("{} milioni", italian(m)),
+            };
+            match n % 1_000_000 {
+                0 => millions,
+                r => format!("{} {}", millions, italian(r)),
+            }
+        }
+    }
+}
+
+fn english(n: u64) -> String {
+    let rest = |r: u64| if r == 0 { String::new() } else { format!(" {}", english(r)) };
+
+    match n {
+        0..20 => EN_UNITS[n as usize].to_string(),
+        20..100 => match n % 10 {
+            0 => EN_TENS[(n / 10) as usize].to_string(),
+            u => format!("{}-{}", EN_TENS[(n / 10) as usize], EN_UNITS[u as usize]),
+        },
+        100..1000 => format!("{} hundred{}", EN_UNITS[(n / 100) as usize], rest(n % 100)),
+        1000..1_000_000 => format!("{} thousand{}", english(n / 1000), rest(n % 1000)),
+        _ => format!("{} million{}", english(n / 1_000_000), rest(n % 1_000_000)),
+    }
+}
+
+/// Name of the currency unit, singular or plural; unknown currencies keep their code
+fn currency_name(language: Language, currency: &str, one: bool) -> String {
+    let name = match (currency, language) {
+        ("CHF", Language::De) => "Franken",
+        ("CHF", Language::Fr) if one => "franc",
+        ("CHF", Language::Fr) => "francs",
+        ("CHF", Language::It) if one => "franco",
+        ("CHF", Language::It) => "franchi",
+        ("CHF", Language::En) if one => "franc",
+        ("CHF", Language::En) => "francs",
+        ("EUR", Language::Fr | Language::En) if !one => "euros",
+        ("EUR", Language::De) => "Euro",
+        ("EUR", _) => "euro",
+        (code, _) => code,
+    };
+    name.to_string()
+}
+
+/// Spells out an amount, e.g. "Eintausendzweihundert Franken fünfzig" for CHF 1200.50.
+/// The centimes follow the currency name and are left out when zero.
+pub fn amount_to_words(amount: f64, language: Language, currency: &str) -> String {
+    let cents_total = (amount.abs() * 100.0).round() as u64;
+    let (whole, cents) = (cents_total / 100, cents_total % 100);
+
+    let number = match language {
+        Language::De => german,
+        Language::Fr => french,
+        Language::It => italian,
+        Language::En => english,
+    };
+    // The article form goes with a noun: "ein Franken", "un franc", "one franc"
+    let whole_words = match (whole, language) {
+        (1, Language::De) => "ein".to_string(),
+        (1, Language::It) => "un".to_string(),
+        (w, _) => number(w),
+    };
+
+    // Round millions take a preposition: "un million de francs", "un milione di franchi"
+    let preposition = match language {
+        Language::Fr if whole > 0 && whole.is_multiple_of(1_000_000) => " de",
+        Language::It if whole > 0 && whole.is_multiple_of(1_000_000) => " di",
+        _ => "",
+    };
+
+    let mut words = format!("{}{} {}", whole_words, preposition, currency_name(language, currency, whole == 1));
+    if cents > 0 {
+        words.push(' ');
+        words.push_str(&number(cents));
+    }
+    if amount < 0.0 && cents_total > 0 {
+        let minus = match language {
+            Language::Fr => "moins",
+            Language::It => "meno",
+            Language::De | Language::En => "minus",
+        };
+        words = format!("{} {}", minus, words);
+    }
+
+    let mut chars = words.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => words,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn amounts_are_spelled_out_with_centimes_after_the_currency() {
+        assert_eq!(amount_to_words(1200.50, Language::De, "CHF"), "Eintausendzweihundert Franken fünfzig");
+        assert_eq!(amount_to_words(1234.05, Language::En, "CHF"), "One thousand two hundred thirty-four francs five");
+        assert_eq!(amount_to_words(81.99, Language::Fr, "EUR"), "Huitante et un euros nonante-neuf");
+        assert_eq!(amount_to_words(-5.0, Language::De, "CHF"), "Minus fünf Franken");
+    }
+
+    #[test]
+    fn round_amounts_leave_the_centimes_out() {
+        assert_eq!(amount_to_words(0.0, Language::De, "CHF"), "Null Franken");
+        assert_eq!(amount_to_words(1.0, Language::De, "CHF"), "Ein Franken");
+        assert_eq!(amount_to_words(1.0, Language::It, "CHF"), "Un franco");
+        assert_eq!(amount_to_words(101.0, Language::De, "CHF"), "Einhunderteins Franken");
+        assert_eq!(amount_to_words(200_000.0, Language::Fr, "CHF"), "Deux cent mille francs");
+        assert_eq!(amount_to_words(1_000_000.0, Language::Fr, "CHF"), "Un million de francs");
+        assert_eq!(amount_to_words(2_000_000.0, Language::It, "CHF"), "Due milioni di franchi");
+    }
+}
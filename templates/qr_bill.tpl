@@ -7,39 +7,56 @@
 
 #set text(font: "Roboto")
 
+// Brand color from Settings, as #rrggbb
+#let accent = rgb("{{accent-color}}")
+#show heading: set text(fill: accent)
+
 #align(right)[
   #image(
     "logo.svg",
     width: 45mm,
   )
 ]
+#line(length: 100%, stroke: 1pt + accent)
 #set text(size: 9pt)
 *Rechnungssteller*
 
-{{creditor-name}} \
-{{creditor-street}} {{creditor-building}} \
-{{creditor-postal-code}} {{creditor-city}} \
-{{creditor-country}}
+#"{{creditor-name}}" \
+#"{{creditor-street}}" #"{{creditor-building}}" \
+#"{{creditor-postal-code}}" #"{{creditor-city}}" \
+#"{{creditor-country}}"
 
 #columns(2)[
   #set text(size: 9pt)
   *Kunde*
 
-  {{client-name}} \
-  {{client-street}} {{client-building}} \
-  {{client-postal-code}} {{client-city}} \
-  {{client-country}}
+  #"{{client-name}}" \
+  #"{{client-street}}" #"{{client-building}}" \
+  #"{{client-postal-code}}" #"{{client-city}}" \
+  #"{{client-country}}"
 
 
   #colbreak()
   *Rechnungsadresse*
 
-  {{debtor-name}} \
-  {{debtor-street}} {{debtor-building}} \
-  {{debtor-postal-code}} {{debtor-city}} \
-  {{debtor-country}}
+  #"{{debtor-name}}" \
+  #"{{debtor-street}}" #"{{debtor-building}}" \
+  #"{{debtor-postal-code}}" #"{{debtor-city}}" \
+  #"{{debtor-country}}"
+]
+
+#if "{{po-number}}" != "" [
+  *#"{{po-number-label}}":* #"{{po-number}}"
 ]
 
+#if "{{service-period}}" != "" [
+  #"{{service-period}}"
+]
+
+{{document-title}}
+
+{{reminder}}
+
 #box(width: 90%, inset: (top: 2em))[
   = Leistungen
 
@@ -48,40 +65,48 @@
     columns: (1fr, auto, auto, auto, auto),
     align: (x, y) => if x < 2 { left } else { right },
     stroke: (x, y) => if y == 0 {
-      (bottom: 1pt + black)
+      (bottom: 1pt + accent)
     } else if y > 0 and {{table-rows}} > 0 and y == {{table-rows}} {
-      (top: 0.5pt + black, bottom: 2pt + black)
+      (top: 0.5pt + accent, bottom: 2pt + accent)
     } else {
       (bottom: 0.2pt + black)
     },
     table.header([*Beschreibung*], [*Typ*], [*Anzahl*], [*Preis*], [*Total*]),
     {{table-contents}}
   )
+
+  {{notes}}
 ]
 
-#place(
-  bottom + left,
-  dx: -10mm,
-  dy: 10mm,
-)[
-  #swiss-qr-bill(
-    account: "{{account}}",
-    creditor-name: "{{creditor-name}}",
-    creditor-street: "{{creditor-street}}",
-    creditor-building: "{{creditor-building}}",
-    creditor-postal-code: "{{creditor-postal-code}}",
-    creditor-city: "{{creditor-city}}",
-    creditor-country: "{{creditor-country}}",
-    amount: {{amount}},
-    currency: "{{currency}}",
-    debtor-name: "{{debtor-name}}",
-    debtor-street: "{{debtor-street}}",
-    debtor-building: "{{debtor-building}}",
-    debtor-postal-code: "{{debtor-postal-code}}",
-    debtor-city: "{{debtor-city}}",
-    debtor-country: "{{debtor-country}}",
-    reference-type: "{{reference-type}}",
-    reference: "{{reference}}",
-    additional-info: "{{additional-info}}",
-  )
+#if not {{qr-bill}} and "{{account}}" != "" [
+  *#"{{payable-to-label}}":* IBAN #"{{account}}"
+]
+
+#if {{qr-bill}} [
+  #place(
+    bottom + left,
+    dx: -10mm,
+    dy: 10mm,
+  )[
+    #swiss-qr-bill(
+      account: "{{account}}",
+      creditor-name: "{{creditor-name}}",
+      creditor-street: "{{creditor-street}}",
+      creditor-building: "{{creditor-building}}",
+      creditor-postal-code: "{{creditor-postal-code}}",
+      creditor-city: "{{creditor-city}}",
+      creditor-country: "{{creditor-country}}",
+      amount: {{amount}},
+      currency: "{{currency}}",
+      debtor-name: "{{debtor-name}}",
+      debtor-street: "{{debtor-street}}",
+      debtor-building: "{{debtor-building}}",
+      debtor-postal-code: "{{debtor-postal-code}}",
+      debtor-city: "{{debtor-city}}",
+      debtor-country: "{{debtor-country}}",
+      reference-type: "{{reference-type}}",
+      reference: "{{reference}}",
+      additional-info: "{{additional-info}}",
+    )
+  ]
 ]
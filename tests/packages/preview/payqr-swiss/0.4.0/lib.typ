@@ -0,0 +1,14 @@
+// Offline stand-in for @preview/payqr-swiss, so the tests can compile invoices without
+// downloading packages. It takes the same named arguments as the real `swiss-qr-bill` and
+// draws the payment part as a plain frame with account and amount instead of a QR code.
+#let swiss-qr-bill(..args) = {
+  let field(name) = args.named().at(name, default: none)
+  block(width: 210mm, height: 105mm, stroke: 0.5pt, inset: 5mm)[
+    *Zahlteil* \
+    #field("account") \
+    #field("currency") #field("amount") \
+    #field("creditor-name") \
+    #field("debtor-name") \
+    #field("reference")
+  ]
+}